@@ -1,7 +1,11 @@
 use std::io::Write;
 use criterion::{criterion_group, criterion_main, Criterion};
-use bilbo::rsa::PickLock;
+use bilbo::progress::SilentSink;
+use bilbo::rsa::{IterationLimit, PickLock};
 use bilbo::entropy::Shannon;
+use bilbo::modmath::Modulus;
+use bilbo::primality::is_probable_prime;
+use bilbo::sieve::{primes, sieve};
 use num_bigint::{BigInt, Sign};
 use openssl::bn::BigNum;
 
@@ -9,12 +13,10 @@ fn benchmark_lock_pick_weak_private_to_crack_large_weak_rsa(c: &mut Criterion) {
     c.bench_function("benchmark_lock_pick_weak_private_to_crack_large_weak_rsa", |b| {
 
         let Ok(large_n) = BigNum::from_dec_str("24051723933323373230335109652699872887260372863633030520380856590934224554506308944154529656903683098544282868895265857723676740447085769973038138116162852753658181861191950778361549639563565516085451073539560657386103501608592321148669427604194877552133864887585897064910317370632491325912646759075452895764136071794899761625652745642888012193592843601786282707419064157922868466879644136792854722277212465067471658496818060980989808791352963906077940588038623347540668963885547785982543883250789113853569537794783330309654648546163063571756203834919697878945651911998161025323667873893944714006021586935213636888431") else {
-            assert!(false);
-            return;
+            panic!();
         };
         let Ok(large_d) = BigNum::from_dec_str("20859605057389981400415296665239606253551311979432043299936333792698939369418558891569637169366135826146428643134992692481438916188899523620207130817470747633629513081286743218201811495234043370443885950972963184234382668232155560092302387896834347699555010854105235260577040893379009940545782216749159515118484219566373157731404293321389017417036945992984437162056145246504943473128453889715274064071687926343900718250671226003207988553491071490774949729393790264296526140962891140650428560103645538027632465103573248308915991466476312603275778085679414182339076676621372222055380237829179961993191380693342799887257") else {
-            assert!(false);
-            return;
+            panic!();
         };
 
         let n=  BigInt::from_bytes_be(Sign::Plus, &large_n.to_vec());
@@ -24,8 +26,7 @@ fn benchmark_lock_pick_weak_private_to_crack_large_weak_rsa(c: &mut Criterion) {
         let pl = PickLock::from_exponent_and_modulus(e.clone(), n.clone());
         b.iter(|| {
             let Ok(res) = pl.try_lock_pick_weak_private() else {
-                assert!(false);
-                return;
+                panic!();
             };
             assert_eq!(res, d);
         });
@@ -35,48 +36,181 @@ fn benchmark_lock_pick_weak_private_to_crack_large_weak_rsa(c: &mut Criterion) {
 fn benchmark_lock_pick_weak_private_to_not_be_able_to_crack_strong_small_rsa(c: &mut Criterion) {
     c.bench_function("benchmark_lock_pick_weak_private_to_not_be_able_to_crack_strong_large_rsa", |b| {
 
-        const PUBLIC_KEY_SAMPLE: &'static str = "-----BEGIN PUBLIC KEY-----
+        const PUBLIC_KEY_SAMPLE: &str = "-----BEGIN PUBLIC KEY-----
 MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAMp2Z+WFY2ygdgPMnWpJNxqtuweA1nix
 kTirAEQ+F3NKfNEdR9J/+Rq+2ViT3wnamtuBG+10SKuKjr9FKhh/T0sCAwEAAQ==
 -----END PUBLIC KEY-----
         ";
 
-        let Ok(pl) = PickLock::from_pem(&PUBLIC_KEY_SAMPLE) else {
-            assert!(false);
-            return;
+        let Ok(pl) = PickLock::from_pem(PUBLIC_KEY_SAMPLE) else {
+            panic!();
         };
 
         b.iter(|| {
             let Err(_) = pl.try_lock_pick_weak_private() else {
-                assert!(false);
-                return;
+                panic!();
             };
         });
     });
 }
 
+fn benchmark_lock_pick_weak_private_sequential_against_parallel_on_large_iteration_cap(c: &mut Criterion) {
+    const PUBLIC_KEY_SAMPLE: &str = "-----BEGIN PUBLIC KEY-----
+MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAMp2Z+WFY2ygdgPMnWpJNxqtuweA1nix
+kTirAEQ+F3NKfNEdR9J/+Rq+2ViT3wnamtuBG+10SKuKjr9FKhh/T0sCAwEAAQ==
+-----END PUBLIC KEY-----
+    ";
+
+    // Fresh key every run, the sequential attack would resume from its exhausted checkpoint otherwise.
+    let pick_lock = || {
+        let Ok(mut pl) = PickLock::from_pem(PUBLIC_KEY_SAMPLE) else {
+            panic!();
+        };
+        let Ok(limit) = IterationLimit::bounded(200_000) else {
+            panic!();
+        };
+        pl.alter_iteration_limit(limit);
+        pl
+    };
+
+    let mut group = c.benchmark_group("benchmark_lock_pick_weak_private_on_large_iteration_cap");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let Err(_) = pick_lock().try_lock_pick_weak_private() else {
+                panic!();
+            };
+        });
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| {
+            let Err(_) = pick_lock().try_lock_pick_weak_private_parallel() else {
+                panic!();
+            };
+        });
+    });
+    group.finish();
+}
+
 fn benchmark_lock_pick_strong_private_to_crack_strong_small_rsa(c: &mut Criterion) {
     c.bench_function("benchmark_lock_pick_strong_private_to_crack_strong_small_rsa", |b| {
 
-        const PUBLIC_KEY_SAMPLE: &'static str = "-----BEGIN PUBLIC KEY-----
+        const PUBLIC_KEY_SAMPLE: &str = "-----BEGIN PUBLIC KEY-----
 MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAMp2Z+WFY2ygdgPMnWpJNxqtuweA1nix
 kTirAEQ+F3NKfNEdR9J/+Rq+2ViT3wnamtuBG+10SKuKjr9FKhh/T0sCAwEAAQ==
 -----END PUBLIC KEY-----
         ";
 
-        let Ok(mut pl) = PickLock::from_pem(&PUBLIC_KEY_SAMPLE) else {
-            assert!(false);
-            return;
+        let Ok(mut pl) = PickLock::from_pem(PUBLIC_KEY_SAMPLE) else {
+            panic!();
+        };
+        let Ok(limit) = IterationLimit::bounded(100) else {
+            panic!();
         };
-        let Ok(_) = pl.alter_max_iter(100) else {
-            assert!(false);
-            return;
+        pl.alter_iteration_limit(limit);
+
+        b.iter(|| {
+            let _ = pl.try_lock_pick_strong_private(&mut SilentSink);
+        });
+    });
+}
+
+fn benchmark_lock_pick_ecm_to_not_be_able_to_crack_strong_small_rsa(c: &mut Criterion) {
+    const PUBLIC_KEY_SAMPLE: &str = "-----BEGIN PUBLIC KEY-----
+MFwwDQYJKoZIhvcNAQEBBQADSwAwSAJBAMp2Z+WFY2ygdgPMnWpJNxqtuweA1nix
+kTirAEQ+F3NKfNEdR9J/+Rq+2ViT3wnamtuBG+10SKuKjr9FKhh/T0sCAwEAAQ==
+-----END PUBLIC KEY-----
+    ";
+
+    let mut group = c.benchmark_group("benchmark_lock_pick_ecm_to_not_be_able_to_crack_strong_small_rsa");
+    group.sample_size(10);
+    group.bench_function("curves", |b| {
+        b.iter(|| {
+            // Fresh key every run, ECM would resume from its checkpoint otherwise.
+            let Ok(mut pl) = PickLock::from_pem(PUBLIC_KEY_SAMPLE) else {
+                panic!();
+            };
+            pl.alter_trial_bound(0);
+            let Err(_) = pl.try_lock_pick_ecm(8, 2_000) else {
+                panic!();
+            };
+        });
+    });
+    group.finish();
+}
+
+fn benchmark_modular_exponentiation_montgomery_against_num_bigint(c: &mut Criterion) {
+    let mut group = c.benchmark_group("benchmark_modular_exponentiation");
+    for bits in [256, 512, 1024, 2048] {
+        let n = (BigInt::from(1) << bits) - BigInt::from(159);
+        let base = BigInt::from(3);
+        let exp = &n - BigInt::from(1);
+        let modulus = Modulus::new(&n);
+        group.bench_function(format!("montgomery_{bits}"), |b| {
+            b.iter(|| modulus.pow_mod(&base, &exp));
+        });
+        group.bench_function(format!("num_bigint_{bits}"), |b| {
+            b.iter(|| base.modpow(&exp, &n));
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_modular_multiplication_chain_montgomery_against_num_bigint(c: &mut Criterion) {
+    // 1000 multiplications modulo n in a row, as the stage 2 of p - 1 and the Lucas sequences of p + 1 do.
+    let mut group = c.benchmark_group("benchmark_modular_multiplication_chain");
+    for bits in [512, 1024, 2048] {
+        let n = (BigInt::from(1) << bits) - BigInt::from(159);
+        let a = (BigInt::from(1) << (bits - 1)) + BigInt::from(12345);
+        let modulus = Modulus::new(&n);
+        group.bench_function(format!("montgomery_{bits}"), |b| {
+            b.iter(|| {
+                let a = modulus.residue(&a);
+                let mut x = a.clone();
+                for _ in 0..1000 {
+                    x = modulus.mul(&x, &a);
+                }
+                modulus.value(&x)
+            });
+        });
+        group.bench_function(format!("num_bigint_{bits}"), |b| {
+            b.iter(|| {
+                let mut x = a.clone();
+                for _ in 0..1000 {
+                    x = (x * &a) % &n;
+                }
+                x
+            });
+        });
+    }
+    group.finish();
+}
+
+fn benchmark_primality_check_of_1024_bit_prime(c: &mut Criterion) {
+    c.bench_function("benchmark_primality_check_of_1024_bit_prime", |b| {
+        let Ok(prime) = BigNum::new().and_then(|mut p| p.generate_prime(1024, false, None, None).map(|_| p)) else {
+            panic!();
         };
+        let prime = BigInt::from_bytes_be(Sign::Plus, &prime.to_vec());
+        b.iter(|| {
+            assert!(is_probable_prime(&prime));
+        });
+    });
+}
 
+fn benchmark_segmented_sieve_against_cached_primes_up_to_2_pow_24(c: &mut Criterion) {
+    let mut group = c.benchmark_group("benchmark_segmented_sieve_against_cached_primes_up_to_2_pow_24");
+    group.bench_function("segmented_sieve", |b| {
+        b.iter(|| {
+            assert_eq!(sieve(1 << 24).len(), 1_077_871);
+        });
+    });
+    group.bench_function("cached_primes", |b| {
         b.iter(|| {
-            let _ = pl.try_lock_pick_strong_private(false);
+            assert_eq!(primes(1 << 24).len(), 1_077_871);
         });
     });
+    group.finish();
 }
 
 fn benchmark_entropy_calculation(c: &mut Criterion) {
@@ -85,7 +219,7 @@ fn benchmark_entropy_calculation(c: &mut Criterion) {
         let mut pre = Shannon::new();
         b.iter(|| {
             let _ = pre.write(info_buffer.as_bytes());
-            let _ = pre.process();
+            pre.process();
             let _ = pre.flush();
         })
     });
@@ -95,7 +229,13 @@ criterion_group!(
     benches,
     benchmark_lock_pick_weak_private_to_crack_large_weak_rsa,
     benchmark_lock_pick_weak_private_to_not_be_able_to_crack_strong_small_rsa,
+    benchmark_lock_pick_weak_private_sequential_against_parallel_on_large_iteration_cap,
     benchmark_lock_pick_strong_private_to_crack_strong_small_rsa,
+    benchmark_lock_pick_ecm_to_not_be_able_to_crack_strong_small_rsa,
+    benchmark_modular_exponentiation_montgomery_against_num_bigint,
+    benchmark_modular_multiplication_chain_montgomery_against_num_bigint,
+    benchmark_primality_check_of_1024_bit_prime,
+    benchmark_segmented_sieve_against_cached_primes_up_to_2_pow_24,
     benchmark_entropy_calculation,
 );
 criterion_main!(benches);
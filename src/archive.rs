@@ -0,0 +1,742 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::errors::BilboError;
+
+const ZIP_LOCAL_HEADER: u32 = 0x04034b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x02014b50;
+const ZIP_END_OF_DIRECTORY: u32 = 0x06054b50;
+const ZIP_END_OF_DIRECTORY_LEN: usize = 22;
+// End of central directory is followed by the archive comment of up to 65535 bytes.
+const ZIP_MAX_COMMENT: usize = 0xffff;
+// ZIP64 archives mark the sizes and offsets they move to the extra field with all ones.
+const ZIP64_MARKER: u32 = 0xffffffff;
+const ZIP_STORED: u16 = 0;
+const ZIP_DEFLATED: u16 = 8;
+const ZIP_ENCRYPTED: u16 = 0x0001;
+const GZIP_MAGIC: [u8; 3] = [0x1f, 0x8b, 8];
+const GZIP_FHCRC: u8 = 0x02;
+const GZIP_FEXTRA: u8 = 0x04;
+const GZIP_FNAME: u8 = 0x08;
+const GZIP_FCOMMENT: u8 = 0x10;
+const TAR_BLOCK: usize = 512;
+const TAR_MAGIC: &[u8] = b"ustar";
+const TAR_MAGIC_OFFSET: usize = 257;
+const TAR_CHECKSUM: std::ops::Range<usize> = 148..156;
+const TAR_SIZE: std::ops::Range<usize> = 124..136;
+const TAR_TYPE: usize = 156;
+const TAR_PREFIX: std::ops::Range<usize> = 345..500;
+const TAR_GNU_LONG_NAME: u8 = b'L';
+const TAR_PAX_HEADER: u8 = b'x';
+const CRC32_POLY: u32 = 0xedb88320;
+const MAX_CODE_BITS: usize = 15;
+const END_OF_BLOCK: u16 = 256;
+// Base lengths and extra bits of the length symbols 257 to 285, RFC 1951 section 3.2.5.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// Base distances and extra bits of the distance symbols 0 to 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// Order the code lengths of the code length alphabet are stored in by dynamic blocks.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// ArchiveKind is the container format told by its magic bytes.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    Gzip,
+}
+
+impl Display for ArchiveKind {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                ArchiveKind::Zip => "zip",
+                ArchiveKind::Tar => "tar",
+                ArchiveKind::Gzip => "gzip",
+            }
+        )
+    }
+}
+
+/// Tells the archive by the head of the data: zip, jar among them, by its local file header,
+/// tar by the ustar magic of its first header and gzip by its magic and deflate method.
+///
+pub fn archive_kind(head: &[u8]) -> Option<ArchiveKind> {
+    if head.len() >= 4 && le32(head, 0) == ZIP_LOCAL_HEADER {
+        return Some(ArchiveKind::Zip);
+    }
+    if head.starts_with(&GZIP_MAGIC) {
+        return Some(ArchiveKind::Gzip);
+    }
+    if head.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + TAR_MAGIC.len()) == Some(TAR_MAGIC) {
+        return Some(ArchiveKind::Tar);
+    }
+
+    None
+}
+
+/// Lists the files of zip archive by its central directory, inflating or copying each of them.
+/// Directories, encrypted entries and entries of other compression methods are skipped.
+/// Fails on ZIP64 archives, more than max_entries files, or more than max_unpacked bytes unpacked altogether,
+/// the declared sizes are not trusted.
+///
+pub fn unzip(
+    data: &[u8],
+    max_entries: usize,
+    max_unpacked: usize,
+) -> Result<Vec<(String, Vec<u8>)>, BilboError> {
+    let search_from = data
+        .len()
+        .saturating_sub(ZIP_END_OF_DIRECTORY_LEN + ZIP_MAX_COMMENT);
+    let Some(end) = (search_from..=data.len().saturating_sub(ZIP_END_OF_DIRECTORY_LEN))
+        .rev()
+        .find(|i| data.len() >= ZIP_END_OF_DIRECTORY_LEN && le32(data, *i) == ZIP_END_OF_DIRECTORY)
+    else {
+        return Err(BilboError::GenericError(
+            "missing zip end of central directory".to_string(),
+        ));
+    };
+    let count = le16(data, end + 10) as usize;
+    let offset = le32(data, end + 16);
+    if offset == ZIP64_MARKER {
+        return Err(BilboError::GenericError(
+            "ZIP64 archives are not supported".to_string(),
+        ));
+    }
+    if count > max_entries {
+        return Err(BilboError::GenericError(format!(
+            "zip archive of {count} entries exceeds the limit of {max_entries}"
+        )));
+    }
+    let mut entries = Vec::new();
+    let mut unpacked = 0;
+    let mut position = offset as usize;
+    for _ in 0..count {
+        let header = slice(data, position, 46)?;
+        if le32(header, 0) != ZIP_CENTRAL_HEADER {
+            return Err(BilboError::GenericError(
+                "invalid zip central directory header".to_string(),
+            ));
+        }
+        let (flags, method, crc) = (le16(header, 8), le16(header, 10), le32(header, 16));
+        let (compressed, local) = (le32(header, 20), le32(header, 42));
+        let (name_len, extra_len, comment_len) = (
+            le16(header, 28) as usize,
+            le16(header, 30) as usize,
+            le16(header, 32) as usize,
+        );
+        let name = String::from_utf8_lossy(slice(data, position + 46, name_len)?).to_string();
+        position += 46 + name_len + extra_len + comment_len;
+        if compressed == ZIP64_MARKER || local == ZIP64_MARKER {
+            return Err(BilboError::GenericError(
+                "ZIP64 archives are not supported".to_string(),
+            ));
+        }
+        if name.ends_with('/') || flags & ZIP_ENCRYPTED != 0 {
+            continue;
+        }
+        // Local header repeats the name, its extra field may differ from the central one.
+        let local = local as usize;
+        let local_header = slice(data, local, 30)?;
+        if le32(local_header, 0) != ZIP_LOCAL_HEADER {
+            return Err(BilboError::GenericError(format!(
+                "invalid zip local header of {name}"
+            )));
+        }
+        let start = local + 30 + le16(local_header, 26) as usize + le16(local_header, 28) as usize;
+        let stored = slice(data, start, compressed as usize)?;
+        let remaining = max_unpacked.saturating_sub(unpacked);
+        let content = match method {
+            ZIP_STORED if stored.len() <= remaining => stored.to_vec(),
+            ZIP_STORED => return Err(unpacked_limit(max_unpacked)),
+            ZIP_DEFLATED => inflate(stored, remaining)?,
+            _ => continue,
+        };
+        if crc32(&content) != crc {
+            return Err(BilboError::GenericError(format!(
+                "zip entry {name} fails its CRC-32"
+            )));
+        }
+        unpacked += content.len();
+        entries.push((name, content));
+    }
+
+    Ok(entries)
+}
+
+/// Lists the regular files of tar archive, ustar with GNU long names and PAX paths, as slices of the data.
+/// Fails on more than max_entries files or invalid header checksum.
+///
+pub fn untar(data: &[u8], max_entries: usize) -> Result<Vec<(String, &[u8])>, BilboError> {
+    let mut entries = Vec::new();
+    let mut position = 0;
+    let mut long_name = None;
+    while position + TAR_BLOCK <= data.len() {
+        let header = &data[position..position + TAR_BLOCK];
+        // Archive ends with two zero blocks, the first one is enough.
+        if header.iter().all(|b| *b == 0) {
+            break;
+        }
+        let checksum = octal(&header[TAR_CHECKSUM])?;
+        let sum: u64 = header
+            .iter()
+            .enumerate()
+            .map(|(i, b)| match TAR_CHECKSUM.contains(&i) {
+                true => b' ' as u64,
+                false => *b as u64,
+            })
+            .sum();
+        if sum != checksum {
+            return Err(BilboError::GenericError(format!(
+                "invalid tar header checksum at {position}"
+            )));
+        }
+        let size = octal(&header[TAR_SIZE])? as usize;
+        let content = slice(data, position + TAR_BLOCK, size)?;
+        position += TAR_BLOCK + size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+        match header[TAR_TYPE] {
+            TAR_GNU_LONG_NAME => {
+                long_name = Some(text(content));
+                continue;
+            }
+            TAR_PAX_HEADER => {
+                long_name = pax_path(content).or(long_name);
+                continue;
+            }
+            b'0' | 0 => (),
+            _ => {
+                long_name = None;
+                continue;
+            }
+        }
+        let name = long_name.take().unwrap_or_else(|| {
+            let (prefix, name) = (text(&header[TAR_PREFIX]), text(&header[..100]));
+            match prefix.is_empty() {
+                true => name,
+                false => format!("{prefix}/{name}"),
+            }
+        });
+        if entries.len() == max_entries {
+            return Err(BilboError::GenericError(format!(
+                "tar archive exceeds the limit of {max_entries} entries"
+            )));
+        }
+        entries.push((name, content));
+    }
+
+    Ok(entries)
+}
+
+/// Decompresses gzip member, RFC 1952, returns the original file name if stored and the data,
+/// verified by its CRC-32 and size. Fails if the data inflates to more than limit bytes.
+///
+pub fn gunzip(data: &[u8], limit: usize) -> Result<(Option<String>, Vec<u8>), BilboError> {
+    let header = slice(data, 0, 10)?;
+    if !header.starts_with(&GZIP_MAGIC) {
+        return Err(BilboError::GenericError("missing gzip magic".to_string()));
+    }
+    let flags = header[3];
+    let mut position = 10;
+    if flags & GZIP_FEXTRA != 0 {
+        position += 2 + le16(slice(data, position, 2)?, 0) as usize;
+    }
+    let mut name = None;
+    for flag in [GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            let field = data.get(position..).unwrap_or_default();
+            let Some(len) = field.iter().position(|b| *b == 0) else {
+                return Err(BilboError::GenericError(
+                    "truncated gzip header".to_string(),
+                ));
+            };
+            if flag == GZIP_FNAME {
+                name = Some(text(&field[..len]));
+            }
+            position += len + 1;
+        }
+    }
+    if flags & GZIP_FHCRC != 0 {
+        position += 2;
+    }
+    let (inflated, consumed) = inflate_raw(data.get(position..).unwrap_or_default(), limit)?;
+    let trailer = slice(data, position + consumed, 8)?;
+    if le32(trailer, 0) != crc32(&inflated) || le32(trailer, 4) != inflated.len() as u32 {
+        return Err(BilboError::GenericError(
+            "gzip data fails its CRC-32 or size".to_string(),
+        ));
+    }
+
+    Ok((name, inflated))
+}
+
+/// Decompresses raw deflate stream, RFC 1951, of stored, fixed and dynamic Huffman blocks.
+/// Fails if the stream inflates to more than limit bytes, so the compression bombs are stopped early.
+///
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, BilboError> {
+    inflate_raw(data, limit).map(|(inflated, _)| inflated)
+}
+
+/// Calculates CRC-32 of zip and gzip, ISO 3309 polynomial in reflected form.
+///
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0_u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = match c & 1 {
+                1 => CRC32_POLY ^ (c >> 1),
+                _ => c >> 1,
+            };
+        }
+        *entry = c;
+    }
+
+    !data.iter().fold(!0_u32, |crc, b| {
+        table[((crc ^ *b as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+// Inflates the stream and returns the data with the number of bytes of the stream consumed.
+#[inline(always)]
+fn inflate_raw(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), BilboError> {
+    let mut bits = Bits::new(data);
+    let mut out = Vec::new();
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => {
+                bits.align();
+                let len = bits.read(16)? as usize;
+                if bits.read(16)? as usize != !len & 0xffff {
+                    return Err(BilboError::GenericError(
+                        "deflate stored block length mismatch".to_string(),
+                    ));
+                }
+                if out.len() + len > limit {
+                    return Err(unpacked_limit(limit));
+                }
+                out.extend_from_slice(bits.bytes(len)?);
+            }
+            1 => {
+                let mut lengths = [8_u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                let (literals, distances) = (Huffman::new(&lengths)?, Huffman::new(&[5; 30])?);
+                block(&mut bits, &mut out, &literals, &distances, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut bits)?;
+                block(&mut bits, &mut out, &literals, &distances, limit)?;
+            }
+            _ => {
+                return Err(BilboError::GenericError(
+                    "invalid deflate block type".to_string(),
+                ))
+            }
+        }
+        if last {
+            bits.align();
+            return Ok((out, bits.position));
+        }
+    }
+}
+
+// Reads the code lengths of the literal and distance codes of dynamic block, RFC 1951 section 3.2.7.
+#[inline(always)]
+fn dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), BilboError> {
+    let literals = bits.read(5)? as usize + 257;
+    let distances = bits.read(5)? as usize + 1;
+    let code_lengths = bits.read(4)? as usize + 4;
+    let mut lengths = [0_u8; 19];
+    for i in CODE_LENGTH_ORDER.iter().take(code_lengths) {
+        lengths[*i] = bits.read(3)? as u8;
+    }
+    let code = Huffman::new(&lengths)?;
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (value, repeat) = match code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let Some(previous) = lengths.last() else {
+                    return Err(BilboError::GenericError(
+                        "deflate repeats missing code length".to_string(),
+                    ));
+                };
+                (*previous, 3 + bits.read(2)?)
+            }
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literals + distances || lengths[END_OF_BLOCK as usize] == 0 {
+        return Err(BilboError::GenericError(
+            "invalid deflate code lengths".to_string(),
+        ));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literals])?,
+        Huffman::new(&lengths[literals..])?,
+    ))
+}
+
+// Decodes the literals and the length and distance pairs of compressed block up to its end.
+#[inline(always)]
+fn block(
+    bits: &mut Bits,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), BilboError> {
+    loop {
+        let symbol = literals.decode(bits)?;
+        if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+        if out.len() >= limit {
+            return Err(unpacked_limit(limit));
+        }
+        if symbol < END_OF_BLOCK {
+            out.push(symbol as u8);
+            continue;
+        }
+        let index = (symbol - 257) as usize;
+        let (Some(base), Some(extra)) = (LENGTH_BASE.get(index), LENGTH_EXTRA.get(index)) else {
+            return Err(BilboError::GenericError(
+                "invalid deflate length symbol".to_string(),
+            ));
+        };
+        let len = *base as usize + bits.read(*extra)? as usize;
+        let index = distances.decode(bits)? as usize;
+        let (Some(base), Some(extra)) = (DISTANCE_BASE.get(index), DISTANCE_EXTRA.get(index))
+        else {
+            return Err(BilboError::GenericError(
+                "invalid deflate distance symbol".to_string(),
+            ));
+        };
+        let distance = *base as usize + bits.read(*extra)? as usize;
+        if distance > out.len() {
+            return Err(BilboError::GenericError(
+                "deflate distance reaches before the data".to_string(),
+            ));
+        }
+        if out.len() + len > limit {
+            return Err(unpacked_limit(limit));
+        }
+        // Copied byte by byte, the match may overlap the bytes it produces.
+        for _ in 0..len {
+            out.push(out[out.len() - distance]);
+        }
+    }
+}
+
+// Canonical Huffman code as counts of the codes of every length and the symbols ordered by their codes.
+struct Huffman {
+    counts: [u16; MAX_CODE_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    // Builds the code of the lengths of the symbols, zero length symbols are not coded.
+    // Over-subscribed codes are refused, incomplete ones are allowed as for single distance code.
+    #[inline(always)]
+    fn new(lengths: &[u8]) -> Result<Self, BilboError> {
+        let mut counts = [0_u16; MAX_CODE_BITS + 1];
+        for len in lengths.iter() {
+            counts[*len as usize] += 1;
+        }
+        let mut left = 1_i32;
+        for count in counts.iter().skip(1) {
+            left = (left << 1) - *count as i32;
+            if left < 0 {
+                return Err(BilboError::GenericError(
+                    "over-subscribed deflate code".to_string(),
+                ));
+            }
+        }
+        let mut offsets = [0_u16; MAX_CODE_BITS + 2];
+        for len in 1..=MAX_CODE_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; offsets[MAX_CODE_BITS + 1] as usize];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len > 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+
+        Ok(Self { counts, symbols })
+    }
+
+    // Decodes the symbol bit by bit, the codes of every length are consecutive integers.
+    #[inline(always)]
+    fn decode(&self, bits: &mut Bits) -> Result<u16, BilboError> {
+        let (mut code, mut first, mut index) = (0_i32, 0_i32, 0_i32);
+        for count in self.counts.iter().skip(1) {
+            code |= bits.read(1)? as i32;
+            let count = *count as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(BilboError::GenericError("invalid deflate code".to_string()))
+    }
+}
+
+// Reader of deflate bits, least significant bit of every byte first.
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u8,
+}
+
+impl<'a> Bits<'a> {
+    #[inline(always)]
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            position: 0,
+            buffer: 0,
+            count: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn read(&mut self, need: u8) -> Result<u32, BilboError> {
+        while self.count < need {
+            let Some(byte) = self.data.get(self.position) else {
+                return Err(BilboError::GenericError(
+                    "truncated deflate stream".to_string(),
+                ));
+            };
+            self.buffer |= (*byte as u32) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1_u64 << need) - 1) as u32;
+        self.buffer >>= need;
+        self.count -= need;
+
+        Ok(value)
+    }
+
+    // Drops the bits left of the current byte, stored blocks and the end of stream are byte aligned.
+    #[inline(always)]
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    #[inline(always)]
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], BilboError> {
+        let bytes = slice(self.data, self.position, len)?;
+        self.position += len;
+
+        Ok(bytes)
+    }
+}
+
+#[inline(always)]
+fn unpacked_limit(limit: usize) -> BilboError {
+    BilboError::GenericError(format!(
+        "archive unpacks to more than the limit of {limit} bytes"
+    ))
+}
+
+#[inline(always)]
+fn slice(data: &[u8], start: usize, len: usize) -> Result<&[u8], BilboError> {
+    data.get(start..start.saturating_add(len)).ok_or_else(|| {
+        BilboError::GenericError(format!("truncated archive, {len} bytes at {start}"))
+    })
+}
+
+#[inline(always)]
+fn le16(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+#[inline(always)]
+fn le32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// Reads NUL terminated field of tar header or gzip name.
+#[inline(always)]
+fn text(field: &[u8]) -> String {
+    let len = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).to_string()
+}
+
+// Parses octal number of tar header, or big endian binary number if its first bit is set, as GNU tar writes large sizes.
+#[inline(always)]
+fn octal(field: &[u8]) -> Result<u64, BilboError> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return Ok(field[1..]
+            .iter()
+            .fold(0_u64, |acc, b| (acc << 8) | *b as u64));
+    }
+    let digits = text(field);
+    let digits = digits.trim_matches(|c: char| c == ' ' || c == '\0');
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8)
+        .map_err(|e| BilboError::GenericError(format!("invalid tar number {digits}: {e}")))
+}
+
+// Reads path record of PAX extended header, records are "length key=value" lines.
+#[inline(always)]
+fn pax_path(records: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(records).lines().find_map(|record| {
+        let (_, pair) = record.split_once(' ')?;
+        pair.strip_prefix("path=").map(|path| path.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds ustar header of the regular file, or of other type, with its checksum.
+    fn tar_header(name: &str, size: usize, kind: u8) -> Vec<u8> {
+        let mut header = vec![0_u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[TAR_SIZE][..11].copy_from_slice(format!("{size:011o}").as_bytes());
+        header[TAR_TYPE] = kind;
+        header[TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 6].copy_from_slice(b"ustar\0");
+        header[TAR_CHECKSUM].fill(b' ');
+        let sum: u32 = header.iter().map(|b| *b as u32).sum();
+        header[TAR_CHECKSUM][..7].copy_from_slice(format!("{sum:06o}\0").as_bytes());
+        header
+    }
+
+    fn tar_entry(archive: &mut Vec<u8>, name: &str, content: &[u8], kind: u8) {
+        archive.extend(tar_header(name, content.len(), kind));
+        archive.extend_from_slice(content);
+        archive.resize(archive.len().div_ceil(TAR_BLOCK) * TAR_BLOCK, 0);
+    }
+
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn it_should_inflate_stored_fixed_and_dynamic_blocks() -> Result<(), BilboError> {
+        let text: String = (0..200).map(|i| format!("{i} ")).collect();
+        // Deflated by zlib at level 9, the first byte announces the final dynamic block.
+        let dynamic = hex("1dd2c901c42010c4c0541402cd3130f927b6f2fedb362e310893c5e6505c1e4d06099964914d0e2972c923cd1c4c9f99ccc5dcccc32ce6653e66b3062b2c5fb9589b7558c5baacc76af660873dd97e71b30fbbd897fdd8cd199c702667713cd0e114e7721ea7a941859ad4a236e5798bbad4a39a3bb8e14eeee26eeee1fa3b97fbb8cd1bbcf0266ff136eff08ae7df3e5ed3830e3de9456ffad0455f5a8c4f438ea1c71064283224199a0c51862a4396e1eecfe6ee83fbe43ebacfeec3fbf43e3efd2260e6e7eb4ec38818152363748c9051325246cbac2f843b39a367048da291349a46d4a81a59b3bf62ee948db4d136e246ddc81b7d237014cef9d2ba13392a47e6e81ca1a374a48ed6113bf5dd01777a47f0281ec9a379448fea913dbae77e97c59df4d13ee247fdc81ffd6380582026c8fb6e953b2bc40cb1430c114bc414b1458c116ba4bfebd7fc00");
+        assert_eq!(inflate(&dynamic, usize::MAX)?, text.as_bytes());
+        assert_eq!(
+            inflate(&hex("4bcacc49ca5748429000"), usize::MAX)?,
+            b"bilbo bilbo bilbo"
+        );
+        let mut stored = vec![1, 5, 0, 0xfa, 0xff];
+        stored.extend_from_slice(b"bilbo");
+        assert_eq!(inflate(&stored, 5)?, b"bilbo");
+        assert!(inflate(&stored, 4).is_err());
+        assert!(inflate(&dynamic[..dynamic.len() / 2], usize::MAX).is_err());
+
+        // 100 000 zeros deflated to 114 bytes are stopped at the limit.
+        let bomb = hex("edc13101000000c2a0f54f6d0d0fa0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000805703");
+        assert_eq!(inflate(&bomb, 100_000)?.len(), 100_000);
+        assert!(inflate(&bomb, 99_999).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_unpack_gzip_zip_and_tar() -> Result<(), BilboError> {
+        let gzip =
+            hex("1f8b08080000000002ff6b65792e747874004bcacc49ca574842905c00c98b7ef312000000");
+        assert_eq!(archive_kind(&gzip), Some(ArchiveKind::Gzip));
+        let (name, data) = gunzip(&gzip, 1024)?;
+        assert_eq!(name.as_deref(), Some("key.txt"));
+        assert_eq!(data, b"bilbo bilbo bilbo\n");
+        let mut tampered = gzip.clone();
+        tampered[gzip.len() - 8] ^= 1;
+        assert!(gunzip(&tampered, 1024).is_err());
+
+        // Written by Python zipfile, directory, deflated and stored entries.
+        let zip = hex(concat!(
+            "504b03041400000000000000210000000000000000000000000005000000646f63732f",
+            "504b0304140000000800000021009faddec64c010000b20200000a000000646f63732f612e747874",
+            "1dd2c901c42010c4c0541402cd3130f927b6f2fedb362e310893c5e6505c1e4d06099964914d0e2972c923cd1c4c9f99ccc5dcccc32ce6653e66b3062b2c5fb9589b7558c5baacc76af660873dd97e71b30fbbd897fdd8cd199c702667713cd0e114e7721ea7a941859ad4a236e5798bbad4a39a3bb8e14eeee26eeee1fa3b97fbb8cd1bbcf0266ff136eff08ae7df3e5ed3830e3de9456ffad0455f5a8c4f438ea1c71064283224199a0c51862a4396e1eecfe6ee83fbe43ebacfeec3fbf43e3efd2260e6e7eb4ec38818152363748c9051325246cbac2f843b39a367048da291349a46d4a81a59b3bf62ee948db4d136e246ddc81b7d237014cef9d2ba13392a47e6e81ca1a374a48ed6113bf5dd01777a47f0281ec9a379448fea913dbae77e97c59df4d13ee247fdc81ffd6380582026c8fb6e953b2bc40cb1430c114bc414b1458c116ba4bfebd7fc00",
+            "504b0304140000000000000021000bf94356060000000600000005000000622e74787473746f726564",
+            "504b0102140314000000000000002100000000000000000000000000050000000000000000000000800100000000646f63732f",
+            "504b01021403140000000800000021009faddec64c010000b20200000a0000000000000000000000800123000000646f63732f612e747874",
+            "504b01021403140000000000000021000bf943560600000006000000050000000000000000000000800197010000622e747874",
+            "504b050600000000030003009e000000c00100000000",
+        ));
+        assert_eq!(archive_kind(&zip), Some(ArchiveKind::Zip));
+        let entries = unzip(&zip, 3, 1024)?;
+        let names: Vec<&str> = entries.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, vec!["docs/a.txt", "b.txt"]);
+        assert_eq!(entries[1].1, b"stored");
+        assert!(unzip(&zip, 2, 1024).is_err());
+        assert!(unzip(&zip, 3, 690).is_err());
+
+        let long_name = format!("{}/key.pem", "d".repeat(120));
+        let mut tar = Vec::new();
+        tar_entry(&mut tar, "keys/", b"", b'5');
+        tar_entry(&mut tar, "keys/a.pem", b"a", b'0');
+        tar_entry(
+            &mut tar,
+            "././@LongLink",
+            long_name.as_bytes(),
+            TAR_GNU_LONG_NAME,
+        );
+        tar_entry(&mut tar, "truncated", b"b", b'0');
+        tar_entry(
+            &mut tar,
+            "PaxHeaders/c",
+            b"21 path=keys/pax.pem\n",
+            TAR_PAX_HEADER,
+        );
+        tar_entry(&mut tar, "c", b"c", 0);
+        tar.extend([0; 2 * TAR_BLOCK]);
+        assert_eq!(archive_kind(&tar), Some(ArchiveKind::Tar));
+        let entries = untar(&tar, 3)?;
+        assert_eq!(
+            entries,
+            vec![
+                ("keys/a.pem".to_string(), &b"a"[..]),
+                (long_name, &b"b"[..]),
+                ("keys/pax.pem".to_string(), &b"c"[..]),
+            ]
+        );
+        assert!(untar(&tar, 2).is_err());
+        tar[TAR_BLOCK + 1] ^= 1;
+        assert!(untar(&tar, 3).is_err());
+
+        Ok(())
+    }
+}
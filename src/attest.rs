@@ -0,0 +1,302 @@
+use openssl::bn::BigNum;
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{HasPublic, Id, PKey, PKeyRef, Private, Public};
+use openssl::sha::sha256;
+use openssl::sign::{Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::SystemTime;
+
+use crate::audit::rfc3339;
+use crate::errors::BilboError;
+use crate::signature::{base64url_decode, base64url_encode};
+
+// Media type of the signed report claims in the JWS header.
+const REPORT_TYPE: &str = "bilbo-report+jws";
+
+/// JwsAlgorithm is the JWS algorithm of the assessor key signing the reports.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JwsAlgorithm {
+    RS256,
+    ES256,
+    ES384,
+    EdDSA,
+}
+
+impl Display for JwsAlgorithm {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                JwsAlgorithm::RS256 => "RS256",
+                JwsAlgorithm::ES256 => "ES256",
+                JwsAlgorithm::ES384 => "ES384",
+                JwsAlgorithm::EdDSA => "EdDSA",
+            }
+        )
+    }
+}
+
+impl JwsAlgorithm {
+    /// Returns the algorithm signing with the key: RSA, EC on P-256 or P-384, or Ed25519.
+    ///
+    #[inline(always)]
+    pub fn of_key<T: HasPublic>(key: &PKeyRef<T>) -> Result<Self, BilboError> {
+        match key.id() {
+            Id::RSA => Ok(JwsAlgorithm::RS256),
+            Id::ED25519 => Ok(JwsAlgorithm::EdDSA),
+            Id::EC => match key.ec_key()?.group().curve_name() {
+                Some(Nid::X9_62_PRIME256V1) => Ok(JwsAlgorithm::ES256),
+                Some(Nid::SECP384R1) => Ok(JwsAlgorithm::ES384),
+                _ => Err(BilboError::GenericError(
+                    "EC report signing key shall be on P-256 or P-384 curve".to_string(),
+                )),
+            },
+            _ => Err(BilboError::GenericError(
+                "report signing key shall be RSA, EC or Ed25519".to_string(),
+            )),
+        }
+    }
+
+    // Digest of the signing input, Ed25519 hashes it itself.
+    #[inline(always)]
+    fn digest(&self) -> Option<MessageDigest> {
+        match self {
+            JwsAlgorithm::RS256 | JwsAlgorithm::ES256 => Some(MessageDigest::sha256()),
+            JwsAlgorithm::ES384 => Some(MessageDigest::sha384()),
+            JwsAlgorithm::EdDSA => None,
+        }
+    }
+
+    // Length of r and s of raw JWS ECDSA signature.
+    #[inline(always)]
+    fn ec_scalar_len(&self) -> Option<i32> {
+        match self {
+            JwsAlgorithm::ES256 => Some(32),
+            JwsAlgorithm::ES384 => Some(48),
+            _ => None,
+        }
+    }
+}
+
+/// JwsHeader is the protected header of the signed report, the key id is SHA-256 fingerprint of the public key.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JwsHeader {
+    pub alg: JwsAlgorithm,
+    pub typ: String,
+    pub kid: String,
+}
+
+/// ReportClaims is the signed payload, the digest of the report content and the time it was signed.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReportClaims {
+    pub report: String,
+    pub sha256: String,
+    pub signed_at: String,
+}
+
+impl Display for ReportClaims {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} with SHA-256 {} signed at {}",
+            self.report, self.sha256, self.signed_at
+        )
+    }
+}
+
+/// ReportSigner signs the reports with the assessor key as detached compact JWS,
+/// so recipients holding the assessor public key can verify the report wasn't modified.
+///
+pub struct ReportSigner {
+    key: PKey<Private>,
+    alg: JwsAlgorithm,
+}
+
+impl ReportSigner {
+    /// Creates the signer of PEM encoded private key, RSA, EC on P-256 or P-384, or Ed25519.
+    ///
+    #[inline(always)]
+    pub fn from_pem(pem: &[u8]) -> Result<Self, BilboError> {
+        let key = PKey::private_key_from_pem(pem)?;
+        let alg = JwsAlgorithm::of_key(&key)?;
+
+        Ok(Self { key, alg })
+    }
+
+    /// Returns the algorithm of the signatures.
+    ///
+    #[inline(always)]
+    pub fn algorithm(&self) -> JwsAlgorithm {
+        self.alg
+    }
+
+    /// Signs the content of the named report now and returns compact JWS of its claims.
+    ///
+    pub fn sign(&self, report: &str, content: &[u8]) -> Result<String, BilboError> {
+        let header = JwsHeader {
+            alg: self.alg,
+            typ: REPORT_TYPE.to_string(),
+            kid: key_id(&self.key)?,
+        };
+        let claims = ReportClaims {
+            report: report.to_string(),
+            sha256: hex(&sha256(content)),
+            signed_at: rfc3339(SystemTime::now()),
+        };
+        let input = format!(
+            "{}.{}",
+            base64url_encode(&serde_json::to_vec(&header)?),
+            base64url_encode(&serde_json::to_vec(&claims)?)
+        );
+        let mut signer = match self.alg.digest() {
+            Some(digest) => Signer::new(digest, &self.key)?,
+            None => Signer::new_without_digest(&self.key)?,
+        };
+        let mut signature = signer.sign_oneshot_to_vec(input.as_bytes())?;
+        if let Some(len) = self.alg.ec_scalar_len() {
+            let sig = EcdsaSig::from_der(&signature)?;
+            signature = sig.r().to_vec_padded(len)?;
+            signature.extend(sig.s().to_vec_padded(len)?);
+        }
+
+        Ok(format!("{input}.{}", base64url_encode(&signature)))
+    }
+}
+
+/// Verifies the compact JWS of the report signed by the holder of the public key
+/// and returns the signed claims if the content is the one signed.
+///
+pub fn verify_report(
+    content: &[u8],
+    jws: &str,
+    key: &PKeyRef<Public>,
+) -> Result<ReportClaims, BilboError> {
+    let not_compact =
+        || BilboError::GenericError("report signature is not a compact JWS".to_string());
+    let (input, signature) = jws.trim().rsplit_once('.').ok_or_else(not_compact)?;
+    let (header, claims) = input.split_once('.').ok_or_else(not_compact)?;
+    if claims.contains('.') {
+        return Err(not_compact());
+    }
+    let header: JwsHeader = serde_json::from_slice(&base64url_decode(header)?)?;
+    let alg = JwsAlgorithm::of_key(key)?;
+    if header.alg != alg || header.typ != REPORT_TYPE {
+        return Err(BilboError::GenericError(format!(
+            "report signature is {} {}, expected {alg} {REPORT_TYPE}",
+            header.alg, header.typ
+        )));
+    }
+    let mut signature = base64url_decode(signature)?;
+    if let Some(len) = alg.ec_scalar_len() {
+        if signature.len() != 2 * len as usize {
+            return Err(BilboError::GenericError(format!(
+                "{alg} report signature shall be {} bytes long",
+                2 * len
+            )));
+        }
+        let (r, s) = signature.split_at(len as usize);
+        signature =
+            EcdsaSig::from_private_components(BigNum::from_slice(r)?, BigNum::from_slice(s)?)?
+                .to_der()?;
+    }
+    let mut verifier = match alg.digest() {
+        Some(digest) => Verifier::new(digest, key)?,
+        None => Verifier::new_without_digest(key)?,
+    };
+    if !verifier.verify_oneshot(&signature, input.as_bytes())? {
+        return Err(BilboError::GenericError(
+            "report signature doesn't verify with the key".to_string(),
+        ));
+    }
+    let claims: ReportClaims = serde_json::from_slice(&base64url_decode(claims)?)?;
+    if claims.sha256 != hex(&sha256(content)) {
+        return Err(BilboError::GenericError(format!(
+            "{} was modified after it was signed at {}",
+            claims.report, claims.signed_at
+        )));
+    }
+
+    Ok(claims)
+}
+
+// Key id is SHA-256 of DER encoded subject public key info.
+#[inline(always)]
+fn key_id<T: HasPublic>(key: &PKeyRef<T>) -> Result<String, BilboError> {
+    Ok(hex(&sha256(&key.public_key_to_der()?)))
+}
+
+#[inline(always)]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::rsa::Rsa;
+
+    #[test]
+    fn it_should_sign_reports_and_detect_modifications() -> Result<(), BilboError> {
+        let (p256, p384) = (
+            EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?,
+            EcGroup::from_curve_name(Nid::SECP384R1)?,
+        );
+        let keys = [
+            PKey::from_rsa(Rsa::generate(2048)?)?,
+            PKey::from_ec_key(EcKey::generate(&p256)?)?,
+            PKey::from_ec_key(EcKey::generate(&p384)?)?,
+            PKey::generate_ed25519()?,
+        ];
+        let expected = [
+            JwsAlgorithm::RS256,
+            JwsAlgorithm::ES256,
+            JwsAlgorithm::ES384,
+            JwsAlgorithm::EdDSA,
+        ];
+        let report = b"{\"event\":\"finding\"}\n";
+        for (key, alg) in keys.iter().zip(expected) {
+            let signer = ReportSigner::from_pem(&key.private_key_to_pem_pkcs8()?)?;
+            assert_eq!(signer.algorithm(), alg);
+            let jws = signer.sign("audit.jsonl", report)?;
+            let public = PKey::public_key_from_pem(&key.public_key_to_pem()?)?;
+
+            let claims = verify_report(report, &jws, &public)?;
+            assert_eq!(claims.report, "audit.jsonl");
+            assert_eq!(claims.sha256, hex(&sha256(report)));
+            assert!(verify_report(b"{}\n", &jws, &public).is_err());
+        }
+
+        // Signature of other assessor or tampered claims don't verify.
+        let signer = ReportSigner::from_pem(&keys[1].private_key_to_pem_pkcs8()?)?;
+        let jws = signer.sign("audit.jsonl", report)?;
+        let other = EcKey::generate(&p256)?;
+        let other = PKey::public_key_from_pem(&PKey::from_ec_key(other)?.public_key_to_pem()?)?;
+        assert!(verify_report(report, &jws, &other).is_err());
+        let segments: Vec<&str> = jws.split('.').collect();
+        let forged = ReportClaims {
+            report: "audit.jsonl".to_string(),
+            sha256: hex(&sha256(b"{}\n")),
+            signed_at: "2024-01-01T00:00:00.000Z".to_string(),
+        };
+        let forged = format!(
+            "{}.{}.{}",
+            segments[0],
+            base64url_encode(&serde_json::to_vec(&forged)?),
+            segments[2]
+        );
+        let public = PKey::public_key_from_pem(&keys[1].public_key_to_pem()?)?;
+        assert!(verify_report(b"{}\n", &forged, &public).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,446 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::errors::BilboError;
+use crate::impact::EvidenceBundle;
+use crate::net::{DualStackReport, Resolver};
+use crate::revocation::Revocation;
+use crate::rsa::PickLock;
+use crate::schema::{upgrade, SCHEMA_VERSION};
+use crate::suppress::Suppression;
+use crate::telemetry::Telemetry;
+
+const SECONDS_IN_DAY: u64 = 86_400;
+
+/// AuditEvent describes a single operation performed by bilbo.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    KeyLoaded {
+        source: String,
+        key: String,
+        bits: u64,
+    },
+    AttackStarted {
+        attack: String,
+        key: String,
+    },
+    AttackFinished {
+        attack: String,
+        key: String,
+        success: bool,
+        elapsed_ms: u64,
+        #[serde(default)]
+        telemetry: Telemetry,
+    },
+    Finding {
+        attack: String,
+        key: String,
+        detail: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        evidence: Option<EvidenceBundle>,
+    },
+    FindingSuppressed {
+        attack: String,
+        key: String,
+        detail: String,
+        reason: String,
+    },
+    HostResolved {
+        host: String,
+        resolver: String,
+        addrs: Vec<IpAddr>,
+    },
+    HostProbed {
+        host: String,
+        port: u16,
+        dual_stack: DualStackReport,
+    },
+    RevocationChecked {
+        key: String,
+        certificate: String,
+        revocation: Revocation,
+    },
+}
+
+/// AuditRecord is a single line of the audit log, an event with the time it occurred.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub schema_version: u32,
+    pub timestamp: String,
+    #[serde(flatten)]
+    pub event: AuditEvent,
+}
+
+/// AuditLog is a sink writing every recorded event as a single JSON line.
+///
+/// Keys are identified by the SHA-256 of the modulus, so records of the same key
+/// can be correlated across runs regardless of the file the key was loaded from.
+/// Identical findings, the same detail of the same key produced by different attacks, are recorded once.
+///
+pub struct AuditLog {
+    out: Mutex<Box<dyn Write + Send>>,
+    reported: Mutex<BTreeSet<(String, String)>>,
+}
+
+impl AuditLog {
+    /// Creates new audit log writing to given output.
+    ///
+    #[inline(always)]
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out: Mutex::new(out),
+            reported: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Creates new audit log appending to the file at given path, the file is created if missing.
+    ///
+    #[inline(always)]
+    pub fn to_file(path: &Path) -> Result<Self, BilboError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(Box::new(file)))
+    }
+
+    /// Records given event with the current time.
+    ///
+    #[inline(always)]
+    pub fn record(&self, event: AuditEvent) -> Result<(), BilboError> {
+        let record = AuditRecord {
+            schema_version: SCHEMA_VERSION,
+            timestamp: rfc3339(SystemTime::now()),
+            event,
+        };
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        let mut out = self
+            .out
+            .lock()
+            .map_err(|e| BilboError::GenericError(format!("audit log lock poisoned: {e}")))?;
+        out.write_all(line.as_bytes())?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Records that the key was loaded from given source.
+    ///
+    #[inline(always)]
+    pub fn key_loaded(&self, source: &str, pl: &PickLock) -> Result<(), BilboError> {
+        self.record(AuditEvent::KeyLoaded {
+            source: source.to_string(),
+            key: pl.modulus_fingerprint(),
+            bits: pl.bits(),
+        })
+    }
+
+    /// Records that the attack against the key has started.
+    ///
+    #[inline(always)]
+    pub fn attack_started(&self, attack: &str, pl: &PickLock) -> Result<(), BilboError> {
+        self.record(AuditEvent::AttackStarted {
+            attack: attack.to_string(),
+            key: pl.modulus_fingerprint(),
+        })
+    }
+
+    /// Records that the attack against the key has finished, with the telemetry of the last attack run by the PickLock.
+    ///
+    #[inline(always)]
+    pub fn attack_finished(
+        &self,
+        attack: &str,
+        pl: &PickLock,
+        success: bool,
+        elapsed: Duration,
+    ) -> Result<(), BilboError> {
+        self.record(AuditEvent::AttackFinished {
+            attack: attack.to_string(),
+            key: pl.modulus_fingerprint(),
+            success,
+            elapsed_ms: elapsed.as_millis() as u64,
+            telemetry: pl.last_telemetry(),
+        })
+    }
+
+    /// Records the finding produced by the attack against the key.
+    ///
+    #[inline(always)]
+    pub fn finding(&self, attack: &str, pl: &PickLock, detail: &str) -> Result<(), BilboError> {
+        self.record_finding(AuditEvent::Finding {
+            attack: attack.to_string(),
+            key: pl.modulus_fingerprint(),
+            detail: detail.to_string(),
+            evidence: None,
+        })
+    }
+
+    /// Records the finding produced by the attack against the key with reproducible evidence.
+    ///
+    #[inline(always)]
+    pub fn finding_with_evidence(
+        &self,
+        attack: &str,
+        pl: &PickLock,
+        detail: &str,
+        evidence: EvidenceBundle,
+    ) -> Result<(), BilboError> {
+        self.record_finding(AuditEvent::Finding {
+            attack: attack.to_string(),
+            key: pl.modulus_fingerprint(),
+            detail: detail.to_string(),
+            evidence: Some(evidence),
+        })
+    }
+
+    /// Records the finding produced by the attack against the key that the allowlist suppressed.
+    ///
+    #[inline(always)]
+    pub fn finding_suppressed(
+        &self,
+        attack: &str,
+        pl: &PickLock,
+        detail: &str,
+        suppression: &Suppression,
+    ) -> Result<(), BilboError> {
+        self.record(AuditEvent::FindingSuppressed {
+            attack: attack.to_string(),
+            key: pl.modulus_fingerprint(),
+            detail: detail.to_string(),
+            reason: suppression.reason.clone(),
+        })
+    }
+
+    /// Records addresses the host was resolved to by given resolver.
+    ///
+    #[inline(always)]
+    pub fn host_resolved(
+        &self,
+        host: &str,
+        resolver: &Resolver,
+        addrs: &[IpAddr],
+    ) -> Result<(), BilboError> {
+        self.record(AuditEvent::HostResolved {
+            host: host.to_string(),
+            resolver: resolver.to_string(),
+            addrs: addrs.to_vec(),
+        })
+    }
+
+    /// Records the outcome of connections to every address of the host, per address family.
+    ///
+    #[inline(always)]
+    pub fn host_probed(
+        &self,
+        host: &str,
+        port: u16,
+        dual_stack: &DualStackReport,
+    ) -> Result<(), BilboError> {
+        self.record(AuditEvent::HostProbed {
+            host: host.to_string(),
+            port,
+            dual_stack: dual_stack.clone(),
+        })
+    }
+
+    /// Records whether the issuer revoked the certificate of the weak key.
+    ///
+    #[inline(always)]
+    pub fn revocation_checked(
+        &self,
+        pl: &PickLock,
+        certificate: &str,
+        revocation: &Revocation,
+    ) -> Result<(), BilboError> {
+        self.record(AuditEvent::RevocationChecked {
+            key: pl.modulus_fingerprint(),
+            certificate: certificate.to_string(),
+            revocation: revocation.clone(),
+        })
+    }
+
+    // Records the finding unless the same detail of the same key was already recorded.
+    #[inline(always)]
+    fn record_finding(&self, event: AuditEvent) -> Result<(), BilboError> {
+        let AuditEvent::Finding { key, detail, .. } = &event else {
+            return self.record(event);
+        };
+        let fresh = self
+            .reported
+            .lock()
+            .map_err(|e| BilboError::GenericError(format!("audit log lock poisoned: {e}")))?
+            .insert((key.clone(), detail.clone()));
+        if !fresh {
+            return Ok(());
+        }
+        self.record(event)
+    }
+}
+
+/// Reads audit log records written by this or any older version of bilbo.
+/// Empty lines are skipped.
+///
+#[inline(always)]
+pub fn read_records(reader: impl BufRead) -> Result<Vec<AuditRecord>, BilboError> {
+    let mut records = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let doc = upgrade(serde_json::from_str(&line)?)?;
+        records.push(serde_json::from_value(doc)?);
+    }
+
+    Ok(records)
+}
+
+/// Formats given time as RFC 3339 UTC timestamp with millisecond precision.
+///
+#[inline(always)]
+pub fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / SECONDS_IN_DAY) as i64);
+    let rest = secs % SECONDS_IN_DAY;
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// Converts days since unix epoch to the proleptic Gregorian calendar date,
+// based on http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+#[inline(always)]
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::{BigInt, Sign};
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_read_records_written_before_schema_versioning() -> Result<(), BilboError> {
+        let given = r#"{"timestamp":"2024-01-01T00:00:00.000Z","event":"attack_started","attack":"weak","key":"ab"}
+
+{"schema_version":1,"timestamp":"2024-01-01T00:00:01.000Z","event":"finding","attack":"weak","key":"ab","detail":"d"}
+"#;
+        let records = read_records(given.as_bytes())?;
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.schema_version == SCHEMA_VERSION));
+        assert_eq!(
+            records[0].event,
+            AuditEvent::AttackStarted {
+                attack: "weak".to_string(),
+                key: "ab".to_string(),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_format_rfc3339_timestamps() {
+        let given: [u64; 3] = [0, 951_782_400, 1_700_000_000];
+        let expected: [&str; 3] = [
+            "1970-01-01T00:00:00.000Z",
+            "2000-02-29T00:00:00.000Z",
+            "2023-11-14T22:13:20.000Z",
+        ];
+        for (g, e) in given.iter().zip(expected) {
+            assert_eq!(rfc3339(UNIX_EPOCH + Duration::from_secs(*g)), e);
+        }
+    }
+
+    #[test]
+    fn it_should_write_one_json_line_per_event() -> Result<(), BilboError> {
+        let buf = Buffer::default();
+        let log = AuditLog::new(Box::new(buf.clone()));
+        let pl = PickLock::from_exponent_and_modulus(
+            BigInt::new(Sign::Plus, vec![65537]),
+            BigInt::new(Sign::Plus, vec![63648259]),
+        );
+
+        log.key_loaded("test", &pl)?;
+        log.attack_started("weak", &pl)?;
+        log.finding("weak", &pl, "private exponent recovered")?;
+        log.finding("strong", &pl, "private exponent recovered")?;
+        pl.try_lock_pick_weak_private()?;
+        log.attack_finished("weak", &pl, true, Duration::from_millis(3))?;
+
+        let out = buf.0.lock().unwrap().clone();
+        let records = read_records(out.as_slice())?;
+        assert_eq!(
+            records.len(),
+            4,
+            "identical finding of the strong attack is recorded once"
+        );
+        assert_eq!(records[0].schema_version, SCHEMA_VERSION);
+        assert_eq!(
+            records[0].event,
+            AuditEvent::KeyLoaded {
+                source: "test".to_string(),
+                key: pl.modulus_fingerprint(),
+                bits: 26,
+            }
+        );
+        assert_eq!(
+            records[3].event,
+            AuditEvent::AttackFinished {
+                attack: "weak".to_string(),
+                key: pl.modulus_fingerprint(),
+                success: true,
+                elapsed_ms: 3,
+                telemetry: pl.last_telemetry(),
+            }
+        );
+        assert!(pl.last_telemetry().iterations > 0);
+
+        log.host_probed("example.com", 443, &DualStackReport::default())?;
+        let out = buf.0.lock().unwrap().clone();
+        let line = String::from_utf8_lossy(&out)
+            .lines()
+            .last()
+            .unwrap()
+            .to_string();
+        assert!(line.contains(r#""event":"host_probed","host":"example.com","port":443"#));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+use std::time::Instant;
+
+use crate::errors::BilboError;
+use crate::rsa::PickLock;
+use crate::telemetry::Telemetry;
+
+/// Returns the product tree of the values, the values are the first level and the root, their product, the last.
+/// Odd node of a level is carried to the next level as is.
+///
+pub fn product_tree(values: &[BigInt]) -> Vec<Vec<BigInt>> {
+    let mut tree = vec![values.to_vec()];
+    while tree[tree.len() - 1].len() > 1 {
+        let level = tree[tree.len() - 1]
+            .chunks(2)
+            .map(|pair| pair.iter().product())
+            .collect();
+        tree.push(level);
+    }
+
+    tree
+}
+
+/// Returns gcd of every modulus with the product of all the other moduli, Bernstein's batch gcd.
+/// The product tree is walked back down as the remainder tree of P mod n², so gcd(n, P / n) = gcd(n, (P mod n²) / n)
+/// costs quasi linear time in the total size of the moduli instead of the quadratic pairwise gcds.
+///
+/// The gcd is 1 for the modulus sharing no prime, a prime for the one sharing a prime
+/// and the modulus itself for the one sharing both primes, duplicate moduli or each prime with another key.
+///
+pub fn batch_gcd(moduli: &[BigInt], telemetry: &mut Telemetry) -> Vec<BigInt> {
+    if moduli.is_empty() {
+        return Vec::new();
+    }
+    let tree = product_tree(moduli);
+    let product = tree[tree.len() - 1][0].clone();
+    remainder_gcds(moduli, &tree, &product, telemetry)
+}
+
+/// Returns gcd of every modulus of the chunk with the product of all the other moduli of the set the chunk is part of,
+/// given the product of the whole set, the batch gcd of the chunk against the set. Chunks of a set covering it
+/// give the batch gcd of the set, each on its own machine, at the cost of the product of the set sent to every one.
+///
+pub fn batch_gcd_of_chunk(
+    chunk: &[BigInt],
+    product: &BigInt,
+    telemetry: &mut Telemetry,
+) -> Vec<BigInt> {
+    if chunk.is_empty() {
+        return Vec::new();
+    }
+    remainder_gcds(chunk, &product_tree(chunk), product, telemetry)
+}
+
+// Walks the product tree of the moduli down as the remainder tree of the product mod n², the product being
+// the root of the tree or the product of a larger set of moduli divisible by the root.
+fn remainder_gcds(
+    moduli: &[BigInt],
+    tree: &[Vec<BigInt>],
+    product: &BigInt,
+    telemetry: &mut Telemetry,
+) -> Vec<BigInt> {
+    let root = &tree[tree.len() - 1][0];
+    let mut remainders = vec![product % (root * root)];
+    for level in tree.iter().rev().skip(1) {
+        remainders = level
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                telemetry.iterations += 1;
+                &remainders[i / 2] % (node * node)
+            })
+            .collect();
+    }
+
+    moduli
+        .iter()
+        .zip(remainders)
+        .map(|(n, remainder)| (remainder / n).gcd(n))
+        .collect()
+}
+
+/// BatchPickLock attacks many keys at once by the primes they share, as generated by devices with poor entropy at boot.
+/// A single batch gcd over the whole set finds every shared prime, thousands of keys take seconds.
+///
+pub struct BatchPickLock {
+    locks: Vec<PickLock>,
+}
+
+impl BatchPickLock {
+    /// Creates a new BatchPickLock attacking all the given locks.
+    ///
+    #[inline(always)]
+    pub fn new(locks: Vec<PickLock>) -> Self {
+        Self { locks }
+    }
+
+    /// Returns the attacked locks in the order they were given.
+    ///
+    #[inline(always)]
+    pub fn locks(&self) -> &[PickLock] {
+        &self.locks
+    }
+
+    /// Attempts to lock pick all the keys by the primes they share with the others.
+    /// Keys whose batch gcd is the modulus itself share both primes, they are split by pairwise gcds
+    /// with the others, only duplicate moduli stay locked.
+    /// Returns result for every lock in the order they were given.
+    /// Every lock records the telemetry of the whole batch.
+    ///
+    pub fn try_lock_pick_shared_primes(&self) -> Vec<Result<BigInt, BilboError>> {
+        let start = Instant::now();
+        let mut telemetry = Telemetry::default();
+        let moduli: Vec<BigInt> = self.locks.iter().map(|l| l.modulus().clone()).collect();
+        let gcds = batch_gcd(&moduli, &mut telemetry);
+        let one = BigInt::from(1);
+
+        let mut values = Vec::with_capacity(gcds.len());
+        for (i, (n, g)) in moduli.iter().zip(gcds).enumerate() {
+            if &g != n {
+                values.push(g);
+                continue;
+            }
+            let shared = moduli
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, other)| {
+                    telemetry.iterations += 1;
+                    n.gcd(other)
+                })
+                .find(|g| g != &one && g != n);
+            values.push(shared.unwrap_or(g));
+        }
+        telemetry.finish(start);
+
+        self.locks
+            .iter()
+            .zip(values)
+            .map(|(lock, value)| {
+                let result = if value == BigInt::from(1) {
+                    Err(BilboError::GenericError(format!(
+                        "modulus {} shares no prime with the other {} moduli",
+                        lock.modulus_fingerprint(),
+                        moduli.len() - 1
+                    )))
+                } else if &value == lock.modulus() {
+                    Err(BilboError::GenericError(format!(
+                        "modulus {} is duplicated, the key is shared but its primes stay unknown",
+                        lock.modulus_fingerprint()
+                    )))
+                } else {
+                    lock.try_lock_pick_auxiliary_gcd(&[value])
+                };
+                lock.record_telemetry(telemetry);
+
+                result
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::DExponentConvention;
+    use num_bigint::Sign;
+
+    #[test]
+    fn it_should_crack_keys_sharing_primes_in_a_batch() -> Result<(), BilboError> {
+        let primes = [
+            1_000_003_u64,
+            1_000_033,
+            1_000_037,
+            1_000_039,
+            1_000_081,
+            1_000_099,
+        ]
+        .map(BigInt::from);
+        let moduli = [
+            &primes[0] * &primes[1],
+            &primes[1] * &primes[2],
+            &primes[3] * &primes[4],
+            &primes[0] * &primes[2],
+            &primes[3] * &primes[4],
+            &primes[5] * BigInt::from(1_000_117_u64),
+        ];
+        let tree = product_tree(&moduli);
+        assert_eq!(tree.len(), 4);
+        assert_eq!(tree[3][0], moduli.iter().product::<BigInt>());
+
+        let gcds = batch_gcd(&moduli, &mut Telemetry::default());
+        assert_eq!(gcds[0], moduli[0]);
+        assert_eq!(gcds[2], moduli[2]);
+        assert_eq!(gcds[5], BigInt::from(1));
+        let chunks: Vec<BigInt> = moduli
+            .chunks(4)
+            .flat_map(|chunk| batch_gcd_of_chunk(chunk, &tree[3][0], &mut Telemetry::default()))
+            .collect();
+        assert_eq!(chunks, gcds);
+
+        let e = BigInt::new(Sign::Plus, vec![65537]);
+        let batch = BatchPickLock::new(
+            moduli
+                .iter()
+                .map(|n| PickLock::from_exponent_and_modulus(e.clone(), n.clone()))
+                .collect(),
+        );
+        let results = batch.try_lock_pick_shared_primes();
+        assert!(results[0].is_ok() && results[1].is_ok() && results[3].is_ok());
+        assert!(results[2].is_err() && results[4].is_err() && results[5].is_err());
+        let lambda = DExponentConvention::Lambda.modulus(&primes[0], &primes[1]);
+        assert_eq!(
+            (&e * results[0].as_ref().unwrap()) % &lambda,
+            BigInt::from(1)
+        );
+        assert!(batch.locks()[0].last_telemetry().iterations > 0);
+        assert!(batch_gcd(&[], &mut Telemetry::default()).is_empty());
+
+        Ok(())
+    }
+}
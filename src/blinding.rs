@@ -0,0 +1,191 @@
+use num_bigint::{BigInt, RandBigInt};
+use num_integer::Integer;
+
+use crate::errors::BilboError;
+
+/// BlindingFactor is the random multiplier s hiding a ciphertext from the oracle.
+/// Blinded ciphertext c·s^e decrypts to m·s, and signs to m^d·s for signing oracles,
+/// so the result is unblinded by multiplying with s^-1 in both cases.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindingFactor {
+    pub s: BigInt,
+    s_e: BigInt,
+    s_inv: BigInt,
+}
+
+impl BlindingFactor {
+    /// Creates blinding factor s for the public key (e, n), s shall be invertible modulo n.
+    ///
+    #[inline(always)]
+    pub fn new(s: BigInt, e: &BigInt, n: &BigInt) -> Result<Self, BilboError> {
+        let s = s.mod_floor(n);
+        let Some(s_inv) = s.modinv(n) else {
+            return Err(BilboError::GenericError(format!(
+                "blinding factor {s} is not invertible modulo n, gcd with n is {}",
+                s.gcd(n)
+            )));
+        };
+
+        Ok(Self {
+            s_e: s.modpow(e, n),
+            s,
+            s_inv,
+        })
+    }
+
+    /// Creates random blinding factor for the public key (e, n).
+    ///
+    #[inline(always)]
+    pub fn random(e: &BigInt, n: &BigInt) -> Result<Self, BilboError> {
+        let mut rng = rand::thread_rng();
+        let two = BigInt::from(2);
+        if n <= &two {
+            return Err(BilboError::GenericError(format!(
+                "modulus {n} is too small to blind"
+            )));
+        }
+        loop {
+            // Non invertible s reveals a factor of n, retrying is only reached for toy moduli.
+            if let Ok(factor) = Self::new(rng.gen_bigint_range(&two, n), e, n) {
+                return Ok(factor);
+            }
+        }
+    }
+
+    /// Blinds the ciphertext, returns c·s^e mod n.
+    ///
+    #[inline(always)]
+    pub fn blind(&self, c: &BigInt, n: &BigInt) -> BigInt {
+        (c * &self.s_e).mod_floor(n)
+    }
+
+    /// Unblinds the oracle answer to the blinded ciphertext, returns m·s^-1 mod n.
+    ///
+    #[inline(always)]
+    pub fn unblind(&self, m: &BigInt, n: &BigInt) -> BigInt {
+        (m * &self.s_inv).mod_floor(n)
+    }
+}
+
+/// Blinder crafts blinded and multiplicatively related ciphertexts for the public key (e, n)
+/// and keeps the blinding factor of each, identified by the returned id, to unblind the oracle answers.
+///
+#[derive(Debug, Clone)]
+pub struct Blinder {
+    e: BigInt,
+    n: BigInt,
+    factors: Vec<BlindingFactor>,
+}
+
+impl Blinder {
+    /// Creates new Blinder for the public key (e, n).
+    ///
+    #[inline(always)]
+    pub fn new(e: BigInt, n: BigInt) -> Self {
+        Self {
+            e,
+            n,
+            factors: Vec::new(),
+        }
+    }
+
+    /// Blinds the ciphertext with new random factor, returns id of the factor and the blinded ciphertext.
+    ///
+    #[inline(always)]
+    pub fn blind(&mut self, c: &BigInt) -> Result<(usize, BigInt), BilboError> {
+        let factor = BlindingFactor::random(&self.e, &self.n)?;
+        Ok(self.keep(c, factor))
+    }
+
+    /// Blinds the ciphertext with chosen factor s, returns id of the factor and the blinded ciphertext.
+    ///
+    #[inline(always)]
+    pub fn blind_with(&mut self, c: &BigInt, s: BigInt) -> Result<(usize, BigInt), BilboError> {
+        let factor = BlindingFactor::new(s, &self.e, &self.n)?;
+        Ok(self.keep(c, factor))
+    }
+
+    /// Crafts ciphertexts c·s^e related to c by every given multiplier s, as used by oracle attacks
+    /// that search for a multiplier producing conforming plaintext m·s.
+    ///
+    #[inline(always)]
+    pub fn related(
+        &mut self,
+        c: &BigInt,
+        multipliers: &[BigInt],
+    ) -> Result<Vec<(usize, BigInt)>, BilboError> {
+        multipliers
+            .iter()
+            .map(|s| self.blind_with(c, s.clone()))
+            .collect()
+    }
+
+    /// Unblinds the oracle answer to the ciphertext blinded by the factor of given id.
+    ///
+    #[inline(always)]
+    pub fn unblind(&self, id: usize, m: &BigInt) -> Result<BigInt, BilboError> {
+        let Some(factor) = self.factors.get(id) else {
+            return Err(BilboError::GenericError(format!(
+                "unknown blinding factor id {id}, {} factors kept",
+                self.factors.len()
+            )));
+        };
+
+        Ok(factor.unblind(m, &self.n))
+    }
+
+    /// Returns the blinding factor of given id.
+    ///
+    #[inline(always)]
+    pub fn factor(&self, id: usize) -> Option<&BlindingFactor> {
+        self.factors.get(id)
+    }
+
+    /// Forgets all the kept blinding factors.
+    ///
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.factors.clear();
+    }
+
+    #[inline(always)]
+    fn keep(&mut self, c: &BigInt, factor: BlindingFactor) -> (usize, BigInt) {
+        let blinded = factor.blind(c, &self.n);
+        self.factors.push(factor);
+        (self.factors.len() - 1, blinded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_blind_and_unblind_through_decryption_oracle() -> Result<(), BilboError> {
+        let (p, q) = (BigInt::from(1_000_003), BigInt::from(999_983));
+        let n = &p * &q;
+        let e = BigInt::from(65537);
+        let d = e.modinv(&((&p - 1) * (&q - 1))).unwrap();
+        let oracle = |c: &BigInt| c.modpow(&d, &n);
+        let m = BigInt::from(424_242);
+        let c = m.modpow(&e, &n);
+
+        let mut blinder = Blinder::new(e.clone(), n.clone());
+        let (id, blinded) = blinder.blind(&c)?;
+        assert_ne!(blinded, c);
+        assert_eq!(blinder.unblind(id, &oracle(&blinded))?, m);
+
+        let related = blinder.related(&c, &[BigInt::from(2), BigInt::from(3)])?;
+        assert_eq!(oracle(&related[0].1), (&m * 2_u32) % &n);
+        assert_eq!(blinder.unblind(related[1].0, &oracle(&related[1].1))?, m);
+        assert_eq!(blinder.factor(related[1].0).unwrap().s, BigInt::from(3));
+
+        assert!(blinder.blind_with(&c, p.clone()).is_err());
+        assert!(blinder.unblind(7, &m).is_err());
+        blinder.clear();
+        assert!(blinder.factor(0).is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::progress::{ProgressEvent, ProgressSink};
+
+// Outcome of the work, the panic payload if it panicked, and the waker of the task awaiting it.
+type Slot<T> = Mutex<(Option<Result<T, Box<dyn Any + Send>>>, Option<Waker>)>;
+
+/// Blocking is the future of CPU bound work running on a thread of its own, off the async reactor,
+/// so bilbo is awaited from async services without stalling their executor whatever runtime they use.
+/// The work runs to the end even if the future is dropped, PickLock::alter_cancel stops it early.
+/// A panic of the work is resumed in the task awaiting it.
+///
+pub struct Blocking<T> {
+    shared: Arc<Slot<T>>,
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    /// Starts the work on a new thread and returns the future of its result.
+    ///
+    #[inline(always)]
+    pub fn spawn(work: impl FnOnce() -> T + Send + 'static) -> Self {
+        let shared: Arc<Slot<T>> = Arc::new(Mutex::new((None, None)));
+        let done = shared.clone();
+        thread::spawn(move || {
+            let outcome = catch_unwind(AssertUnwindSafe(work));
+            if let Ok(mut done) = done.lock() {
+                done.0 = Some(outcome);
+                if let Some(waker) = done.1.take() {
+                    waker.wake();
+                }
+            }
+        });
+
+        Self { shared }
+    }
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut shared = match self.shared.lock() {
+            Ok(shared) => shared,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match shared.0.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => resume_unwind(panic),
+            None => {
+                shared.1 = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    events: VecDeque<ProgressEvent>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// ProgressStream yields the progress events of the attack running on the blocking thread,
+/// it ends once the attack finished. poll_next has the signature of the Stream trait of the futures crate,
+/// wrap it with futures::stream::poll_fn to use the stream combinators.
+///
+pub struct ProgressStream {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl ProgressStream {
+    /// Creates the stream and the sink the attack sends its progress to.
+    ///
+    #[inline(always)]
+    pub fn channel() -> (StreamSink, Self) {
+        let queue = Arc::new(Mutex::new(Queue::default()));
+        (
+            StreamSink {
+                queue: queue.clone(),
+            },
+            Self { queue },
+        )
+    }
+
+    /// Polls the next event, None once the attack finished and all the events were taken.
+    ///
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<ProgressEvent>> {
+        let mut queue = match self.queue.lock() {
+            Ok(queue) => queue,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Some(event) = queue.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if queue.closed {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    /// Awaits the next event, None once the attack finished and all the events were taken.
+    ///
+    #[inline(always)]
+    pub async fn next(&mut self) -> Option<ProgressEvent> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+}
+
+/// StreamSink queues the progress events for the ProgressStream, dropping it ends the stream.
+///
+pub struct StreamSink {
+    queue: Arc<Mutex<Queue>>,
+}
+
+impl StreamSink {
+    #[inline(always)]
+    fn update(&self, update: impl FnOnce(&mut Queue)) {
+        if let Ok(mut queue) = self.queue.lock() {
+            update(&mut queue);
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl ProgressSink for StreamSink {
+    #[inline(always)]
+    fn progress(&mut self, event: ProgressEvent) {
+        self.update(|queue| queue.events.push_back(event));
+    }
+}
+
+impl Drop for StreamSink {
+    #[inline(always)]
+    fn drop(&mut self) {
+        self.update(|queue| queue.closed = true);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    // Minimal executor of the tests, parks the thread until the future is woken.
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn it_should_await_blocking_work_and_its_progress() {
+        let (mut sink, mut stream) = ProgressStream::channel();
+        let work = Blocking::spawn(move || {
+            for total in 1..=3 {
+                sink.progress(ProgressEvent::Started {
+                    attack: "spin",
+                    total,
+                });
+            }
+            42
+        });
+
+        assert_eq!(block_on(work), 42);
+        let events = block_on(async {
+            let mut events = Vec::new();
+            while let Some(event) = stream.next().await {
+                events.push(event);
+            }
+            events
+        });
+        assert_eq!(events.len(), 3);
+    }
+}
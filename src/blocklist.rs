@@ -0,0 +1,201 @@
+use num_bigint::BigInt;
+use openssl::sha::sha1;
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::errors::BilboError;
+use crate::fingerprint::modulus_fingerprint;
+
+// Debian openssl-blacklist files hold the last 20 hex digits, 80 bits, of SHA-1 of `Modulus=<HEX>\n`.
+const DEBIAN_FINGERPRINT_LEN: usize = 20;
+// Other lists hold the hex encoded SHA-256 of the modulus, as the audit log and the allowlist.
+const MODULUS_FINGERPRINT_LEN: usize = 64;
+
+/// Blocklist is the set of fingerprints of publicly known weak and compromised keys, each with the source listing it,
+/// such as the keys of the Debian OpenSSL random generator flaw, CVE-2008-0166, and the default keys of the devices
+/// published by the house of keys project. Keys found in it are broken whatever their strength, the private key is public.
+///
+/// The lists are read from the text files, one fingerprint per line, empty lines and text after `#` skipped:
+///
+///  - Debian, the last 20 hex digits of SHA-1 of `Modulus=<HEX>\n`, the format of /usr/share/openssl-blacklist/blacklist.RSA-*.
+///  - Modulus, the hex encoded SHA-256 of the modulus, as PickLock::modulus_fingerprint writes it.
+///
+/// Lists are embedded in the binary by reading them from `include_str!`, or loaded from the files at run time.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Blocklist {
+    fingerprints: HashMap<String, String>,
+}
+
+impl Blocklist {
+    /// Reads the blocklist of a single source from the text format.
+    ///
+    #[inline(always)]
+    pub fn read(reader: impl BufRead, source: &str) -> Result<Self, BilboError> {
+        let mut blocklist = Self::default();
+        blocklist.load(reader, source)?;
+
+        Ok(blocklist)
+    }
+
+    /// Adds the fingerprints of the source read from the text format, returns their number.
+    /// A fingerprint listed by several sources keeps the first one.
+    ///
+    pub fn load(&mut self, reader: impl BufRead, source: &str) -> Result<usize, BilboError> {
+        let mut count = 0;
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let fingerprint = line
+                .split_once('#')
+                .map_or(line.as_str(), |(f, _)| f)
+                .trim();
+            if fingerprint.is_empty() {
+                continue;
+            }
+            if !matches!(
+                fingerprint.len(),
+                DEBIAN_FINGERPRINT_LEN | MODULUS_FINGERPRINT_LEN
+            ) || !fingerprint.chars().all(|c| c.is_ascii_hexdigit())
+            {
+                return Err(BilboError::GenericError(format!(
+                    "line {}: {fingerprint} is neither Debian nor SHA-256 fingerprint of the modulus",
+                    number + 1
+                )));
+            }
+            self.fingerprints
+                .entry(fingerprint.to_ascii_lowercase())
+                .or_insert_with(|| source.to_string());
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Returns the number of the fingerprints of all the sources.
+    ///
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.fingerprints.len()
+    }
+
+    /// Returns true if no source was loaded.
+    ///
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.fingerprints.is_empty()
+    }
+
+    /// Returns the source listing the modulus under any of the fingerprints, None for the keys of no list.
+    ///
+    #[inline(always)]
+    pub fn source_of(&self, n: &BigInt) -> Option<&str> {
+        [debian_fingerprint(n), modulus_fingerprint(n)]
+            .iter()
+            .find_map(|f| self.fingerprints.get(f))
+            .map(String::as_str)
+    }
+}
+
+/// Returns the fingerprint of the modulus of the Debian openssl-blacklist files,
+/// the last 20 hex digits of SHA-1 of `Modulus=<HEX>\n` as printed by `openssl rsa -noout -modulus`.
+///
+#[inline(always)]
+pub fn debian_fingerprint(n: &BigInt) -> String {
+    let line = format!("Modulus={}\n", n.to_str_radix(16).to_ascii_uppercase());
+    let digest: String = sha1(line.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    digest[digest.len() - DEBIAN_FINGERPRINT_LEN..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::PickLock;
+
+    fn key(p: u64, q: u64) -> PickLock {
+        PickLock::from_exponent_and_modulus(BigInt::from(65537), BigInt::from(p * q))
+    }
+
+    #[test]
+    fn it_should_compute_debian_fingerprint_of_the_modulus() {
+        assert_eq!(
+            debian_fingerprint(&BigInt::from(1_000_003_u64 * 1_000_033)),
+            "13dfea8f86ad21b763a2"
+        );
+    }
+
+    #[test]
+    fn it_should_find_the_source_of_blocklisted_keys() -> Result<(), BilboError> {
+        let (debian, device) = (key(1_000_003, 1_000_033), key(1_000_037, 1_000_039));
+        let mut blocklist = Blocklist::read(
+            "# Keys generated with the broken random generator\n13DFEA8F86AD21B763A2\n\n"
+                .as_bytes(),
+            "debian",
+        )?;
+        let given = format!(
+            "{}  # router firmware default key\n",
+            device.modulus_fingerprint()
+        );
+        assert_eq!(blocklist.load(given.as_bytes(), "house-of-keys")?, 1);
+
+        assert_eq!(debian.check_blocklist(&blocklist), Some("debian"));
+        assert_eq!(device.check_blocklist(&blocklist), Some("house-of-keys"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_keep_the_first_source_of_fingerprint_listed_twice() -> Result<(), BilboError> {
+        let mut blocklist = Blocklist::read("13dfea8f86ad21b763a2".as_bytes(), "debian")?;
+        assert_eq!(
+            blocklist.load("13dfea8f86ad21b763a2\n".as_bytes(), "mirror")?,
+            1
+        );
+
+        assert_eq!(blocklist.len(), 1);
+        assert_eq!(
+            key(1_000_003, 1_000_033).check_blocklist(&blocklist),
+            Some("debian")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_flag_keys_of_no_list() -> Result<(), BilboError> {
+        let sound = key(1_000_081, 1_000_099);
+        let blocklist = Blocklist::read("13dfea8f86ad21b763a2".as_bytes(), "debian")?;
+
+        assert_eq!(sound.check_blocklist(&blocklist), None);
+        assert!(Blocklist::default().is_empty());
+        assert_eq!(sound.check_blocklist(&Blocklist::default()), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_corrupt_lines_with_their_number() {
+        for (given, line) in [
+            ("13dfea8f86ad21b763", 1),
+            ("13dfea8f86ad21b763a2\n13dfea8f86ad21b763zz", 2),
+            ("# header\n\n13dfea8f 86ad21b763a2", 3),
+            ("13dfea8f86ad21b763a2 debian", 1),
+        ] {
+            let Err(BilboError::GenericError(message)) =
+                Blocklist::read(given.as_bytes(), "corrupt")
+            else {
+                panic!("expected corrupt line error of {given:?}");
+            };
+            assert!(message.starts_with(&format!("line {line}: ")), "{message}");
+        }
+        let mut blocklist = Blocklist::default();
+        assert!(blocklist
+            .load(
+                "13dfea8f86ad21b763a2\nnot a fingerprint".as_bytes(),
+                "partial"
+            )
+            .is_err());
+    }
+}
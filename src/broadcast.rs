@@ -0,0 +1,149 @@
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+
+use crate::corpus::MessageCorpus;
+use crate::errors::BilboError;
+use crate::rsa::small_e_root_attack;
+
+/// Returns x and the product of the moduli, x ≡ r_i mod n_i for every pair (r_i, n_i), by the Chinese remainder theorem.
+/// Fails naming the first pair of moduli that are not coprime.
+///
+pub fn crt(residues: &[(BigInt, BigInt)]) -> Result<(BigInt, BigInt), BilboError> {
+    for (i, (_, a)) in residues.iter().enumerate() {
+        for (j, (_, b)) in residues.iter().enumerate().skip(i + 1) {
+            let g = a.gcd(b);
+            if g != BigInt::from(1) {
+                return Err(BilboError::GenericError(format!(
+                    "moduli {i} and {j} are not coprime, they share factor {g:#x}"
+                )));
+            }
+        }
+    }
+
+    let modulus: BigInt = residues.iter().map(|(_, n)| n).product();
+    let mut x = BigInt::from(0);
+    for (r, n) in residues.iter() {
+        let rest = &modulus / n;
+        let Some(inverse) = rest.modinv(n) else {
+            return Err(BilboError::GenericError(format!(
+                "modulus {n:#x} is not invertible"
+            )));
+        };
+        x += r * &rest * inverse;
+    }
+
+    Ok((x.mod_floor(&modulus), modulus))
+}
+
+/// Håstad's broadcast attack, recovers unpadded message m encrypted under the same small e by e or more keys
+/// of different moduli, given as pairs (n_i, c_i) of the modulus and the ciphertext.
+/// The ciphertexts combine by CRT into m^e modulo the product of the moduli, which m^e does not wrap,
+/// so m is its exact integer e-th root.
+///
+/// Moduli must be pairwise coprime, the error names the pair that isn't.
+/// Such pair shares a prime and both keys are broken by its gcd, see PickLock::try_lock_pick_auxiliary_gcd.
+///
+pub fn hastad_broadcast(e: &BigInt, captured: &[(BigInt, BigInt)]) -> Result<BigInt, BilboError> {
+    if captured.is_empty() {
+        return Err(BilboError::GenericError(
+            "no ciphertexts to recover the broadcast message from".to_string(),
+        ));
+    }
+    for (i, (n, c)) in captured.iter().enumerate() {
+        if c.sign() == Sign::Minus || c >= n {
+            return Err(BilboError::GenericError(format!(
+                "ciphertext {i} {c:#x} is not below its modulus"
+            )));
+        }
+    }
+    let residues: Vec<(BigInt, BigInt)> = captured
+        .iter()
+        .map(|(n, c)| (c.clone(), n.clone()))
+        .collect();
+    let (power, modulus) = crt(&residues)?;
+
+    small_e_root_attack(&power, e, &modulus).map_err(|err| {
+        BilboError::GenericError(format!(
+            "cannot recover the message broadcast under e {e} from {} ciphertexts, e of them are needed: {err}",
+            captured.len()
+        ))
+    })
+}
+
+/// Håstad's broadcast attack on corpora of different moduli, see hastad_broadcast.
+/// Message at the given index of every corpus is the ciphertext of the same message.
+///
+pub fn hastad_broadcast_corpora(
+    corpora: &[MessageCorpus],
+    index: usize,
+) -> Result<BigInt, BilboError> {
+    let Some(first) = corpora.first() else {
+        return Err(BilboError::GenericError(
+            "no corpora to recover the broadcast message from".to_string(),
+        ));
+    };
+    let mut captured = Vec::with_capacity(corpora.len());
+    for (i, corpus) in corpora.iter().enumerate() {
+        if corpus.e != first.e {
+            return Err(BilboError::GenericError(format!(
+                "corpus {i} has public exponent {}, the broadcast needs the same e {}",
+                corpus.e, first.e
+            )));
+        }
+        let Some(message) = corpus.messages.get(index) else {
+            return Err(BilboError::GenericError(format!(
+                "corpus {i} has no message {index}"
+            )));
+        };
+        captured.push((corpus.n.clone(), message.ciphertext.clone()));
+    }
+
+    hastad_broadcast(&first.e, &captured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::bn::BigNum;
+    use openssl::rsa::Rsa;
+
+    #[test]
+    fn it_should_recover_the_message_broadcast_to_small_e_keys() -> Result<(), BilboError> {
+        let e = BigInt::from(3);
+        let m = BigInt::from_bytes_be(
+            Sign::Plus,
+            b"there and back again, a hobbit's tale by bilbo baggins",
+        );
+        let mut corpora = Vec::new();
+        for _ in 0..3 {
+            let key = Rsa::generate_with_e(512, BigNum::from_u32(3)?.as_ref())?;
+            let n = BigInt::from_bytes_be(Sign::Plus, &key.n().to_vec());
+            let mut corpus = MessageCorpus::new(e.clone(), n.clone());
+            corpus.push(m.modpow(&e, &n))?;
+            corpora.push(corpus);
+        }
+        assert!(m.pow(3) > corpora[0].n);
+
+        assert_eq!(hastad_broadcast_corpora(&corpora, 0)?, m);
+        assert!(hastad_broadcast_corpora(&corpora[..2], 0).is_err());
+        assert!(hastad_broadcast_corpora(&corpora, 1).is_err());
+
+        let (x, modulus) =
+            crt(&[(2, 3), (3, 5), (2, 7)].map(|(r, n)| (BigInt::from(r), BigInt::from(n))))?;
+        assert_eq!((x, modulus), (BigInt::from(23), BigInt::from(105)));
+
+        // Moduli sharing a prime are reported by their pair.
+        let shared = &corpora[0].n * BigInt::from(1_000_003);
+        let captured = [
+            (corpora[0].n.clone(), BigInt::from(2)),
+            (corpora[1].n.clone(), BigInt::from(2)),
+            (shared, BigInt::from(2)),
+        ];
+        let Err(err) = hastad_broadcast(&e, &captured) else {
+            panic!("expected moduli 0 and 2 not coprime");
+        };
+        assert!(err.to_string().contains("moduli 0 and 2 are not coprime"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use openssl::sha::sha256;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{create_dir_all, read, write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::errors::BilboError;
+
+// Time to live of the cached entries unless configured otherwise.
+const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+// Fetch time and value of the cached entries by material and key.
+type Entries = HashMap<(Material, String), (SystemTime, Vec<u8>)>;
+
+/// Material is the kind of remote key material, or revocation list of it, fetched by the network scanners.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Material {
+    Certificate,
+    Jwks,
+    Dns,
+    Crl,
+}
+
+impl Display for Material {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Material::Certificate => "certificate",
+                Material::Jwks => "jwks",
+                Material::Dns => "dns",
+                Material::Crl => "crl",
+            }
+        )
+    }
+}
+
+/// Cache keeps fetched certificates, JWKS documents and DNS records for their time to live,
+/// so repeated runs and watch mode don't hammer the targets or rate limited APIs.
+///
+/// Entries live in memory and, when the cache has a directory, in one file per entry,
+/// named by SHA-256 of the material and key, shared by consecutive runs.
+/// Failed fetches are never cached.
+///
+#[derive(Debug)]
+pub struct Cache {
+    ttl: HashMap<Material, Duration>,
+    default_ttl: Duration,
+    dir: Option<PathBuf>,
+    entries: Mutex<Entries>,
+}
+
+impl Default for Cache {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+impl Cache {
+    /// Creates new in memory cache with given time to live of every material.
+    ///
+    #[inline(always)]
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl: HashMap::new(),
+            default_ttl: ttl,
+            dir: None,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Persists the entries to given directory, created if missing.
+    ///
+    #[inline(always)]
+    pub fn with_dir(mut self, dir: PathBuf) -> Result<Self, BilboError> {
+        create_dir_all(&dir)?;
+        self.dir = Some(dir);
+
+        Ok(self)
+    }
+
+    /// Overrides the time to live of given material.
+    ///
+    #[inline(always)]
+    pub fn with_ttl(mut self, material: Material, ttl: Duration) -> Self {
+        self.ttl.insert(material, ttl);
+        self
+    }
+
+    /// Returns the time to live of given material.
+    ///
+    #[inline(always)]
+    pub fn ttl(&self, material: Material) -> Duration {
+        self.ttl.get(&material).copied().unwrap_or(self.default_ttl)
+    }
+
+    /// Returns the cached value of the key if it is still fresh, from memory or from the disk.
+    ///
+    pub fn get(&self, material: Material, key: &str) -> Option<Vec<u8>> {
+        let ttl = self.ttl(material);
+        let fresh = |fetched: SystemTime| fetched.elapsed().is_ok_and(|age| age < ttl);
+        if let Ok(entries) = self.entries.lock() {
+            if let Some((fetched, value)) = entries.get(&(material, key.to_string())) {
+                if fresh(*fetched) {
+                    return Some(value.clone());
+                }
+            }
+        }
+        let (fetched, value) = self.read_entry(material, key)?;
+        if !fresh(fetched) {
+            return None;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert((material, key.to_string()), (fetched, value.clone()));
+        }
+
+        Some(value)
+    }
+
+    /// Stores the value of the key fetched just now.
+    ///
+    pub fn put(&self, material: Material, key: &str, value: &[u8]) -> Result<(), BilboError> {
+        let fetched = SystemTime::now();
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert((material, key.to_string()), (fetched, value.to_vec()));
+        }
+        if let Some(path) = self.path(material, key) {
+            let seconds = fetched.duration_since(UNIX_EPOCH).unwrap_or_default();
+            let mut entry = format!("{} {material} {key}\n", seconds.as_secs()).into_bytes();
+            entry.extend_from_slice(value);
+            write(path, entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the fresh cached value of the key or fetches it and caches the result if the fetch succeeds.
+    ///
+    pub fn fetch(
+        &self,
+        material: Material,
+        key: &str,
+        fetch: impl FnOnce() -> Result<Vec<u8>, BilboError>,
+    ) -> Result<Vec<u8>, BilboError> {
+        if let Some(value) = self.get(material, key) {
+            return Ok(value);
+        }
+        let value = fetch()?;
+        self.put(material, key, &value)?;
+
+        Ok(value)
+    }
+
+    // Entry file holds the fetch time in unix seconds, the material and the key on the first line, then the value.
+    #[inline(always)]
+    fn read_entry(&self, material: Material, key: &str) -> Option<(SystemTime, Vec<u8>)> {
+        let entry = read(self.path(material, key)?).ok()?;
+        let end = entry.iter().position(|b| *b == b'\n')?;
+        let header = String::from_utf8_lossy(&entry[..end]);
+        let (seconds, stored) = header.split_once(' ')?;
+        if stored != format!("{material} {key}") {
+            return None;
+        }
+        let fetched = UNIX_EPOCH + Duration::from_secs(seconds.parse().ok()?);
+
+        Some((fetched, entry[end + 1..].to_vec()))
+    }
+
+    #[inline(always)]
+    fn path(&self, material: Material, key: &str) -> Option<PathBuf> {
+        let name: String = sha256(format!("{material} {key}").as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        Some(self.dir.as_ref()?.join(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    #[test]
+    fn it_should_cache_fetched_material_for_its_ttl() -> Result<(), BilboError> {
+        let dir = temp_dir().join(format!("bilbo-cache-{}", std::process::id()));
+        let cache = Cache::new(Duration::from_secs(60))
+            .with_ttl(Material::Dns, Duration::ZERO)
+            .with_dir(dir.clone())?;
+        let jwks = br#"{"keys":[]}"#.to_vec();
+
+        let mut fetches = 0;
+        for _ in 0..3 {
+            let value = cache.fetch(Material::Jwks, "https://example.com/jwks", || {
+                fetches += 1;
+                Ok(jwks.clone())
+            })?;
+            assert_eq!(value, jwks);
+        }
+        assert_eq!(fetches, 1);
+
+        // The next run finds the entry on the disk.
+        let next_run = Cache::new(Duration::from_secs(60)).with_dir(dir.clone())?;
+        assert_eq!(
+            next_run.get(Material::Jwks, "https://example.com/jwks"),
+            Some(jwks)
+        );
+        assert_eq!(
+            next_run.get(Material::Certificate, "https://example.com/jwks"),
+            None
+        );
+
+        // Expired entries and failed fetches are fetched again.
+        cache.put(Material::Dns, "example.com", b"10.0.0.1")?;
+        assert_eq!(cache.get(Material::Dns, "example.com"), None);
+        assert!(cache
+            .fetch(Material::Certificate, "example.com:443", || Err(
+                BilboError::GenericError("refused".to_string())
+            ))
+            .is_err());
+        assert_eq!(cache.get(Material::Certificate, "example.com:443"), None);
+
+        remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
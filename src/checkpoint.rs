@@ -0,0 +1,116 @@
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::errors::BilboError;
+use crate::schema::{upgrade, SCHEMA_VERSION};
+
+/// AttackState is where the interrupted attack stopped.
+///
+///  - Fermat, the weak lock pick, the next candidate a of a² − n = b², decimal, and the iterations done.
+///  - Ecm, Lenstra's ECM with stage 1 bound b1 and the number of curves done.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "attack", rename_all = "snake_case")]
+pub enum AttackState {
+    Fermat { a: String, iterations: u64 },
+    Ecm { b1: u64, curves_done: u64 },
+}
+
+/// AttackCheckpoint is the state of the long running attack of a single key, recorded by PickLock as it runs.
+/// Written as JSON and handed to PickLock::resume_from, possibly in another process, the attack continues
+/// where it stopped instead of starting over, so a multi-day job survives process restarts.
+/// The key is the modulus fingerprint, the checkpoint resumes the attack of that key only.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AttackCheckpoint {
+    pub schema_version: u32,
+    pub key: String,
+    pub state: AttackState,
+}
+
+impl AttackCheckpoint {
+    /// Creates the checkpoint of Fermat's method of the key of given fingerprint at the next candidate a.
+    ///
+    #[inline(always)]
+    pub fn fermat(key: &str, a: &BigInt, iterations: u64) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            key: key.to_string(),
+            state: AttackState::Fermat {
+                a: a.to_string(),
+                iterations,
+            },
+        }
+    }
+
+    /// Creates the checkpoint of ECM of the key of given fingerprint after curves_done curves of bound b1.
+    ///
+    #[inline(always)]
+    pub fn ecm(key: &str, b1: u64, curves_done: u64) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            key: key.to_string(),
+            state: AttackState::Ecm { b1, curves_done },
+        }
+    }
+
+    /// Returns the name of the checkpointed attack.
+    ///
+    #[inline(always)]
+    pub fn attack(&self) -> &str {
+        match self.state {
+            AttackState::Fermat { .. } => "weak",
+            AttackState::Ecm { .. } => "ecm",
+        }
+    }
+
+    /// Reads the checkpoint written by this or any older version of bilbo.
+    ///
+    #[inline(always)]
+    pub fn read(reader: impl Read) -> Result<Self, BilboError> {
+        let doc = upgrade(serde_json::from_reader(reader)?)?;
+        let checkpoint: Self = serde_json::from_value(doc)?;
+        if let AttackState::Fermat { a, .. } = &checkpoint.state {
+            if a.parse::<BigInt>().is_err() {
+                return Err(BilboError::GenericError(format!(
+                    "invalid Fermat checkpoint candidate {a}, expected decimal integer"
+                )));
+            }
+        }
+
+        Ok(checkpoint)
+    }
+
+    /// Writes the checkpoint as pretty printed JSON.
+    ///
+    #[inline(always)]
+    pub fn write(&self, mut writer: impl Write) -> Result<(), BilboError> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_write_and_read_checkpoints() -> Result<(), BilboError> {
+        let checkpoint = AttackCheckpoint::fermat("ab", &BigInt::from(123_456_789), 42);
+        let mut buf = Vec::new();
+        checkpoint.write(&mut buf)?;
+        assert_eq!(AttackCheckpoint::read(buf.as_slice())?, checkpoint);
+        assert_eq!(checkpoint.attack(), "weak");
+
+        let given = r#"{"key":"cd","state":{"attack":"ecm","b1":11000,"curves_done":7}}"#;
+        let checkpoint = AttackCheckpoint::read(given.as_bytes())?;
+        assert_eq!(checkpoint, AttackCheckpoint::ecm("cd", 11_000, 7));
+
+        let given = r#"{"key":"cd","state":{"attack":"fermat","a":"0x1f","iterations":1}}"#;
+        assert!(AttackCheckpoint::read(given.as_bytes()).is_err());
+
+        Ok(())
+    }
+}
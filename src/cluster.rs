@@ -0,0 +1,235 @@
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+const FINGERPRINT_BITS: u64 = 16;
+// Product of the small primes the residue fingerprint is computed for, 3·5·7·11·13.
+const RESIDUE_MODULUS: u64 = 15_015;
+// Generator of the multiplicative subgroup the primes of the ROCA vulnerable keys are drawn from.
+const ROCA_GENERATOR: u64 = 65_537;
+// Small primes for which n mod r of ROCA vulnerable key falls in the subgroup generated by 65537.
+const ROCA_PRIMES: [u64; 38] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167,
+];
+
+/// Feature is the structural property of the modulus keys are clustered by.
+///
+///  - TopBits, the most significant 16 bits, generators forcing prime prefixes share them.
+///  - LowBits, the least significant 16 bits, generators with fixed prime suffixes share them.
+///  - Residues, n mod 3·5·7·11·13, generators restricting primes modulo small primes share them.
+///  - Roca, n mod r falls in the subgroup generated by 65537 for all the small primes r,
+///    the discrete log fingerprint of the Infineon RSALib keys, CVE-2017-15361.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    TopBits,
+    LowBits,
+    Residues,
+    Roca,
+}
+
+impl Display for Feature {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Feature::TopBits => "top bits",
+                Feature::LowBits => "low bits",
+                Feature::Residues => "residues",
+                Feature::Roca => "roca",
+            }
+        )
+    }
+}
+
+/// Fingerprint is the structural fingerprint of the modulus.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub bits: u64,
+    pub top_bits: u64,
+    pub low_bits: u64,
+    pub residues: u64,
+    pub roca: bool,
+}
+
+impl Fingerprint {
+    /// Computes the fingerprint of given modulus.
+    ///
+    #[inline(always)]
+    pub fn of(n: &BigInt) -> Self {
+        let bits = n.bits();
+        let mask = (BigInt::from(1) << FINGERPRINT_BITS) - 1;
+        let low = |v: BigInt| u64::try_from(v & &mask).unwrap_or_default();
+
+        Self {
+            bits,
+            top_bits: low(n >> bits.saturating_sub(FINGERPRINT_BITS)),
+            low_bits: low(n.clone()),
+            residues: residue(n, RESIDUE_MODULUS),
+            roca: is_roca_fingerprint(n),
+        }
+    }
+
+    /// Returns the value of given feature, fingerprints of the same size with equal value cluster together.
+    ///
+    #[inline(always)]
+    pub fn feature(&self, feature: Feature) -> u64 {
+        match feature {
+            Feature::TopBits => self.top_bits,
+            Feature::LowBits => self.low_bits,
+            Feature::Residues => self.residues,
+            Feature::Roca => self.roca as u64,
+        }
+    }
+}
+
+/// Cluster is the family of moduli sharing the feature, likely produced by the same generator.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    pub feature: Feature,
+    pub bits: u64,
+    pub value: u64,
+    pub members: Vec<usize>,
+}
+
+impl Display for Cluster {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} moduli of {} bits share {} {:#x}: {}",
+            self.members.len(),
+            self.bits,
+            self.feature,
+            self.value,
+            self.members
+                .iter()
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+/// Clusters moduli by every feature and returns families of at least min_size members, largest first.
+/// Random moduli are spread over 2^16 values of bit features and 15015 residues,
+/// so for large corpora pick min_size well above the expected birthday collisions.
+/// Non ROCA moduli are never reported as a Roca family.
+///
+pub fn families(moduli: &[BigInt], min_size: usize) -> Vec<Cluster> {
+    let fingerprints: Vec<Fingerprint> = moduli.iter().map(Fingerprint::of).collect();
+    let mut groups: BTreeMap<(Feature, u64, u64), Vec<usize>> = BTreeMap::new();
+    for (index, fp) in fingerprints.iter().enumerate() {
+        for feature in [
+            Feature::TopBits,
+            Feature::LowBits,
+            Feature::Residues,
+            Feature::Roca,
+        ] {
+            if feature == Feature::Roca && !fp.roca {
+                continue;
+            }
+            groups
+                .entry((feature, fp.bits, fp.feature(feature)))
+                .or_default()
+                .push(index);
+        }
+    }
+    let mut clusters: Vec<Cluster> = groups
+        .into_iter()
+        .filter(|(_, members)| members.len() >= min_size.max(1))
+        .map(|((feature, bits, value), members)| Cluster {
+            feature,
+            bits,
+            value,
+            members,
+        })
+        .collect();
+    clusters.sort_by_key(|c| Reverse(c.members.len()));
+
+    clusters
+}
+
+/// Returns true if the modulus has the ROCA fingerprint, n mod r is a power of 65537 modulo r for every small prime r.
+/// Random moduli pass with probability around 2^-154, so the fingerprint has virtually no false positives.
+///
+#[inline(always)]
+pub fn is_roca_fingerprint(n: &BigInt) -> bool {
+    ROCA_PRIMES.iter().all(|r| {
+        let residue = residue(n, *r);
+        residue != 0 && subgroup(ROCA_GENERATOR % r, *r).contains(&residue)
+    })
+}
+
+#[inline(always)]
+fn residue(n: &BigInt, m: u64) -> u64 {
+    u64::try_from(n % m).unwrap_or_default()
+}
+
+// Returns the multiplicative subgroup generated by g modulo prime r.
+#[inline(always)]
+fn subgroup(g: u64, r: u64) -> HashSet<u64> {
+    let mut group = HashSet::new();
+    let mut x = 1;
+    while group.insert(x) {
+        x = x * g % r;
+    }
+
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::Sign;
+
+    // Builds modulus that satisfies the ROCA fingerprint, n ≡ 65537^k mod r for every small prime r, by CRT.
+    fn roca_like(k: u64) -> BigInt {
+        let mut n = BigInt::from(0);
+        let mut m = BigInt::from(1);
+        for r in ROCA_PRIMES.iter() {
+            let target = BigInt::from(ROCA_GENERATOR).modpow(&BigInt::from(k), &BigInt::from(*r));
+            while (&n % r) != target {
+                n += &m;
+            }
+            m *= *r;
+        }
+        n + (m << 64)
+    }
+
+    #[test]
+    fn it_should_cluster_moduli_by_structural_fingerprint() {
+        let random = BigInt::from_bytes_be(Sign::Plus, &[0xab; 64]) + 2_u32;
+        let given = vec![
+            roca_like(3),
+            random.clone(),
+            roca_like(11),
+            (random.clone() >> 16 << 16) + 0x1235_u32,
+            (BigInt::from_bytes_be(Sign::Plus, &[0xdc; 64]) >> 16 << 16) + 0x1235_u32,
+        ];
+        assert!(is_roca_fingerprint(&given[0]));
+        assert!(!is_roca_fingerprint(&random));
+
+        let clusters = families(&given, 2);
+        let roca = clusters
+            .iter()
+            .find(|c| c.feature == Feature::Roca)
+            .unwrap();
+        assert_eq!(roca.members, vec![0, 2]);
+        assert!(clusters
+            .iter()
+            .any(|c| c.feature == Feature::TopBits && c.members == vec![1, 3]));
+        assert!(clusters
+            .iter()
+            .any(|c| c.feature == Feature::LowBits && c.members == vec![3, 4]));
+        assert!(families(&given[..2], 2).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use num_bigint::{BigInt, Sign};
+use openssl::rsa::Rsa;
+
+use crate::errors::BilboError;
+use crate::partial::{parse_index, parse_value, KnownBits};
+use crate::textbook::{analyze_ciphertexts, TextbookReport};
+
+/// Plaintext of the captured ciphertext, as much of it as is known.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Plaintext {
+    #[default]
+    Unknown,
+    Known(BigInt),
+    Partial(KnownBits),
+}
+
+/// CapturedMessage is the ciphertext with whatever is known about its plaintext.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedMessage {
+    pub ciphertext: BigInt,
+    pub plaintext: Plaintext,
+}
+
+/// MessageCorpus holds ciphertexts captured under a single public key (e, n),
+/// optionally with known or partially known plaintexts.
+/// Message recovery attacks consume it uniformly: Håstad broadcast takes corpora of different moduli,
+/// common modulus takes corpora of the same modulus and different exponents,
+/// Franklin–Reiter takes related messages and parity oracle a single ciphertext.
+///
+/// It is imported from the text format, one message per line, `#` starts a comment:
+///
+/// ```text
+/// 0x5f1c...                          # unknown plaintext
+/// 0x5f1c... = 0x68656c6c6f           # known plaintext
+/// 0x5f1c... bits 0..64 are 0x0a0b..  # partially known plaintext
+/// ```
+///
+/// Partially known plaintext may list more bit ranges separated by commas, in the format of the partial key.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageCorpus {
+    pub e: BigInt,
+    pub n: BigInt,
+    pub messages: Vec<CapturedMessage>,
+}
+
+impl MessageCorpus {
+    /// Creates empty corpus bound to the public key (e, n).
+    ///
+    #[inline(always)]
+    pub fn new(e: BigInt, n: BigInt) -> Self {
+        Self {
+            e,
+            n,
+            messages: Vec::new(),
+        }
+    }
+
+    /// Creates empty corpus bound to the public key in PEM format.
+    ///
+    #[inline(always)]
+    pub fn from_pem(rsa_pem: &str) -> Result<Self, BilboError> {
+        let public_rsa = Rsa::public_key_from_pem(rsa_pem.as_bytes())?;
+
+        Ok(Self::new(
+            BigInt::from_bytes_be(Sign::Plus, &public_rsa.e().to_vec()),
+            BigInt::from_bytes_be(Sign::Plus, &public_rsa.n().to_vec()),
+        ))
+    }
+
+    /// Adds ciphertext of unknown plaintext, returns its index.
+    ///
+    #[inline(always)]
+    pub fn push(&mut self, ciphertext: BigInt) -> Result<usize, BilboError> {
+        self.push_message(ciphertext, Plaintext::Unknown)
+    }
+
+    /// Adds ciphertext of known plaintext, returns its index. Fails if the plaintext does not encrypt to the ciphertext,
+    /// so only unpadded pairs are accepted, padded plaintexts are imported as partially known.
+    ///
+    #[inline(always)]
+    pub fn push_known(
+        &mut self,
+        ciphertext: BigInt,
+        plaintext: BigInt,
+    ) -> Result<usize, BilboError> {
+        if plaintext.modpow(&self.e, &self.n) != ciphertext {
+            return Err(BilboError::GenericError(format!(
+                "plaintext {plaintext:#x} does not encrypt to ciphertext {ciphertext:#x}"
+            )));
+        }
+        self.push_message(ciphertext, Plaintext::Known(plaintext))
+    }
+
+    /// Adds ciphertext of partially known plaintext, returns its index.
+    ///
+    #[inline(always)]
+    pub fn push_partial(
+        &mut self,
+        ciphertext: BigInt,
+        bits: KnownBits,
+    ) -> Result<usize, BilboError> {
+        self.push_message(ciphertext, Plaintext::Partial(bits))
+    }
+
+    /// Parses messages from the text format and adds them to the corpus.
+    ///
+    pub fn ingest(&mut self, text: &str) -> Result<(), BilboError> {
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.ingest_line(line)
+                .map_err(|e| BilboError::GenericError(format!("line {}: {e}", number + 1)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns all ciphertexts in the order they were added.
+    ///
+    #[inline(always)]
+    pub fn ciphertexts(&self) -> Vec<BigInt> {
+        self.messages.iter().map(|m| m.ciphertext.clone()).collect()
+    }
+
+    /// Returns pairs of ciphertext and plaintext for messages of known plaintext.
+    ///
+    #[inline(always)]
+    pub fn known(&self) -> Vec<(&BigInt, &BigInt)> {
+        self.messages
+            .iter()
+            .filter_map(|m| match &m.plaintext {
+                Plaintext::Known(p) => Some((&m.ciphertext, p)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Analyzes the ciphertexts for signs of textbook RSA, see textbook::analyze_ciphertexts.
+    ///
+    #[inline(always)]
+    pub fn analyze(&self) -> TextbookReport {
+        analyze_ciphertexts(&self.e, &self.n, &self.ciphertexts())
+    }
+
+    #[inline(always)]
+    fn push_message(
+        &mut self,
+        ciphertext: BigInt,
+        plaintext: Plaintext,
+    ) -> Result<usize, BilboError> {
+        if ciphertext.sign() == Sign::Minus || ciphertext >= self.n {
+            return Err(BilboError::GenericError(format!(
+                "ciphertext {ciphertext:#x} is not below the modulus"
+            )));
+        }
+        self.messages.push(CapturedMessage {
+            ciphertext,
+            plaintext,
+        });
+
+        Ok(self.messages.len() - 1)
+    }
+
+    #[inline(always)]
+    fn ingest_line(&mut self, line: &str) -> Result<usize, BilboError> {
+        let (ciphertext, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let ciphertext = parse_value(ciphertext, 0)?;
+        let rest = rest.trim();
+        if rest.is_empty() {
+            return self.push(ciphertext);
+        }
+        if let Some(plaintext) = rest.strip_prefix('=') {
+            return self.push_known(ciphertext, parse_value(plaintext.trim(), 0)?);
+        }
+        let mut bits = KnownBits::default();
+        for statement in rest.split(',') {
+            let words: Vec<&str> = statement.split_whitespace().collect();
+            let ["bits", range, "are", value] = words.as_slice() else {
+                return Err(BilboError::GenericError(format!(
+                    "expected '<c>', '<c> = <m>' or '<c> bits <lo>..<hi> are <value>', found {line:?}"
+                )));
+            };
+            let Some((lo, hi)) = range.split_once("..") else {
+                return Err(BilboError::GenericError(format!(
+                    "invalid bit range {range}"
+                )));
+            };
+            let (lo, hi) = (parse_index(lo)?, parse_index(hi)?);
+            bits.set(lo, hi, &parse_value(value, hi.saturating_sub(lo))?)?;
+        }
+
+        self.push_partial(ciphertext, bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_ingest_captured_messages() -> Result<(), BilboError> {
+        let e = BigInt::from(3);
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(999_983_u64);
+        let m = BigInt::from(0x6869);
+        let c = m.modpow(&e, &n);
+        let given = format!(
+            "# captured at gateway\n{c:#x}\n{c} = {m:#x}\n0x2a bits 0..8 are 0x69, bits 8..16 are 0b01101000\n"
+        );
+
+        let mut corpus = MessageCorpus::new(e, n);
+        corpus.ingest(&given)?;
+        assert_eq!(corpus.messages.len(), 3);
+        assert_eq!(corpus.messages[0].plaintext, Plaintext::Unknown);
+        assert_eq!(corpus.known(), vec![(&c, &m)]);
+        let Plaintext::Partial(bits) = &corpus.messages[2].plaintext else {
+            panic!("expected partial plaintext");
+        };
+        assert_eq!(bits.low_bits(), Some((m.clone(), 16)));
+        assert!(corpus.analyze().is_textbook());
+
+        assert!(corpus.ingest(&format!("{c} = 0x6868")).is_err());
+        assert!(corpus.push(corpus.n.clone()).is_err());
+        assert!(corpus.ingest("0x2a bits 0..8").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::sync::OnceLock;
+
+// Largest modulus in 64 bit limbs, 8192 bits, the kernels keep their scratch on the stack.
+const MAX_LIMBS: usize = 128;
+
+static FEATURES: OnceLock<CpuFeatures> = OnceLock::new();
+
+/// CpuFeatures are the instruction set extensions of the running CPU relevant to big integer arithmetic.
+///
+///  - adx, add with carry on two independent carry chains, ADCX and ADOX.
+///  - bmi2, flagless multiplication MULX, interleaves with the ADX carry chains.
+///  - avx2 and avx512f, wide vector units, detected and reported only,
+///    carry chain bound limb multiplication of the attacks gains nothing from them.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CpuFeatures {
+    pub adx: bool,
+    pub bmi2: bool,
+    pub avx2: bool,
+    pub avx512f: bool,
+}
+
+impl Display for CpuFeatures {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let names: Vec<&str> = [
+            (self.adx, "adx"),
+            (self.bmi2, "bmi2"),
+            (self.avx2, "avx2"),
+            (self.avx512f, "avx512f"),
+        ]
+        .into_iter()
+        .filter_map(|(present, name)| present.then_some(name))
+        .collect();
+        if names.is_empty() {
+            return write!(f, "none");
+        }
+        write!(f, "{}", names.join(" "))
+    }
+}
+
+impl CpuFeatures {
+    /// Detects the features of the running CPU once, later calls return the cached result.
+    ///
+    #[inline(always)]
+    pub fn detect() -> Self {
+        *FEATURES.get_or_init(Self::probe)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[inline(always)]
+    fn probe() -> Self {
+        Self {
+            adx: std::arch::is_x86_feature_detected!("adx"),
+            bmi2: std::arch::is_x86_feature_detected!("bmi2"),
+            avx2: std::arch::is_x86_feature_detected!("avx2"),
+            avx512f: std::arch::is_x86_feature_detected!("avx512f"),
+        }
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    #[inline(always)]
+    fn probe() -> Self {
+        Self::default()
+    }
+}
+
+/// Kernel is the implementation of the Montgomery multiplication and squaring used by the attack inner loops.
+///
+///  - Portable, plain 64 bit limbs with 128 bit products, runs everywhere.
+///  - Bmi2Adx, the same algorithm compiled for MULX, ADCX and ADOX, selected on x86_64 CPUs that have them.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    Portable,
+    Bmi2Adx,
+}
+
+impl Display for Kernel {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Kernel::Portable => "portable",
+                Kernel::Bmi2Adx => "bmi2+adx",
+            }
+        )
+    }
+}
+
+impl Kernel {
+    /// Selects the fastest kernel the running CPU supports.
+    ///
+    #[inline(always)]
+    pub fn detect() -> Self {
+        Self::select(&CpuFeatures::detect())
+    }
+
+    /// Selects the fastest kernel supported by given features.
+    ///
+    #[inline(always)]
+    pub fn select(features: &CpuFeatures) -> Self {
+        if cfg!(target_arch = "x86_64") && features.bmi2 && features.adx {
+            return Kernel::Bmi2Adx;
+        }
+
+        Kernel::Portable
+    }
+}
+
+/// Montgomery is the arithmetic modulo odd n on numbers kept in Montgomery form a·R mod n, R = 2^(64·limbs).
+/// Multiplication and squaring avoid the division of the plain modular reduction and allocate nothing,
+/// they dispatch to the kernel selected for the running CPU.
+///
+#[derive(Debug, Clone)]
+pub struct Montgomery {
+    n: BigInt,
+    limbs: Vec<u64>,
+    // −n^(−1) mod 2^64.
+    n_inv: u64,
+    kernel: Kernel,
+}
+
+impl Montgomery {
+    /// Creates the arithmetic modulo n with the kernel of the running CPU,
+    /// None for even, non positive or larger than 8192 bits n.
+    ///
+    #[inline(always)]
+    pub fn new(n: &BigInt) -> Option<Self> {
+        Self::with_kernel(n, Kernel::detect())
+    }
+
+    /// Creates the arithmetic modulo n with given kernel, None for even, non positive or larger than 8192 bits n.
+    /// The kernel falls back to portable when the running CPU doesn't support it.
+    ///
+    #[inline(always)]
+    pub fn with_kernel(n: &BigInt, kernel: Kernel) -> Option<Self> {
+        if n.sign() != Sign::Plus || n.is_even() {
+            return None;
+        }
+        let limbs = n.to_u64_digits().1;
+        if limbs.len() > MAX_LIMBS {
+            return None;
+        }
+        // Newton iteration doubles the correct low bits of the inverse every step, 6 steps reach 64 bits.
+        let mut inv: u64 = 1;
+        for _ in 0..6 {
+            inv = inv.wrapping_mul(2_u64.wrapping_sub(limbs[0].wrapping_mul(inv)));
+        }
+        let kernel = match kernel {
+            Kernel::Bmi2Adx if Kernel::detect() == Kernel::Bmi2Adx => Kernel::Bmi2Adx,
+            _ => Kernel::Portable,
+        };
+
+        Some(Self {
+            n: n.clone(),
+            limbs,
+            n_inv: inv.wrapping_neg(),
+            kernel,
+        })
+    }
+
+    /// Returns the kernel in use.
+    ///
+    #[inline(always)]
+    pub fn kernel(&self) -> Kernel {
+        self.kernel
+    }
+
+    /// Converts a into Montgomery form a·R mod n.
+    ///
+    #[inline(always)]
+    pub fn to_montgomery(&self, a: &BigInt) -> Vec<u64> {
+        let shifted: BigInt = a.mod_floor(&self.n) << (64 * self.limbs.len());
+        self.pad(&shifted.mod_floor(&self.n))
+    }
+
+    /// Converts a from Montgomery form back to the plain residue.
+    ///
+    #[inline(always)]
+    pub fn from_montgomery(&self, a: &[u64]) -> BigInt {
+        let mut one = vec![0; self.limbs.len()];
+        one[0] = 1;
+        let mut out = vec![0; self.limbs.len()];
+        self.mul(a, &one, &mut out);
+        to_bigint(&out)
+    }
+
+    /// Returns the residue in Montgomery form as plain number, a·R mod n, e.g. to take gcd with n.
+    ///
+    #[inline(always)]
+    pub fn raw(&self, a: &[u64]) -> BigInt {
+        to_bigint(a)
+    }
+
+    /// Multiplies a and b in Montgomery form into out.
+    ///
+    #[inline(always)]
+    pub fn mul(&self, a: &[u64], b: &[u64], out: &mut [u64]) {
+        match self.kernel {
+            Kernel::Portable => mul_portable(a, b, &self.limbs, self.n_inv, out),
+            #[cfg(target_arch = "x86_64")]
+            // SAFETY: the kernel is only selected when the running CPU supports bmi2 and adx.
+            Kernel::Bmi2Adx => unsafe { mul_bmi2_adx(a, b, &self.limbs, self.n_inv, out) },
+            #[cfg(not(target_arch = "x86_64"))]
+            Kernel::Bmi2Adx => mul_portable(a, b, &self.limbs, self.n_inv, out),
+        }
+    }
+
+    /// Squares a in Montgomery form into out.
+    ///
+    #[inline(always)]
+    pub fn sqr(&self, a: &[u64], out: &mut [u64]) {
+        self.mul(a, a, out);
+    }
+
+    /// Adds b to a modulo n in place.
+    ///
+    #[inline(always)]
+    pub fn add_assign(&self, a: &mut [u64], b: &[u64]) {
+        let mut carry = 0_u64;
+        for (a, b) in a.iter_mut().zip(b) {
+            let (sum, c1) = a.overflowing_add(*b);
+            let (sum, c2) = sum.overflowing_add(carry);
+            *a = sum;
+            carry = u64::from(c1 || c2);
+        }
+        if carry == 1 || !less_than(a, &self.limbs) {
+            subtract(a, &self.limbs);
+        }
+    }
+
+    /// Writes a − b modulo n into out.
+    ///
+    #[inline(always)]
+    pub fn sub(&self, a: &[u64], b: &[u64], out: &mut [u64]) {
+        out.copy_from_slice(a);
+        if less_than(a, b) {
+            let mut carry = 0_u64;
+            for (o, n) in out.iter_mut().zip(&self.limbs) {
+                let (sum, c1) = o.overflowing_add(*n);
+                let (sum, c2) = sum.overflowing_add(carry);
+                *o = sum;
+                carry = u64::from(c1 || c2);
+            }
+        }
+        subtract(out, b);
+    }
+
+    #[inline(always)]
+    fn pad(&self, a: &BigInt) -> Vec<u64> {
+        let mut limbs = a.to_u64_digits().1;
+        limbs.resize(self.limbs.len(), 0);
+        limbs
+    }
+}
+
+#[inline(always)]
+fn to_bigint(a: &[u64]) -> BigInt {
+    let digits: Vec<u32> = a
+        .iter()
+        .flat_map(|limb| [*limb as u32, (*limb >> 32) as u32])
+        .collect();
+    BigInt::new(Sign::Plus, digits)
+}
+
+#[inline(always)]
+fn less_than(a: &[u64], b: &[u64]) -> bool {
+    for (a, b) in a.iter().zip(b).rev() {
+        if a != b {
+            return a < b;
+        }
+    }
+    false
+}
+
+// Subtracts b from a in place, the borrow out of the top limb is dropped.
+#[inline(always)]
+fn subtract(a: &mut [u64], b: &[u64]) {
+    let mut borrow = 0_u64;
+    for (a, b) in a.iter_mut().zip(b) {
+        let (diff, b1) = a.overflowing_sub(*b);
+        let (diff, b2) = diff.overflowing_sub(borrow);
+        *a = diff;
+        borrow = u64::from(b1 || b2);
+    }
+}
+
+#[inline(always)]
+fn mul_portable(a: &[u64], b: &[u64], n: &[u64], n_inv: u64, out: &mut [u64]) {
+    cios(a, b, n, n_inv, out)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "bmi2,adx")]
+fn mul_bmi2_adx(a: &[u64], b: &[u64], n: &[u64], n_inv: u64, out: &mut [u64]) {
+    cios(a, b, n, n_inv, out)
+}
+
+// Coarsely integrated operand scanning Montgomery multiplication, a·b·R^(−1) mod n,
+// reduction interleaved with the multiplication limb by limb. Inlined into every kernel,
+// so the compiler emits the instructions of the kernel's target features.
+#[inline(always)]
+fn cios(a: &[u64], b: &[u64], n: &[u64], n_inv: u64, out: &mut [u64]) {
+    let s = n.len();
+    let mut t = [0_u64; MAX_LIMBS + 2];
+    for b in b.iter().take(s) {
+        let mut carry = 0_u128;
+        for j in 0..s {
+            let sum = t[j] as u128 + a[j] as u128 * *b as u128 + carry;
+            t[j] = sum as u64;
+            carry = sum >> 64;
+        }
+        let sum = t[s] as u128 + carry;
+        t[s] = sum as u64;
+        t[s + 1] = (sum >> 64) as u64;
+
+        let m = t[0].wrapping_mul(n_inv);
+        let mut carry = (t[0] as u128 + m as u128 * n[0] as u128) >> 64;
+        for j in 1..s {
+            let sum = t[j] as u128 + m as u128 * n[j] as u128 + carry;
+            t[j - 1] = sum as u64;
+            carry = sum >> 64;
+        }
+        let sum = t[s] as u128 + carry;
+        t[s - 1] = sum as u64;
+        t[s] = t[s + 1] + (sum >> 64) as u64;
+    }
+    if t[s] != 0 || !less_than(&t[..s], n) {
+        subtract(&mut t[..s], n);
+    }
+    out.copy_from_slice(&t[..s]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_multiply_the_same_with_every_kernel() {
+        let n = (BigInt::from(1) << 255) - 19;
+        let a = (BigInt::from(1) << 200) + 12_345;
+        let b = &n - 7;
+        for kernel in [Kernel::Portable, Kernel::Bmi2Adx] {
+            let mont = Montgomery::with_kernel(&n, kernel).unwrap();
+            let (ma, mb) = (mont.to_montgomery(&a), mont.to_montgomery(&b));
+            let mut out = vec![0; ma.len()];
+            mont.mul(&ma, &mb, &mut out);
+            assert_eq!(mont.from_montgomery(&out), (&a * &b) % &n);
+            mont.sqr(&mb, &mut out);
+            assert_eq!(mont.from_montgomery(&out), BigInt::from(49));
+            mont.sub(&ma, &mb, &mut out);
+            assert_eq!(mont.from_montgomery(&out), (&a - &b).mod_floor(&n));
+            let mut sum = mb.clone();
+            mont.add_assign(&mut sum, &mb);
+            assert_eq!(mont.from_montgomery(&sum), (&b * 2_u32).mod_floor(&n));
+        }
+        assert!(Montgomery::new(&BigInt::from(10)).is_none());
+        assert_eq!(Kernel::select(&CpuFeatures::default()), Kernel::Portable);
+        assert!(!CpuFeatures::detect().to_string().is_empty());
+    }
+}
@@ -0,0 +1,236 @@
+use std::io::{stderr, Write};
+use std::time::{Duration, Instant};
+
+use crate::errors::BilboError;
+
+const BAR_WIDTH: usize = 32;
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_FINDINGS_SHOWN: usize = 5;
+
+/// Progress of a single attack tracked by the Dashboard.
+///
+#[derive(Debug)]
+pub struct AttackProgress {
+    name: String,
+    done: usize,
+    total: usize,
+    started: Instant,
+    finished: bool,
+}
+
+impl AttackProgress {
+    /// Number of processed units per second since the attack started.
+    ///
+    #[inline(always)]
+    pub fn rate(&self) -> f64 {
+        let secs = self.started.elapsed().as_secs_f64();
+        if secs == 0_f64 {
+            return 0_f64;
+        }
+        self.done as f64 / secs
+    }
+
+    /// Estimated time left to process all units, None if the rate is unknown.
+    ///
+    #[inline(always)]
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.rate();
+        if rate == 0_f64 || self.done >= self.total {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            (self.total - self.done) as f64 / rate,
+        ))
+    }
+
+    #[inline(always)]
+    fn bar(&self) -> String {
+        let filled = (BAR_WIDTH * self.done.min(self.total))
+            .checked_div(self.total)
+            .unwrap_or(BAR_WIDTH);
+        format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+    }
+}
+
+/// Dashboard renders live progress of running attacks in the terminal.
+///
+/// Each attack gets its own progress bar with processed units per second (stages/sec for the strong pick lock)
+/// and ETA. Findings are listed below the bars as they stream in.
+/// The dashboard redraws itself in place using ANSI escape codes, so it shall be written to a terminal.
+///
+pub struct Dashboard {
+    out: Box<dyn Write + Send>,
+    attacks: Vec<AttackProgress>,
+    findings: Vec<String>,
+    drawn_lines: usize,
+    last_render: Option<Instant>,
+}
+
+impl Default for Dashboard {
+    fn default() -> Self {
+        Self::new(Box::new(stderr()))
+    }
+}
+
+impl Dashboard {
+    /// Creates new dashboard rendering to the given output.
+    ///
+    #[inline(always)]
+    pub fn new(out: Box<dyn Write + Send>) -> Self {
+        Self {
+            out,
+            attacks: Vec::new(),
+            findings: Vec::new(),
+            drawn_lines: 0,
+            last_render: None,
+        }
+    }
+
+    /// Adds attack to track and returns its id.
+    ///
+    #[inline(always)]
+    pub fn add_attack(&mut self, name: &str, total: usize) -> usize {
+        self.attacks.push(AttackProgress {
+            name: name.to_string(),
+            done: 0,
+            total,
+            started: Instant::now(),
+            finished: false,
+        });
+        self.attacks.len() - 1
+    }
+
+    /// Advances attack of given id by given number of units, redraws at most every 100 ms.
+    ///
+    #[inline(always)]
+    pub fn advance(&mut self, id: usize, units: usize) -> Result<(), BilboError> {
+        let Some(attack) = self.attacks.get_mut(id) else {
+            return Err(BilboError::GenericError(format!(
+                "unknown dashboard attack id {id}"
+            )));
+        };
+        attack.done += units;
+        if self
+            .last_render
+            .is_some_and(|last| last.elapsed() < REFRESH_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.render()
+    }
+
+    /// Marks attack of given id as finished and redraws.
+    ///
+    #[inline(always)]
+    pub fn finish(&mut self, id: usize) -> Result<(), BilboError> {
+        let Some(attack) = self.attacks.get_mut(id) else {
+            return Err(BilboError::GenericError(format!(
+                "unknown dashboard attack id {id}"
+            )));
+        };
+        attack.finished = true;
+        self.render()
+    }
+
+    /// Adds finding to the dashboard and redraws.
+    ///
+    #[inline(always)]
+    pub fn finding(&mut self, finding: &str) -> Result<(), BilboError> {
+        self.findings.push(finding.to_string());
+        self.render()
+    }
+
+    /// Returns tracked attacks.
+    ///
+    #[inline(always)]
+    pub fn attacks(&self) -> &[AttackProgress] {
+        &self.attacks
+    }
+
+    /// Redraws the dashboard in place.
+    ///
+    pub fn render(&mut self) -> Result<(), BilboError> {
+        let mut frame = String::new();
+        if self.drawn_lines > 0 {
+            frame.push_str(&format!("\x1b[{}A", self.drawn_lines));
+        }
+        let mut lines = Vec::with_capacity(self.attacks.len() + MAX_FINDINGS_SHOWN + 2);
+        for attack in self.attacks.iter() {
+            let eta = match (attack.finished, attack.eta()) {
+                (true, _) => "done".to_string(),
+                (false, Some(eta)) => format!("{}s", eta.as_secs()),
+                (false, None) => "--".to_string(),
+            };
+            lines.push(format!(
+                "{0: <24} {1} {2: >8}/{3: <8} {4: >10.1}/s ETA {5}",
+                attack.name,
+                attack.bar(),
+                attack.done,
+                attack.total,
+                attack.rate(),
+                eta
+            ));
+        }
+        lines.push(format!("Findings: {}", self.findings.len()));
+        let skip = self.findings.len().saturating_sub(MAX_FINDINGS_SHOWN);
+        for finding in self.findings.iter().skip(skip) {
+            lines.push(format!(" -> {finding}"));
+        }
+        for line in lines.iter() {
+            frame.push_str("\x1b[2K");
+            frame.push_str(line);
+            frame.push('\n');
+        }
+        self.out.write_all(frame.as_bytes())?;
+        self.out.flush()?;
+        self.drawn_lines = lines.len();
+        self.last_render = Some(Instant::now());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_should_render_progress_bars_and_findings() -> Result<(), BilboError> {
+        let buf = Buffer::default();
+        let mut dashboard = Dashboard::new(Box::new(buf.clone()));
+        let id = dashboard.add_attack("strong pick lock", 100);
+        dashboard.advance(id, 50)?;
+        dashboard.finding("cracked key.pem")?;
+        dashboard.finish(id)?;
+
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert!(out.contains("strong pick lock"));
+        assert!(out.contains("50/100"));
+        assert!(out.contains("cracked key.pem"));
+        assert!(out.contains("done"));
+        assert_eq!(dashboard.attacks()[0].done, 50);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fail_on_unknown_attack_id() {
+        let mut dashboard = Dashboard::new(Box::new(Buffer::default()));
+        assert!(dashboard.advance(1, 1).is_err());
+    }
+}
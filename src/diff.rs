@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{Read, Write};
+
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::errors::BilboError;
+use crate::schema::{upgrade, SCHEMA_VERSION};
+
+/// A finding of a single run, identified by the key and the detail.
+/// Identical findings produced by different attacks are deduplicated, scanners lists all of them
+/// and attack is the first one.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RunFinding {
+    pub key: String,
+    pub attack: String,
+    pub detail: String,
+    #[serde(default)]
+    pub scanners: Vec<String>,
+}
+
+impl RunFinding {
+    // Findings are the same regardless of the attacks that produced them.
+    #[inline(always)]
+    fn same(&self, other: &RunFinding) -> bool {
+        self.key == other.key && self.detail == other.detail
+    }
+}
+
+impl Display for RunFinding {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} [ {} ] {}",
+            self.key,
+            self.scanners.join(", "),
+            self.detail
+        )
+    }
+}
+
+/// RunDiff describes how findings changed between two runs.
+///
+/// - new: present in the newer run only,
+/// - resolved: present in the older run only,
+/// - persisting: present in both runs.
+///
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RunDiff {
+    pub schema_version: u32,
+    pub new: Vec<RunFinding>,
+    pub resolved: Vec<RunFinding>,
+    pub persisting: Vec<RunFinding>,
+}
+
+impl RunDiff {
+    /// Returns true if the newer run introduced findings that weren't in the older run.
+    ///
+    #[inline(always)]
+    pub fn has_new(&self) -> bool {
+        !self.new.is_empty()
+    }
+}
+
+impl Display for RunDiff {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for (label, findings) in [
+            ("NEW", &self.new),
+            ("RESOLVED", &self.resolved),
+            ("PERSISTING", &self.persisting),
+        ] {
+            writeln!(f, "[ {0: <10} ] {1}", label, findings.len())?;
+            for finding in findings.iter() {
+                writeln!(f, "  {finding}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Baseline is the recorded set of accepted findings, only findings missing from it fail the CI gate,
+/// so bilbo is introduced into existing repositories and fleets without fixing every known finding first.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Baseline {
+    pub schema_version: u32,
+    pub findings: Vec<RunFinding>,
+}
+
+impl Baseline {
+    /// Records the findings of a single run as the baseline.
+    ///
+    #[inline(always)]
+    pub fn from_records(records: &[AuditRecord]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            findings: findings(records),
+        }
+    }
+
+    /// Reads the baseline written by this or any older version of bilbo.
+    ///
+    #[inline(always)]
+    pub fn read(reader: impl Read) -> Result<Self, BilboError> {
+        let doc = upgrade(serde_json::from_reader(reader)?)?;
+        Ok(serde_json::from_value(doc)?)
+    }
+
+    /// Writes the baseline as pretty printed JSON.
+    ///
+    #[inline(always)]
+    pub fn write(&self, mut writer: impl Write) -> Result<(), BilboError> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    /// Returns true if the finding of given detail of the key is in the baseline, whichever attack produced it.
+    ///
+    #[inline(always)]
+    pub fn contains(&self, key: &str, detail: &str) -> bool {
+        self.findings
+            .iter()
+            .any(|f| f.key == key && f.detail == detail)
+    }
+
+    /// Returns the findings of the run missing from the baseline.
+    ///
+    #[inline(always)]
+    pub fn new_findings(&self, records: &[AuditRecord]) -> Vec<RunFinding> {
+        findings(records)
+            .into_iter()
+            .filter(|f| !self.contains(&f.key, &f.detail))
+            .collect()
+    }
+}
+
+/// Extracts unique findings from the records of a single run, sorted by key.
+///
+#[inline(always)]
+pub fn findings(records: &[AuditRecord]) -> Vec<RunFinding> {
+    let mut unique: BTreeMap<(&str, &str), Vec<String>> = BTreeMap::new();
+    for record in records.iter() {
+        if let AuditEvent::Finding {
+            attack,
+            key,
+            detail,
+            ..
+        } = &record.event
+        {
+            let scanners = unique.entry((key, detail)).or_default();
+            if !scanners.contains(attack) {
+                scanners.push(attack.clone());
+            }
+        }
+    }
+    unique
+        .into_iter()
+        .map(|((key, detail), scanners)| RunFinding {
+            key: key.to_string(),
+            attack: scanners[0].clone(),
+            detail: detail.to_string(),
+            scanners,
+        })
+        .collect()
+}
+
+/// Compares findings of the older and the newer run.
+///
+#[inline(always)]
+pub fn diff_runs(old: &[AuditRecord], new: &[AuditRecord]) -> RunDiff {
+    let old = findings(old);
+    let new = findings(new);
+    let contains = |run: &[RunFinding], f: &RunFinding| run.iter().any(|r| r.same(f));
+
+    RunDiff {
+        schema_version: SCHEMA_VERSION,
+        new: new.iter().filter(|f| !contains(&old, f)).cloned().collect(),
+        resolved: old.iter().filter(|f| !contains(&new, f)).cloned().collect(),
+        persisting: new.iter().filter(|f| contains(&old, f)).cloned().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::found_by;
+
+    fn finding(key: &str) -> AuditRecord {
+        found_by(key, "weak")
+    }
+
+    #[test]
+    fn it_should_report_new_resolved_and_persisting_findings() {
+        let started = AuditRecord {
+            schema_version: SCHEMA_VERSION,
+            timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+            event: AuditEvent::AttackStarted {
+                attack: "weak".to_string(),
+                key: "aa".to_string(),
+            },
+        };
+        let old = [started.clone(), finding("aa"), finding("bb"), finding("bb")];
+        let new = [started, finding("bb"), finding("cc")];
+
+        let diff = diff_runs(&old, &new);
+
+        assert!(diff.has_new());
+        assert_eq!(diff.new.len(), 1);
+        assert_eq!(diff.new[0].key, "cc");
+        assert_eq!(diff.resolved.len(), 1);
+        assert_eq!(diff.resolved[0].key, "aa");
+        assert_eq!(diff.persisting.len(), 1);
+        assert_eq!(diff.persisting[0].key, "bb");
+    }
+
+    #[test]
+    fn it_should_report_nothing_new_for_identical_runs() {
+        let run = [finding("aa")];
+        let diff = diff_runs(&run, &run);
+        assert!(!diff.has_new());
+        assert!(diff.resolved.is_empty());
+        assert_eq!(diff.persisting.len(), 1);
+    }
+
+    #[test]
+    fn it_should_gate_only_findings_missing_from_baseline() -> Result<(), BilboError> {
+        let baseline = Baseline::from_records(&[finding("aa"), found_by("aa", "strong")]);
+        let mut written = Vec::new();
+        baseline.write(&mut written)?;
+        let baseline = Baseline::read(written.as_slice())?;
+        assert_eq!(baseline.findings.len(), 1);
+        assert!(baseline.contains("aa", "private exponent recovered"));
+
+        let new = baseline.new_findings(&[found_by("aa", "ratios"), finding("bb")]);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].key, "bb");
+        assert!(Baseline::read(r#"{"findings":[]}"#.as_bytes())?
+            .findings
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_deduplicate_identical_findings_across_scanners() {
+        let old = [finding("aa")];
+        let new = [
+            found_by("aa", "strong"),
+            finding("aa"),
+            found_by("aa", "strong"),
+        ];
+
+        let unique = findings(&new);
+        assert_eq!(unique.len(), 1);
+        assert_eq!(unique[0].scanners, vec!["strong", "weak"]);
+        assert_eq!(
+            unique[0].to_string(),
+            "aa [ strong, weak ] private exponent recovered"
+        );
+
+        let diff = diff_runs(&old, &new);
+        assert!(!diff.has_new());
+        assert_eq!(diff.persisting.len(), 1);
+    }
+}
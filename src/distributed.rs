@@ -0,0 +1,453 @@
+use num_bigint::BigInt;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+use crate::batch_gcd::{batch_gcd_of_chunk, product_tree};
+use crate::ecm::ecm_curve;
+use crate::errors::BilboError;
+use crate::factor;
+use crate::race::Cancel;
+use crate::schema::{upgrade, SCHEMA_VERSION};
+use crate::telemetry::Telemetry;
+
+// Fermat candidates scanned between checks of cancellation.
+const FERMAT_CANCEL_CHECK: u64 = 1 << 14;
+
+/// Work is the part of the factorization a single machine runs, integers are decimal.
+///
+///  - Fermat, Fermat's method over steps candidates a of a² − n = b² starting at from.
+///  - Ecm, a batch of curves of Lenstra's ECM of stage 1 bound b1 and stage 2 bound b2.
+///  - BatchGcd, the batch gcd of a chunk of the moduli against the product of the whole set.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "attack", rename_all = "snake_case")]
+pub enum Work {
+    Fermat {
+        n: String,
+        from: String,
+        steps: u64,
+    },
+    Ecm {
+        n: String,
+        curves: u64,
+        b1: u64,
+        b2: u64,
+    },
+    BatchGcd {
+        moduli: Vec<String>,
+        product: String,
+    },
+}
+
+/// WorkUnit is the partition of the factorization the Coordinator assigns to one machine, written as JSON,
+/// run there by WorkUnit::run and its WorkResult sent back to the coordinator.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WorkUnit {
+    pub schema_version: u32,
+    pub id: u64,
+    pub work: Work,
+}
+
+/// Found is the nontrivial factor of the modulus n found by the work unit, decimal.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Found {
+    pub n: String,
+    pub factor: String,
+}
+
+/// WorkResult is the outcome of the work unit of given id, the factors it found, none if it found nothing,
+/// and the telemetry of the run.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WorkResult {
+    pub schema_version: u32,
+    pub id: u64,
+    pub found: Vec<Found>,
+    pub telemetry: Telemetry,
+}
+
+impl WorkUnit {
+    /// Runs the work unit, gives up with BilboError::Cancelled when cancelled, the unit shall be assigned again.
+    ///
+    pub fn run(&self, cancel: &Cancel) -> Result<WorkResult, BilboError> {
+        let mut telemetry = Telemetry::default();
+        let cancelled = || BilboError::Cancelled {
+            attack: format!("work unit {}", self.id),
+        };
+        let found = match &self.work {
+            Work::Fermat { n, from, steps } => {
+                let (n, mut a) = (decimal(n)?, decimal(from)?);
+                let mut rest = *steps;
+                let mut factor = None;
+                while rest > 0 && factor.is_none() {
+                    if cancel.is_cancelled() {
+                        return Err(cancelled());
+                    }
+                    let chunk = rest.min(FERMAT_CANCEL_CHECK);
+                    factor = factor::fermat(&n, &a, chunk, &mut telemetry);
+                    a += chunk;
+                    rest -= chunk;
+                }
+                factor.into_iter().map(|p| (n.clone(), p)).collect()
+            }
+            Work::Ecm { n, curves, b1, b2 } => {
+                let n = decimal(n)?;
+                let mut factor = None;
+                for _ in 0..*curves {
+                    if cancel.is_cancelled() {
+                        return Err(cancelled());
+                    }
+                    factor = ecm_curve(&n, *b1, *b2, &mut telemetry);
+                    if factor.is_some() {
+                        break;
+                    }
+                }
+                factor.into_iter().map(|p| (n.clone(), p)).collect()
+            }
+            Work::BatchGcd { moduli, product } => {
+                let moduli = moduli
+                    .iter()
+                    .map(|n| decimal(n))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if cancel.is_cancelled() {
+                    return Err(cancelled());
+                }
+                batch_gcd_of_chunk(&moduli, &decimal(product)?, &mut telemetry)
+                    .into_iter()
+                    .zip(moduli)
+                    .filter(|(g, n)| g > &BigInt::from(1) && g < n)
+                    .map(|(g, n)| (n, g))
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        Ok(WorkResult {
+            schema_version: SCHEMA_VERSION,
+            id: self.id,
+            found: found
+                .into_iter()
+                .map(|(n, factor)| Found {
+                    n: n.to_string(),
+                    factor: factor.to_string(),
+                })
+                .collect(),
+            telemetry,
+        })
+    }
+
+    /// Reads the work unit written by this or any older version of bilbo.
+    ///
+    #[inline(always)]
+    pub fn read(reader: impl Read) -> Result<Self, BilboError> {
+        let doc = upgrade(serde_json::from_reader(reader)?)?;
+        Ok(serde_json::from_value(doc)?)
+    }
+
+    /// Writes the work unit as pretty printed JSON.
+    ///
+    #[inline(always)]
+    pub fn write(&self, mut writer: impl Write) -> Result<(), BilboError> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+impl WorkResult {
+    /// Reads the work result written by this or any older version of bilbo.
+    ///
+    #[inline(always)]
+    pub fn read(reader: impl Read) -> Result<Self, BilboError> {
+        let doc = upgrade(serde_json::from_reader(reader)?)?;
+        Ok(serde_json::from_value(doc)?)
+    }
+
+    /// Writes the work result as pretty printed JSON.
+    ///
+    #[inline(always)]
+    pub fn write(&self, mut writer: impl Write) -> Result<(), BilboError> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+}
+
+/// Coordinator fans a big factorization out across many machines: it splits the Fermat range,
+/// the ECM curves or the moduli of the batch gcd into work units, hands them out and merges the results back.
+/// Results come from other machines, so every factor is checked to divide its modulus before it is accepted.
+/// Units of cancelled or lost machines stay pending until their result is merged, to be assigned again.
+///
+#[derive(Debug, Clone)]
+pub struct Coordinator {
+    units: Vec<WorkUnit>,
+    merged: Vec<bool>,
+    moduli: Vec<BigInt>,
+    factors: Vec<(BigInt, BigInt)>,
+    telemetry: Telemetry,
+}
+
+impl Coordinator {
+    /// Splits Fermat's method over steps candidates from ⌊√n⌋ + 1 into ranges of the given number of units.
+    ///
+    pub fn fermat(n: &BigInt, steps: u64, units: usize) -> Self {
+        let start: BigInt = n.sqrt() + 1;
+        let works = split(steps, units)
+            .map(|(from, steps)| Work::Fermat {
+                n: n.to_string(),
+                from: (&start + from).to_string(),
+                steps,
+            })
+            .collect();
+        Self::of(works, vec![n.clone()])
+    }
+
+    /// Splits curves of Lenstra's ECM of stage 1 bound b1 and stage 2 bound b2 into batches of the given number of units.
+    ///
+    pub fn ecm(n: &BigInt, curves: u64, b1: u64, b2: u64, units: usize) -> Self {
+        let works = split(curves, units)
+            .map(|(_, curves)| Work::Ecm {
+                n: n.to_string(),
+                curves,
+                b1,
+                b2,
+            })
+            .collect();
+        Self::of(works, vec![n.clone()])
+    }
+
+    /// Splits the batch gcd of the moduli into chunks of the given number of units, each unit carries
+    /// its chunk and the product of all the moduli. Moduli sharing both primes with the others have gcd n,
+    /// no factor, and are left to BatchPickLock.
+    ///
+    pub fn batch_gcd(moduli: &[BigInt], units: usize) -> Self {
+        let tree = product_tree(moduli);
+        let product = tree[tree.len() - 1]
+            .first()
+            .cloned()
+            .unwrap_or_default()
+            .to_string();
+        let works = split(moduli.len() as u64, units)
+            .map(|(from, len)| Work::BatchGcd {
+                moduli: moduli[from as usize..(from + len) as usize]
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect(),
+                product: product.clone(),
+            })
+            .collect();
+        Self::of(works, moduli.to_vec())
+    }
+
+    #[inline(always)]
+    fn of(works: Vec<Work>, moduli: Vec<BigInt>) -> Self {
+        let units: Vec<WorkUnit> = works
+            .into_iter()
+            .enumerate()
+            .map(|(id, work)| WorkUnit {
+                schema_version: SCHEMA_VERSION,
+                id: id as u64,
+                work,
+            })
+            .collect();
+        Self {
+            merged: vec![false; units.len()],
+            units,
+            moduli,
+            factors: Vec::new(),
+            telemetry: Telemetry::default(),
+        }
+    }
+
+    /// Returns all the work units.
+    ///
+    #[inline(always)]
+    pub fn units(&self) -> &[WorkUnit] {
+        &self.units
+    }
+
+    /// Returns the work units whose result was not merged yet.
+    ///
+    #[inline(always)]
+    pub fn pending(&self) -> impl Iterator<Item = &WorkUnit> {
+        self.units
+            .iter()
+            .zip(&self.merged)
+            .filter(|(_, merged)| !**merged)
+            .map(|(unit, _)| unit)
+    }
+
+    /// Merges the result of the work unit, rejecting results of unknown or already merged units
+    /// and factors not dividing their modulus of the job.
+    ///
+    pub fn merge(&mut self, result: &WorkResult) -> Result<(), BilboError> {
+        let Some(merged) = self.merged.get_mut(result.id as usize) else {
+            return Err(BilboError::GenericError(format!(
+                "unknown work unit {}",
+                result.id
+            )));
+        };
+        if *merged {
+            return Err(BilboError::GenericError(format!(
+                "work unit {} already merged",
+                result.id
+            )));
+        }
+        let mut factors = Vec::with_capacity(result.found.len());
+        for found in result.found.iter() {
+            let (n, factor) = (decimal(&found.n)?, decimal(&found.factor)?);
+            if !self.moduli.contains(&n) {
+                return Err(BilboError::GenericError(format!(
+                    "work unit {} found factor of unknown modulus {n}",
+                    result.id
+                )));
+            }
+            if factor <= BigInt::from(1) || factor >= n || (&n % &factor) != BigInt::from(0) {
+                return Err(BilboError::GenericError(format!(
+                    "work unit {} found {factor}, not a factor of {n}",
+                    result.id
+                )));
+            }
+            factors.push((n, factor));
+        }
+        *merged = true;
+        self.factors.extend(factors);
+        self.telemetry.merge(&result.telemetry);
+        Ok(())
+    }
+
+    /// Returns true once every unit is merged or the factor of the single modulus of Fermat or ECM is found,
+    /// the pending units may be dropped then.
+    ///
+    #[inline(always)]
+    pub fn is_done(&self) -> bool {
+        self.merged.iter().all(|merged| *merged)
+            || (self.moduli.len() == 1 && !self.factors.is_empty())
+    }
+
+    /// Returns the factors merged so far, as pairs of the modulus and its factor.
+    ///
+    #[inline(always)]
+    pub fn factors(&self) -> &[(BigInt, BigInt)] {
+        &self.factors
+    }
+
+    /// Returns the telemetry of all the merged units.
+    ///
+    #[inline(always)]
+    pub fn telemetry(&self) -> Telemetry {
+        self.telemetry
+    }
+}
+
+// Returns the offsets and lengths of total items split into at most the given number of parts, the longer parts first.
+#[inline(always)]
+fn split(total: u64, parts: usize) -> impl Iterator<Item = (u64, u64)> {
+    let parts = (parts.max(1) as u64).min(total.max(1));
+    let (len, longer) = (total / parts, total % parts);
+    (0..parts).map(move |i| (i * len + i.min(longer), len + u64::from(i < longer)))
+}
+
+#[inline(always)]
+fn decimal(value: &str) -> Result<BigInt, BilboError> {
+    value
+        .parse()
+        .map_err(|_| BilboError::GenericError(format!("invalid integer {value}, expected decimal")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primality::is_probable_prime;
+
+    // Runs every unit as a remote machine would, through the JSON documents, merging the results.
+    fn run_all(coordinator: &mut Coordinator) -> Result<(), BilboError> {
+        let units: Vec<WorkUnit> = coordinator.pending().cloned().collect();
+        for unit in units {
+            let mut buf = Vec::new();
+            unit.write(&mut buf)?;
+            let result = WorkUnit::read(buf.as_slice())?.run(&Cancel::default())?;
+            let mut buf = Vec::new();
+            result.write(&mut buf)?;
+            coordinator.merge(&WorkResult::read(buf.as_slice())?)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_split_work_and_merge_results_of_many_machines() -> Result<(), BilboError> {
+        // Primes 6 · 10^6 apart, a = (p + q) / 2 is about 4500 candidates past √n, in the third of four units.
+        let p = BigInt::from(1_000_000_007_u64);
+        let q = (6_000_000_u64..)
+            .map(|d| &p + d)
+            .find(is_probable_prime)
+            .unwrap_or_default();
+        let n = &p * &q;
+        let mut fermat = Coordinator::fermat(&n, 8_000, 4);
+        assert_eq!(fermat.units().len(), 4);
+        run_all(&mut fermat)?;
+        assert!(fermat.is_done());
+        assert_eq!(fermat.factors(), &[(n.clone(), q.clone())]);
+        assert!(fermat.telemetry().iterations < 8_000);
+
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(4_294_967_311_u64);
+        let mut ecm = Coordinator::ecm(&n, 60, 2_000, 200_000, 3);
+        run_all(&mut ecm)?;
+        assert!(ecm
+            .factors()
+            .iter()
+            .all(|(m, f)| m == &n && (&n % f) == BigInt::from(0)));
+        assert!(!ecm.factors().is_empty());
+
+        let primes = [1_000_003_u64, 1_000_033, 1_000_037, 1_000_039, 1_000_081].map(BigInt::from);
+        let moduli = [
+            &primes[0] * &primes[1],
+            &primes[2] * &primes[3],
+            &primes[1] * &primes[4],
+        ];
+        let mut gcd = Coordinator::batch_gcd(&moduli, 2);
+        run_all(&mut gcd)?;
+        assert!(gcd.is_done());
+        assert_eq!(
+            gcd.factors(),
+            &[
+                (moduli[0].clone(), primes[1].clone()),
+                (moduli[2].clone(), primes[1].clone())
+            ]
+        );
+
+        let forged = WorkResult {
+            schema_version: SCHEMA_VERSION,
+            id: 0,
+            found: vec![Found {
+                n: moduli[1].to_string(),
+                factor: "7".to_string(),
+            }],
+            telemetry: Telemetry::default(),
+        };
+        let mut fresh = Coordinator::batch_gcd(&moduli, 2);
+        assert!(fresh.merge(&forged).is_err());
+        assert!(fresh
+            .merge(&WorkResult {
+                id: 9,
+                ..forged.clone()
+            })
+            .is_err());
+        assert!(fresh
+            .merge(&WorkResult {
+                found: Vec::new(),
+                ..forged.clone()
+            })
+            .is_ok());
+        assert!(fresh
+            .merge(&WorkResult {
+                found: Vec::new(),
+                ..forged
+            })
+            .is_err());
+        assert_eq!(fresh.pending().count(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use num_bigint::{BigInt, Sign};
+use openssl::base64::decode_block;
+use openssl::pkey::{Id, PKey, Public};
+use openssl::rsa::Rsa;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::BufRead;
+
+use crate::cache::Cache;
+use crate::errors::BilboError;
+use crate::net::Resolver;
+use crate::rsa::PickLock;
+
+/// Selectors tried on the domains without their own selector list, as published by the common mail providers.
+///
+pub const COMMON_SELECTORS: [&str; 12] = [
+    "default",
+    "dkim",
+    "google",
+    "k1",
+    "k2",
+    "mail",
+    "s1",
+    "s2",
+    "selector1",
+    "selector2",
+    "smtp",
+    "mx",
+];
+/// Shortest RSA signing key verifiers accept, as required by RFC 8301.
+///
+pub const MIN_DKIM_BITS: u32 = 1024;
+/// Shortest RSA signing key signers are recommended to use, as recommended by RFC 8301.
+///
+pub const RECOMMENDED_DKIM_BITS: u32 = 2048;
+
+/// DkimWeakness is a weakness of the published DKIM key record.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DkimWeakness {
+    ShortKey(u32),
+    ShortOfRecommended(u32),
+    Sha1Only,
+    Testing,
+}
+
+impl Display for DkimWeakness {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            DkimWeakness::ShortKey(bits) => {
+                write!(f, "{bits} bit key shorter than {MIN_DKIM_BITS} bits")
+            }
+            DkimWeakness::ShortOfRecommended(bits) => write!(
+                f,
+                "{bits} bit key shorter than recommended {RECOMMENDED_DKIM_BITS} bits"
+            ),
+            DkimWeakness::Sha1Only => write!(f, "sha1 only hash"),
+            DkimWeakness::Testing => write!(f, "testing mode"),
+        }
+    }
+}
+
+/// DkimKey is the key record published by the domain under the selector.
+///
+///  - key_type is the k= tag, rsa by default.
+///  - hashes is the h= tag, empty if any hash is allowed.
+///  - testing is the t=y flag, verifiers treat failed signatures as unsigned.
+///  - public_key is None if the key was revoked by empty p= tag.
+///
+#[derive(Debug, Clone)]
+pub struct DkimKey {
+    pub domain: String,
+    pub selector: String,
+    pub key_type: String,
+    pub hashes: Vec<String>,
+    pub testing: bool,
+    pub public_key: Option<PKey<Public>>,
+}
+
+impl Display for DkimKey {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} {}", self.name(), self.key_type)?;
+        match &self.public_key {
+            Some(key) => write!(f, " {} bits", key.bits())?,
+            None => write!(f, " revoked")?,
+        }
+        if !self.hashes.is_empty() {
+            write!(f, ", hashes {}", self.hashes.join(":"))?;
+        }
+        if self.testing {
+            write!(f, ", testing")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DkimKey {
+    /// Parses DKIM key record of the selector of the domain, such as v=DKIM1; k=rsa; p=MIIB...
+    /// Keys are accepted as subject public key info, as the RFC requires, or bare PKCS#1 some signers publish.
+    ///
+    pub fn parse(domain: &str, selector: &str, record: &str) -> Result<Self, BilboError> {
+        let mut key = Self {
+            domain: domain.to_string(),
+            selector: selector.to_string(),
+            key_type: "rsa".to_string(),
+            hashes: Vec::new(),
+            testing: false,
+            public_key: None,
+        };
+        let mut published = None;
+        for tag in record.split(';') {
+            let Some((name, value)) = tag.split_once('=') else {
+                continue;
+            };
+            let value: String = value.split_whitespace().collect();
+            match name.trim() {
+                "v" if value != "DKIM1" => {
+                    return Err(BilboError::GenericError(format!(
+                        "{} is not DKIM1 record",
+                        key.name()
+                    )))
+                }
+                "k" => key.key_type = value.to_ascii_lowercase(),
+                "h" => key.hashes = value.split(':').map(|h| h.to_string()).collect(),
+                "t" => key.testing = value.split(':').any(|f| f == "y"),
+                "p" => published = Some(value),
+                _ => (),
+            }
+        }
+        let Some(published) = published else {
+            return Err(BilboError::GenericError(format!(
+                "{} record has no p= tag",
+                key.name()
+            )));
+        };
+        if !published.is_empty() {
+            let der = decode_block(&published)?;
+            key.public_key = Some(match PKey::public_key_from_der(&der) {
+                Ok(public_key) => public_key,
+                Err(_) => PKey::from_rsa(Rsa::public_key_from_der_pkcs1(&der)?)?,
+            });
+        }
+
+        Ok(key)
+    }
+
+    /// Returns the DNS name of the record, selector._domainkey.domain.
+    ///
+    #[inline(always)]
+    pub fn name(&self) -> String {
+        format!("{}._domainkey.{}", self.selector, self.domain)
+    }
+
+    /// Returns weaknesses of the record, short RSA key, signing limited to sha1 or testing mode.
+    ///
+    pub fn weaknesses(&self) -> Vec<DkimWeakness> {
+        let mut weaknesses = Vec::new();
+        if let Some(key) = self.public_key.as_ref().filter(|k| k.id() == Id::RSA) {
+            if key.bits() < MIN_DKIM_BITS {
+                weaknesses.push(DkimWeakness::ShortKey(key.bits()));
+            } else if key.bits() < RECOMMENDED_DKIM_BITS {
+                weaknesses.push(DkimWeakness::ShortOfRecommended(key.bits()));
+            }
+        }
+        if !self.hashes.is_empty() && self.hashes.iter().all(|h| h == "sha1") {
+            weaknesses.push(DkimWeakness::Sha1Only);
+        }
+        if self.testing {
+            weaknesses.push(DkimWeakness::Testing);
+        }
+
+        weaknesses
+    }
+
+    /// Returns PickLock of the published key, None if the key is revoked or not RSA.
+    ///
+    #[inline(always)]
+    pub fn pick_lock(&self) -> Result<Option<PickLock>, BilboError> {
+        let Some(Ok(rsa)) = self.public_key.as_ref().map(|k| k.rsa()) else {
+            return Ok(None);
+        };
+
+        Ok(Some(PickLock::from_exponent_and_modulus(
+            BigInt::from_bytes_be(Sign::Plus, &rsa.e().to_vec()),
+            BigInt::from_bytes_be(Sign::Plus, &rsa.n().to_vec()),
+        )))
+    }
+}
+
+/// Reads the list of domains, one per line optionally followed by its selectors separated by whitespace or commas.
+/// Blank lines and lines starting with # are skipped.
+///
+pub fn read_domains(reader: impl BufRead) -> Result<Vec<(String, Vec<String>)>, BilboError> {
+    let mut domains = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut words = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|w| !w.is_empty());
+        let Some(domain) = words.next() else {
+            continue;
+        };
+        domains.push((
+            domain.trim_end_matches('.').to_ascii_lowercase(),
+            words.map(|w| w.to_string()).collect(),
+        ));
+    }
+
+    Ok(domains)
+}
+
+/// Queries the DKIM key record of the selector of the domain, None if the selector publishes no DKIM record.
+///
+pub fn scan(
+    domain: &str,
+    selector: &str,
+    resolver: &Resolver,
+    cache: &Cache,
+) -> Result<Option<DkimKey>, BilboError> {
+    let name = format!("{selector}._domainkey.{domain}");
+    let records = resolver.txt_cached(&name, cache)?;
+    // Names without DKIM record often answer with the wildcard SPF or verification records of the domain.
+    let Some(record) = records
+        .iter()
+        .find(|r| r.contains("p=") && (r.contains("v=DKIM1") || r.contains("k=")))
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(DkimKey::parse(domain, selector, record)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::base64::encode_block;
+
+    #[test]
+    fn it_should_parse_dkim_records_and_flag_weak_keys() -> Result<(), BilboError> {
+        let given = "# mail domains\nexample.com\nexample.org selector1, selector2\n";
+        assert_eq!(
+            read_domains(given.as_bytes())?,
+            vec![
+                ("example.com".to_string(), vec![]),
+                (
+                    "example.org".to_string(),
+                    vec!["selector1".to_string(), "selector2".to_string()]
+                )
+            ]
+        );
+
+        let short = Rsa::generate(768)?;
+        let spki = encode_block(&PKey::from_rsa(short.clone())?.public_key_to_der()?);
+        let record = format!(
+            "v=DKIM1; k=rsa; h=sha1; t=y; p={} {}",
+            &spki[..40],
+            &spki[40..]
+        );
+        let key = DkimKey::parse("example.com", "s1", &record)?;
+        assert_eq!(key.name(), "s1._domainkey.example.com");
+        assert_eq!(
+            key.weaknesses(),
+            vec![
+                DkimWeakness::ShortKey(768),
+                DkimWeakness::Sha1Only,
+                DkimWeakness::Testing
+            ]
+        );
+        assert_eq!(key.pick_lock()?.map(|pl| pl.bits()), Some(768));
+        assert_eq!(
+            key.to_string(),
+            "s1._domainkey.example.com rsa 768 bits, hashes sha1, testing"
+        );
+
+        let pkcs1 = encode_block(&Rsa::generate(1024)?.public_key_to_der_pkcs1()?);
+        let key = DkimKey::parse("example.com", "s2", &format!("p={pkcs1}"))?;
+        assert_eq!(
+            key.weaknesses(),
+            vec![DkimWeakness::ShortOfRecommended(1024)]
+        );
+
+        let revoked = DkimKey::parse("example.com", "old", "v=DKIM1; p=")?;
+        assert!(revoked.public_key.is_none());
+        assert!(revoked.weaknesses().is_empty());
+        assert!(revoked.pick_lock()?.is_none());
+        assert!(DkimKey::parse("example.com", "spf", "v=spf1 -all").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+use num_bigint::{BigInt, RandBigInt};
+use num_integer::Integer;
+
+use crate::int::Int;
+use crate::sieve;
+use crate::telemetry::Telemetry;
+
+// Giant step of stage 2, primes are paired with the baby step j · Q below half of it, 2 · 3 · 5 · 7.
+const STAGE_2_STEP: u64 = 210;
+// Number of stage 2 primes whose differences are multiplied together before a single gcd is taken.
+const GCD_BATCH: usize = 100;
+
+/// Point is a point of Montgomery curve B · y² = x³ + A · x² + x in projective x-only coordinates X : Z,
+/// y is never needed, the point at infinity has Z = 0.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Point {
+    pub x: Int,
+    pub z: Int,
+}
+
+/// Curve is Montgomery curve modulo n given by (A + 2) / 4, the constant of its doubling formula.
+///
+#[derive(Debug, Clone)]
+pub struct Curve {
+    n: Int,
+    a24: Int,
+}
+
+impl Curve {
+    /// Creates the curve of Suyama's parametrization of sigma with its starting point,
+    /// the group order of every such curve is divisible by 12, which makes it more likely smooth.
+    /// Returns Err with the factor of n if a denominator is not invertible, a lucky find.
+    ///
+    pub fn suyama(n: &BigInt, sigma: &BigInt) -> Result<(Self, Point), BigInt> {
+        let u = (sigma * sigma - 5_u32).mod_floor(n);
+        let v = (sigma * 4_u32).mod_floor(n);
+        let u3 = u.modpow(&BigInt::from(3), n);
+        let v3 = v.modpow(&BigInt::from(3), n);
+        // (A + 2) / 4 = (v − u)³ · (3u + v) / (16 · u³ · v).
+        let numerator = ((&v - &u).modpow(&BigInt::from(3), n) * (&u * 3_u32 + &v)).mod_floor(n);
+        let denominator = (&u3 * &v * 16_u32).mod_floor(n);
+        let Some(inverse) = denominator.modinv(n) else {
+            return Err(denominator.gcd(n));
+        };
+        let curve = Self {
+            n: Int::from(n),
+            a24: Int::from(&(numerator * inverse).mod_floor(n)),
+        };
+        let start = Point {
+            x: Int::from(&u3),
+            z: Int::from(&v3),
+        };
+
+        Ok((curve, start))
+    }
+
+    /// Returns 2 · P.
+    ///
+    #[inline(always)]
+    pub fn double(&self, p: &Point) -> Point {
+        let n = &self.n;
+        let sum = (&p.x + &p.z).square().mod_floor(n);
+        let diff = (&p.x - &p.z).square().mod_floor(n);
+        let t = &sum - &diff;
+        Point {
+            x: (&sum * &diff).mod_floor(n),
+            z: (&t * &(&diff + &(&self.a24 * &t))).mod_floor(n),
+        }
+    }
+
+    /// Returns P + Q given their difference P − Q, the differential addition of x-only coordinates.
+    ///
+    #[inline(always)]
+    pub fn add(&self, p: &Point, q: &Point, difference: &Point) -> Point {
+        let n = &self.n;
+        let u = &(&p.x - &p.z) * &(&q.x + &q.z);
+        let v = &(&p.x + &p.z) * &(&q.x - &q.z);
+        Point {
+            x: (&difference.z * &(&u + &v).square()).mod_floor(n),
+            z: (&difference.x * &(&u - &v).square()).mod_floor(n),
+        }
+    }
+
+    /// Returns k · P by the Montgomery ladder, the same sequence of operations for every bit of k.
+    ///
+    pub fn multiply(&self, p: &Point, k: u64) -> Point {
+        if k == 0 {
+            return Point {
+                x: Int::from(0),
+                z: Int::from(0),
+            };
+        }
+        let (mut r0, mut r1) = (p.clone(), self.double(p));
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            if k >> bit & 1 == 1 {
+                r0 = self.add(&r1, &r0, p);
+                r1 = self.double(&r1);
+            } else {
+                r1 = self.add(&r0, &r1, p);
+                r0 = self.double(&r0);
+            }
+        }
+
+        r0
+    }
+}
+
+/// Lenstra's elliptic curve method with a single random curve of Suyama's parametrization in Montgomery form.
+/// Stage 1 multiplies the point by all prime powers up to b1, finding factor p of n when the order of
+/// the curve modulo p is b1 smooth. Stage 2 allows the order one more prime up to b2, paired by baby and giant steps.
+/// Stage 2 bound not above b1 runs stage 1 only.
+///
+pub fn ecm_curve(n: &BigInt, b1: u64, b2: u64, telemetry: &mut Telemetry) -> Option<BigInt> {
+    telemetry.iterations += 1;
+    telemetry.candidates_sieved += b1.max(b2);
+    let sigma = rand::thread_rng().gen_bigint_range(&BigInt::from(6), n);
+    let (curve, mut q) = match Curve::suyama(n, &sigma) {
+        Ok(start) => start,
+        Err(factor) => return (&factor != n).then_some(factor),
+    };
+    let (n, one) = (&curve.n, Int::from(1));
+    let primes = sieve::primes(b1.max(b2));
+    let stage_1 = primes.partition_point(|p| *p <= b1);
+    for p in primes[..stage_1].iter().copied() {
+        let mut power = p;
+        while power <= b1 / p {
+            power *= p;
+        }
+        q = curve.multiply(&q, power);
+    }
+    let g = q.z.gcd(n);
+    if g != one || stage_1 == primes.len() {
+        return (g > one && &g < n).then(|| g.to_bigint());
+    }
+
+    // Stage 2, prime p = m · STEP ± j meets (m · STEP) · Q and j · Q at the same x modulo the factor.
+    let half = (STAGE_2_STEP / 2) as usize;
+    let doubled = curve.double(&q);
+    let mut baby = vec![q.clone(), curve.add(&doubled, &q, &q)];
+    while 2 * baby.len() < half {
+        let next = curve.add(&baby[baby.len() - 1], &doubled, &baby[baby.len() - 2]);
+        baby.push(next);
+    }
+    let giant = curve.multiply(&q, STAGE_2_STEP);
+    let mut m = ((b1 + STAGE_2_STEP / 2) / STAGE_2_STEP).max(1);
+    let mut current = curve.multiply(&q, m * STAGE_2_STEP);
+    // Giant step from 1 · G has no predecessor, 2 · G is a doubling.
+    let mut previous = (m > 1).then(|| curve.multiply(&q, (m - 1) * STAGE_2_STEP));
+    let mut product = one.clone();
+    let mut pending = 0;
+    let mut rest = primes[stage_1..].iter().copied().peekable();
+    while let Some(p) = rest.peek().copied() {
+        let center = m * STAGE_2_STEP;
+        if p > center + STAGE_2_STEP / 2 {
+            let next = match &previous {
+                Some(previous) => curve.add(&current, &giant, previous),
+                None => curve.double(&current),
+            };
+            previous = Some(std::mem::replace(&mut current, next));
+            m += 1;
+            continue;
+        }
+        rest.next();
+        product = if p < STAGE_2_STEP / 2 {
+            // Primes below the first giant step are baby steps themselves, p · Q is at infinity modulo the factor.
+            (&product * &baby[p as usize / 2].z).mod_floor(n)
+        } else {
+            let b = &baby[p.abs_diff(center) as usize / 2];
+            (&product * &(&(&current.x * &b.z) - &(&b.x * &current.z))).mod_floor(n)
+        };
+        pending += 1;
+        if pending == GCD_BATCH || rest.peek().is_none() {
+            pending = 0;
+            let g = product.gcd(n);
+            if g != one {
+                return (&g < n).then(|| g.to_bigint());
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_find_factors_on_montgomery_curves() {
+        // Point arithmetic agrees, 5 · P by the ladder is 2 · P + 3 · P and 4 · P + P.
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(4_294_967_311_u64);
+        let (curve, p) = Curve::suyama(&n, &BigInt::from(11)).unwrap();
+        let (p2, p3, p4) = (
+            curve.double(&p),
+            curve.multiply(&p, 3),
+            curve.multiply(&p, 4),
+        );
+        let m = Int::from(&n);
+        let same_x =
+            |a: &Point, b: &Point| (&(&a.x * &b.z) - &(&b.x * &a.z)).mod_floor(&m).is_zero();
+        assert!(same_x(&curve.add(&p3, &p2, &p), &curve.multiply(&p, 5)));
+        assert!(same_x(&curve.add(&p4, &p, &p3), &curve.multiply(&p, 5)));
+        assert!(curve.multiply(&p, 0).z.is_zero());
+
+        let mut telemetry = Telemetry::default();
+        let factor = (0..200)
+            .find_map(|_| ecm_curve(&n, 200, 200, &mut telemetry))
+            .unwrap();
+        assert!(factor == BigInt::from(1_000_003) || factor == BigInt::from(4_294_967_311_u64));
+
+        // Stage 2 finds the factor in fewer curves than stage 1 alone with the same b1.
+        let (mut stage_1, mut stage_2) = (Telemetry::default(), Telemetry::default());
+        let n = BigInt::from(1_000_000_007_u64) * BigInt::from(4_294_967_311_u64);
+        for _ in 0..5 {
+            while ecm_curve(&n, 50, 50, &mut stage_1).is_none() {}
+            while ecm_curve(&n, 50, 5_000, &mut stage_2).is_none() {}
+        }
+        assert!(stage_2.iterations < stage_1.iterations);
+    }
+}
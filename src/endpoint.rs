@@ -0,0 +1,733 @@
+use num_bigint::{BigInt, Sign};
+use openssl::sha::sha256;
+use openssl::ssl::{
+    SslConnector, SslConnectorBuilder, SslMethod, SslStream, SslVerifyMode, SslVersion,
+};
+use openssl::x509::{X509VerifyResult, X509};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::BufRead;
+use std::net::{IpAddr, TcpStream};
+use std::time::Duration;
+
+use crate::cache::{Cache, Material};
+use crate::errors::BilboError;
+use crate::http::{get_unverified, Response, Url};
+use crate::net::{handshake_error, Dialer, DualStackReport};
+use crate::revocation::{check_revocation, Revocation};
+use crate::rsa::PickLock;
+
+const MAX_REDIRECTS: usize = 10;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Handshake errors of probes the local OpenSSL build can't offer, rather than the endpoint refusing them.
+const LOCALLY_UNSUPPORTED: [&str; 2] = ["no protocols available", "no ciphers available"];
+
+/// Endpoint is the host actually serving the certificate, reached from the requested URL after following redirects,
+/// with the addresses the resolver of the scan answered for it.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub url: String,
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub addrs: Vec<IpAddr>,
+}
+
+impl Display for Endpoint {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if self.host.contains(':') {
+            write!(f, "{} → [{}]:{}", self.url, self.host, self.port)?;
+        } else {
+            write!(f, "{} → {}:{}", self.url, self.host, self.port)?;
+        }
+        if !self.addrs.is_empty() {
+            let addrs: Vec<String> = self.addrs.iter().map(|a| a.to_string()).collect();
+            write!(f, " at {}", addrs.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ServedCertificate is a unique leaf certificate with all the endpoints serving it
+/// and its issuer certificate, if any endpoint served the chain.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServedCertificate {
+    pub fingerprint: String,
+    pub subject: String,
+    pub der: Vec<u8>,
+    pub issuer: Option<Vec<u8>>,
+    pub endpoints: Vec<Endpoint>,
+}
+
+impl ServedCertificate {
+    /// Parses DER encoded certificate, it is served by no endpoint yet.
+    ///
+    #[inline(always)]
+    pub fn from_der(der: &[u8]) -> Result<Self, BilboError> {
+        let cert = X509::from_der(der)?;
+        let subject = cert
+            .subject_name()
+            .entries()
+            .map(|e| {
+                format!(
+                    "{}={}",
+                    e.object().nid().short_name().unwrap_or("?"),
+                    e.data()
+                        .as_utf8()
+                        .map(|s| s.to_string())
+                        .unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ok(Self {
+            fingerprint: hex(&sha256(der)),
+            subject,
+            der: der.to_vec(),
+            issuer: None,
+            endpoints: Vec::new(),
+        })
+    }
+
+    /// Checks whether the issuer has already revoked the certificate.
+    ///
+    #[inline(always)]
+    pub fn revocation(&self, cache: &Cache) -> Revocation {
+        check_revocation(&self.der, self.issuer.as_deref(), cache)
+    }
+
+    /// Returns PickLock of the certificate key, None if the key is not RSA.
+    ///
+    #[inline(always)]
+    pub fn pick_lock(&self) -> Result<Option<PickLock>, BilboError> {
+        let Ok(rsa) = X509::from_der(&self.der)?.public_key()?.rsa() else {
+            return Ok(None);
+        };
+
+        Ok(Some(PickLock::from_exponent_and_modulus(
+            BigInt::from_bytes_be(Sign::Plus, &rsa.e().to_vec()),
+            BigInt::from_bytes_be(Sign::Plus, &rsa.n().to_vec()),
+        )))
+    }
+}
+
+/// TransportWeakness is a weak protocol version or cipher class offered by the endpoint.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportWeakness {
+    Ssl3,
+    Tls10,
+    Export,
+    Rc4,
+    TripleDes,
+}
+
+/// All the weaknesses probed by the transport scan.
+///
+pub const TRANSPORT_WEAKNESSES: [TransportWeakness; 5] = [
+    TransportWeakness::Ssl3,
+    TransportWeakness::Tls10,
+    TransportWeakness::Export,
+    TransportWeakness::Rc4,
+    TransportWeakness::TripleDes,
+];
+
+impl Display for TransportWeakness {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                TransportWeakness::Ssl3 => "SSLv3",
+                TransportWeakness::Tls10 => "TLS 1.0",
+                TransportWeakness::Export => "export ciphers",
+                TransportWeakness::Rc4 => "RC4",
+                TransportWeakness::TripleDes => "3DES",
+            }
+        )
+    }
+}
+
+impl TransportWeakness {
+    /// Returns the weakness of the protocol version named by OpenSSL, such as TLSv1, if any.
+    ///
+    #[inline(always)]
+    pub fn of_version(version: &str) -> Option<Self> {
+        match version {
+            "SSLv3" => Some(TransportWeakness::Ssl3),
+            "TLSv1" => Some(TransportWeakness::Tls10),
+            _ => None,
+        }
+    }
+
+    /// Returns the weakness of the cipher suite named by OpenSSL, such as DES-CBC3-SHA, if any.
+    ///
+    #[inline(always)]
+    pub fn of_cipher(cipher: &str) -> Option<Self> {
+        let cipher = cipher.to_ascii_uppercase();
+        if cipher.starts_with("EXP") {
+            Some(TransportWeakness::Export)
+        } else if cipher.contains("RC4") {
+            Some(TransportWeakness::Rc4)
+        } else if cipher.contains("DES-CBC3") || cipher.contains("3DES") {
+            Some(TransportWeakness::TripleDes)
+        } else {
+            None
+        }
+    }
+
+    // Restricts the handshake to the weak protocol or cipher class, security level 0 lets OpenSSL offer them.
+    #[inline(always)]
+    fn restrict(&self, builder: &mut SslConnectorBuilder) -> Result<(), BilboError> {
+        let (version, ciphers) = match self {
+            TransportWeakness::Ssl3 => (SslVersion::SSL3, "ALL:@SECLEVEL=0"),
+            TransportWeakness::Tls10 => (SslVersion::TLS1, "ALL:@SECLEVEL=0"),
+            TransportWeakness::Export => (SslVersion::TLS1_2, "EXP:@SECLEVEL=0"),
+            TransportWeakness::Rc4 => (SslVersion::TLS1_2, "RC4:@SECLEVEL=0"),
+            TransportWeakness::TripleDes => (SslVersion::TLS1_2, "3DES:@SECLEVEL=0"),
+        };
+        builder.set_max_proto_version(Some(version))?;
+        if let TransportWeakness::Ssl3 | TransportWeakness::Tls10 = self {
+            builder.set_min_proto_version(Some(version))?;
+        }
+        builder.set_cipher_list(ciphers)?;
+
+        Ok(())
+    }
+}
+
+/// TransportReport is the protocol version and cipher suite the endpoint negotiates by default,
+/// the weak ones it offers when asked for them and whether it accepts connections over IPv4 and IPv6.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportReport {
+    pub host: String,
+    pub port: u16,
+    pub version: String,
+    pub cipher: String,
+    pub offered: Vec<TransportWeakness>,
+    pub untested: Vec<TransportWeakness>,
+    pub dual_stack: DualStackReport,
+}
+
+impl Display for TransportReport {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let list = |weaknesses: &[TransportWeakness]| {
+            weaknesses
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "{}:{} negotiates {} {}",
+            self.host, self.port, self.version, self.cipher
+        )?;
+        if !self.offered.is_empty() {
+            write!(f, ", offers {}", list(&self.offered))?;
+        }
+        if !self.untested.is_empty() {
+            write!(f, ", untested {}", list(&self.untested))?;
+        }
+        if !self.dual_stack.attempts.is_empty() {
+            write!(f, ", {}", self.dual_stack)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// CertInventory collects the certificates grabbed from the list of URLs,
+/// every unique certificate once with all its endpoints, and the URLs that failed.
+///
+#[derive(Debug, Clone, Default)]
+pub struct CertInventory {
+    certificates: Vec<ServedCertificate>,
+    transports: Vec<TransportReport>,
+    failures: Vec<(String, String)>,
+}
+
+impl CertInventory {
+    /// Adds the certificate served by the endpoint with its issuer, if served, returns true if it wasn't seen yet.
+    ///
+    pub fn add(
+        &mut self,
+        endpoint: Endpoint,
+        der: &[u8],
+        issuer: Option<&[u8]>,
+    ) -> Result<bool, BilboError> {
+        let fingerprint = hex(&sha256(der));
+        if let Some(cert) = self
+            .certificates
+            .iter_mut()
+            .find(|c| c.fingerprint == fingerprint)
+        {
+            if !cert.endpoints.contains(&endpoint) {
+                cert.endpoints.push(endpoint);
+            }
+            if cert.issuer.is_none() {
+                cert.issuer = issuer.map(|i| i.to_vec());
+            }
+            return Ok(false);
+        }
+        let mut cert = ServedCertificate::from_der(der)?;
+        cert.issuer = issuer.map(|i| i.to_vec());
+        cert.endpoints.push(endpoint);
+        self.certificates.push(cert);
+
+        Ok(true)
+    }
+
+    /// Adds the transport report of the endpoint.
+    ///
+    #[inline(always)]
+    pub fn add_transport(&mut self, report: TransportReport) {
+        self.transports.push(report);
+    }
+
+    /// Returns the transport report of the host and port, if it was probed.
+    ///
+    #[inline(always)]
+    pub fn transport(&self, host: &str, port: u16) -> Option<&TransportReport> {
+        self.transports
+            .iter()
+            .find(|t| t.host == host && t.port == port)
+    }
+
+    /// Returns transport reports of all the probed endpoints.
+    ///
+    #[inline(always)]
+    pub fn transports(&self) -> &[TransportReport] {
+        &self.transports
+    }
+
+    /// Records the URL that served no certificate.
+    ///
+    #[inline(always)]
+    pub fn fail(&mut self, url: &str, err: &BilboError) {
+        self.failures.push((url.to_string(), err.to_string()));
+    }
+
+    /// Returns unique certificates in the order they were first grabbed.
+    ///
+    #[inline(always)]
+    pub fn certificates(&self) -> &[ServedCertificate] {
+        &self.certificates
+    }
+
+    /// Returns URLs that failed with the reason.
+    ///
+    #[inline(always)]
+    pub fn failures(&self) -> &[(String, String)] {
+        &self.failures
+    }
+}
+
+/// Reads the list of URLs, one per line, blank lines and lines starting with # are skipped.
+/// Bare host names, as found in asset inventories, are taken as https URLs.
+///
+pub fn read_urls(reader: impl BufRead) -> Result<Vec<String>, BilboError> {
+    let mut urls = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let url = line.trim();
+        if url.is_empty() || url.starts_with('#') {
+            continue;
+        }
+        if url.contains("://") {
+            urls.push(url.to_string());
+        } else {
+            urls.push(format!("https://{url}"));
+        }
+    }
+
+    Ok(urls)
+}
+
+/// Returns the target of the redirect response, None if the response is not a redirect.
+///
+#[inline(always)]
+pub fn redirect(url: &Url, response: &Response) -> Result<Option<Url>, BilboError> {
+    if !matches!(response.status, 301 | 302 | 303 | 307 | 308) {
+        return Ok(None);
+    }
+    let Some(location) = response.header("Location") else {
+        return Err(BilboError::GenericError(format!(
+            "{url} redirects with status {} but without location",
+            response.status
+        )));
+    };
+
+    Ok(Some(url.join(location)?))
+}
+
+/// Follows the redirects of the URL and returns the final URL actually serving the content.
+/// Certificates of the hosts on the way are not verified, their names are resolved by the dialer
+/// and failed requests retried by its policy.
+///
+pub fn final_url(url: &Url, dialer: &Dialer) -> Result<Url, BilboError> {
+    let mut visited = vec![url.clone()];
+    for _ in 0..MAX_REDIRECTS {
+        let current = &visited[visited.len() - 1];
+        let response = dialer
+            .retry()
+            .run(|| get_unverified(current, &[], dialer.resolver()))?;
+        let Some(next) = redirect(current, &response)? else {
+            return Ok(current.clone());
+        };
+        if visited.contains(&next) {
+            return Err(BilboError::GenericError(format!(
+                "{url} redirects in a loop at {next}"
+            )));
+        }
+        visited.push(next);
+    }
+
+    Err(BilboError::GenericError(format!(
+        "{url} redirects more than {MAX_REDIRECTS} times"
+    )))
+}
+
+/// Handshakes TLS with the host and returns DER of the leaf certificate it serves
+/// and DER of the issuer certificate if the host serves the chain.
+/// The certificate is not verified, invalid and self signed certificates are grabbed too.
+/// Certificates are cached by host and port, failed handshakes are retried by the policy of the dialer.
+///
+pub fn grab_certificate(
+    host: &str,
+    port: u16,
+    dialer: &Dialer,
+    cache: &Cache,
+) -> Result<(Vec<u8>, Option<Vec<u8>>), BilboError> {
+    // The chain is cached PEM encoded, the leaf first, entries of older versions hold the DER of the leaf alone.
+    let chain = cache.fetch(Material::Certificate, &format!("{host}:{port}"), || {
+        let stream = dialer
+            .retry()
+            .run(|| tls_connect(host, port, dialer, cache, None)?)?;
+        let Some(cert) = stream.ssl().peer_certificate() else {
+            return Err(BilboError::GenericError(format!(
+                "{host}:{port} served no certificate"
+            )));
+        };
+        let (der, mut pem) = (cert.to_der()?, cert.to_pem()?);
+        // Self signed leaf issues itself, it has no other issuer to ask about revocation.
+        let issuer = stream.ssl().peer_cert_chain().and_then(|chain| {
+            chain
+                .iter()
+                .find(|c| {
+                    c.issued(&cert) == X509VerifyResult::OK && c.to_der().is_ok_and(|d| d != der)
+                })
+                .map(|c| c.to_pem())
+        });
+        if let Some(issuer) = issuer {
+            pem.extend(issuer?);
+        }
+
+        Ok(pem)
+    })?;
+    let chain =
+        X509::stack_from_pem(&chain).or_else(|_| X509::from_der(&chain).map(|c| vec![c]))?;
+    let mut chain = chain.into_iter();
+    let Some(leaf) = chain.next() else {
+        return Err(BilboError::GenericError(format!(
+            "{host}:{port} served no certificate"
+        )));
+    };
+
+    Ok((
+        leaf.to_der()?,
+        chain.next().map(|c| c.to_der()).transpose()?,
+    ))
+}
+
+/// Records protocol version and cipher suite the host negotiates by default
+/// and probes it with handshakes restricted to every weak protocol and cipher class.
+/// Probes the local OpenSSL can't attempt are reported as untested.
+/// Every address of the host is connected to, so the families it listens on are reported too.
+///
+pub fn probe_transport(
+    host: &str,
+    port: u16,
+    dialer: &Dialer,
+    cache: &Cache,
+) -> Result<TransportReport, BilboError> {
+    // The connection is closed before probing, some servers handle one connection at a time.
+    let (version, cipher) = {
+        let stream = dialer
+            .retry()
+            .run(|| tls_connect(host, port, dialer, cache, None)?)?;
+        let cipher = stream.ssl().current_cipher().map(|c| c.name().to_string());
+        (
+            stream.ssl().version_str().to_string(),
+            cipher.unwrap_or_default(),
+        )
+    };
+    let mut offered: Vec<TransportWeakness> = TransportWeakness::of_version(&version)
+        .into_iter()
+        .chain(TransportWeakness::of_cipher(&cipher))
+        .collect();
+    let mut untested = Vec::new();
+    for weakness in TRANSPORT_WEAKNESSES {
+        if offered.contains(&weakness) {
+            continue;
+        }
+        match tls_connect(host, port, dialer, cache, Some(weakness)) {
+            Ok(Ok(stream)) => {
+                let ssl = stream.ssl();
+                let negotiated = match weakness {
+                    TransportWeakness::Ssl3 | TransportWeakness::Tls10 => {
+                        TransportWeakness::of_version(ssl.version_str())
+                    }
+                    _ => ssl
+                        .current_cipher()
+                        .and_then(|c| TransportWeakness::of_cipher(c.name())),
+                };
+                if negotiated == Some(weakness) {
+                    offered.push(weakness);
+                }
+            }
+            Ok(Err(e))
+                if LOCALLY_UNSUPPORTED
+                    .iter()
+                    .any(|u| e.to_string().contains(u)) =>
+            {
+                untested.push(weakness)
+            }
+            Ok(Err(_)) => (),
+            Err(BilboError::OpensslStackError(_)) => untested.push(weakness),
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(TransportReport {
+        host: host.to_string(),
+        port,
+        version,
+        cipher,
+        offered,
+        untested,
+        dual_stack: dialer.probe(host, port, cache)?,
+    })
+}
+
+// Connects to the host and handshakes TLS without verifying the certificate, restricted to the weakness when probing.
+// The outer error is connection failure or probe the local OpenSSL can't configure, the inner one the failed handshake.
+#[inline(always)]
+fn tls_connect(
+    host: &str,
+    port: u16,
+    dialer: &Dialer,
+    cache: &Cache,
+    probe: Option<TransportWeakness>,
+) -> Result<Result<SslStream<TcpStream>, BilboError>, BilboError> {
+    let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+    builder.set_verify(SslVerifyMode::NONE);
+    match probe {
+        Some(weakness) => weakness.restrict(&mut builder)?,
+        // Legacy only endpoints still serve their certificate.
+        None => builder.set_cipher_list("DEFAULT:@SECLEVEL=0")?,
+    }
+    let mut config = builder.build().configure()?;
+    config.set_verify_hostname(false);
+    // Server name indication is not sent for IP addresses.
+    config.set_use_server_name_indication(host.parse::<IpAddr>().is_err());
+    let tcp = dialer.connect(host, port, HANDSHAKE_TIMEOUT, cache)?;
+
+    Ok(config.connect(host, tcp).map_err(handshake_error))
+}
+
+/// Grabs the certificates of all the URLs following their redirects, each unique certificate is analyzed once.
+/// Transport of every endpoint is probed once too. Host names are resolved by the dialer.
+///
+pub fn grab_certificates(urls: &[String], dialer: &Dialer, cache: &Cache) -> CertInventory {
+    let mut inventory = CertInventory::default();
+    for url in urls.iter() {
+        let grabbed = Url::parse(url)
+            .and_then(|u| final_url(&u, dialer))
+            .and_then(|u| {
+                if !u.tls {
+                    return Err(BilboError::GenericError(format!(
+                        "{url} ends at {u} without TLS"
+                    )));
+                }
+                let (der, issuer) = grab_certificate(&u.host, u.port, dialer, cache)?;
+                if inventory.transport(&u.host, u.port).is_none() {
+                    inventory.add_transport(probe_transport(&u.host, u.port, dialer, cache)?);
+                }
+                let endpoint = Endpoint {
+                    url: url.clone(),
+                    addrs: dialer.resolve(&u.host, cache)?,
+                    host: u.host,
+                    port: u.port,
+                };
+                inventory.add(endpoint, &der, issuer.as_deref())
+            });
+        if let Err(e) = grabbed {
+            inventory.fail(url, &e);
+        }
+    }
+
+    inventory
+}
+
+#[inline(always)]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{AddrFamily, ConnectAttempt, NetOutcome};
+    use crate::testing::self_signed;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use std::net::SocketAddr;
+
+    fn certificate(cn: &str) -> Result<Vec<u8>, BilboError> {
+        let key = PKey::from_rsa(Rsa::generate(1024)?)?;
+
+        Ok(self_signed(cn, &key, 365)?.to_der()?)
+    }
+
+    #[test]
+    fn it_should_follow_redirects_and_analyze_unique_certificates_once() -> Result<(), BilboError> {
+        let given = "# inventory\n\nexample.com\nhttp://www.example.com/login\n";
+        assert_eq!(
+            read_urls(given.as_bytes())?,
+            vec!["https://example.com", "http://www.example.com/login"]
+        );
+
+        let url = Url::parse("http://www.example.com/login")?;
+        let response = |status: u16, location: &[(&str, &str)]| Response {
+            status,
+            headers: location
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            body: Vec::new(),
+        };
+        let next = redirect(
+            &url,
+            &response(301, &[("Location", "https://example.com/login")]),
+        )?;
+        assert_eq!(
+            next.map(|u| (u.tls, u.host)),
+            Some((true, "example.com".to_string()))
+        );
+        assert_eq!(redirect(&url, &response(200, &[]))?, None);
+        assert!(redirect(&url, &response(302, &[])).is_err());
+
+        let (shared, other) = (certificate("example.com")?, certificate("api.example.com")?);
+        let endpoint = |url: &str, host: &str| Endpoint {
+            url: url.to_string(),
+            host: host.to_string(),
+            port: 443,
+            addrs: vec!["192.0.2.1".parse().unwrap()],
+        };
+        let mut inventory = CertInventory::default();
+        assert!(inventory.add(
+            endpoint("https://example.com", "example.com"),
+            &shared,
+            None
+        )?);
+        assert!(!inventory.add(
+            endpoint("http://www.example.com", "example.com"),
+            &shared,
+            Some(&other)
+        )?);
+        assert!(inventory.add(
+            endpoint("https://api.example.com", "api.example.com"),
+            &other,
+            None
+        )?);
+        inventory.fail(
+            "https://down.example.com",
+            &BilboError::GenericError("refused".to_string()),
+        );
+
+        assert_eq!(inventory.certificates().len(), 2);
+        assert_eq!(inventory.certificates()[0].endpoints.len(), 2);
+        assert_eq!(
+            inventory.certificates()[0].endpoints[0].to_string(),
+            "https://example.com → example.com:443 at 192.0.2.1"
+        );
+        assert_eq!(inventory.certificates()[0].subject, "CN=example.com");
+        assert_eq!(inventory.certificates()[0].issuer, Some(other.clone()));
+        assert_eq!(inventory.failures().len(), 1);
+        let pl = inventory.certificates()[1].pick_lock()?.unwrap();
+        assert_eq!(pl.bits(), 1024);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_classify_transport_weaknesses() {
+        assert_eq!(
+            TransportWeakness::of_version("TLSv1"),
+            Some(TransportWeakness::Tls10)
+        );
+        assert_eq!(TransportWeakness::of_version("TLSv1.2"), None);
+        let given = [
+            "EXP-RC4-MD5",
+            "RC4-SHA",
+            "DES-CBC3-SHA",
+            "ECDHE-RSA-DES-CBC3-SHA",
+            "ECDHE-RSA-AES128-GCM-SHA256",
+        ];
+        let expected = [
+            Some(TransportWeakness::Export),
+            Some(TransportWeakness::Rc4),
+            Some(TransportWeakness::TripleDes),
+            Some(TransportWeakness::TripleDes),
+            None,
+        ];
+        for (g, e) in given.iter().zip(expected) {
+            assert_eq!(TransportWeakness::of_cipher(g), e);
+        }
+
+        let report = TransportReport {
+            host: "example.com".to_string(),
+            port: 443,
+            version: "TLSv1.2".to_string(),
+            cipher: "DES-CBC3-SHA".to_string(),
+            offered: vec![TransportWeakness::TripleDes, TransportWeakness::Tls10],
+            untested: vec![TransportWeakness::Ssl3],
+            dual_stack: DualStackReport::default(),
+        };
+        assert_eq!(
+            report.to_string(),
+            "example.com:443 negotiates TLSv1.2 DES-CBC3-SHA, offers 3DES, TLS 1.0, untested SSLv3"
+        );
+
+        let attempt = |addr: &str, outcome| ConnectAttempt {
+            addr: addr.parse().unwrap(),
+            family: AddrFamily::of(&addr.parse::<SocketAddr>().unwrap().ip()),
+            outcome,
+            elapsed_ms: 1,
+            message: None,
+        };
+        let report = TransportReport {
+            dual_stack: DualStackReport {
+                attempts: vec![
+                    attempt("[2001:db8::1]:443", NetOutcome::Refused),
+                    attempt("192.0.2.1:443", NetOutcome::Success),
+                ],
+            },
+            ..report
+        };
+        assert_eq!(
+            report.to_string(),
+            "example.com:443 negotiates TLSv1.2 DES-CBC3-SHA, offers 3DES, TLS 1.0, untested SSLv3, IPv4 success, IPv6 connection refused"
+        );
+    }
+}
@@ -64,11 +64,11 @@ impl Shannon {
                 .or_insert(1_f64);
         }
         let div: f64 = self.buf.len() as f64;
-        let sum = self.freq.iter().fold(0_f64, |mut acc, (_, v)| {
+        let sum = -self.freq.iter().fold(0_f64, |mut acc, (_, v)| {
             let f = v / div;
             acc += f * f64::log2(f);
             acc
-        }) * -1_f64;
+        });
         f64::ceil(sum) as u64 * div as u64
     }
 }
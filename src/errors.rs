@@ -3,7 +3,13 @@ use ping::Error;
 use std::{io, str::Utf8Error};
 use thiserror::Error;
 
+use crate::net::NetOutcome;
+use crate::rsa::Coverage;
+
 /// BilboError describes and wraps around all other errors.
+/// Attacks fail with NotFactorable, IterationLimitReached, Exhausted, Cancelled, QuotaExceeded or TimedOut,
+/// key loaders with InvalidKeyMaterial or UnsupportedKeyType, so callers branch on the failure mode
+/// instead of the message, GenericError is left for the rest.
 ///
 #[derive(Error, Debug)]
 pub enum BilboError {
@@ -17,6 +23,32 @@ pub enum BilboError {
     Utf8Error(#[from] Utf8Error),
     #[error("Openssl failed with message: {0}")]
     OpensslStackError(#[from] ErrorStack),
+    #[error("Json failed with message: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Network failed with {outcome} after {attempts} attempt(s): {message}")]
+    NetworkError {
+        outcome: NetOutcome,
+        attempts: u32,
+        message: String,
+    },
+    #[error("Attack {attack} cannot factor the modulus, {reason}")]
+    NotFactorable { attack: String, reason: String },
+    #[error("Attack {attack} reached its limit of {limit} iterations without a factor")]
+    IterationLimitReached { attack: String, limit: u64 },
+    #[error("Invalid key material, {reason}")]
+    InvalidKeyMaterial { reason: String },
+    #[error("Unsupported key type {key_type}, only RSA keys are picked")]
+    UnsupportedKeyType { key_type: String },
+    #[error("Attack {attack} exhausted its budget without success, covered {coverage}")]
+    Exhausted { attack: String, coverage: Coverage },
+    #[error(
+        "Attack {attack} was cancelled, another attack finished first or the caller aborted it"
+    )]
+    Cancelled { attack: String },
+    #[error("Attack {attack} exceeded {quota}")]
+    QuotaExceeded { attack: String, quota: String },
+    #[error("Attack {attack} timed out, it ran out of its {limit_ms} ms time limit")]
+    TimedOut { attack: String, limit_ms: u64 },
     #[error("Bilbo failed with message: {0}")]
     GenericError(String),
 }
@@ -24,6 +56,6 @@ pub enum BilboError {
 impl From<BilboError> for std::io::Error {
     #[inline(always)]
     fn from(value: BilboError) -> Self {
-        Self::new(io::ErrorKind::Other, format!("{value}"))
+        Self::other(format!("{value}"))
     }
 }
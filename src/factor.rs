@@ -0,0 +1,705 @@
+use num_bigint::{BigInt, RandBigInt, Sign};
+use num_integer::Integer;
+
+use crate::cpu::Montgomery;
+use crate::int::Int;
+use crate::modmath::{Modulus, Residue};
+use crate::race::Cancel;
+use crate::sieve;
+use crate::telemetry::Telemetry;
+
+// Seeds of Williams' p + 1 with discriminants v² − 4 of independent residuosity, 5, 3, 2 and 77,
+// so at least one is a non-residue mod p but for one in sixteen primes.
+const WILLIAMS_SEEDS: [u64; 4] = [3, 4, 6, 9];
+// Number of rho steps whose differences are multiplied together before a single gcd is taken.
+const GCD_BATCH: u64 = 100;
+// Steps of Lehman's method between checks of cancellation.
+const LEHMAN_CANCEL_CHECK: u128 = 1 << 16;
+// Primes tried by trial division between checks of cancellation.
+const TRIAL_CANCEL_CHECK: usize = 1 << 12;
+// Circumference of the wheel, the product of its primes, candidates are the numbers coprime to it.
+const WHEEL: u64 = 210;
+const WHEEL_PRIMES: [u64; 4] = [2, 3, 5, 7];
+
+/// Largest modulus, in bits, factored by Lehman's method, 4 · k · n and a² stay below 2^128.
+///
+pub const MAX_LEHMAN_BITS: u64 = 90;
+
+/// Returns all primes up to given bound, a copy of the primes cached by the segmented sieve.
+///
+#[inline(always)]
+pub fn small_primes(bound: u64) -> Vec<u64> {
+    sieve::primes(bound).to_vec()
+}
+
+/// Returns the smallest prime factor of n up to given bound.
+/// Gives up when cancelled, checked every few thousand primes.
+///
+#[inline(always)]
+pub fn trial_division(
+    n: &BigInt,
+    bound: u64,
+    telemetry: &mut Telemetry,
+    cancel: &Cancel,
+) -> Option<BigInt> {
+    telemetry.candidates_sieved += bound;
+    sieve::primes(bound)
+        .iter()
+        .copied()
+        .enumerate()
+        .take_while(|(i, _)| i % TRIAL_CANCEL_CHECK != 0 || !cancel.is_cancelled())
+        .map(|(_, p)| BigInt::from(p))
+        .inspect(|_| telemetry.primes_tested += 1)
+        .find(|p| p < n && (n % p).sign() == Sign::NoSign)
+}
+
+/// Returns the smallest prime factor of n up to given bound by trial division on the wheel of 2 · 3 · 5 · 7:
+/// past the wheel primes only the 48 of every 210 numbers coprime to them are tried, with no sieve to build,
+/// and the candidates whose product fits a word share a single reduction of n.
+/// Composite candidates never divide n before their smaller prime factors do, so the first divisor is a prime.
+///
+pub fn wheel_trial_division(n: &BigInt, bound: u64, telemetry: &mut Telemetry) -> Option<BigInt> {
+    telemetry.candidates_sieved += bound;
+    let spokes: Vec<u64> = (1..WHEEL).filter(|r| r.gcd(&WHEEL) == 1).collect();
+    let candidates = WHEEL_PRIMES
+        .into_iter()
+        .chain(
+            (0..)
+                .step_by(WHEEL as usize)
+                .flat_map(|base| spokes.iter().map(move |r| base + r))
+                .skip(1),
+        )
+        .take_while(|d| *d <= bound);
+
+    let divides = |batch: &[u64], product: u64, telemetry: &mut Telemetry| {
+        let residue = u64::try_from(n % product).unwrap_or_default();
+        telemetry.primes_tested += batch.len() as u64;
+        batch
+            .iter()
+            .find(|d| residue % **d == 0 && BigInt::from(**d) < *n)
+            .map(|d| BigInt::from(*d))
+    };
+    let mut batch = Vec::new();
+    let mut product = 1_u64;
+    for d in candidates {
+        let Some(next) = product.checked_mul(d) else {
+            if let Some(p) = divides(&batch, product, telemetry) {
+                return Some(p);
+            }
+            batch.clear();
+            batch.push(d);
+            product = d;
+            continue;
+        };
+        batch.push(d);
+        product = next;
+    }
+
+    divides(&batch, product, telemetry)
+}
+
+/// Pollard's rho, finds factor p of n in about √p iterations of x → x² + c mod n.
+/// Brent's cycle detection, the tortoise jumps to the hare at powers of two, so every step squares once
+/// instead of the three squarings of Floyd's, and differences are accumulated in batches to save gcd computations.
+/// The iteration runs in Montgomery form on the multiplication kernel selected for the running CPU.
+/// Gives up when cancelled, checked once per batch.
+///
+pub fn pollard_rho(
+    n: &BigInt,
+    iterations: u64,
+    c: u64,
+    telemetry: &mut Telemetry,
+    cancel: &Cancel,
+) -> Option<BigInt> {
+    let one = BigInt::from(1);
+    let Some(mont) = Montgomery::new(n) else {
+        // Even n has the factor 2, too large n is out of reach of rho anyway.
+        return (n.is_even() && n > &BigInt::from(2)).then(|| BigInt::from(2));
+    };
+    let c = mont.to_montgomery(&BigInt::from(c));
+    let mut square = vec![0; c.len()];
+    let mut f = |x: &mut Vec<u64>| {
+        mont.sqr(x, &mut square);
+        x.copy_from_slice(&square);
+        mont.add_assign(x, &c);
+    };
+    let mut y = mont.to_montgomery(&BigInt::from(2));
+    let mut diff = vec![0; c.len()];
+    let mut next = vec![0; c.len()];
+    let mut done = 0;
+    let mut cycle = 1;
+    while done < iterations && !cancel.is_cancelled() {
+        let x = y.clone();
+        for _ in 0..cycle {
+            f(&mut y);
+        }
+        done += cycle;
+        telemetry.iterations += cycle;
+        let mut walked = 0;
+        while walked < cycle && done < iterations {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let batch_y = y.clone();
+            let mut product = mont.to_montgomery(&one);
+            let batch = GCD_BATCH.min(cycle - walked).min(iterations - done);
+            for _ in 0..batch {
+                f(&mut y);
+                mont.sub(&x, &y, &mut diff);
+                mont.mul(&product, &diff, &mut next);
+                std::mem::swap(&mut product, &mut next);
+            }
+            walked += batch;
+            done += batch;
+            telemetry.iterations += batch;
+            let g = mont.raw(&product).gcd(n);
+            if g == one {
+                continue;
+            }
+            if &g != n {
+                return Some(g);
+            }
+            // The batch overshot, replay it step by step from its start.
+            let mut y = batch_y;
+            for _ in 0..batch {
+                f(&mut y);
+                mont.sub(&x, &y, &mut diff);
+                let g = mont.raw(&diff).gcd(n);
+                if g != one {
+                    return (&g != n).then_some(g);
+                }
+            }
+        }
+        cycle *= 2;
+    }
+
+    None
+}
+
+/// Pollard's p − 1, finds factor p of n when p − 1 is smooth, all its prime power factors are below bound.
+/// Stage 2 above bound allows p − 1 one more prime factor up to stage_2_bound, the standard continuation
+/// walks the primes by their gaps, so each costs a single multiplication by a cached power.
+/// Stage 2 bound not above bound runs stage 1 only.
+/// Gives up when cancelled.
+///
+pub fn pollard_p_minus_1(
+    n: &BigInt,
+    bound: u64,
+    stage_2_bound: u64,
+    telemetry: &mut Telemetry,
+    cancel: &Cancel,
+) -> Option<BigInt> {
+    let one = BigInt::from(1);
+    let modulus = Modulus::new(n);
+    let mut a = modulus.residue(&BigInt::from(2));
+    let primes = sieve::primes(bound.max(stage_2_bound));
+    telemetry.candidates_sieved += bound.max(stage_2_bound);
+    // Prime 2 stays in stage 1 even below bound 2, the gaps of stage 2 are between odd primes.
+    let stage_1 = primes.partition_point(|q| *q <= bound.max(2));
+    for q in primes[..stage_1].iter().copied() {
+        if cancel.is_cancelled() {
+            return None;
+        }
+        telemetry.iterations += 1;
+        let mut power = q;
+        while power <= bound / q {
+            power *= q;
+        }
+        a = modulus.pow(&a, &BigInt::from(power));
+    }
+    let g = (modulus.value(&a) - &one).gcd(n);
+    if g > one || stage_1 == primes.len() {
+        return (g > one && &g < n).then_some(g);
+    }
+
+    // Stage 2, a^q for every prime q above bound, accumulated in batches between gcds.
+    let unit = modulus.one();
+    let mut gaps: Vec<Residue> = Vec::new();
+    let mut x = modulus.pow(&a, &BigInt::from(primes[stage_1]));
+    let mut product = unit.clone();
+    let mut previous = primes[stage_1];
+    for (i, q) in primes[stage_1..].iter().copied().enumerate() {
+        if i > 0 {
+            let gap = ((q - previous) / 2) as usize;
+            while gaps.len() < gap {
+                gaps.push(modulus.pow(&a, &BigInt::from(2 * (gaps.len() + 1))));
+            }
+            x = modulus.mul(&x, &gaps[gap - 1]);
+            previous = q;
+        }
+        telemetry.iterations += 1;
+        product = modulus.mul(&product, &modulus.sub(&x, &unit));
+        if (i as u64 + 1).is_multiple_of(GCD_BATCH) || stage_1 + i + 1 == primes.len() {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            let g = modulus.value(&product).gcd(n);
+            if g > one {
+                return (&g < n).then_some(g);
+            }
+        }
+    }
+
+    None
+}
+
+/// Williams' p + 1, finds factor p of n when p + 1 is smooth, all its prime power factors are below bound.
+/// Runs the Lucas sequence V of every seed, each succeeds when its discriminant is a quadratic non-residue mod p,
+/// a residue finds p − 1 smooth primes instead.
+/// Gives up when cancelled.
+///
+pub fn williams_p_plus_1(
+    n: &BigInt,
+    bound: u64,
+    telemetry: &mut Telemetry,
+    cancel: &Cancel,
+) -> Option<BigInt> {
+    let (one, two) = (BigInt::from(1), BigInt::from(2));
+    let modulus = Modulus::new(n);
+    let primes = sieve::primes(bound);
+    telemetry.candidates_sieved += bound;
+    for seed in WILLIAMS_SEEDS {
+        let mut v = modulus.residue(&BigInt::from(seed));
+        for q in primes.iter().copied() {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            telemetry.iterations += 1;
+            let mut power = q;
+            while power <= bound / q {
+                power *= q;
+            }
+            v = lucas_v(&modulus, &v, power);
+        }
+        let g = (modulus.value(&v) - &two).gcd(n);
+        if g > one && &g < n {
+            return Some(g);
+        }
+    }
+
+    None
+}
+
+// Returns V_m(v) mod n of the Lucas sequence V_0 = 2, V_1 = v, V_k+1 = v · V_k − V_k−1, by the binary ladder.
+#[inline(always)]
+fn lucas_v(modulus: &Modulus, v: &Residue, m: u64) -> Residue {
+    let two = modulus.residue(&BigInt::from(2));
+    let (mut x, mut y) = (v.clone(), modulus.sub(&modulus.square(v), &two));
+    for bit in (0..63 - m.leading_zeros()).rev() {
+        if m >> bit & 1 == 1 {
+            x = modulus.sub(&modulus.mul(&x, &y), v);
+            y = modulus.sub(&modulus.square(&y), &two);
+        } else {
+            y = modulus.sub(&modulus.mul(&x, &y), v);
+            x = modulus.sub(&modulus.square(&x), &two);
+        }
+    }
+
+    x
+}
+
+/// Fermat's method, scans steps candidates from a for a² − n = b², returns the factor a + b of n once found.
+/// a² − n is tracked modulo a few small moduli in machine words instead of as a big integer, candidates are
+/// filtered by batches of 64 a bit each, non squares modulo any of the moduli drop out, about 1 in 400 candidates
+/// remain for the full square test. Chunks of the range starting at any a are scanned independently,
+/// by the parallel weak lock pick on its workers.
+///
+pub fn fermat(n: &BigInt, a: &BigInt, steps: u64, telemetry: &mut Telemetry) -> Option<BigInt> {
+    let mut filter = SquareFilter::new(n, a);
+    let (start, n) = (Int::from(a), Int::from(n));
+    let mut at = 0;
+    while at < steps {
+        let batch = (steps - at).min(64);
+        let mut survivors = filter.next_batch(batch);
+        while survivors != 0 {
+            let j = u64::from(survivors.trailing_zeros());
+            survivors &= survivors - 1;
+            let mut candidate = start.clone();
+            candidate += at + j;
+            let rest = &candidate.square() - &n;
+            if rest.is_perfect_square() {
+                telemetry.iterations += at + j + 1;
+                return Some((&candidate + &rest.sqrt()).to_bigint());
+            }
+        }
+        at += batch;
+    }
+    telemetry.iterations += steps;
+
+    None
+}
+
+// Moduli of the square filter of Fermat's method with their squares, 12 in 64, 16 in 63, 21 in 65, 6 in 11, 9 in 17
+// and 10 in 19, a² − n passes all of them with probability about 1/430 unless it is a square.
+const SQUARE_FILTER_MODULI: [u64; 6] = [64, 63, 65, 11, 17, 19];
+
+// SquareFilter tracks a² − n and 2a + 1 modulo the filter moduli as a steps by one, the limited precision sieve
+// stage of Fermat's method, every step costs a few word additions of each modulus, data parallel over the batch.
+struct SquareFilter {
+    lanes: Vec<(u64, u128, u64, u64)>,
+}
+
+impl SquareFilter {
+    #[inline(always)]
+    fn new(n: &BigInt, a: &BigInt) -> Self {
+        let residue =
+            |v: &BigInt, m: u64| u64::try_from(v.mod_floor(&BigInt::from(m))).unwrap_or_default();
+        let (rest, step) = (a * a - n, a * 2 + 1);
+        let lanes = SQUARE_FILTER_MODULI
+            .iter()
+            .map(|m| {
+                let squares = (0..*m).fold(0_u128, |squares, x| squares | 1 << (x * x % m));
+                (*m, squares, residue(&rest, *m), residue(&step, *m))
+            })
+            .collect();
+        Self { lanes }
+    }
+
+    // Returns bit j set for the next candidates j up to len, 64 at most, whose a² − n is a square modulo all the moduli.
+    #[inline(always)]
+    fn next_batch(&mut self, len: u64) -> u64 {
+        let mut survivors = if len == 64 { u64::MAX } else { (1 << len) - 1 };
+        for (m, squares, rest, step) in self.lanes.iter_mut() {
+            let mut passed = 0;
+            for j in 0..len {
+                passed |= u64::from((*squares >> *rest) as u8 & 1) << j;
+                *rest += *step;
+                if *rest >= *m {
+                    *rest -= *m;
+                }
+                *step += 2;
+                if *step >= *m {
+                    *step -= *m;
+                }
+            }
+            survivors &= passed;
+        }
+
+        survivors
+    }
+}
+
+/// Lehman's method, deterministically finds a factor of n up to MAX_LEHMAN_BITS in O(n^(1/3)) steps.
+/// For every k up to n^(1/3) it scans a from √(4 · k · n) up to n^(1/6) / (4 · √k) further for a² − 4 · k · n = b²,
+/// then gcd(a + b, n) is a factor; it misses only factors below n^(1/3), which trial division finds.
+/// The scan runs first, balanced primes are found for small k long before trial division would finish.
+/// Returns None for primes and moduli out of range, gives up when cancelled.
+///
+pub fn lehman(n: u128, telemetry: &mut Telemetry, cancel: &Cancel) -> Option<u128> {
+    if n < 4 || 128 - n.leading_zeros() as u64 > MAX_LEHMAN_BITS {
+        return None;
+    }
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    let cube_root = integer_cbrt(n);
+    let sixth_root = (n as f64).powf(1.0 / 6.0);
+    for k in 1..=cube_root {
+        if k.is_multiple_of(LEHMAN_CANCEL_CHECK) && cancel.is_cancelled() {
+            return None;
+        }
+        let four_kn = 4 * k * n;
+        let root = four_kn.isqrt();
+        let first = if root * root == four_kn {
+            root
+        } else {
+            root + 1
+        };
+        let last = root + (sixth_root / (4.0 * (k as f64).sqrt())) as u128 + 1;
+        for a in first..=last {
+            telemetry.iterations += 1;
+            let c = a * a - four_kn;
+            let b = c.isqrt();
+            if b * b == c {
+                let g = (a + b).gcd(&n);
+                if g > 1 && g < n {
+                    return Some(g);
+                }
+            }
+        }
+    }
+
+    telemetry.candidates_sieved += cube_root as u64;
+    for d in (3..=cube_root).step_by(2) {
+        if d % LEHMAN_CANCEL_CHECK == 1 && cancel.is_cancelled() {
+            return None;
+        }
+        telemetry.primes_tested += 1;
+        if n.is_multiple_of(d) {
+            return Some(d);
+        }
+    }
+
+    None
+}
+
+// Returns the integer cube root of n, the float estimate corrected by exact arithmetic.
+#[inline(always)]
+fn integer_cbrt(n: u128) -> u128 {
+    let mut r = (n as f64).cbrt() as u128;
+    while r * r * r > n {
+        r -= 1;
+    }
+    while (r + 1) * (r + 1) * (r + 1) <= n {
+        r += 1;
+    }
+
+    r
+}
+
+/// Returns m and the largest k ≥ 2 of n = m^k, None if n is not a perfect power.
+/// Every perfect power is a power of a prime exponent, so only the integer roots of the prime k up to log2 n
+/// are taken, and the root found is tried again from the same k, the exponents of nested powers multiply.
+///
+pub fn perfect_power(n: &BigInt) -> Option<(BigInt, u32)> {
+    let mut base = n.clone();
+    let mut exponent = 1;
+    let mut smallest = 2;
+    while base > BigInt::from(1) {
+        let Some((k, root)) = sieve::primes(base.bits())
+            .iter()
+            .map(|k| *k as u32)
+            .filter(|k| *k >= smallest)
+            .map(|k| (k, base.nth_root(k)))
+            .find(|(k, root)| root.pow(*k) == base)
+        else {
+            break;
+        };
+        smallest = k;
+        exponent *= k;
+        base = root;
+    }
+
+    (exponent > 1).then_some((base, exponent))
+}
+
+/// Lenstra's elliptic curve method with a single random curve in affine Weierstrass coordinates.
+/// Finds factor p of n when the order of the curve modulo p is smooth up to b1,
+/// the factor is revealed by the failure to invert a denominator modulo n.
+///
+pub fn ecm_curve(n: &BigInt, b1: u64, telemetry: &mut Telemetry) -> Option<BigInt> {
+    telemetry.iterations += 1;
+    telemetry.candidates_sieved += b1;
+    let mut rng = rand::thread_rng();
+    let a = rng.gen_bigint_range(&BigInt::from(0), n);
+    let mut point = Point::Affine(
+        rng.gen_bigint_range(&BigInt::from(0), n),
+        rng.gen_bigint_range(&BigInt::from(0), n),
+    );
+    for q in sieve::primes(b1).iter().copied() {
+        let mut power = q;
+        while power <= b1 / q {
+            power *= q;
+        }
+        point = match point.mul(power, &a, n) {
+            Ok(point) => point,
+            Err(factor) => return (&factor != n).then_some(factor),
+        };
+    }
+
+    None
+}
+
+#[derive(Clone)]
+enum Point {
+    Infinity,
+    Affine(BigInt, BigInt),
+}
+
+impl Point {
+    // Adds points on y² = x³ + a·x + b mod n, the error holds gcd of the non invertible denominator and n.
+    #[inline(always)]
+    fn add(&self, other: &Point, a: &BigInt, n: &BigInt) -> Result<Point, BigInt> {
+        let (Point::Affine(x1, y1), Point::Affine(x2, y2)) = (self, other) else {
+            return Ok(match self {
+                Point::Infinity => other.clone(),
+                _ => self.clone(),
+            });
+        };
+        let (numerator, denominator) = if x1 == x2 {
+            if ((y1 + y2) % n).sign() == Sign::NoSign {
+                return Ok(Point::Infinity);
+            }
+            (BigInt::from(3) * x1 * x1 + a, BigInt::from(2) * y1)
+        } else {
+            (y2 - y1, x2 - x1)
+        };
+        let denominator = denominator.mod_floor(n);
+        let Some(inverse) = denominator.modinv(n) else {
+            return Err(denominator.gcd(n));
+        };
+        let slope = (numerator * inverse).mod_floor(n);
+        let x3 = (&slope * &slope - x1 - x2).mod_floor(n);
+        let y3 = (slope * (x1 - &x3) - y1).mod_floor(n);
+
+        Ok(Point::Affine(x3, y3))
+    }
+
+    // Multiplies the point by k with double and add.
+    #[inline(always)]
+    fn mul(&self, k: u64, a: &BigInt, n: &BigInt) -> Result<Point, BigInt> {
+        let mut result = Point::Infinity;
+        let mut addend = self.clone();
+        let mut k = k;
+        while k > 0 {
+            if k & 1 == 1 {
+                result = result.add(&addend, a, n)?;
+            }
+            addend = addend.add(&addend, a, n)?;
+            k >>= 1;
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_find_factors_with_every_stage() {
+        let mut telemetry = Telemetry::default();
+        let cancel = Cancel::default();
+        assert_eq!(small_primes(20), vec![2, 3, 5, 7, 11, 13, 17, 19]);
+
+        let n = BigInt::from(1_000_003_u64 * 65_521);
+        assert_eq!(
+            trial_division(&n, 1 << 16, &mut telemetry, &cancel),
+            Some(BigInt::from(65_521))
+        );
+        assert_eq!(trial_division(&n, 1_000, &mut telemetry, &cancel), None);
+        let mut wheel_telemetry = Telemetry::default();
+        assert_eq!(
+            wheel_trial_division(&n, 1 << 16, &mut wheel_telemetry),
+            Some(BigInt::from(65_521))
+        );
+        assert_eq!(wheel_trial_division(&n, 1_000, &mut wheel_telemetry), None);
+        for (n, p) in [(7 * 1_000_003, Some(7)), (121 * 13, Some(11)), (7, None)] {
+            assert_eq!(
+                wheel_trial_division(&BigInt::from(n), 1_000, &mut wheel_telemetry),
+                p.map(BigInt::from)
+            );
+        }
+
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(999_983_u64);
+        let factor = pollard_rho(&n, 10_000, 1, &mut telemetry, &cancel).unwrap();
+        assert!(factor == BigInt::from(1_000_003) || factor == BigInt::from(999_983));
+
+        // 1_000_003 − 1 = 2 · 3 · 166_667 is not smooth, 1_048_129 − 1 = 2^6 · 3 · 53 · 103 is.
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(1_048_129_u64);
+        assert_eq!(
+            pollard_p_minus_1(&n, 200, 200, &mut telemetry, &cancel),
+            Some(BigInt::from(1_048_129))
+        );
+        // 1_000_033 − 1 = 2^5 · 3 · 11 · 947 needs stage 2 for its largest prime.
+        let n = BigInt::from(1_000_033_u64) * BigInt::from(1_000_003_u64);
+        assert_eq!(
+            pollard_p_minus_1(&n, 200, 200, &mut telemetry, &cancel),
+            None
+        );
+        assert_eq!(
+            pollard_p_minus_1(&n, 200, 20_000, &mut telemetry, &cancel),
+            Some(BigInt::from(1_000_033))
+        );
+        // 1_000_003 + 1 = 2^2 · 53^2 · 89 is smooth, 4_294_967_311 ± 1 are not.
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(4_294_967_311_u64);
+        assert_eq!(
+            pollard_p_minus_1(&n, 3_000, 3_000, &mut telemetry, &cancel),
+            None
+        );
+        assert_eq!(
+            williams_p_plus_1(&n, 3_000, &mut telemetry, &cancel),
+            Some(BigInt::from(1_000_003))
+        );
+        assert_eq!(williams_p_plus_1(&n, 100, &mut telemetry, &cancel), None);
+        // Balanced, unbalanced and prime moduli.
+        let mut lehman_telemetry = Telemetry::default();
+        let balanced = 4_294_967_311_u128 * 4_294_967_357;
+        let factor = lehman(balanced, &mut lehman_telemetry, &cancel).unwrap();
+        assert!(factor == 4_294_967_311 || factor == 4_294_967_357);
+        let unbalanced = 1_009 * 1_000_000_000_039;
+        assert_eq!(
+            lehman(unbalanced, &mut lehman_telemetry, &cancel),
+            Some(1_009)
+        );
+        assert_eq!(
+            lehman(1_000_000_000_039, &mut lehman_telemetry, &cancel),
+            None
+        );
+        assert_eq!(lehman(1 << 100, &mut lehman_telemetry, &cancel), None);
+        cancel.cancel();
+        assert_eq!(
+            pollard_p_minus_1(&n, 200, 20_000, &mut telemetry, &cancel),
+            None
+        );
+
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(4_294_967_311_u64);
+        let factor = (0..200)
+            .find_map(|_| ecm_curve(&n, 200, &mut telemetry))
+            .unwrap();
+        assert!(factor == BigInt::from(1_000_003) || factor == BigInt::from(4_294_967_311_u64));
+        // 6542 primes below 2^16 and 168 below 1000 tested by trial division.
+        assert_eq!(telemetry.primes_tested, 6_542 + 168);
+        assert!(telemetry.iterations > 0);
+    }
+
+    #[test]
+    fn it_should_run_p_minus_1_stage_2_below_bound_2() {
+        let mut telemetry = Telemetry::default();
+        let cancel = Cancel::default();
+        // 107 − 1 = 2 · 53, the prime 53 left to stage 2 from the odd prime 3 on.
+        let n = BigInt::from(107_u64 * 1_000_003);
+        for bound in [0, 1] {
+            assert_eq!(
+                pollard_p_minus_1(&n, bound, 100, &mut telemetry, &cancel),
+                Some(BigInt::from(107))
+            );
+            assert_eq!(
+                pollard_p_minus_1(&n, bound, bound, &mut telemetry, &cancel),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn it_should_find_the_roots_of_perfect_powers() {
+        let p = BigInt::from(1_000_003);
+        assert_eq!(perfect_power(&p.pow(2)), Some((p.clone(), 2)));
+        assert_eq!(perfect_power(&p.pow(15)), Some((p.clone(), 15)));
+        assert_eq!(
+            perfect_power(&BigInt::from(1 << 12)),
+            Some((BigInt::from(2), 12))
+        );
+        assert_eq!(
+            perfect_power(&(BigInt::from(6) * &p).pow(3)),
+            Some((BigInt::from(6) * &p, 3))
+        );
+        assert_eq!(perfect_power(&p), None);
+        assert_eq!(perfect_power(&(p.pow(2) * 2)), None);
+        assert_eq!(perfect_power(&BigInt::from(1)), None);
+    }
+
+    #[test]
+    fn it_should_scan_the_same_fermat_candidates_as_plain_square_tests() {
+        let n = BigInt::from(1_000_003_u64) * BigInt::from(1_003_001_u64);
+        let naive = |a: &BigInt, steps: u64| {
+            (0..steps).find_map(|k| {
+                let a = a + k;
+                let rest: BigInt = &a * &a - &n;
+                let b = rest.sign().ne(&Sign::Minus).then(|| rest.sqrt())?;
+                (&b * &b == rest).then(|| (a + b, k + 1))
+            })
+        };
+        let start: BigInt = n.sqrt() + 1;
+        for a in [&start - 100, start.clone(), &start + 1_000] {
+            for steps in [1, 63, 64, 65, 200, 3_000] {
+                let mut telemetry = Telemetry::default();
+                let found = fermat(&n, &a, steps, &mut telemetry);
+                let expected = naive(&a, steps);
+                assert_eq!(found, expected.clone().map(|(p, _)| p));
+                assert_eq!(telemetry.iterations, expected.map_or(steps, |(_, k)| k));
+            }
+        }
+    }
+}
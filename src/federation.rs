@@ -0,0 +1,487 @@
+use num_bigint::{BigInt, Sign};
+use openssl::base64::decode_block;
+use openssl::bn::BigNum;
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Public};
+use openssl::rsa::Rsa;
+use openssl::sha::sha256;
+use openssl::x509::X509;
+use serde::Deserialize;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::BufRead;
+
+use crate::cache::{Cache, Material};
+use crate::errors::BilboError;
+use crate::http::{get_cached, Url};
+use crate::rsa::PickLock;
+use crate::signature::base64url_decode;
+
+/// Shortest RSA key federation entities shall sign and encrypt with, as NIST SP 800-131A requires since 2014.
+///
+pub const MIN_FEDERATION_BITS: u32 = 2048;
+
+/// KeyUse is what the federation entity uses the key for, as declared by the metadata.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyUse {
+    Signing,
+    Encryption,
+    Unspecified,
+}
+
+impl Display for KeyUse {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                KeyUse::Signing => "signing",
+                KeyUse::Encryption => "encryption",
+                KeyUse::Unspecified => "signing and encryption",
+            }
+        )
+    }
+}
+
+impl KeyUse {
+    // Parses SAML KeyDescriptor use or JWK use parameter, absent use allows both.
+    #[inline(always)]
+    fn parse(value: Option<&str>) -> Self {
+        match value {
+            Some("signing") | Some("sig") => KeyUse::Signing,
+            Some("encryption") | Some("enc") => KeyUse::Encryption,
+            _ => KeyUse::Unspecified,
+        }
+    }
+}
+
+/// KeyHolder is the entity publishing the key, its entity ID or issuer, for given use,
+/// with the origin of the key in the metadata, the certificate or the JWK.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyHolder {
+    pub entity: String,
+    pub key_use: KeyUse,
+    pub origin: String,
+}
+
+impl Display for KeyHolder {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} {} key, {}", self.entity, self.key_use, self.origin)
+    }
+}
+
+/// FederationKey is a unique public key published in the federation metadata with all the entities holding it.
+///
+#[derive(Debug, Clone)]
+pub struct FederationKey {
+    pub fingerprint: String,
+    pub public_key: PKey<Public>,
+    pub holders: Vec<KeyHolder>,
+}
+
+impl FederationKey {
+    /// Returns true if the key is RSA shorter than MIN_FEDERATION_BITS.
+    ///
+    #[inline(always)]
+    pub fn is_short(&self) -> bool {
+        self.public_key.id() == Id::RSA && self.public_key.bits() < MIN_FEDERATION_BITS
+    }
+
+    /// Returns PickLock of the key, None if the key is not RSA.
+    ///
+    #[inline(always)]
+    pub fn pick_lock(&self) -> Result<Option<PickLock>, BilboError> {
+        let Ok(rsa) = self.public_key.rsa() else {
+            return Ok(None);
+        };
+
+        Ok(Some(PickLock::from_exponent_and_modulus(
+            BigInt::from_bytes_be(Sign::Plus, &rsa.e().to_vec()),
+            BigInt::from_bytes_be(Sign::Plus, &rsa.n().to_vec()),
+        )))
+    }
+}
+
+/// FederationInventory collects the keys of all the scanned metadata, every unique public key once,
+/// and the metadata URLs that failed.
+///
+#[derive(Debug, Clone, Default)]
+pub struct FederationInventory {
+    keys: Vec<FederationKey>,
+    failures: Vec<(String, String)>,
+}
+
+impl FederationInventory {
+    /// Adds the key of the holder, returns true if it wasn't seen yet.
+    ///
+    pub fn add(&mut self, holder: KeyHolder, public_key: PKey<Public>) -> Result<bool, BilboError> {
+        let fingerprint: String = sha256(&public_key.public_key_to_der()?)
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        if let Some(key) = self.keys.iter_mut().find(|k| k.fingerprint == fingerprint) {
+            if !key.holders.contains(&holder) {
+                key.holders.push(holder);
+            }
+            return Ok(false);
+        }
+        self.keys.push(FederationKey {
+            fingerprint,
+            public_key,
+            holders: vec![holder],
+        });
+
+        Ok(true)
+    }
+
+    /// Adds the certificates of all the entity descriptors of SAML metadata, single entity or aggregate.
+    /// Returns the number of certificates found.
+    ///
+    pub fn add_saml_metadata(&mut self, xml: &str) -> Result<usize, BilboError> {
+        let mut entity = String::new();
+        let mut key_use = KeyUse::Unspecified;
+        let mut found = 0;
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            let Some(end) = rest[start..].find('>') else {
+                break;
+            };
+            let tag = &rest[start + 1..start + end];
+            rest = &rest[start + end + 1..];
+            let name = tag.split_whitespace().next().unwrap_or_default();
+            match local_name(name) {
+                (false, "EntityDescriptor") => {
+                    entity = attribute(tag, "entityID").unwrap_or_default()
+                }
+                (false, "KeyDescriptor") => {
+                    key_use = KeyUse::parse(attribute(tag, "use").as_deref())
+                }
+                (true, "KeyDescriptor") => key_use = KeyUse::Unspecified,
+                (false, "X509Certificate") => {
+                    let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+                    let encoded: String = text.split_whitespace().collect();
+                    let cert = X509::from_der(&decode_block(&encoded)?)?;
+                    let origin = format!(
+                        "certificate {}",
+                        sha256(&cert.to_der()?)
+                            .iter()
+                            .map(|b| format!("{b:02x}"))
+                            .collect::<String>()
+                    );
+                    let holder = KeyHolder {
+                        entity: entity.clone(),
+                        key_use,
+                        origin,
+                    };
+                    self.add(holder, cert.public_key()?)?;
+                    found += 1;
+                }
+                _ => (),
+            }
+        }
+
+        Ok(found)
+    }
+
+    /// Adds the RSA and EC keys of the JWKS published by the issuer, other key types are skipped.
+    /// Returns the number of keys found.
+    ///
+    pub fn add_jwks(&mut self, issuer: &str, jwks: &[u8]) -> Result<usize, BilboError> {
+        let jwks: Jwks = serde_json::from_slice(jwks)?;
+        let mut found = 0;
+        for jwk in jwks.keys.iter() {
+            let Some(public_key) = jwk.public_key()? else {
+                continue;
+            };
+            let holder = KeyHolder {
+                entity: issuer.to_string(),
+                key_use: KeyUse::parse(jwk.key_use.as_deref()),
+                origin: format!("jwk {} kid {}", jwk.kty, jwk.kid.as_deref().unwrap_or("-")),
+            };
+            self.add(holder, public_key)?;
+            found += 1;
+        }
+
+        Ok(found)
+    }
+
+    /// Records the metadata URL that failed.
+    ///
+    #[inline(always)]
+    pub fn fail(&mut self, url: &str, err: &BilboError) {
+        self.failures.push((url.to_string(), err.to_string()));
+    }
+
+    /// Returns unique keys in the order they were first found.
+    ///
+    #[inline(always)]
+    pub fn keys(&self) -> &[FederationKey] {
+        &self.keys
+    }
+
+    /// Returns metadata URLs that failed with the reason.
+    ///
+    #[inline(always)]
+    pub fn failures(&self) -> &[(String, String)] {
+        &self.failures
+    }
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Jwks {
+    pub(crate) keys: Vec<Jwk>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Jwk {
+    pub(crate) kty: String,
+    kid: Option<String>,
+    #[serde(rename = "use")]
+    key_use: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+    // Private exponent of RSA and private key of EC JWKs, only checked for presence.
+    pub(crate) d: Option<String>,
+}
+
+impl Jwk {
+    // Builds public key of RSA and EC JWKs, None for other key types.
+    #[inline(always)]
+    pub(crate) fn public_key(&self) -> Result<Option<PKey<Public>>, BilboError> {
+        let component = |c: &Option<String>| -> Result<BigNum, BilboError> {
+            let Some(c) = c else {
+                return Err(BilboError::GenericError(format!(
+                    "{} jwk {} misses key parameters",
+                    self.kty,
+                    self.kid.as_deref().unwrap_or("-")
+                )));
+            };
+            Ok(BigNum::from_slice(&base64url_decode(c)?)?)
+        };
+        match self.kty.as_str() {
+            "RSA" => Ok(Some(PKey::from_rsa(Rsa::from_public_components(
+                component(&self.n)?,
+                component(&self.e)?,
+            )?)?)),
+            "EC" => {
+                let nid = match self.crv.as_deref() {
+                    Some("P-256") => Nid::X9_62_PRIME256V1,
+                    Some("P-384") => Nid::SECP384R1,
+                    Some("P-521") => Nid::SECP521R1,
+                    _ => return Ok(None),
+                };
+                let group = EcGroup::from_curve_name(nid)?;
+                let (x, y) = (component(&self.x)?, component(&self.y)?);
+                let key = EcKey::from_public_key_affine_coordinates(&group, &x, &y)?;
+                key.check_key()?;
+                Ok(Some(PKey::from_ec_key(key)?))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Reads the list of metadata URLs, SAML metadata, OIDC discovery documents or bare issuers, one per line.
+/// Blank lines and lines starting with # are skipped.
+///
+pub fn read_metadata_urls(reader: impl BufRead) -> Result<Vec<String>, BilboError> {
+    let mut urls = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let url = line.trim();
+        if url.is_empty() || url.starts_with('#') {
+            continue;
+        }
+        urls.push(url.to_string());
+    }
+
+    Ok(urls)
+}
+
+/// Fetches the metadata and adds its keys: SAML metadata XML, OIDC discovery document with the JWKS it points to,
+/// or JWKS alone. Issuer URLs serving none of them are tried with the well known discovery path.
+/// Documents are cached as JWKS material. Returns the number of keys found.
+///
+pub fn scan_metadata(
+    url: &str,
+    inventory: &mut FederationInventory,
+    cache: &Cache,
+) -> Result<usize, BilboError> {
+    let parsed = Url::parse(url)?;
+    let document = get_cached(&parsed, &[], Material::Jwks, cache);
+    if let Ok(document) = &document {
+        if let Some(found) = add_document(url, document, inventory, cache)? {
+            return Ok(found);
+        }
+    }
+    if !url.contains("/.well-known/") {
+        let discovery = format!(
+            "{}/.well-known/openid-configuration",
+            url.trim_end_matches('/')
+        );
+        let document = get_cached(&Url::parse(&discovery)?, &[], Material::Jwks, cache)?;
+        if let Some(found) = add_document(&discovery, &document, inventory, cache)? {
+            return Ok(found);
+        }
+    }
+    document?;
+
+    Err(BilboError::GenericError(format!(
+        "{url} serves neither SAML metadata, OIDC discovery document nor JWKS"
+    )))
+}
+
+// Adds the keys of the recognized document, None if the document is not federation metadata.
+#[inline(always)]
+fn add_document(
+    url: &str,
+    document: &[u8],
+    inventory: &mut FederationInventory,
+    cache: &Cache,
+) -> Result<Option<usize>, BilboError> {
+    let text = String::from_utf8_lossy(document);
+    if text.trim_start().starts_with('<') {
+        if !text.contains("EntityDescriptor") {
+            return Ok(None);
+        }
+        return Ok(Some(inventory.add_saml_metadata(&text)?));
+    }
+    if let Ok(discovery) = serde_json::from_slice::<Discovery>(document) {
+        let jwks_uri = Url::parse(url)?.join(&discovery.jwks_uri)?;
+        let jwks = get_cached(&jwks_uri, &[], Material::Jwks, cache)?;
+        return Ok(Some(inventory.add_jwks(&discovery.issuer, &jwks)?));
+    }
+    if serde_json::from_slice::<Jwks>(document).is_ok() {
+        return Ok(Some(inventory.add_jwks(url, document)?));
+    }
+
+    Ok(None)
+}
+
+// Strips the namespace prefix of the element name, returns whether the tag closes the element too.
+#[inline(always)]
+fn local_name(name: &str) -> (bool, &str) {
+    let (closing, name) = match name.strip_prefix('/') {
+        Some(name) => (true, name),
+        None => (false, name),
+    };
+
+    (
+        closing,
+        name.rsplit_once(':').map_or(name, |(_, local)| local),
+    )
+}
+
+// Returns the value of the attribute of the tag, quoted by double or single quotes.
+#[inline(always)]
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    for quote in ['"', '\''] {
+        let prefix = format!("{name}={quote}");
+        if let Some(start) = tag.find(&prefix) {
+            let value = &tag[start + prefix.len()..];
+            return Some(value[..value.find(quote)?].to_string());
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::base64url_encode;
+    use crate::testing::self_signed;
+    use openssl::base64::encode_block;
+
+    #[test]
+    fn it_should_collect_keys_of_saml_metadata_and_jwks_per_entity() -> Result<(), BilboError> {
+        let idp = PKey::from_rsa(Rsa::generate(1024)?)?;
+        let encoded = encode_block(&self_signed("idp.example.com", &idp, 365)?.to_der()?);
+        let metadata = format!(
+            r#"<?xml version="1.0"?>
+<md:EntitiesDescriptor xmlns:md="urn:oasis:names:tc:SAML:2.0:metadata" xmlns:ds="http://www.w3.org/2000/09/xmldsig#">
+  <md:EntityDescriptor entityID="https://idp.example.com/saml">
+    <md:IDPSSODescriptor>
+      <md:KeyDescriptor use="signing"><ds:KeyInfo><ds:X509Data>
+        <ds:X509Certificate>
+{}
+        </ds:X509Certificate>
+      </ds:X509Data></ds:KeyInfo></md:KeyDescriptor>
+    </md:IDPSSODescriptor>
+  </md:EntityDescriptor>
+  <EntityDescriptor entityID='https://sp.example.org'>
+    <KeyDescriptor><KeyInfo><X509Data><X509Certificate>{encoded}</X509Certificate></X509Data></KeyInfo></KeyDescriptor>
+  </EntityDescriptor>
+</md:EntitiesDescriptor>"#,
+            encoded
+        );
+        let mut inventory = FederationInventory::default();
+        assert_eq!(inventory.add_saml_metadata(&metadata)?, 2);
+        assert_eq!(inventory.keys().len(), 1);
+        assert_eq!(
+            inventory.keys()[0]
+                .holders
+                .iter()
+                .map(|h| (h.entity.as_str(), h.key_use))
+                .collect::<Vec<_>>(),
+            vec![
+                ("https://idp.example.com/saml", KeyUse::Signing),
+                ("https://sp.example.org", KeyUse::Unspecified)
+            ]
+        );
+        assert!(inventory.keys()[0].is_short());
+        assert_eq!(
+            inventory.keys()[0].pick_lock()?.map(|pl| pl.bits()),
+            Some(1024)
+        );
+
+        let rsa = Rsa::generate(2048)?;
+        let p256 = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let ec = EcKey::generate(&p256)?;
+        let mut ctx = openssl::bn::BigNumContext::new()?;
+        let (mut x, mut y) = (BigNum::new()?, BigNum::new()?);
+        ec.public_key()
+            .affine_coordinates(ec.group(), &mut x, &mut y, &mut ctx)?;
+        let jwks = serde_json::json!({"keys": [
+            {"kty": "RSA", "kid": "r1", "use": "sig", "n": base64url_encode(&rsa.n().to_vec()), "e": base64url_encode(&rsa.e().to_vec())},
+            {"kty": "EC", "kid": "e1", "use": "enc", "crv": "P-256", "x": base64url_encode(&x.to_vec()), "y": base64url_encode(&y.to_vec())},
+            {"kty": "oct", "kid": "s1", "k": "c2VjcmV0"}
+        ]});
+        let issuer = "https://login.example.com";
+        assert_eq!(inventory.add_jwks(issuer, &serde_json::to_vec(&jwks)?)?, 2);
+        let keys = inventory.keys();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(
+            keys[1].holders[0].to_string(),
+            "https://login.example.com signing key, jwk RSA kid r1"
+        );
+        assert!(!keys[1].is_short());
+        assert_eq!(keys[1].pick_lock()?.map(|pl| pl.bits()), Some(2048));
+        assert_eq!(keys[2].holders[0].key_use, KeyUse::Encryption);
+        assert!(keys[2].pick_lock()?.is_none());
+
+        let broken = serde_json::json!({"keys": [{"kty": "RSA", "kid": "r2"}]});
+        assert!(inventory
+            .add_jwks(issuer, &serde_json::to_vec(&broken)?)
+            .is_err());
+        assert_eq!(
+            read_metadata_urls("# federation\nhttps://idp.example.com/metadata\n\n".as_bytes())?,
+            vec!["https://idp.example.com/metadata".to_string()]
+        );
+
+        Ok(())
+    }
+}
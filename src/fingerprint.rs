@@ -0,0 +1,220 @@
+use num_bigint::{BigInt, Sign};
+use openssl::{
+    base64::encode_block,
+    bn::BigNum,
+    hash::{hash, MessageDigest},
+    pkey::PKey,
+    rsa::Rsa,
+    sha::sha256,
+};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::errors::BilboError;
+use crate::ssh::{put_mpint, put_string};
+
+const SSH_RSA: &str = "ssh-rsa";
+
+/// FingerprintKind is the canonical identifier of RSA public key, the same key has the same one in every format.
+///
+///  - Modulus, hex encoded SHA-256 of the big endian modulus, the identifier of the audit log, reports, allowlists and scans.
+///  - Spki, hex encoded SHA-256 of the DER SubjectPublicKeyInfo, as certificate transparency and key pinning use it.
+///  - OpenSshSha256, `SHA256:` and unpadded base64 of SHA-256 of the SSH public key blob, as `ssh-keygen -l` prints it.
+///  - OpenSshMd5, `MD5:` and colon separated hex of MD5 of the SSH public key blob, as `ssh-keygen -l -E md5` prints it.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FingerprintKind {
+    Modulus,
+    Spki,
+    OpenSshSha256,
+    OpenSshMd5,
+}
+
+impl Display for FingerprintKind {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            FingerprintKind::Modulus => write!(f, "modulus"),
+            FingerprintKind::Spki => write!(f, "spki"),
+            FingerprintKind::OpenSshSha256 => write!(f, "openssh-sha256"),
+            FingerprintKind::OpenSshMd5 => write!(f, "openssh-md5"),
+        }
+    }
+}
+
+/// Returns the fingerprint of the kind of RSA public key (e, n).
+/// Fails for non positive exponent or modulus, such key has no encoding to fingerprint.
+///
+#[inline(always)]
+pub fn fingerprint(e: &BigInt, n: &BigInt, kind: FingerprintKind) -> Result<String, BilboError> {
+    positive(e, n)?;
+    match kind {
+        FingerprintKind::Modulus => Ok(modulus_fingerprint(n)),
+        FingerprintKind::Spki => spki_fingerprint(e, n),
+        FingerprintKind::OpenSshSha256 => Ok(openssh_sha256_fingerprint(e, n)),
+        FingerprintKind::OpenSshMd5 => openssh_md5_fingerprint(e, n),
+    }
+}
+
+/// Returns the hex encoded SHA-256 of the big endian modulus, the identifier of the key in scanning dedupe,
+/// the audit log, the reports and the allow and block lists.
+///
+#[inline(always)]
+pub fn modulus_fingerprint(n: &BigInt) -> String {
+    hex(&sha256(&n.to_bytes_be().1))
+}
+
+/// Returns the hex encoded SHA-256 of the DER SubjectPublicKeyInfo of the key.
+///
+#[inline(always)]
+pub fn spki_fingerprint(e: &BigInt, n: &BigInt) -> Result<String, BilboError> {
+    positive(e, n)?;
+    let bn = |v: &BigInt| BigNum::from_slice(&v.to_bytes_be().1);
+    let key = PKey::from_rsa(Rsa::from_public_components(bn(n)?, bn(e)?)?)?;
+
+    Ok(hex(&sha256(&key.public_key_to_der()?)))
+}
+
+/// Returns the OpenSSH SHA-256 fingerprint of the key, `SHA256:` followed by unpadded base64.
+///
+#[inline(always)]
+pub fn openssh_sha256_fingerprint(e: &BigInt, n: &BigInt) -> String {
+    let digest = encode_block(&sha256(&ssh_blob(e, n)));
+    format!("SHA256:{}", digest.trim_end_matches('='))
+}
+
+/// Returns the legacy OpenSSH MD5 fingerprint of the key, `MD5:` followed by colon separated hex.
+///
+#[inline(always)]
+pub fn openssh_md5_fingerprint(e: &BigInt, n: &BigInt) -> Result<String, BilboError> {
+    positive(e, n)?;
+    let digest = hash(MessageDigest::md5(), &ssh_blob(e, n))?;
+    let pairs: Vec<String> = digest.iter().map(|b| format!("{b:02x}")).collect();
+
+    Ok(format!("MD5:{}", pairs.join(":")))
+}
+
+// DER and SSH encodings drop the sign, negative and zero keys would share fingerprints with other keys.
+#[inline(always)]
+fn positive(e: &BigInt, n: &BigInt) -> Result<(), BilboError> {
+    if e.sign() != Sign::Plus || n.sign() != Sign::Plus {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: format!("exponent {e} and modulus {n} shall be positive to be fingerprinted"),
+        });
+    }
+
+    Ok(())
+}
+
+// Returns the SSH wire public key blob of ssh-rsa key, RFC 4253 section 6.6.
+#[inline(always)]
+fn ssh_blob(e: &BigInt, n: &BigInt) -> Vec<u8> {
+    let mut blob = Vec::new();
+    put_string(&mut blob, SSH_RSA.as_bytes());
+    put_mpint(&mut blob, e);
+    put_mpint(&mut blob, n);
+
+    blob
+}
+
+#[inline(always)]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::PickLock;
+
+    fn key() -> (BigInt, BigInt) {
+        (BigInt::from(65537), BigInt::from(1_000_003_u64 * 1_000_033))
+    }
+
+    #[test]
+    fn it_should_fingerprint_the_modulus() -> Result<(), BilboError> {
+        let (e, n) = key();
+        let pl = PickLock::from_exponent_and_modulus(e.clone(), n.clone());
+        let expected = "19704cb7c472c584da5476aa1509be332b44b73490eb746ffa51fff183371cfb";
+
+        assert_eq!(pl.fingerprint(FingerprintKind::Modulus)?, expected);
+        assert_eq!(pl.modulus_fingerprint(), expected);
+        assert_eq!(fingerprint(&e, &n, FingerprintKind::Modulus)?, expected);
+        assert_eq!(
+            fingerprint(&BigInt::from(3), &n, FingerprintKind::Modulus)?,
+            expected,
+            "the exponent is not part of the modulus fingerprint"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fingerprint_the_spki() -> Result<(), BilboError> {
+        let (e, n) = key();
+        let pl = PickLock::from_exponent_and_modulus(e.clone(), n.clone());
+        let expected = "08a65ea288a454509b04265b7a92834b04165cf8913c42cfe27a5706ba575106";
+
+        assert_eq!(pl.fingerprint(FingerprintKind::Spki)?, expected);
+        assert_eq!(fingerprint(&e, &n, FingerprintKind::Spki)?, expected);
+        assert_ne!(spki_fingerprint(&BigInt::from(3), &n)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fingerprint_like_openssh() -> Result<(), BilboError> {
+        let (e, n) = key();
+        let pl = PickLock::from_exponent_and_modulus(e.clone(), n.clone());
+
+        let sha256 = pl.fingerprint(FingerprintKind::OpenSshSha256)?;
+        assert_eq!(sha256, "SHA256:N0M1uwZXvMerxrm1ageHqRljQreIw645kCkP3nmtMPc");
+        assert!(!sha256.ends_with('='), "base64 is unpadded");
+        let md5 = pl.fingerprint(FingerprintKind::OpenSshMd5)?;
+        assert_eq!(md5, "MD5:ac:a0:96:74:ec:52:13:d5:c4:80:6d:de:66:4c:f1:bf");
+        assert_eq!(openssh_md5_fingerprint(&e, &n)?, md5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_encode_moduli_of_high_bit_as_positive_mpint() {
+        // The mpint of the modulus with its top bit set is prefixed by zero byte, it would be negative otherwise.
+        let blob = ssh_blob(&BigInt::from(3), &BigInt::from(0x80));
+        assert_eq!(
+            blob,
+            [
+                &[0, 0, 0, 7][..],
+                SSH_RSA.as_bytes(),
+                &[0, 0, 0, 1, 3],
+                &[0, 0, 0, 2, 0, 0x80],
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn it_should_refuse_to_fingerprint_keys_without_encoding() {
+        let (e, n) = key();
+        for (e, n) in [
+            (e.clone(), BigInt::from(0)),
+            (e.clone(), -n.clone()),
+            (BigInt::from(0), n.clone()),
+            (-e, n),
+        ] {
+            for kind in [
+                FingerprintKind::Modulus,
+                FingerprintKind::Spki,
+                FingerprintKind::OpenSshSha256,
+                FingerprintKind::OpenSshMd5,
+            ] {
+                assert!(
+                    matches!(
+                        fingerprint(&e, &n, kind),
+                        Err(BilboError::InvalidKeyMaterial { .. })
+                    ),
+                    "{kind} of e {e} and n {n}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+
+/// Returns the partial quotients of the continued fraction expansion of the positive rational num / den,
+/// [a0; a1, a2, ...] by the Euclidean algorithm, empty for zero denominator.
+///
+#[inline(always)]
+pub fn continued_fraction(num: &BigInt, den: &BigInt) -> Vec<BigInt> {
+    let (mut num, mut den) = (num.clone(), den.clone());
+    let mut quotients = Vec::new();
+    while den.sign() != Sign::NoSign {
+        let (quotient, rest) = num.div_rem(&den);
+        quotients.push(quotient);
+        num = den;
+        den = rest;
+    }
+
+    quotients
+}
+
+/// Convergents iterates the convergents h / k of the continued fraction given by its partial quotients,
+/// each the best rational approximation of the expanded number with its denominator.
+///
+#[derive(Debug, Clone)]
+pub struct Convergents {
+    quotients: Vec<BigInt>,
+    next: usize,
+    h: (BigInt, BigInt),
+    k: (BigInt, BigInt),
+}
+
+impl Convergents {
+    /// Creates the iterator over the convergents of the partial quotients.
+    ///
+    #[inline(always)]
+    pub fn new(quotients: Vec<BigInt>) -> Self {
+        Self {
+            quotients,
+            next: 0,
+            h: (BigInt::from(0), BigInt::from(1)),
+            k: (BigInt::from(1), BigInt::from(0)),
+        }
+    }
+
+    /// Creates the iterator over the convergents of the positive rational num / den.
+    ///
+    #[inline(always)]
+    pub fn of(num: &BigInt, den: &BigInt) -> Self {
+        Self::new(continued_fraction(num, den))
+    }
+}
+
+impl Iterator for Convergents {
+    type Item = (BigInt, BigInt);
+
+    // h_i = a_i · h_i−1 + h_i−2 and k_i = a_i · k_i−1 + k_i−2, seeded by h_−2 = 0, h_−1 = 1, k_−2 = 1, k_−1 = 0.
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let a = self.quotients.get(self.next)?;
+        self.next += 1;
+        let h = a * &self.h.1 + &self.h.0;
+        let k = a * &self.k.1 + &self.k.0;
+        self.h = (self.h.1.clone(), h.clone());
+        self.k = (self.k.1.clone(), k.clone());
+
+        Some((h, k))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_expand_rationals_and_iterate_their_convergents() {
+        // 649 / 200 = [3; 4, 12, 4].
+        let (num, den) = (BigInt::from(649), BigInt::from(200));
+        assert_eq!(
+            continued_fraction(&num, &den),
+            [3, 4, 12, 4].map(BigInt::from).to_vec()
+        );
+        let convergents: Vec<(BigInt, BigInt)> = Convergents::of(&num, &den).collect();
+        assert_eq!(
+            convergents,
+            [(3, 1), (13, 4), (159, 49), (649, 200)]
+                .map(|(h, k)| (BigInt::from(h), BigInt::from(k)))
+                .to_vec()
+        );
+        assert!(continued_fraction(&num, &BigInt::from(0)).is_empty());
+        assert_eq!(
+            Convergents::of(&BigInt::from(3), &BigInt::from(7)).next(),
+            Some((BigInt::from(0), BigInt::from(1)))
+        );
+    }
+}
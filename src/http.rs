@@ -0,0 +1,398 @@
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::Duration;
+
+use crate::cache::{Cache, Material};
+use crate::errors::BilboError;
+use crate::net::{handshake_error, happy_eyeballs, Resolver};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+// Status, header and chunk size lines longer than that are no HTTP, servers cap them to 8 KiB.
+const MAX_LINE_SIZE: usize = 8 * 1024;
+const MAX_HEADERS: usize = 256;
+
+/// Url is a minimal parsed http or https URL.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Url {
+    /// Parses URL in the form scheme://host[:port][/path][?query].
+    ///
+    #[inline(always)]
+    pub fn parse(url: &str) -> Result<Self, BilboError> {
+        let (tls, rest) = if let Some(rest) = url.strip_prefix("https://") {
+            (true, rest)
+        } else if let Some(rest) = url.strip_prefix("http://") {
+            (false, rest)
+        } else {
+            return Err(BilboError::GenericError(format!(
+                "unsupported url scheme in {url}, expected http or https"
+            )));
+        };
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+        let default_port = if tls { 443 } else { 80 };
+        // IPv6 hosts are enclosed in square brackets, so the port is after the closing bracket.
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(v6) => match v6.split_once(']') {
+                Some((host, rest)) => (host, rest.strip_prefix(':')),
+                None => {
+                    return Err(BilboError::GenericError(format!(
+                        "unclosed ipv6 host in url {url}"
+                    )))
+                }
+            },
+            None => match authority.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port
+                .parse::<u16>()
+                .map_err(|e| BilboError::GenericError(format!("invalid port in url {url}: {e}")))?,
+            None => default_port,
+        };
+        if host.is_empty() {
+            return Err(BilboError::GenericError(format!(
+                "missing host in url {url}"
+            )));
+        }
+
+        Ok(Self {
+            tls,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// Resolves given location header against this url.
+    ///
+    #[inline(always)]
+    pub fn join(&self, location: &str) -> Result<Self, BilboError> {
+        if location.starts_with("http://") || location.starts_with("https://") {
+            return Self::parse(location);
+        }
+        let path = if location.starts_with('/') {
+            location.to_string()
+        } else {
+            let dir = &self.path[..self.path.rfind('/').map(|i| i + 1).unwrap_or(0)];
+            format!("{dir}{location}")
+        };
+        Ok(Self {
+            path,
+            ..self.clone()
+        })
+    }
+}
+
+impl Display for Url {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let scheme = if self.tls { "https" } else { "http" };
+        if self.host.contains(':') {
+            write!(f, "{scheme}://[{}]:{}{}", self.host, self.port, self.path)
+        } else {
+            write!(f, "{scheme}://{}:{}{}", self.host, self.port, self.path)
+        }
+    }
+}
+
+/// Response of the http request.
+///
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Returns value of the first header with given case insensitive name.
+    ///
+    #[inline(always)]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Performs HTTP/1.1 GET request with given extra headers.
+///
+#[inline(always)]
+pub fn get(url: &Url, headers: &[(&str, &str)]) -> Result<Response, BilboError> {
+    request(url, "GET", headers, &[])
+}
+
+/// Performs HTTP/1.1 GET request without verifying the server certificate, the host resolved by the resolver.
+/// Meant for the scanners, invalid and self signed certificates are what they are looking for.
+///
+#[inline(always)]
+pub fn get_unverified(
+    url: &Url,
+    headers: &[(&str, &str)],
+    resolver: &Resolver,
+) -> Result<Response, BilboError> {
+    send(url, "GET", headers, &[], false, resolver)
+}
+
+/// Fetches the body of the url with GET request, or returns it from the cache while fresh.
+/// Only successful responses, status 200, are cached, other statuses are returned as error.
+///
+pub fn get_cached(
+    url: &Url,
+    headers: &[(&str, &str)],
+    material: Material,
+    cache: &Cache,
+) -> Result<Vec<u8>, BilboError> {
+    cache.fetch(material, &url.to_string(), || {
+        let response = get(url, headers)?;
+        if response.status != 200 {
+            return Err(BilboError::GenericError(format!(
+                "{url} responded with status {}",
+                response.status
+            )));
+        }
+        Ok(response.body)
+    })
+}
+
+/// Performs HTTP/1.1 request with given method, extra headers and body.
+/// Both IPv4 and IPv6 addresses of the host are attempted, the first to connect is used.
+/// Connection is closed after every request.
+///
+#[inline(always)]
+pub fn request(
+    url: &Url,
+    method: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Response, BilboError> {
+    send(url, method, headers, body, true, &Resolver::System)
+}
+
+fn send(
+    url: &Url,
+    method: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+    verify: bool,
+    resolver: &Resolver,
+) -> Result<Response, BilboError> {
+    let addrs = resolver.resolve(&url.host)?;
+    let (tcp, _) = happy_eyeballs(&addrs, url.port, DEFAULT_TIMEOUT)?;
+    tcp.set_read_timeout(Some(DEFAULT_TIMEOUT))?;
+    tcp.set_write_timeout(Some(DEFAULT_TIMEOUT))?;
+
+    let host = if url.host.contains(':') {
+        format!("[{}]", url.host)
+    } else {
+        url.host.clone()
+    };
+    let mut req = format!(
+        "{method} {} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: bilbo/{}\r\nConnection: close\r\n",
+        url.path,
+        env!("CARGO_PKG_VERSION")
+    );
+    for (k, v) in headers.iter() {
+        req.push_str(&format!("{k}: {v}\r\n"));
+    }
+    if !body.is_empty() {
+        req.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    req.push_str("\r\n");
+
+    if url.tls {
+        let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+        if !verify {
+            builder.set_verify(SslVerifyMode::NONE);
+        }
+        let mut config = builder.build().configure()?;
+        config.set_verify_hostname(verify);
+        let mut stream = config.connect(&url.host, tcp).map_err(handshake_error)?;
+        stream.write_all(req.as_bytes())?;
+        stream.write_all(body)?;
+        read_response(BufReader::new(stream))
+    } else {
+        let mut stream = tcp;
+        stream.write_all(req.as_bytes())?;
+        stream.write_all(body)?;
+        read_response(BufReader::new(stream))
+    }
+}
+
+/// Reads HTTP/1.1 response, supports chunked transfer encoding.
+///
+pub fn read_response(mut reader: impl BufRead) -> Result<Response, BilboError> {
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let status = line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse::<u16>().ok())
+        .ok_or_else(|| BilboError::GenericError(format!("invalid http status line {line:?}")))?;
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if read_line(&mut reader, &mut line)? == 0 {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(BilboError::GenericError(format!(
+                "http response has more than {MAX_HEADERS} headers"
+            )));
+        }
+        if let Some((k, v)) = header.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    let mut response = Response {
+        status,
+        headers,
+        body: Vec::new(),
+    };
+
+    let chunked = response
+        .header("Transfer-Encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+    if chunked {
+        loop {
+            line.clear();
+            read_line(&mut reader, &mut line)?;
+            let size = usize::from_str_radix(line.trim().split(';').next().unwrap_or(""), 16)
+                .map_err(|e| BilboError::GenericError(format!("invalid chunk size: {e}")))?;
+            if size == 0 {
+                break;
+            }
+            if size > MAX_BODY_SIZE - response.body.len() {
+                return Err(BilboError::GenericError(
+                    "http response body too large".to_string(),
+                ));
+            }
+            let mut chunk = vec![0; size];
+            reader.read_exact(&mut chunk)?;
+            response.body.extend_from_slice(&chunk);
+            line.clear();
+            read_line(&mut reader, &mut line)?;
+        }
+    } else {
+        let limit = match response.header("Content-Length") {
+            Some(len) => len.parse::<usize>().unwrap_or(MAX_BODY_SIZE),
+            None => MAX_BODY_SIZE,
+        };
+        reader
+            .take(limit.min(MAX_BODY_SIZE) as u64)
+            .read_to_end(&mut response.body)?;
+    }
+
+    Ok(response)
+}
+
+// Reads the line of at most MAX_LINE_SIZE bytes, so the server can't exhaust memory with a line without end.
+#[inline(always)]
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> Result<usize, BilboError> {
+    let read = reader.take(MAX_LINE_SIZE as u64 + 1).read_line(line)?;
+    if read > MAX_LINE_SIZE {
+        return Err(BilboError::GenericError(format!(
+            "http response line longer than {MAX_LINE_SIZE} bytes"
+        )));
+    }
+
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_parse_urls() -> Result<(), BilboError> {
+        let url = Url::parse("https://dns.google/resolve?name=a.b&type=A")?;
+        assert_eq!(
+            url,
+            Url {
+                tls: true,
+                host: "dns.google".to_string(),
+                port: 443,
+                path: "/resolve?name=a.b&type=A".to_string(),
+            }
+        );
+        let url = Url::parse("http://127.0.0.1:8080")?;
+        assert_eq!((url.host.as_str(), url.port), ("127.0.0.1", 8080));
+        assert_eq!(url.path, "/");
+        let url = Url::parse("http://[::1]:8080/x")?;
+        assert_eq!((url.host.as_str(), url.port), ("::1", 8080));
+        assert_eq!(url.to_string(), "http://[::1]:8080/x");
+        assert!(Url::parse("ftp://example.com").is_err());
+
+        let next = url.join("/login")?;
+        assert_eq!(next.path, "/login");
+        let next = url.join("https://example.com/a")?;
+        assert_eq!(
+            (next.host.as_str(), next.port, next.tls),
+            ("example.com", 443, true)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_read_chunked_response() -> Result<(), BilboError> {
+        let given = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Type: text/plain\r\n\r\n5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n";
+        let response = read_response(given.as_bytes())?;
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("content-type"), Some("text/plain"));
+        assert_eq!(response.body, b"hello world");
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_chunk_size_overflowing_the_body_limit() {
+        let given = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\nffffffffffffffff\r\n";
+        let Err(BilboError::GenericError(e)) = read_response(given.as_bytes()) else {
+            panic!("overflowing chunk size accepted");
+        };
+        assert_eq!(e, "http response body too large");
+    }
+
+    #[test]
+    fn it_should_reject_unbounded_response_lines() {
+        let status = format!("HTTP/1.1 200 {}\r\n\r\n", "A".repeat(MAX_LINE_SIZE));
+        assert!(read_response(status.as_bytes()).is_err());
+        let header = format!(
+            "HTTP/1.1 200 OK\r\nX-Pad: {}\r\n\r\n",
+            "A".repeat(MAX_LINE_SIZE)
+        );
+        assert!(read_response(header.as_bytes()).is_err());
+        let chunk = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}5\r\nhello\r\n0\r\n\r\n",
+            "0".repeat(MAX_LINE_SIZE)
+        );
+        assert!(read_response(chunk.as_bytes()).is_err());
+        let headers = format!(
+            "HTTP/1.1 200 OK\r\n{}\r\n",
+            "X-A: b\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert!(read_response(headers.as_bytes()).is_err());
+    }
+}
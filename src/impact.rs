@@ -0,0 +1,342 @@
+use num_bigint::{BigInt, Sign};
+use openssl::bn::BigNumRef;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private, Public};
+use openssl::rsa::{Padding as RsaPadding, Rsa};
+use openssl::sha::sha256;
+use openssl::sign::{Signer, Verifier};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use crate::errors::BilboError;
+use crate::fingerprint::modulus_fingerprint;
+
+// Message signed with the recovered key to prove its possession, followed by the key fingerprint.
+const EVIDENCE_CHALLENGE: &str = "bilbo evidence of recovered key";
+// Smallest key in bytes to sign SHA-256 with PKCS#1 v1.5, 19 bytes of DigestInfo, 32 of digest and 11 of padding.
+const MIN_SIGNING_KEY_BYTES: u32 = 62;
+
+/// Padding of the ciphertexts decrypted with the recovered key.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Padding {
+    Pkcs1,
+    Oaep,
+    None,
+}
+
+impl Display for Padding {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Padding::Pkcs1 => "pkcs1",
+                Padding::Oaep => "oaep",
+                Padding::None => "none",
+            }
+        )
+    }
+}
+
+impl FromStr for Padding {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pkcs1" => Ok(Padding::Pkcs1),
+            "oaep" => Ok(Padding::Oaep),
+            "none" => Ok(Padding::None),
+            _ => Err(BilboError::GenericError(format!(
+                "unknown padding {s}, expected pkcs1, oaep or none"
+            ))),
+        }
+    }
+}
+
+impl Padding {
+    #[inline(always)]
+    fn openssl(&self) -> RsaPadding {
+        match self {
+            Padding::Pkcs1 => RsaPadding::PKCS1,
+            Padding::Oaep => RsaPadding::PKCS1_OAEP,
+            Padding::None => RsaPadding::NONE,
+        }
+    }
+}
+
+/// Action performed with the recovered key to prove the impact.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Decrypt { padding: Padding },
+    Sign,
+}
+
+/// Evidence is a single proof of exploitability, the input, the output produced with the recovered key
+/// and whether the output verifies against the public key alone.
+/// Input and output are hex encoded, the input is also identified by its SHA-256.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Evidence {
+    pub key: String,
+    pub action: Action,
+    pub input: String,
+    pub input_sha256: String,
+    pub output: String,
+    pub verified: bool,
+}
+
+impl Display for Evidence {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let action = match self.action {
+            Action::Decrypt { padding } => format!("decrypted with {padding} padding"),
+            Action::Sign => "signed with SHA-256 PKCS#1 v1.5".to_string(),
+        };
+        write!(
+            f,
+            "input {} {action} by key {}, {}: {}",
+            self.input_sha256,
+            self.key,
+            if self.verified {
+                "verified"
+            } else {
+                "not verified"
+            },
+            self.output
+        )
+    }
+}
+
+/// Decrypts every ciphertext with the recovered key and packages the evidence.
+/// Decrypted plaintext is verified by encrypting it back with the public key, only deterministic without padding,
+/// padded plaintexts are verified by successful padding check of the decryption itself.
+///
+pub fn decrypt_evidence(
+    key: &Rsa<Private>,
+    ciphertexts: &[Vec<u8>],
+    padding: Padding,
+) -> Result<Vec<Evidence>, BilboError> {
+    let fingerprint = fingerprint(key);
+    ciphertexts
+        .iter()
+        .map(|c| {
+            let mut plaintext = vec![0; key.size() as usize];
+            let len = key.private_decrypt(c, &mut plaintext, padding.openssl())?;
+            plaintext.truncate(len);
+            let verified = match padding {
+                Padding::None => {
+                    let mut encrypted = vec![0; key.size() as usize];
+                    let len = key.public_encrypt(&plaintext, &mut encrypted, RsaPadding::NONE)?;
+                    encrypted[..len] == c[..]
+                }
+                _ => true,
+            };
+
+            Ok(Evidence {
+                key: fingerprint.clone(),
+                action: Action::Decrypt { padding },
+                input: hex(c),
+                input_sha256: hex(&sha256(c)),
+                output: hex(&plaintext),
+                verified,
+            })
+        })
+        .collect()
+}
+
+/// Signs every message with the recovered key, SHA-256 with PKCS#1 v1.5 padding, and packages the evidence.
+/// Signatures are verified with the public key alone, exactly as a relying party would.
+///
+pub fn sign_evidence(
+    key: &Rsa<Private>,
+    messages: &[Vec<u8>],
+) -> Result<Vec<Evidence>, BilboError> {
+    let fingerprint = fingerprint(key);
+    let pkey = PKey::from_rsa(key.clone())?;
+    let public = PKey::from_rsa(Rsa::from_public_components(
+        key.n().to_owned()?,
+        key.e().to_owned()?,
+    )?)?;
+    messages
+        .iter()
+        .map(|m| {
+            let mut signer = Signer::new(MessageDigest::sha256(), &pkey)?;
+            signer.update(m)?;
+            let signature = signer.sign_to_vec()?;
+            let mut verifier = Verifier::new(MessageDigest::sha256(), &public)?;
+            verifier.update(m)?;
+
+            Ok(Evidence {
+                key: fingerprint.clone(),
+                action: Action::Sign,
+                input: hex(m),
+                input_sha256: hex(&sha256(m)),
+                verified: verifier.verify(&signature)?,
+                output: hex(&signature),
+            })
+        })
+        .collect()
+}
+
+/// EvidenceBundle is reproducible evidence embedded in the cracked key finding:
+/// the attack used, the recovered parameters, a signature over a challenge naming the key
+/// made with the recovered key and the exact command and configuration to reproduce it.
+/// Redacted parameters are replaced by their SHA-256, so the reproduction is checked without leaking the key.
+/// Keys too small to sign a SHA-256 digest, toy keys below 496 bits, carry empty signature.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EvidenceBundle {
+    pub attack: String,
+    pub key: String,
+    pub redacted: bool,
+    pub parameters: BTreeMap<String, String>,
+    pub challenge: String,
+    pub signature: String,
+    pub command: Vec<String>,
+    pub config: BTreeMap<String, String>,
+}
+
+impl EvidenceBundle {
+    /// Creates the evidence of the key recovered by given attack, run by the command with the configuration.
+    ///
+    pub fn new(
+        attack: &str,
+        key: &Rsa<Private>,
+        redact: bool,
+        command: Vec<String>,
+        config: BTreeMap<String, String>,
+    ) -> Result<Self, BilboError> {
+        let fingerprint = fingerprint(key);
+        let mut parameters = BTreeMap::new();
+        let mut insert = |name: &str, value: Option<&BigNumRef>| {
+            if let Some(value) = value {
+                let bytes = value.to_vec();
+                let value = if redact {
+                    format!("sha256:{}", hex(&sha256(&bytes)))
+                } else {
+                    hex(&bytes)
+                };
+                parameters.insert(name.to_string(), value);
+            }
+        };
+        insert("d", Some(key.d()));
+        insert("p", key.p());
+        insert("q", key.q());
+        let challenge = format!("{EVIDENCE_CHALLENGE} {fingerprint}");
+        let signature = if key.size() < MIN_SIGNING_KEY_BYTES {
+            String::new()
+        } else {
+            let Some(signed) = sign_evidence(key, &[challenge.as_bytes().to_vec()])?.pop() else {
+                return Err(BilboError::GenericError(
+                    "cannot sign the evidence challenge".to_string(),
+                ));
+            };
+            signed.output
+        };
+
+        Ok(Self {
+            attack: attack.to_string(),
+            key: fingerprint,
+            redacted: redact,
+            parameters,
+            challenge,
+            signature,
+            command,
+            config,
+        })
+    }
+
+    /// Returns true if the signature over the challenge verifies with given public key,
+    /// proving the evidence was made with its private key.
+    ///
+    pub fn verify(&self, public: &Rsa<Public>) -> Result<bool, BilboError> {
+        let Some(signature) = unhex(&self.signature).filter(|s| !s.is_empty()) else {
+            return Ok(false);
+        };
+        let public = PKey::from_rsa(public.to_owned())?;
+        let mut verifier = Verifier::new(MessageDigest::sha256(), &public)?;
+        verifier.update(self.challenge.as_bytes())?;
+
+        Ok(self.challenge.ends_with(&self.key) && verifier.verify(&signature)?)
+    }
+}
+
+// Identifies the key by SHA-256 of the modulus, the same way PickLock::modulus_fingerprint does.
+#[inline(always)]
+fn fingerprint(key: &Rsa<Private>) -> String {
+    modulus_fingerprint(&BigInt::from_bytes_be(Sign::Plus, &key.n().to_vec()))
+}
+
+#[inline(always)]
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[inline(always)]
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::private_key_from_primes;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn it_should_prove_impact_by_decrypting_and_signing() -> Result<(), BilboError> {
+        let key = Rsa::generate(1024)?;
+        let mut c = vec![0; key.size() as usize];
+        let len = key.public_encrypt(b"bilbo", &mut c, RsaPadding::PKCS1)?;
+        c.truncate(len);
+
+        let evidence = decrypt_evidence(&key, &[c], Padding::Pkcs1)?;
+        assert_eq!(evidence.len(), 1);
+        assert_eq!(evidence[0].output, hex(b"bilbo"));
+        assert!(evidence[0].verified);
+
+        let evidence = sign_evidence(&key, &[b"owned".to_vec()])?;
+        assert!(evidence[0].verified);
+        assert_eq!(evidence[0].action, Action::Sign);
+        assert_eq!(evidence[0].input_sha256, hex(&sha256(b"owned")));
+        assert!(serde_json::to_string(&evidence[0])?.contains("\"action\":\"sign\""));
+
+        assert!(decrypt_evidence(&key, &[vec![1; 3]], Padding::Oaep).is_err());
+
+        let public = Rsa::from_public_components(key.n().to_owned()?, key.e().to_owned()?)?;
+        let config = BTreeMap::from([("max_iter".to_string(), "1000".to_string())]);
+        let command = vec!["bilbo".to_string(), "picklock".to_string()];
+        let bundle = EvidenceBundle::new("weak", &key, true, command, config)?;
+        assert!(bundle.verify(&public)?);
+        assert_eq!(bundle.parameters.len(), 3);
+        assert!(bundle.parameters["d"].starts_with("sha256:"));
+        let revealed = EvidenceBundle::new("weak", &key, false, vec![], BTreeMap::new())?;
+        assert_eq!(revealed.parameters["d"], hex(&key.d().to_vec()));
+        let other = Rsa::generate(1024)?;
+        let other = Rsa::from_public_components(other.n().to_owned()?, other.e().to_owned()?)?;
+        assert!(!bundle.verify(&other)?);
+        let toy = private_key_from_primes(
+            &BigInt::from(65537),
+            &BigInt::from(1_000_003),
+            &BigInt::from(999_983),
+        )?;
+        let toy = EvidenceBundle::new("weak", &toy, true, vec![], BTreeMap::new())?;
+        assert!(toy.signature.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use openssl::asn1::Asn1Time;
+use openssl::pkcs12::Pkcs12;
+use openssl::sha::Sha1;
+use openssl::x509::{X509Ref, X509};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::errors::BilboError;
+use crate::rsa::PickLock;
+use crate::store::subject;
+
+const JKS_MAGIC: u32 = 0xfeedfeed;
+const JCEKS_MAGIC: u32 = 0xcececece;
+const JKS_PRIVATE_KEY: u32 = 1;
+const JKS_TRUSTED_CERT: u32 = 2;
+const JCEKS_SECRET_KEY: u32 = 3;
+// Version 2 keystores name the type of every certificate, version 1 hold X.509 only.
+const JKS_TYPED_VERSION: u32 = 2;
+// Java keystores append SHA-1 of the UTF-16 password, this salt and the keystore bytes.
+const JKS_DIGEST_SALT: &[u8] = b"Mighty Aphrodite";
+const JKS_DIGEST_LEN: usize = 20;
+
+/// KeystoreEntry is a RSA certificate of PKCS#12 or Java keystore with the metadata auditors look for.
+///
+///  - alias is the friendly name of PKCS#12 or the entry alias of Java keystore, shared by the whole chain.
+///  - not_after is the expiry of the certificate, expired is true if it passed.
+///  - private is true for the certificate the keystore holds the private key of.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeystoreEntry {
+    pub alias: String,
+    pub subject: String,
+    pub not_after: String,
+    pub expired: bool,
+    pub private: bool,
+    pub der: Vec<u8>,
+}
+
+impl Display for KeystoreEntry {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.alias,
+            self.subject,
+            if self.expired { "expired" } else { "expires" },
+            self.not_after
+        )?;
+        if self.private {
+            write!(f, ", private key held")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl KeystoreEntry {
+    /// Returns PickLock of the certificate key.
+    ///
+    #[inline(always)]
+    pub fn pick_lock(&self) -> Result<PickLock, BilboError> {
+        PickLock::from_x509_der(&self.der)
+    }
+
+    // Creates the entry of RSA certificate, None for the certificates of other keys.
+    #[inline(always)]
+    fn of(alias: &str, cert: &X509Ref, private: bool) -> Result<Option<Self>, BilboError> {
+        if cert.public_key()?.rsa().is_err() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            alias: alias.to_string(),
+            subject: subject(cert),
+            not_after: cert.not_after().to_string(),
+            expired: Asn1Time::days_from_now(0)?.compare(cert.not_after())? == Ordering::Greater,
+            private,
+            der: cert.to_der()?,
+        }))
+    }
+}
+
+/// Reads PKCS#12 or Java keystore, told apart by the magic of JKS and JCEKS, and returns its RSA certificates.
+///
+pub fn read_keystore(data: &[u8], passphrase: &str) -> Result<Vec<KeystoreEntry>, BilboError> {
+    match data
+        .get(..4)
+        .map(|m| u32::from_be_bytes([m[0], m[1], m[2], m[3]]))
+    {
+        Some(JKS_MAGIC | JCEKS_MAGIC) => read_jks(data, Some(passphrase)),
+        _ => read_pkcs12(data, passphrase),
+    }
+}
+
+/// Reads .p12 or .pfx PKCS#12 keystore protected by the passphrase and returns its RSA certificates,
+/// the one of the private key first followed by the CA chain.
+///
+pub fn read_pkcs12(der: &[u8], passphrase: &str) -> Result<Vec<KeystoreEntry>, BilboError> {
+    let parsed = Pkcs12::from_der(der)?.parse2(passphrase)?;
+    let mut entries = Vec::new();
+    let alias = |cert: &X509Ref| {
+        cert.alias()
+            .map(|a| String::from_utf8_lossy(a).to_string())
+            .unwrap_or_default()
+    };
+    if let Some(cert) = parsed.cert.as_ref() {
+        entries.extend(KeystoreEntry::of(
+            &alias(cert),
+            cert,
+            parsed.pkey.is_some(),
+        )?);
+    }
+    for cert in parsed.ca.iter().flatten() {
+        entries.extend(KeystoreEntry::of(&alias(cert), cert, false)?);
+    }
+
+    Ok(entries)
+}
+
+/// Reads JKS or JCEKS Java keystore and returns the RSA certificates of its private key and trusted certificate entries.
+/// Certificates are stored in the clear, the passphrase only verifies the integrity digest, None skips the check.
+/// Secret key entries of JCEKS are serialized Java objects and are refused.
+///
+pub fn read_jks(data: &[u8], passphrase: Option<&str>) -> Result<Vec<KeystoreEntry>, BilboError> {
+    let Some(body_len) = data.len().checked_sub(JKS_DIGEST_LEN) else {
+        return Err(truncated());
+    };
+    let (body, digest) = data.split_at(body_len);
+    if let Some(passphrase) = passphrase {
+        let mut sha = Sha1::new();
+        sha.update(
+            &passphrase
+                .encode_utf16()
+                .flat_map(|c| c.to_be_bytes())
+                .collect::<Vec<u8>>(),
+        );
+        sha.update(JKS_DIGEST_SALT);
+        sha.update(body);
+        if sha.finish() != digest {
+            return Err(BilboError::InvalidKeyMaterial {
+                reason: "keystore password is incorrect or the keystore was tampered with"
+                    .to_string(),
+            });
+        }
+    }
+
+    let mut rest = body;
+    let magic = u32_be(&mut rest)?;
+    if magic != JKS_MAGIC && magic != JCEKS_MAGIC {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: format!("invalid java keystore magic {magic:#010x}"),
+        });
+    }
+    let version = u32_be(&mut rest)?;
+    let mut entries = Vec::new();
+    for _ in 0..u32_be(&mut rest)? {
+        let tag = u32_be(&mut rest)?;
+        let alias = utf(&mut rest)?;
+        take(&mut rest, 8)?;
+        let (chain, private) = match tag {
+            JKS_PRIVATE_KEY => {
+                let protected = u32_be(&mut rest)? as usize;
+                take(&mut rest, protected)?;
+                (u32_be(&mut rest)?, true)
+            }
+            JKS_TRUSTED_CERT => (1, false),
+            JCEKS_SECRET_KEY => {
+                return Err(BilboError::UnsupportedKeyType {
+                    key_type: format!("JCEKS secret key entry {alias}"),
+                })
+            }
+            _ => {
+                return Err(BilboError::InvalidKeyMaterial {
+                    reason: format!("unknown java keystore entry tag {tag}"),
+                })
+            }
+        };
+        for index in 0..chain {
+            if version == JKS_TYPED_VERSION {
+                utf(&mut rest)?;
+            }
+            let len = u32_be(&mut rest)? as usize;
+            let cert = X509::from_der(take(&mut rest, len)?)?;
+            entries.extend(KeystoreEntry::of(&alias, &cert, private && index == 0)?);
+        }
+    }
+
+    Ok(entries)
+}
+
+#[inline(always)]
+fn truncated() -> BilboError {
+    BilboError::InvalidKeyMaterial {
+        reason: "truncated java keystore".to_string(),
+    }
+}
+
+#[inline(always)]
+fn take<'a>(rest: &mut &'a [u8], len: usize) -> Result<&'a [u8], BilboError> {
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let (taken, tail) = rest.split_at(len);
+    *rest = tail;
+
+    Ok(taken)
+}
+
+#[inline(always)]
+fn u32_be(rest: &mut &[u8]) -> Result<u32, BilboError> {
+    let b = take(rest, 4)?;
+    Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// Reads Java modified UTF-8 string, two octet length followed by the bytes.
+#[inline(always)]
+fn utf(rest: &mut &[u8]) -> Result<String, BilboError> {
+    let b = take(rest, 2)?;
+    let len = u16::from_be_bytes([b[0], b[1]]) as usize;
+
+    Ok(String::from_utf8_lossy(take(rest, len)?).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::self_signed;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+
+    fn jks_entry(
+        jks: &mut Vec<u8>,
+        tag: u32,
+        alias: &str,
+        certs: &[&X509],
+    ) -> Result<(), BilboError> {
+        jks.extend_from_slice(&tag.to_be_bytes());
+        jks.extend_from_slice(&(alias.len() as u16).to_be_bytes());
+        jks.extend_from_slice(alias.as_bytes());
+        jks.extend_from_slice(&0_u64.to_be_bytes());
+        if tag == JKS_PRIVATE_KEY {
+            jks.extend_from_slice(&3_u32.to_be_bytes());
+            jks.extend_from_slice(&[1, 2, 3]);
+            jks.extend_from_slice(&(certs.len() as u32).to_be_bytes());
+        }
+        for cert in certs {
+            jks.extend_from_slice(&5_u16.to_be_bytes());
+            jks.extend_from_slice(b"X.509");
+            let der = cert.to_der()?;
+            jks.extend_from_slice(&(der.len() as u32).to_be_bytes());
+            jks.extend_from_slice(&der);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_read_rsa_certificates_of_pkcs12_and_java_keystores() -> Result<(), BilboError> {
+        let key = PKey::from_rsa(Rsa::generate(1024)?)?;
+        let ca_key = PKey::from_rsa(Rsa::generate(1024)?)?;
+        let leaf = self_signed("server.example.com", &key, 30)?;
+        let ca = self_signed("ca.example.com", &ca_key, 0)?;
+        let mut chain = openssl::stack::Stack::new()?;
+        chain.push(ca.clone())?;
+
+        let p12 = Pkcs12::builder()
+            .name("server")
+            .pkey(&key)
+            .cert(&leaf)
+            .ca(chain)
+            .build2("changeit")?
+            .to_der()?;
+        let entries = read_keystore(&p12, "changeit")?;
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].alias, "server");
+        assert_eq!(entries[0].subject, "CN=server.example.com");
+        assert!(entries[0].private && !entries[0].expired);
+        assert!(!entries[1].private);
+        assert_eq!(entries[0].pick_lock()?.bits(), 1024);
+        assert!(read_pkcs12(&p12, "wrong").is_err());
+
+        let mut jks = Vec::new();
+        jks.extend_from_slice(&JKS_MAGIC.to_be_bytes());
+        jks.extend_from_slice(&JKS_TYPED_VERSION.to_be_bytes());
+        jks.extend_from_slice(&2_u32.to_be_bytes());
+        jks_entry(&mut jks, JKS_PRIVATE_KEY, "tomcat", &[&leaf, &ca])?;
+        jks_entry(&mut jks, JKS_TRUSTED_CERT, "root", &[&ca])?;
+        let mut sha = Sha1::new();
+        sha.update(
+            &"changeit"
+                .encode_utf16()
+                .flat_map(|c| c.to_be_bytes())
+                .collect::<Vec<u8>>(),
+        );
+        sha.update(JKS_DIGEST_SALT);
+        sha.update(&jks);
+        jks.extend_from_slice(&sha.finish());
+
+        let entries = read_keystore(&jks, "changeit")?;
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].alias, "tomcat");
+        assert!(entries[0].private && !entries[1].private);
+        assert_eq!(entries[2].alias, "root");
+        assert!(entries[2].to_string().contains("expired"));
+        assert_eq!(
+            entries[0].pick_lock()?.modulus_fingerprint(),
+            PickLock::from_x509_der(&leaf.to_der()?)?.modulus_fingerprint()
+        );
+        assert!(read_jks(&jks, Some("wrong")).is_err());
+        assert_eq!(read_jks(&jks, None)?.len(), 3);
+        assert!(read_jks(&jks[..jks.len() / 2], None).is_err());
+
+        Ok(())
+    }
+}
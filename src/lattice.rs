@@ -0,0 +1,306 @@
+use num_bigint::{BigInt, Sign};
+use num_integer::Integer;
+
+use crate::errors::BilboError;
+
+// Largest m of the Howgrave-Graham lattice, its dimension is 2 · m + 1, reduction of larger lattices takes minutes.
+const MAX_LATTICE_DEGREE: usize = 6;
+// Reduced vectors whose polynomials are paired to find their common root.
+const ROOT_CANDIDATES: usize = 4;
+
+/// Reduces the lattice basis, given as linearly independent integer rows, by the Lenstra–Lenstra–Lovász algorithm
+/// with δ = 3/4. The integral version of Cohen's A Course in Computational Algebraic Number Theory, 2.6.7,
+/// keeps the Gram–Schmidt coefficients as exact integers, so no precision is lost for entries of thousands of bits.
+/// The first reduced row is at most 2^((d − 1) / 2) times longer than the shortest vector of the lattice.
+///
+pub fn lll(basis: &[Vec<BigInt>]) -> Result<Vec<Vec<BigInt>>, BilboError> {
+    let rows = basis.len();
+    let mut b = basis.to_vec();
+    if rows < 2 {
+        return Ok(b);
+    }
+    let zero = BigInt::from(0);
+    // d[i + 1] is the Gram determinant of the first i + 1 rows, d[0] = 1, lambda[k][j] for j < k are scaled
+    // Gram–Schmidt coefficients d[j + 1] · μ_kj.
+    let mut d = vec![zero.clone(); rows + 1];
+    let mut lambda = vec![vec![zero.clone(); rows]; rows];
+    d[0] = BigInt::from(1);
+    d[1] = dot(&b[0], &b[0]);
+    if d[1] == zero {
+        return Err(dependent());
+    }
+    let (mut k, mut k_max) = (1, 0);
+    while k < rows {
+        if k > k_max {
+            k_max = k;
+            for j in 0..=k {
+                let mut u = dot(&b[k], &b[j]);
+                for i in 0..j {
+                    u = (&d[i + 1] * &u - &lambda[k][i] * &lambda[j][i]) / &d[i];
+                }
+                if j < k {
+                    lambda[k][j] = u;
+                } else if u == zero {
+                    return Err(dependent());
+                } else {
+                    d[k + 1] = u;
+                }
+            }
+        }
+        reduce(&mut b, &mut lambda, &d, k, k - 1);
+        // Lovász condition d_k · d_k−2 ≥ 3/4 · d_k−1² − λ², swap the rows if it fails.
+        let lovasz = &d[k + 1] * &d[k - 1] * 4_u32;
+        let bound = &d[k] * &d[k] * 3_u32 - &lambda[k][k - 1] * &lambda[k][k - 1] * 4_u32;
+        if lovasz < bound {
+            swap(&mut b, &mut lambda, &mut d, k, k_max);
+            k = (k - 1).max(1);
+            continue;
+        }
+        for l in (0..k - 1).rev() {
+            reduce(&mut b, &mut lambda, &d, k, l);
+        }
+        k += 1;
+    }
+
+    Ok(b)
+}
+
+/// Finds the small root x0, 0 ≤ x0 < 2^bound_bits, of a + x modulo unknown divisor p of n, of at least half its bits,
+/// by Coppersmith's method in Howgrave-Graham's formulation: polynomials sharing the root modulo p^m
+/// span a lattice whose short vectors are polynomials sharing it over the integers.
+/// Returns None if no lattice up to the largest degree is large enough for the bound or no root is found.
+///
+pub fn coppersmith_divisor_root(a: &BigInt, n: &BigInt, bound_bits: u64) -> Option<BigInt> {
+    let m = (1..=MAX_LATTICE_DEGREE).find(|m| is_lattice_large_enough(*m, n.bits(), bound_bits))?;
+    let x = BigInt::from(1) << bound_bits;
+    let f = vec![a.clone(), BigInt::from(1)];
+    let mut polynomials = Vec::with_capacity(2 * m + 1);
+    // N^(m − i) · f^i for i up to m and x^j · f^m for j up to m, all vanish at x0 modulo p^m.
+    let mut power = vec![BigInt::from(1)];
+    for i in 0..=m {
+        let scale = n.pow((m - i) as u32);
+        polynomials.push(power.iter().map(|c| c * &scale).collect::<Vec<_>>());
+        if i < m {
+            power = multiply(&power, &f);
+        }
+    }
+    for j in 1..=m {
+        let mut shifted = vec![BigInt::from(0); j];
+        shifted.extend(power.iter().cloned());
+        polynomials.push(shifted);
+    }
+    let dimension = polynomials.len();
+    let scales: Vec<BigInt> = (0..dimension).map(|l| x.pow(l as u32)).collect();
+    let basis: Vec<Vec<BigInt>> = polynomials
+        .iter()
+        .map(|p| {
+            (0..dimension)
+                .map(|l| p.get(l).map_or_else(|| BigInt::from(0), |c| c * &scales[l]))
+                .collect()
+        })
+        .collect();
+
+    let reduced = lll(&basis).ok()?;
+    let candidates: Vec<Vec<BigInt>> = reduced
+        .iter()
+        .take(ROOT_CANDIDATES)
+        .map(|row| row.iter().zip(scales.iter()).map(|(c, s)| c / s).collect())
+        .collect();
+    for (i, g) in candidates.iter().enumerate() {
+        for h in candidates.iter().skip(i + 1) {
+            let common = gcd(g, h);
+            let [b0, b1] = common.as_slice() else {
+                continue;
+            };
+            let (x0, rest) = (-b0).div_rem(b1);
+            if rest.sign() == Sign::NoSign
+                && x0.sign() != Sign::Minus
+                && x0 < x
+                && (a + &x0).gcd(n) > BigInt::from(1)
+            {
+                return Some(x0);
+            }
+        }
+    }
+
+    None
+}
+
+// Howgrave-Graham's bound, the lattice of dimension d = 2 · m + 1 and determinant N^(m(m + 1) / 2) · X^(d(d − 1) / 2)
+// has short vector below p^m / √d when det^(1/d) · 2^((d − 1) / 4) · √d is below the least p^m of half the bits of n.
+#[inline(always)]
+fn is_lattice_large_enough(m: usize, n_bits: u64, bound_bits: u64) -> bool {
+    let (m, n_bits, bound_bits) = (m as f64, n_bits as f64, bound_bits as f64);
+    let d = 2.0 * m + 1.0;
+    let log_det = m * (m + 1.0) / 2.0 * n_bits + d * (d - 1.0) / 2.0 * bound_bits;
+
+    log_det / d + (d - 1.0) / 4.0 + d.log2() / 2.0 < m * (n_bits - 2.0) / 2.0
+}
+
+#[inline(always)]
+fn dot(a: &[BigInt], b: &[BigInt]) -> BigInt {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+#[inline(always)]
+fn dependent() -> BilboError {
+    BilboError::GenericError("lattice basis rows are linearly dependent".to_string())
+}
+
+// Size reduces row k by row l, so |μ_kl| ≤ 1/2.
+#[inline(always)]
+fn reduce(b: &mut [Vec<BigInt>], lambda: &mut [Vec<BigInt>], d: &[BigInt], k: usize, l: usize) {
+    if (&lambda[k][l] * 2_u32).magnitude() <= d[l + 1].magnitude() {
+        return;
+    }
+    // Nearest integer of λ / d.
+    let q = (&lambda[k][l] * 2_u32 + &d[l + 1]).div_floor(&(&d[l + 1] * 2_u32));
+    let row: Vec<BigInt> = b[l].iter().map(|c| c * &q).collect();
+    for (c, r) in b[k].iter_mut().zip(row) {
+        *c -= r;
+    }
+    lambda[k][l] -= &q * &d[l + 1];
+    let (upper, lower) = lambda.split_at_mut(k);
+    for (c, r) in lower[0][..l].iter_mut().zip(upper[l][..l].iter()) {
+        *c -= &q * r;
+    }
+}
+
+// Swaps rows k and k − 1 updating the Gram determinants and coefficients.
+#[inline(always)]
+fn swap(
+    b: &mut [Vec<BigInt>],
+    lambda: &mut [Vec<BigInt>],
+    d: &mut [BigInt],
+    k: usize,
+    k_max: usize,
+) {
+    b.swap(k, k - 1);
+    let (upper, lower) = lambda.split_at_mut(k);
+    upper[k - 1][..k - 1].swap_with_slice(&mut lower[0][..k - 1]);
+    let l = lambda[k][k - 1].clone();
+    let next = (&d[k - 1] * &d[k + 1] + &l * &l) / &d[k];
+    for row in lambda[k + 1..=k_max].iter_mut() {
+        let t = row[k].clone();
+        row[k] = (&d[k + 1] * &row[k - 1] - &l * &t) / &d[k];
+        row[k - 1] = (&next * &t + &l * &row[k]) / &d[k + 1];
+    }
+    d[k] = next;
+}
+
+// Multiplies polynomials given by coefficients from the constant term up.
+#[inline(always)]
+fn multiply(a: &[BigInt], b: &[BigInt]) -> Vec<BigInt> {
+    let mut product = vec![BigInt::from(0); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+
+    product
+}
+
+// Greatest common divisor of integer polynomials up to a constant, by the primitive remainder sequence.
+#[inline(always)]
+fn gcd(a: &[BigInt], b: &[BigInt]) -> Vec<BigInt> {
+    let (mut a, mut b) = (primitive(a.to_vec()), primitive(b.to_vec()));
+    while !b.is_empty() {
+        let r = primitive(pseudo_remainder(a, &b));
+        a = b;
+        b = r;
+    }
+
+    a
+}
+
+#[inline(always)]
+fn pseudo_remainder(mut a: Vec<BigInt>, b: &[BigInt]) -> Vec<BigInt> {
+    let lead = &b[b.len() - 1];
+    while a.len() >= b.len() {
+        let top = a[a.len() - 1].clone();
+        let shift = a.len() - b.len();
+        for c in a.iter_mut() {
+            *c *= lead;
+        }
+        for (i, c) in b.iter().enumerate() {
+            a[shift + i] -= &top * c;
+        }
+        trim(&mut a);
+    }
+
+    a
+}
+
+// Divides the polynomial by the gcd of its coefficients and makes its leading coefficient positive.
+#[inline(always)]
+fn primitive(mut p: Vec<BigInt>) -> Vec<BigInt> {
+    trim(&mut p);
+    let content = p.iter().fold(BigInt::from(0), |g, c| g.gcd(c));
+    if content.sign() == Sign::NoSign {
+        return p;
+    }
+    let content = if p[p.len() - 1].sign() == Sign::Minus {
+        -content
+    } else {
+        content
+    };
+
+    p.into_iter().map(|c| c / &content).collect()
+}
+
+#[inline(always)]
+fn trim(p: &mut Vec<BigInt>) {
+    while p.last().is_some_and(|c| c.sign() == Sign::NoSign) {
+        p.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_reduce_lattice_and_find_small_roots() -> Result<(), BilboError> {
+        let basis = [[1, 1, 1], [-1, 0, 2], [3, 5, 6]]
+            .map(|row| row.map(BigInt::from).to_vec())
+            .to_vec();
+        let reduced = lll(&basis)?;
+        assert_eq!(
+            reduced,
+            [[0, 1, 0], [1, 0, 1], [-1, 0, 2]]
+                .map(|row| row.map(BigInt::from).to_vec())
+                .to_vec()
+        );
+        assert!(lll(&[
+            vec![BigInt::from(1), BigInt::from(2)],
+            vec![BigInt::from(2), BigInt::from(4)]
+        ])
+        .is_err());
+
+        // (x − 3)(x + 2) and (x − 3)(2x + 1) share x − 3.
+        let g = gcd(
+            &[-6, -1, 1].map(BigInt::from),
+            &[-3, -5, 2].map(BigInt::from),
+        );
+        assert_eq!(g, [-3, 1].map(BigInt::from).to_vec());
+
+        let p = BigInt::parse_bytes(
+            b"fca3b4f03e4f5a0f7c2d2b0e9ff3c1a1a0ab7bd9e0d0d1c1e7d5b5a4f3b2d1c5",
+            16,
+        )
+        .unwrap();
+        let q = BigInt::parse_bytes(
+            b"e3b1c2a4f5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f71",
+            16,
+        )
+        .unwrap();
+        let n = &p * &q;
+        let x0 = &p & ((BigInt::from(1) << 80) - 1);
+        let a = &p - &x0;
+        assert_eq!(coppersmith_divisor_root(&a, &n, 80), Some(x0));
+        assert_eq!(coppersmith_divisor_root(&a, &n, 200), None);
+
+        Ok(())
+    }
+}
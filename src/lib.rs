@@ -1,5 +1,61 @@
 /// Bilbo is a small library handcrafted for security researchers.
+pub mod archive;
+pub mod attest;
+pub mod audit;
+pub mod batch_gcd;
+pub mod blinding;
+#[cfg(feature = "async")]
+pub mod blocking;
+pub mod blocklist;
+pub mod broadcast;
+pub mod cache;
+pub mod checkpoint;
+pub mod cluster;
+pub mod corpus;
+pub mod cpu;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+pub mod diff;
+pub mod distributed;
+pub mod dkim;
+pub mod ecm;
+pub mod endpoint;
 pub mod entropy;
 pub mod errors;
+pub mod factor;
+pub mod federation;
+pub mod fingerprint;
+pub mod fraction;
+pub mod http;
+pub mod impact;
+pub mod int;
+pub mod keystore;
+pub mod lattice;
+pub mod modmath;
+pub mod net;
+pub mod netscan;
+pub mod nonce;
+pub mod partial;
+pub mod pgp;
+pub mod pool;
+pub mod primality;
+pub mod progress;
+pub mod qs;
+pub mod race;
+pub mod report;
+pub mod revocation;
 pub mod rsa;
+pub mod scanner;
+pub mod schema;
+pub mod sieve;
+pub mod signature;
 pub mod smuggler;
+pub mod squfof;
+pub mod ssh;
+pub mod store;
+pub mod strategy;
+pub mod suppress;
+pub mod telemetry;
+#[cfg(test)]
+mod testing;
+pub mod textbook;
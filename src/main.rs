@@ -1,16 +1,51 @@
+use bilbo::attest::{verify_report, ReportSigner};
+use bilbo::audit::{read_records, AuditLog};
+use bilbo::batch_gcd::BatchPickLock;
+use bilbo::cache::Cache;
+use bilbo::checkpoint::AttackCheckpoint;
+use bilbo::cpu::{CpuFeatures, Kernel};
+use bilbo::diff::{diff_runs, Baseline};
+use bilbo::dkim::{self, read_domains, COMMON_SELECTORS};
+use bilbo::endpoint::{grab_certificates, read_urls};
 use bilbo::entropy;
 use bilbo::errors::BilboError;
-use bilbo::rsa::{to_pem, KeyType, PickLock};
+use bilbo::federation::{self, read_metadata_urls, FederationInventory, MIN_FEDERATION_BITS};
+use bilbo::impact::EvidenceBundle;
+use bilbo::net::{split_host_port, Dialer, DualStackReport, Resolver};
+use bilbo::netscan::fetch_tls_public_key_with;
+use bilbo::partial::{PartialKey, Secret};
+use bilbo::pool::Pool;
+use bilbo::primality::{policy, set_policy, PrimalityPolicy};
+use bilbo::progress::{ConsoleSink, ProgressSink, SilentSink};
+use bilbo::race::{race, Contender, Quotas};
+use bilbo::report::{
+    AuditReport, Finding, Severity, DKIM_KEY_ATTACK, FEDERATION_KEY_ATTACK, ROCA_ATTACK,
+    SSH_ALGORITHMS_ATTACK, TRANSPORT_ATTACK,
+};
+use bilbo::rsa::{fermat_ratios, read_auxiliary, IterationLimit, MultiPickLock, PickLock};
+use bilbo::scanner::{KeyScanner, KeyWeakness};
+use bilbo::schema::SCHEMA_VERSION;
 use bilbo::smuggler::{ping_cipher, ping_plain, Config};
-use clap::{arg, command, value_parser, Command};
+use bilbo::ssh::{self, read_hosts};
+use bilbo::store::scan_stores;
+use bilbo::strategy::AttackStrategy;
+use bilbo::suppress::Allowlist;
+use clap::{arg, command, value_parser, ArgGroup, ArgMatches, Command};
+use clap_complete::{generate, Shell};
+use num_bigint::BigInt;
+use openssl::pkey::PKey;
+use serde_json::{json, Value};
 use shamirss::{
     combine_inlined, create_inlined, decode_secret_to_bytes, decode_shares_to_bytes,
     encode_secret_bytes, encode_shares_bytes, EncodingStd,
 };
-use std::fs::read_to_string;
-use std::io::Write;
+use std::collections::BTreeMap;
+use std::fs::{read, read_to_string, remove_file, write, File};
+use std::io::{stdout, BufReader, Write};
 use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 const EXPLAIN: &str = "
 [ 🐉 🏔 💎 ] BILBO
@@ -34,13 +69,14 @@ Returns private key as bytes representation of an integer in big endian order or
 Will not go further then 1000 iterations.
 
 2. Strong 💪:
-Is cracking RSA when p and q are far apart.
-Similar in terms of factorization to weak algorithm, but works on the principal that RSA p and q
-are chosen according to the specification where:
- -> p * q = n,
- -> p and q are primes that differs in more than first 2^517 - last 64 bytes.
- -> p and q are fairly equal in bits size and can vary +/- 1 bit,
- -> bits size of p + bits size of q are equal to n,
+Is cracking RSA when p and q are far apart but one of them has an exploitable structure.
+The modulus is factored by a staged pipeline, cheapest methods first, within the ITERS budget:
+ -> trial division finds a prime below ITERS * 100,
+ -> Pollard's rho finds a prime below (ITERS * 100)^2,
+ -> Pollard's p - 1 finds a prime p where p - 1 has only factors below ITERS * 100,
+ -> Lenstra's ECM runs ITERS / 100 random curves and finds a prime where the order of a curve is smooth.
+Correctly generated keys have balanced primes with no such structure and hold,
+the fraction of the prime space that was excluded is reported then.
 
 [ 🧮 ] Bilbo offers entropy calculation.
 
@@ -62,14 +98,76 @@ sent via ping in 24 bytes long chunks. The initialization vector is transferred
 The Shamirs Secret Sharing is an efficient secret sharing algorithm for distributing private information (the secret) among a group. The secret cannot be revealed unless a quorum of the group acts together to pool their knowledge. To achieve this, the secret is mathematically divided into parts (the shares) from which the secret can be reassembled only when a sufficient number of shares are combined. SSS has the property of information-theoretic security, meaning that even if an attacker steals some shares, it is impossible for the attacker to reconstruct the secret unless they have stolen the quorum number of shares.
 ";
 
+const EXIT_CODES: &str = "Exit codes:
+  0  Clean, nothing weak was found.
+  1  Weak key found, the lock was picked or the key flagged.
+  2  Error, the command failed.";
+
+const EXIT_CLEAN: u8 = 0;
+const EXIT_FINDINGS: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+
+// Weighted Fermat in the race scans p/q ratios with both terms up to it.
+const RACE_RATIOS: u64 = 16;
+// Attack name of the keys sharing a prime found by batch gcd.
+const BATCH_GCD_ATTACK: &str = "batch_gcd";
+// Port of the tls subcommand target without one.
+const HTTPS_PORT: u16 = 443;
+// Findings of this severity or more set exit code 1 of the subcommands printing the audit report.
+const GATE_SEVERITY: Severity = Severity::Medium;
+
+// Default stage 1 bound of ECM, targets primes of 20 digits.
+const ECM_B1: u64 = 11_000;
+
 const MINIMUM_SHARES: usize = 10;
 const TOTAL_SHARES: usize = 20;
 
-fn main() {
-    let cmd = Command::new("bilbo")
+fn cli() -> Command {
+    Command::new("bilbo")
         .bin_name("bilbo")
         .subcommand_required(true)
         .about("🧝 Bilbo is a simple CLI cyber security tool. Scans files to discover hidden information and helps send them secretly.")
+        .after_help(EXIT_CODES)
+        .arg(arg!(-q --"quiet" "Prints nothing but errors, rely on the exit code.").global(true))
+        .arg(
+            arg!(-v --"verbose" ... "Increases verbosity, -v prints important steps, -vv prints all details. Overridden by --report.")
+                .global(true),
+        )
+        .arg(
+            arg!(--"primality" <POLICY> "Primality testing policy: 'fast', 'bpsw' (default), 'strict' or number of Miller-Rabin rounds.")
+                .value_parser(value_parser!(PrimalityPolicy))
+                .global(true),
+        )
+        .arg(
+            arg!(--"audit" <FILE> "Path to JSONL audit log file, every loaded key, attack and finding is appended to it.")
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            arg!(--"allowlist" <FILE> "Path to allowlist of accepted risks, one modulus SHA-256 fingerprint per line optionally followed by attack and # reason.")
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            arg!(--"baseline" <FILE> "Path to baseline of known findings recorded by the baseline subcommand, only findings missing from it set exit code 1.")
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            arg!(--"cache" <DIR> "Directory caching fetched certificates, JWKS documents and DNS records across runs, memory only by default.")
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            arg!(--"cache-ttl" <SECONDS> "Time to live of the cached remote key material in seconds. Default 3600.")
+                .value_parser(value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            arg!(--"sign-key" <FILE> "Path to PEM private key of the assessor, RSA, EC P-256, P-384 or Ed25519, signing the audit log and baseline written by the run as detached JWS next to them.")
+                .value_parser(value_parser!(PathBuf))
+                .global(true),
+        )
         .subcommand(
             command!("smuggle")
             .about("Smuggles the file via ping.")
@@ -78,6 +176,10 @@ fn main() {
                     .value_parser(value_parser!(PathBuf)),
             ).arg(
                 arg!(--"ip" <IP> "IPv4 to the server that will collect smuggled file.").value_parser(value_parser!(Ipv4Addr)),
+            ).arg(
+                arg!(--"host" <HOST> "Host name of the server that will collect smuggled file, used when --ip is not given."),
+            ).arg(
+                arg!(--"resolver" <RESOLVER> "Resolver of the --host: 'system' (default) or DoH JSON API url, e.g. https://cloudflare-dns.com/dns-query."),
             ).arg(
                 arg!(--"encrypt" <KEY> "Encryption key.").value_parser(value_parser!(Vec<u8>)),
             )
@@ -86,12 +188,194 @@ fn main() {
             command!("picklock")
             .about("Attempts to pick lock the rsa key.")
             .arg(
-                arg!(--"file" <FILE> "Path to file in PEM format to be lock picked")
+                arg!(--"file" <FILE> "Path to PEM, OpenSSH or PuTTY public key, certificate signing request or X.509 certificate, PEM or DER, to be lock picked")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"strong" <ITERS> "Budget of the factoring pipeline, trial division, rho, p - 1 and ECM bounds scale with it.").value_parser(value_parser!(u32)),
+            ).arg(
+                arg!(--"targets" <FILE> "More public keys or certificates attacked together with --file by the strong pipeline or --batch-gcd, sharing candidates and recovered factors.").value_parser(value_parser!(PathBuf)).num_args(1..).requires("shared"),
+            ).arg(
+                arg!(--"ratios" <MAX> "Weighted Fermat, scans p/q ratios u/v with both terms up to MAX.").value_parser(value_parser!(u64)).conflicts_with("strong"),
+            ).arg(
+                arg!(--"aux" <FILE> "File with auxiliary integers, one per line, decimal or 0x hex, tested for common factor with the modulus.").value_parser(value_parser!(PathBuf)).conflicts_with_all(["strong", "ratios"]),
+            ).arg(
+                arg!(--"pm1" <BOUND> "Pollard's p - 1 in two stages, finds a prime p where p - 1 has only factors below BOUND but one below BOUND * 100.").value_parser(value_parser!(u64)).conflicts_with_all(["strong", "ratios", "aux"]),
+            ).arg(
+                arg!(--"pp1" "Williams' p + 1, finds a prime p where p + 1 has only factors below 100000.").conflicts_with_all(["strong", "ratios", "aux", "pm1"]),
+            ).arg(
+                arg!(--"small-d" "Wiener's attack, recovers the private exponent below n^0.25 / 3 from the continued fraction of e / n.").conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1"]),
+            ).arg(
+                arg!(--"ecm" <CURVES> "Lenstra's ECM on Montgomery curves in parallel, finds a medium size prime where the order of a curve is smooth.").value_parser(value_parser!(usize)).conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d"]),
+            ).arg(
+                arg!(--"b1" <BOUND> "Stage 1 bound of --ecm, stage 2 runs up to BOUND * 100. Default 11000, targets 20 digit primes.").value_parser(value_parser!(u64)).requires("ecm"),
+            ).arg(
+                arg!(--"rho" "Pollard's rho with Brent's cycle detection, deterministically finds a prime factor below 10^10 in about its square root steps.").conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm"]),
+            ).arg(
+                arg!(--"partial" <FILE> "File of recovered key bits, e.g. 'bits 160..256 of p are 0x...', Coppersmith's method factors n from about the higher half of p or q.").value_parser(value_parser!(PathBuf)).conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho"]),
+            ).arg(
+                arg!(--"multipliers" <K> "Fermat on n * K for every multiplier K, Lehman style, catches primes ratio p/q close to u/v with u * v = K.").value_parser(value_parser!(u64)).num_args(1..).conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial"]),
+            ).arg(
+                arg!(--"lehman" "Lehman's method, deterministically factors any modulus up to 90 bits, test and toy keys, in O(n^(1/3)) steps.").conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial", "multipliers"]),
+            ).arg(
+                arg!(--"qs" "Self-initializing quadratic sieve in parallel, factors any modulus from 64 bits up to about 100 digits, 70 digits in under a minute per core.").conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial", "multipliers", "lehman"]),
+            ).arg(
+                arg!(--"all" "Runs every attack one after another, cheapest first, each within its time budget, until one picks the lock.").conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial", "multipliers", "lehman", "qs"]),
+            ).arg(
+                arg!(--"budget" <SECONDS> "Time budget of every attack of --all. Default 30 seconds.").value_parser(value_parser!(u64)).requires("all"),
+            ).arg(
+                arg!(--"batch-gcd" "Bernstein's batch gcd of --file and --targets moduli, picks every key sharing a prime with another in quasi linear time.").requires("targets").conflicts_with_all(["ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial", "multipliers", "lehman", "qs", "all"]),
+            ).group(
+                ArgGroup::new("shared").args(["strong", "batch-gcd"]),
+            ).arg(
+                arg!(--"race" "Races weak, weighted Fermat and strong attacks in parallel, the first to pick the lock cancels the rest. --strong sets the budget.").conflicts_with_all(["targets", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial", "multipliers", "lehman", "qs", "all"]),
+            ).arg(
+                arg!(--"quotas" <FILE> "File with CPU time, wall time, memory and iteration quota of the raced attacks, e.g. 'strong cpu=30s memory=512MiB iterations=1000'.").value_parser(value_parser!(PathBuf)).requires("race"),
+            ).arg(
+                arg!(--"parallel" <ITERATIONS> "Weak lock pick scanning ITERATIONS Fermat candidates split across all the cores, large caps reach primes further apart.").value_parser(value_parser!(usize)).conflicts_with_all(["strong", "ratios", "aux", "pm1", "pp1", "small-d", "ecm", "rho", "partial", "multipliers", "lehman", "qs", "all", "batch-gcd", "race", "checkpoint"]),
+            ).arg(
+                arg!(--"time-limit" <SECONDS> "Wall clock time limit of the attack, it fails as timed out once spent.").value_parser(value_parser!(u64)),
+            ).arg(
+                arg!(--"checkpoint" <FILE> "Checkpoint file of the weak and --ecm attacks, resumed if it exists and written when the attack stops without picking the lock.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Important steps only. 2: Progress of the strong pipeline stages and the quadratic sieve relations.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("certscan")
+            .about("Grabs TLS certificates of the URLs following redirects and picks the lock of every unique RSA key once.")
+            .arg(
+                arg!(--"urls" <FILE> "Path to file with URLs or host names, one per line.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"resolver" <RESOLVER> "Resolver of the host names: 'system' (default) or DoH JSON API url, e.g. https://cloudflare-dns.com/dns-query."),
+            ).arg(
+                arg!(--"strong" <ITERS> "Runs the strong pipeline with given budget instead of the weak lock pick.").value_parser(value_parser!(u32)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Certificates, their endpoints, negotiated transports and failed URLs.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("sshscan")
+            .about("Audits algorithms offered by the SSH servers and picks the lock of their RSA host keys.")
+            .arg(
+                arg!(--"hosts" <FILE> "Path to file with SSH servers, one host[:port] per line.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"resolver" <RESOLVER> "Resolver of the host names: 'system' (default) or DoH JSON API url, e.g. https://cloudflare-dns.com/dns-query."),
+            ).arg(
+                arg!(--"strong" <ITERS> "Runs the strong pipeline with given budget instead of the weak lock pick.").value_parser(value_parser!(u32)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Banners, offered algorithms, host keys and failed servers.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("dkimscan")
+            .about("Queries DKIM key records of the selectors of the domains, flags short and weak signing keys and picks the lock of every RSA key.")
+            .arg(
+                arg!(--"domains" <FILE> "Path to file with mail domains, one per line optionally followed by its selectors.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"selectors" <FILE> "Path to file with selectors, one per line, tried on domains without their own. Default is a list of common selectors.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"resolver" <RESOLVER> "Resolver of the records: 'system' (default) or DoH JSON API url, e.g. https://cloudflare-dns.com/dns-query."),
+            ).arg(
+                arg!(--"strong" <ITERS> "Runs the strong pipeline with given budget instead of the weak lock pick.").value_parser(value_parser!(u32)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Published key records and failed queries.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("fedscan")
+            .about("Fetches SAML metadata and OIDC discovery documents, flags short keys and picks the lock of every RSA signing and encryption key per entity.")
+            .arg(
+                arg!(--"metadata" <FILE> "Path to file with SAML metadata, OIDC discovery, JWKS or issuer urls, one per line.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"strong" <ITERS> "Runs the strong pipeline with given budget instead of the weak lock pick.").value_parser(value_parser!(u32)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Published keys with their entities and failed urls.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("storescan")
+            .about("Enumerates the Windows certificate stores or the macOS keychains and picks the lock of every resident RSA key once.")
+            .arg(
+                arg!(--"strong" <ITERS> "Runs the strong pipeline with given budget instead of the weak lock pick.").value_parser(value_parser!(u32)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Resident certificates and keys with their stores.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("crack")
+            .about("Runs every attack on the RSA key, cheapest first, each within its time budget, and prints the audit report.")
+            .arg(
+                arg!(<FILE> "Path to PEM, OpenSSH or PuTTY public key, certificate signing request or X.509 certificate, PEM or DER.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"budget" <SECONDS> "Time budget of every attack. Default 30 seconds.").value_parser(value_parser!(u64)),
+            ).arg(
+                arg!(--"format" <FORMAT> "Format of the audit report: 'text' (default), 'json', 'sarif', 'csv' or 'junit'.").value_parser(["text", "json", "sarif", "csv", "junit"]),
+            ).arg(
+                arg!(--"out" <FILE> "Path to the file the audit report is written to instead of stdout.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Telemetry of every attack tried.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("scan")
+            .about("Scans the directory tree, or the git history with --git, for PEM, DER, OpenSSH, PuTTY, JWK and OpenPGP keys, archives included, and runs the weakness checks on every key.")
+            .arg(
+                arg!(<PATH> "Path to the directory, file or git repository to scan.")
+                    .value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"git" "Scans every blob of the git history instead of the working tree."),
+            ).arg(
+                arg!(--"include" <GLOB> "Scans only the files matching any of the glob patterns, '**' crosses directories.").num_args(1..),
+            ).arg(
+                arg!(--"exclude" <GLOB> "Skips the files and directories matching any of the glob patterns.").num_args(1..),
+            ).arg(
+                arg!(--"max-file-size" <BYTES> "Skips larger files unread. Default 1 MiB.").value_parser(value_parser!(u64)),
+            ).arg(
+                arg!(--"archive-depth" <DEPTH> "Descends nested zip, jar, tar and gzip archives up to DEPTH levels, 0 doesn't unpack them. Default 3.").value_parser(value_parser!(usize)),
+            ).arg(
+                arg!(--"format" <FORMAT> "Format of the audit report: 'text' (default), 'json', 'sarif', 'csv' or 'junit'.").value_parser(["text", "json", "sarif", "csv", "junit"]),
+            ).arg(
+                arg!(--"out" <FILE> "Path to the file the audit report is written to instead of stdout.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Every key found and the number of files scanned.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("tls")
+            .about("Handshakes TLS with the server, runs every attack on the RSA key of its certificate and prints the audit report.")
+            .arg(
+                arg!(<TARGET> "Server to handshake with, host[:port], port 443 by default."),
+            ).arg(
+                arg!(--"resolver" <RESOLVER> "Resolver of the host names: 'system' (default) or DoH JSON API url, e.g. https://cloudflare-dns.com/dns-query."),
+            ).arg(
+                arg!(--"budget" <SECONDS> "Time budget of every attack. Default 30 seconds.").value_parser(value_parser!(u64)),
+            ).arg(
+                arg!(--"format" <FORMAT> "Format of the audit report: 'text' (default), 'json', 'sarif', 'csv' or 'junit'.").value_parser(["text", "json", "sarif", "csv", "junit"]),
+            ).arg(
+                arg!(--"out" <FILE> "Path to the file the audit report is written to instead of stdout.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Telemetry of every attack tried.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("batch-gcd")
+            .about("Collects the RSA keys found in the directory tree and picks every one sharing a prime with another by Bernstein's batch gcd.")
+            .arg(
+                arg!(<DIR> "Path to the directory to collect the keys from, archives included.")
                     .value_parser(value_parser!(PathBuf)),
             ).arg(
-                arg!(--"strong" <ITERS> "Number of primes to iterate over. Primes are randomly generated").value_parser(value_parser!(u32)),
+                arg!(--"format" <FORMAT> "Format of the audit report: 'text' (default), 'json', 'sarif', 'csv' or 'junit'.").value_parser(["text", "json", "sarif", "csv", "junit"]),
+            ).arg(
+                arg!(--"out" <FILE> "Path to the file the audit report is written to instead of stdout.").value_parser(value_parser!(PathBuf)),
             ).arg(
-                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Important steps only. 2: Information about number of primes checked.").value_parser(value_parser!(u8)),
+                arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Number of keys collected and telemetry.").value_parser(value_parser!(u8)),
+            ),
+        ).subcommand(
+            command!("report")
+            .about("Prints the findings recorded in the audit log as audit report, for code scanning, spreadsheets or CI gates.")
+            .arg(
+                arg!(--"log" <FILE> "Path to the audit log of the run.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"format" <FORMAT> "Format of the audit report: 'text' (default), 'json', 'sarif', 'csv' or 'junit'.").value_parser(["text", "json", "sarif", "csv", "junit"]),
+            ).arg(
+                arg!(--"out" <FILE> "Path to the file the audit report is written to instead of stdout.").value_parser(value_parser!(PathBuf)),
             ),
         ).subcommand(
             command!("explain").about("Explains used algorithms."),
@@ -115,35 +399,342 @@ fn main() {
             ).arg(
                 arg!(--"report" <LEVEL> "Level of reporting. 0 (default): Only results. 1: Important steps only. 2: All foundings such as each line entropy.").value_parser(value_parser!(u8)),
             )
-        );
-    let matches = cmd.get_matches();
-    match matches.subcommand() {
+        ).subcommand(
+            command!("completions")
+            .about("Prints shell completion script to stdout.")
+            .arg(
+                arg!(<SHELL> "Shell to generate completions for: bash, zsh or fish.")
+                    .value_parser(value_parser!(Shell)),
+            )
+        ).subcommand(
+            command!("introspect")
+            .about("Prints JSON description of all subcommands and their arguments."),
+        ).subcommand(
+            command!("diff")
+            .about("Compares findings of two runs stored as audit logs. Exits with 1 if the newer run has new findings.")
+            .arg(
+                arg!(--"old" <FILE> "Path to the audit log of the older run.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"new" <FILE> "Path to the audit log of the newer run.").value_parser(value_parser!(PathBuf)),
+            ).arg(arg!(--"json" "Prints the difference as JSON."))
+        ).subcommand(
+            command!("baseline")
+            .about("Records findings of the run stored as audit log as the baseline, later runs with --baseline fail only on new findings.")
+            .arg(
+                arg!(--"log" <FILE> "Path to the audit log of the run.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"out" <FILE> "Path to the baseline file to write.").value_parser(value_parser!(PathBuf)),
+            )
+        )
+        .subcommand(
+            command!("verify")
+            .about("Verifies the signature of the report signed with --sign-key. Fails if the report was modified after it was signed.")
+            .arg(
+                arg!(--"report" <FILE> "Path to the signed report, audit log or baseline.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"signature" <FILE> "Path to the detached JWS of the report. Default is the report path followed by .jws.").value_parser(value_parser!(PathBuf)),
+            ).arg(
+                arg!(--"key" <FILE> "Path to PEM public key of the assessor.").value_parser(value_parser!(PathBuf)),
+            )
+        )
+}
+
+fn main() -> ExitCode {
+    ExitCode::from(run(&cli().get_matches()))
+}
+
+// Runs the subcommand of the parsed command line and returns the exit code of the process.
+fn run(matches: &ArgMatches) -> u8 {
+    let quiet = matches.get_flag("quiet");
+    let verbosity = match (quiet, matches.get_count("verbose")) {
+        (true, _) => Some(0),
+        (false, 0) => None,
+        (false, v) => Some(v.min(2)),
+    };
+    if let Some(policy) = matches.get_one::<PrimalityPolicy>("primality") {
+        set_policy(*policy);
+    }
+    let audit = match matches
+        .get_one::<PathBuf>("audit")
+        .map(|p| AuditLog::to_file(p))
+    {
+        None => None,
+        Some(Ok(audit)) => Some(audit),
+        Some(Err(e)) => {
+            eprintln!("🤷 Audit Log Failure: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+    let gate = match Gate::load(
+        matches.get_one::<PathBuf>("allowlist"),
+        matches.get_one::<PathBuf>("baseline"),
+    ) {
+        Ok(gate) => gate,
+        Err(e) => {
+            eprintln!("🤷 Gate Failure: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+    let cache = match cache(
+        matches.get_one::<PathBuf>("cache"),
+        matches.get_one::<u64>("cache-ttl"),
+    ) {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("🤷 Cache Failure: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+    let signer = match matches.get_one::<PathBuf>("sign-key").map(|p| {
+        read(p)
+            .map_err(BilboError::from)
+            .and_then(|pem| ReportSigner::from_pem(&pem))
+    }) {
+        None => None,
+        Some(Ok(signer)) => Some(signer),
+        Some(Err(e)) => {
+            eprintln!("🤷 Report Signing Failure: {}", e);
+            return EXIT_ERROR;
+        }
+    };
+    let mut code = match matches.subcommand() {
         Some(("picklock", matches)) => {
             match run_picklock(
                 matches.get_one::<PathBuf>("file"),
+                &matches
+                    .get_many::<PathBuf>("targets")
+                    .unwrap_or_default()
+                    .collect::<Vec<_>>(),
+                picklock_attack(matches),
+                &Control {
+                    time_limit: matches.get_one::<u64>("time-limit"),
+                    checkpoint: matches.get_one::<PathBuf>("checkpoint"),
+                },
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+            ) {
+                Ok(Some(s)) => {
+                    if !quiet {
+                        println!("🗝 Lock picked private PEM key:\n{s}\n");
+                    }
+                    EXIT_FINDINGS
+                }
+                Ok(None) => EXIT_CLEAN,
+                Err(e) => {
+                    eprintln!("🤷 LockPick Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("certscan", matches)) => {
+            match run_certscan(
+                matches.get_one::<PathBuf>("urls"),
+                matches.get_one::<String>("resolver"),
+                matches.get_one::<u32>("strong"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+                &cache,
+            ) {
+                Ok(Some(s)) => {
+                    if !quiet {
+                        println!("🚨 Weak transports and lock picked private PEM keys of the certificates:\n{s}\n");
+                    }
+                    EXIT_FINDINGS
+                }
+                Ok(None) => EXIT_CLEAN,
+                Err(e) => {
+                    eprintln!("🤷 CertScan Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("sshscan", matches)) => {
+            match run_sshscan(
+                matches.get_one::<PathBuf>("hosts"),
+                matches.get_one::<String>("resolver"),
+                matches.get_one::<u32>("strong"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+                &cache,
+            ) {
+                Ok(Some(s)) => {
+                    if !quiet {
+                        println!(
+                            "🚨 Weak algorithms and lock picked private PEM host keys:\n{s}\n"
+                        );
+                    }
+                    EXIT_FINDINGS
+                }
+                Ok(None) => EXIT_CLEAN,
+                Err(e) => {
+                    eprintln!("🤷 SshScan Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("dkimscan", matches)) => {
+            match run_dkimscan(
+                matches.get_one::<PathBuf>("domains"),
+                matches.get_one::<PathBuf>("selectors"),
+                matches.get_one::<String>("resolver"),
+                matches.get_one::<u32>("strong"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+                &cache,
+            ) {
+                Ok(Some(s)) => {
+                    if !quiet {
+                        println!(
+                            "🚨 Weak DKIM records and lock picked private PEM signing keys:\n{s}\n"
+                        );
+                    }
+                    EXIT_FINDINGS
+                }
+                Ok(None) => EXIT_CLEAN,
+                Err(e) => {
+                    eprintln!("🤷 DkimScan Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("fedscan", matches)) => {
+            match run_fedscan(
+                matches.get_one::<PathBuf>("metadata"),
+                matches.get_one::<u32>("strong"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+                &cache,
+            ) {
+                Ok(Some(s)) => {
+                    if !quiet {
+                        println!(
+                            "🚨 Short federation keys and lock picked private PEM keys of the entities:\n{s}\n"
+                        );
+                    }
+                    EXIT_FINDINGS
+                }
+                Ok(None) => EXIT_CLEAN,
+                Err(e) => {
+                    eprintln!("🤷 FedScan Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("storescan", matches)) => {
+            match run_storescan(
                 matches.get_one::<u32>("strong"),
-                matches.get_one::<u8>("report"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
             ) {
-                Ok(s) => println!("🗝 Lock picked private PEM key:\n{s}\n"),
-                Err(e) => println!("🤷 LockPick Failure: {}", e),
+                Ok(Some(s)) => {
+                    if !quiet {
+                        println!("🚨 Lock picked private PEM keys of the resident certificates and keys:\n{s}\n");
+                    }
+                    EXIT_FINDINGS
+                }
+                Ok(None) => EXIT_CLEAN,
+                Err(e) => {
+                    eprintln!("🤷 StoreScan Failure: {}", e);
+                    EXIT_ERROR
+                }
             }
         }
+        Some(("crack", matches)) => print_report(
+            run_crack(
+                matches.get_one::<PathBuf>("FILE"),
+                matches.get_one::<u64>("budget"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+            ),
+            matches,
+            quiet,
+            "Crack",
+        ),
+        Some(("scan", matches)) => print_report(
+            run_scan(
+                matches,
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+            ),
+            matches,
+            quiet,
+            "Scan",
+        ),
+        Some(("tls", matches)) => print_report(
+            run_tls(
+                matches.get_one::<String>("TARGET"),
+                matches.get_one::<String>("resolver"),
+                matches.get_one::<u64>("budget"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+                &cache,
+            ),
+            matches,
+            quiet,
+            "Tls",
+        ),
+        Some(("batch-gcd", matches)) => print_report(
+            run_batch_gcd(
+                matches.get_one::<PathBuf>("DIR"),
+                report_level(matches, verbosity.as_ref()),
+                audit.as_ref(),
+                &gate,
+            ),
+            matches,
+            quiet,
+            "BatchGcd",
+        ),
+        Some(("report", matches)) => print_report(
+            run_report(matches.get_one::<PathBuf>("log"), &gate),
+            matches,
+            quiet,
+            "Report",
+        ),
         Some(("entropy", matches)) => {
             match run_entropy(
                 matches.get_one::<PathBuf>("file"),
-                matches.get_one::<u8>("report"),
+                report_level(matches, verbosity.as_ref()),
             ) {
-                Ok(s) => println!("📶 Entropy:\n{s}\n"),
-                Err(e) => println!("🤷 Entropy Failure: {}", e),
+                Ok(s) => {
+                    if !quiet {
+                        println!("📶 Entropy:\n{s}\n");
+                    }
+                    EXIT_CLEAN
+                }
+                Err(e) => {
+                    eprintln!("🤷 Entropy Failure: {}", e);
+                    EXIT_ERROR
+                }
             }
         }
         Some(("smuggle", matches)) => match smuggle_file_via_ping(
             matches.get_one("file"),
             matches.get_one("ip"),
+            matches.get_one::<String>("host"),
+            matches.get_one::<String>("resolver"),
             matches.get_one("encrypt"),
+            audit.as_ref(),
+            &cache,
         ) {
-            Ok(s) => println!("📦 Ping Smuggler: \n{s}\n"),
-            Err(e) => println!("🤷 Failure: {}", e),
+            Ok(s) => {
+                if !quiet {
+                    println!("📦 Ping Smuggler: \n{s}\n");
+                }
+                EXIT_CLEAN
+            }
+            Err(e) => {
+                eprintln!("🤷 Failure: {}", e);
+                EXIT_ERROR
+            }
         },
         Some(("shamirs", matches)) => {
             match run_shamirs(
@@ -154,14 +745,263 @@ fn main() {
                 matches.get_one::<usize>("total"),
                 matches.get_one::<String>("encoding"),
             ) {
-                Ok(s) => println!("{s}"),
-                Err(e) => println!("🤷 Shamirs Secret Sharing Failure: {}", e),
+                Ok(s) => {
+                    if !quiet {
+                        println!("{s}");
+                    }
+                    EXIT_CLEAN
+                }
+                Err(e) => {
+                    eprintln!("🤷 Shamirs Secret Sharing Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("explain", _matches)) => {
+            println!("{EXPLAIN}");
+            EXIT_CLEAN
+        }
+        Some(("completions", matches)) => {
+            let Some(shell) = matches.get_one::<Shell>("SHELL") else {
+                eprintln!("🤷 Completions Failure: shell not specified");
+                return EXIT_ERROR;
+            };
+            generate(*shell, &mut cli(), "bilbo", &mut stdout());
+            EXIT_CLEAN
+        }
+        Some(("introspect", _matches)) => {
+            let mut cmd = cli();
+            cmd.build();
+            match serde_json::to_string_pretty(&introspect(&cmd)) {
+                Ok(s) => {
+                    println!("{s}");
+                    EXIT_CLEAN
+                }
+                Err(e) => {
+                    eprintln!("🤷 Introspect Failure: {}", e);
+                    EXIT_ERROR
+                }
+            }
+        }
+        Some(("diff", matches)) => match run_diff(
+            matches.get_one::<PathBuf>("old"),
+            matches.get_one::<PathBuf>("new"),
+            matches.get_flag("json"),
+            &gate,
+        ) {
+            Ok((s, has_new)) => {
+                if !quiet {
+                    println!("{s}");
+                }
+                if has_new {
+                    EXIT_FINDINGS
+                } else {
+                    EXIT_CLEAN
+                }
+            }
+            Err(e) => {
+                eprintln!("🤷 Diff Failure: {}", e);
+                EXIT_ERROR
+            }
+        },
+        Some(("baseline", matches)) => match run_baseline(
+            matches.get_one::<PathBuf>("log"),
+            matches.get_one::<PathBuf>("out"),
+            &gate,
+        ) {
+            Ok(s) => {
+                if !quiet {
+                    println!("📌 {s}");
+                }
+                EXIT_CLEAN
+            }
+            Err(e) => {
+                eprintln!("🤷 Baseline Failure: {}", e);
+                EXIT_ERROR
+            }
+        },
+        Some(("verify", matches)) => match run_verify(
+            matches.get_one::<PathBuf>("report"),
+            matches.get_one::<PathBuf>("signature"),
+            matches.get_one::<PathBuf>("key"),
+        ) {
+            Ok(s) => {
+                if !quiet {
+                    println!("✍️ {s}");
+                }
+                EXIT_CLEAN
+            }
+            Err(e) => {
+                eprintln!("🤷 Verify Failure: {}", e);
+                EXIT_ERROR
+            }
+        },
+        None => EXIT_CLEAN,
+        Some((name, _)) => {
+            eprintln!("🤷 Unknown subcommand {name}");
+            EXIT_ERROR
+        }
+    };
+
+    if let Some(signer) = &signer {
+        let mut reports: Vec<&PathBuf> = matches.get_one::<PathBuf>("audit").into_iter().collect();
+        if let Some((_, matches)) = matches.subcommand() {
+            reports.extend(matches.try_get_one::<PathBuf>("out").ok().flatten());
+        }
+        for report in reports.into_iter().filter(|r| r.exists()) {
+            match sign_report(signer, report) {
+                Ok(s) => {
+                    if !quiet {
+                        println!("✍️ {s}");
+                    }
+                }
+                Err(e) => {
+                    eprintln!("🤷 Report Signing Failure: {}", e);
+                    code = EXIT_ERROR;
+                }
             }
         }
-        Some(("explain", _matches)) => println!("{EXPLAIN}"),
-        None => (),
-        _ => unreachable!("unreachable code"),
+    }
+
+    code
+}
+
+#[inline(always)]
+fn run_diff(
+    old: Option<&PathBuf>,
+    new: Option<&PathBuf>,
+    json: bool,
+    gate: &Gate,
+) -> Result<(String, bool), BilboError> {
+    let (Some(old), Some(new)) = (old, new) else {
+        return Err(BilboError::GenericError(
+            "Both --old and --new audit logs are required to compare the runs.".to_string(),
+        ));
+    };
+    let old = gate
+        .allowlist
+        .filter(read_records(BufReader::new(File::open(old)?))?);
+    let new = gate
+        .allowlist
+        .filter(read_records(BufReader::new(File::open(new)?))?);
+    let diff = diff_runs(&old, &new);
+    let s = if json {
+        serde_json::to_string_pretty(&diff)?
+    } else {
+        diff.to_string()
+    };
+
+    Ok((s, diff.has_new()))
+}
+
+#[inline(always)]
+fn run_baseline(
+    log: Option<&PathBuf>,
+    out: Option<&PathBuf>,
+    gate: &Gate,
+) -> Result<String, BilboError> {
+    let (Some(log), Some(out)) = (log, out) else {
+        return Err(BilboError::GenericError(
+            "Both --log audit log and --out baseline file are required to record the baseline."
+                .to_string(),
+        ));
+    };
+    let records = gate
+        .allowlist
+        .filter(read_records(BufReader::new(File::open(log)?))?);
+    let baseline = Baseline::from_records(&records);
+    baseline.write(File::create(out)?)?;
+
+    Ok(format!(
+        "Baseline of {} findings written to {}.",
+        baseline.findings.len(),
+        out.to_string_lossy()
+    ))
+}
+
+// Signs the report as detached JWS written next to it, the report path followed by .jws.
+#[inline(always)]
+fn sign_report(signer: &ReportSigner, report: &PathBuf) -> Result<String, BilboError> {
+    let name = report
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let jws = signer.sign(&name, &read(report)?)?;
+    let mut out = report.clone().into_os_string();
+    out.push(".jws");
+    write(&out, jws)?;
+
+    Ok(format!(
+        "Report {} signed with {} to {}.",
+        report.to_string_lossy(),
+        signer.algorithm(),
+        out.to_string_lossy()
+    ))
+}
+
+#[inline(always)]
+fn run_verify(
+    report: Option<&PathBuf>,
+    signature: Option<&PathBuf>,
+    key: Option<&PathBuf>,
+) -> Result<String, BilboError> {
+    let (Some(report), Some(key)) = (report, key) else {
+        return Err(BilboError::GenericError(
+            "Both --report and the assessor public --key are required to verify the report."
+                .to_string(),
+        ));
+    };
+    let signature = match signature {
+        Some(signature) => signature.clone(),
+        None => {
+            let mut signature = report.clone().into_os_string();
+            signature.push(".jws");
+            PathBuf::from(signature)
+        }
     };
+    let key = PKey::public_key_from_pem(&read(key)?)?;
+    let claims = verify_report(&read(report)?, &read_to_string(signature)?, &key)?;
+
+    Ok(format!("Signature verifies, {claims}."))
+}
+
+/// Describes the command, its arguments and subcommands as JSON, so wrappers can discover capabilities.
+///
+#[inline(always)]
+fn introspect(cmd: &Command) -> Value {
+    let args = cmd
+        .get_arguments()
+        .filter(|a| !a.is_hide_set())
+        .map(|a| {
+            json!({
+                "id": a.get_id().as_str(),
+                "long": a.get_long(),
+                "short": a.get_short(),
+                "value_names": a.get_value_names().map(|v| v.iter().map(|n| n.as_str()).collect::<Vec<_>>()),
+                "takes_value": a.get_num_args().is_some_and(|n| n.takes_values()),
+                "required": a.is_required_set(),
+                "global": a.is_global_set(),
+                "help": a.get_help().map(|h| h.to_string()),
+            })
+        })
+        .collect::<Vec<_>>();
+    let subcommands = cmd.get_subcommands().map(introspect).collect::<Vec<_>>();
+
+    json!({
+        "schema_version": SCHEMA_VERSION,
+        "name": cmd.get_name(),
+        "version": cmd.get_version(),
+        "about": cmd.get_about().map(|a| a.to_string()),
+        "args": args,
+        "subcommands": subcommands,
+    })
+}
+
+/// Subcommand --report level takes precedence over global verbosity flags.
+///
+#[inline(always)]
+fn report_level<'a>(matches: &'a ArgMatches, verbosity: Option<&'a u8>) -> Option<&'a u8> {
+    matches.get_one::<u8>("report").or(verbosity)
 }
 
 #[inline(always)]
@@ -239,43 +1079,1445 @@ fn run_shamirs(
     Ok(result)
 }
 
-#[inline(always)]
-fn run_picklock(
-    path: Option<&PathBuf>,
-    strong_iters: Option<&u32>,
-    report_level: Option<&u8>,
-) -> Result<String, BilboError> {
-    let report_level = check_level(report_level)?;
-    let Some(path) = path else {
-        return Err(BilboError::GenericError(
-            "I received an empty file path... I don't know what to picklock, please be specific..."
-                .to_string(),
-        ));
-    };
+// Limits of the picklock attack spanning runs, its time limit and the checkpoint it resumes and records.
+struct Control<'a> {
+    time_limit: Option<&'a u64>,
+    checkpoint: Option<&'a PathBuf>,
+}
 
-    let rsa_pem = read_to_string(path)?;
-    let mut pl = PickLock::from_pem(&rsa_pem)?;
+/// Lock pick attack selected by the picklock subcommand flags.
+///
+enum Attack<'a> {
+    Weak,
+    WeakParallel(usize),
+    Strong(u32),
+    Ratios(u64),
+    Auxiliary(&'a PathBuf),
+    PMinus1(u64),
+    PPlus1,
+    SmallD,
+    Ecm(usize, u64),
+    Rho,
+    Partial(&'a PathBuf),
+    Multipliers(Vec<u64>),
+    Lehman,
+    QuadraticSieve,
+    All(Option<u64>),
+    BatchGcd,
+    Race(u32, Option<&'a PathBuf>),
+}
 
-    let d = match strong_iters {
-        None => {
-            if report_level >= 1 {
-                println!("🔐 Starting lock picking the weak RSA private key.\n");
-            }
-            pl.try_lock_pick_weak_private()?
-        }
-        Some(iter) => {
-            if report_level >= 1 {
-                println!("🔐 Starting lock picking the strong RSA private key.\n");
-            }
-            if *iter != 0 {
-                pl.alter_max_iter(*iter as usize)?;
-            }
-            pl.try_lock_pick_strong_private(report_level == 2)?
+impl Attack<'_> {
+    #[inline(always)]
+    fn name(&self) -> &'static str {
+        match self {
+            Attack::Weak => "weak",
+            Attack::WeakParallel(_) => "weak_parallel",
+            Attack::Strong(_) => "strong",
+            Attack::Ratios(_) => "weighted_fermat",
+            Attack::Auxiliary(_) => "auxiliary_gcd",
+            Attack::PMinus1(_) => "pollard_p_minus_1",
+            Attack::PPlus1 => "williams_p_plus_1",
+            Attack::SmallD => "wiener",
+            Attack::Ecm(..) => "ecm",
+            Attack::Rho => "pollards_rho",
+            Attack::Partial(_) => "coppersmith_partial_p",
+            Attack::Multipliers(_) => "fermat_multipliers",
+            Attack::Lehman => "lehman",
+            Attack::QuadraticSieve => "quadratic_sieve",
+            Attack::All(_) => "try_all",
+            Attack::BatchGcd => BATCH_GCD_ATTACK,
+            Attack::Race(..) => "race",
         }
+    }
+}
+
+#[inline(always)]
+fn picklock_attack(matches: &ArgMatches) -> Attack<'_> {
+    if matches.get_flag("race") {
+        return Attack::Race(
+            matches
+                .get_one::<u32>("strong")
+                .copied()
+                .unwrap_or_default(),
+            matches.get_one::<PathBuf>("quotas"),
+        );
+    }
+    if matches.get_flag("batch-gcd") {
+        return Attack::BatchGcd;
+    }
+    if let Some(iter) = matches.get_one::<u32>("strong") {
+        return Attack::Strong(*iter);
+    }
+    if let Some(max) = matches.get_one::<u64>("ratios") {
+        return Attack::Ratios(*max);
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("aux") {
+        return Attack::Auxiliary(path);
+    }
+    if let Some(bound) = matches.get_one::<u64>("pm1") {
+        return Attack::PMinus1(*bound);
+    }
+    if matches.get_flag("pp1") {
+        return Attack::PPlus1;
+    }
+    if matches.get_flag("small-d") {
+        return Attack::SmallD;
+    }
+    if let Some(curves) = matches.get_one::<usize>("ecm") {
+        return Attack::Ecm(
+            *curves,
+            matches.get_one::<u64>("b1").copied().unwrap_or(ECM_B1),
+        );
+    }
+    if matches.get_flag("rho") {
+        return Attack::Rho;
+    }
+    if let Some(path) = matches.get_one::<PathBuf>("partial") {
+        return Attack::Partial(path);
+    }
+    if let Some(ks) = matches.get_many::<u64>("multipliers") {
+        return Attack::Multipliers(ks.copied().collect());
+    }
+    if matches.get_flag("lehman") {
+        return Attack::Lehman;
+    }
+    if matches.get_flag("qs") {
+        return Attack::QuadraticSieve;
+    }
+    if matches.get_flag("all") {
+        return Attack::All(matches.get_one::<u64>("budget").copied());
+    }
+    if let Some(iter) = matches.get_one::<usize>("parallel") {
+        return Attack::WeakParallel(*iter);
+    }
+
+    Attack::Weak
+}
+
+#[inline(always)]
+fn run_picklock(
+    path: Option<&PathBuf>,
+    targets: &[&PathBuf],
+    attack: Attack,
+    control: &Control,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(path) = path else {
+        return Err(BilboError::GenericError(
+            "I received an empty file path... I don't know what to picklock, please be specific..."
+                .to_string(),
+        ));
+    };
+
+    let mut pl = load_lock(path)?;
+    if let Some(seconds) = control.time_limit {
+        pl.alter_time_limit(Duration::from_secs(*seconds));
+    }
+    if let Some(checkpoint) = control.checkpoint.filter(|path| path.exists()) {
+        pl.resume_from(AttackCheckpoint::read(BufReader::new(File::open(
+            checkpoint,
+        )?))?)?;
+    }
+    if let Some(audit) = audit {
+        audit.key_loaded(&path.to_string_lossy(), &pl)?;
+    }
+    if let Some(roca) = flag_roca(&pl, report_level, audit, gate)? {
+        println!("{roca}\n");
+    }
+
+    if let (Attack::Strong(iter), false) = (&attack, targets.is_empty()) {
+        return run_shared_picklock(pl, targets, *iter, report_level, audit, gate);
+    }
+    if let Attack::BatchGcd = attack {
+        return run_batch_picklock(pl, targets, report_level, audit, gate);
+    }
+
+    if let Some(audit) = audit {
+        audit.attack_started(attack.name(), &pl)?;
+    }
+    if report_level >= 1 {
+        println!(
+            "🖥️ Big integer kernel {}, CPU features: {}.\n",
+            Kernel::detect(),
+            CpuFeatures::detect()
+        );
+    }
+    let start = Instant::now();
+    let mut found_by = attack.name().to_string();
+    let d = match attack {
+        Attack::Weak => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the weak RSA private key.\n");
+            }
+            pl.try_lock_pick_weak_private()
+        }
+        Attack::WeakParallel(iter) => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the weak RSA private key with {iter} Fermat candidates on {} cores.\n",
+                    Pool::global().workers()
+                );
+            }
+            pl.alter_iteration_limit(IterationLimit::bounded(iter as u64)?);
+            pl.try_lock_pick_weak_private_parallel()
+        }
+        Attack::Ratios(max) => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the RSA private key with p/q ratios up to {max}.\n"
+                );
+            }
+            pl.try_lock_pick_weighted_fermat(&fermat_ratios(max))
+        }
+        Attack::Auxiliary(aux) => {
+            let values = read_auxiliary(BufReader::new(File::open(aux)?))?;
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the RSA private key with {} auxiliary values.\n",
+                    values.len()
+                );
+            }
+            pl.try_lock_pick_auxiliary_gcd(&values)
+        }
+        Attack::PMinus1(bound) => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the RSA private key with Pollard's p - 1 bound {bound}.\n"
+                );
+            }
+            pl.try_lock_pick_pollard_p_minus_1(bound)
+        }
+        Attack::PPlus1 => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the RSA private key with Williams' p + 1.\n");
+            }
+            pl.try_lock_pick_williams_p_plus_1()
+        }
+        Attack::SmallD => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the small RSA private exponent with Wiener's attack.\n");
+            }
+            pl.try_lock_pick_small_d()
+        }
+        Attack::Ecm(curves, b1) => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the RSA private key with {curves} ECM curves of bound {b1}.\n");
+            }
+            pl.try_lock_pick_ecm(curves, b1)
+        }
+        Attack::Rho => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the RSA private key with Pollard's rho.\n");
+            }
+            pl.try_lock_pick_pollards_rho()
+        }
+        Attack::Partial(path) => {
+            let key = PartialKey::parse(&read_to_string(path)?)?;
+            let half = pl.bits().div_ceil(2);
+            let Some((known, run)) = [Secret::P, Secret::Q]
+                .iter()
+                .find_map(|secret| key.get(*secret).and_then(|bits| bits.high_bits(half)))
+            else {
+                return Err(BilboError::GenericError(format!(
+                    "{} has no high bits of p or q, of {half} bits",
+                    path.to_string_lossy()
+                )));
+            };
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the RSA private key from {run} known high bits of a prime by Coppersmith's method.\n");
+            }
+            pl.try_lock_pick_partial_p(&known.to_bytes_be().1)
+        }
+        Attack::Multipliers(ref ks) => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the RSA private key with Fermat on {} multipliers.\n",
+                    ks.len()
+                );
+            }
+            pl.try_lock_pick_weak_private_with_multipliers(ks)
+        }
+        Attack::Lehman => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the tiny RSA private key with Lehman's method.\n"
+                );
+            }
+            pl.try_lock_pick_lehman()
+        }
+        Attack::QuadraticSieve => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting lock picking the RSA private key with the quadratic sieve.\n"
+                );
+            }
+            pl.try_lock_pick_quadratic_sieve(&mut *progress_sink(report_level))
+        }
+        Attack::All(budget) => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the RSA private key with every attack, cheapest first.\n");
+            }
+            let mut strategy = AttackStrategy::new();
+            if let Some(seconds) = budget {
+                strategy = strategy.with_default_budget(Duration::from_secs(seconds));
+            }
+            let outcome = pl.try_all(strategy);
+            if report_level >= 1 {
+                for (technique, telemetry) in outcome.telemetry.iter() {
+                    println!("🧰 {technique}: {telemetry}");
+                }
+                println!("\n{}\n", outcome.audit_report(&pl));
+            }
+            if let Some(technique) = outcome.technique {
+                found_by = technique.to_string();
+            }
+            outcome.result
+        }
+        Attack::BatchGcd => unreachable!("batch gcd attacks the set of keys"),
+        Attack::Strong(iter) => {
+            if report_level >= 1 {
+                println!("🔐 Starting lock picking the strong RSA private key.\n");
+            }
+            if iter != 0 {
+                pl.alter_iteration_limit(IterationLimit::bounded(iter as u64)?);
+            }
+            pl.try_lock_pick_strong_private(&mut *progress_sink(report_level))
+        }
+        Attack::Race(iter, quotas) => {
+            if report_level >= 1 {
+                println!(
+                    "🔐 Starting race of the weak, weighted Fermat and strong lock picking.\n"
+                );
+            }
+            if iter != 0 {
+                pl.alter_iteration_limit(IterationLimit::bounded(iter as u64)?);
+            }
+            let quotas = match quotas {
+                Some(path) => Quotas::read(BufReader::new(File::open(path)?))?,
+                None => Quotas::default(),
+            };
+            let ratios = fermat_ratios(RACE_RATIOS);
+            let contenders = [
+                Contender::new("weak", |pl| pl.try_lock_pick_weak_private()),
+                Contender::new("weighted_fermat", |pl| {
+                    pl.try_lock_pick_weighted_fermat(&ratios)
+                }),
+                Contender::new("strong", |pl| {
+                    pl.try_lock_pick_strong_private(&mut SilentSink)
+                }),
+            ]
+            .map(|c| {
+                let quota = quotas.quota(c.name);
+                c.with_quota(quota)
+            });
+            let outcome = race(&pl, &contenders);
+            if report_level >= 1 {
+                for (name, telemetry) in outcome.telemetry.iter() {
+                    println!("🏁 {name}: {telemetry}");
+                }
+                println!();
+            }
+            if let Some(winner) = outcome.winner {
+                found_by = winner;
+            }
+            outcome.result
+        }
+    };
+    if report_level >= 1 {
+        println!("⏱️ Telemetry: {}\n", pl.last_telemetry());
+    }
+    if let Some(path) = control.checkpoint {
+        match pl.checkpoint() {
+            Some(checkpoint) => checkpoint.write(File::create(path)?)?,
+            None if d.is_ok() && path.exists() => remove_file(path)?,
+            None => (),
+        }
+    }
+    let reported = match &d {
+        Ok(d) => report_finding(&found_by, &pl, d, report_level, audit, gate)?,
+        Err(_) => false,
+    };
+    if let Some(audit) = audit {
+        audit.attack_finished(attack.name(), &pl, d.is_ok(), start.elapsed())?;
+    }
+    let d = match d {
+        Ok(d) => d,
+        Err(e) => {
+            if report_level >= 1 {
+                println!("🔒 The lock holds: {e}\n");
+            }
+            return Ok(None);
+        }
+    };
+    if !reported {
+        return Ok(None);
+    }
+    let pem_priv = pl.to_cracked_key(&d)?.to_pkcs1_pem();
+
+    Ok(Some(pem_priv))
+}
+
+#[inline(always)]
+fn run_shared_picklock(
+    pl: PickLock,
+    targets: &[&PathBuf],
+    iter: u32,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    let mut multi = MultiPickLock::new(load_targets(pl, targets, audit)?);
+    if iter != 0 {
+        multi.alter_iteration_limit(IterationLimit::bounded(iter as u64)?);
+    }
+    if report_level >= 1 {
+        println!(
+            "🔐 Starting lock picking {} strong RSA private keys together.\n",
+            multi.locks().len()
+        );
+    }
+    if let Some(audit) = audit {
+        for lock in multi.locks() {
+            audit.attack_started("strong_shared", lock)?;
+        }
+    }
+    let start = Instant::now();
+    let results = multi.try_lock_pick_strong_private(&mut *progress_sink(report_level));
+
+    report_shared(
+        "strong_shared",
+        multi.locks(),
+        results,
+        start,
+        report_level,
+        audit,
+        gate,
+    )
+}
+
+#[inline(always)]
+fn run_batch_picklock(
+    pl: PickLock,
+    targets: &[&PathBuf],
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    let batch = BatchPickLock::new(load_targets(pl, targets, audit)?);
+    if report_level >= 1 {
+        println!(
+            "🔐 Starting lock picking {} RSA private keys by batch gcd of their moduli.\n",
+            batch.locks().len()
+        );
+    }
+    if let Some(audit) = audit {
+        for lock in batch.locks() {
+            audit.attack_started(BATCH_GCD_ATTACK, lock)?;
+        }
+    }
+    let start = Instant::now();
+    let results = batch.try_lock_pick_shared_primes();
+
+    report_shared(
+        BATCH_GCD_ATTACK,
+        batch.locks(),
+        results,
+        start,
+        report_level,
+        audit,
+        gate,
+    )
+}
+
+// Loads the keys of the targets after the key of --file, auditing every one.
+// Loads the public key of PEM file, the first ssh-rsa key of OpenSSH public key file, PuTTY key file,
+// the subject key of certificate signing request or the subject key of X.509 certificate, PEM or DER.
+#[inline(always)]
+fn load_lock(path: &PathBuf) -> Result<PickLock, BilboError> {
+    let data = read(path)?;
+    match std::str::from_utf8(&data) {
+        Ok(pem) if pem.contains("-----BEGIN CERTIFICATE-----") => PickLock::from_x509_pem(pem),
+        Ok(pem) if pem.contains("-----BEGIN CERTIFICATE REQUEST-----") => {
+            PickLock::from_csr_pem(pem)
+        }
+        Ok(pem) if pem.contains("-----BEGIN") => PickLock::from_pem(pem),
+        Ok(ppk) if ppk.starts_with("PuTTY-User-Key-File-") => PickLock::from_ppk(ppk),
+        Ok(line) if line.contains("ssh-rsa ") => PickLock::from_openssh_public(line),
+        _ => PickLock::from_x509_der(&data),
+    }
+}
+
+#[inline(always)]
+fn load_targets(
+    pl: PickLock,
+    targets: &[&PathBuf],
+    audit: Option<&AuditLog>,
+) -> Result<Vec<PickLock>, BilboError> {
+    let mut locks = vec![pl];
+    for path in targets.iter() {
+        let lock = load_lock(path)?;
+        if let Some(audit) = audit {
+            audit.key_loaded(&path.to_string_lossy(), &lock)?;
+        }
+        locks.push(lock);
+    }
+
+    Ok(locks)
+}
+
+// Reports the results of an attack on a set of keys, returns the private PEM keys of the picked ones.
+#[inline(always)]
+fn report_shared(
+    attack: &str,
+    locks: &[PickLock],
+    results: Vec<Result<BigInt, BilboError>>,
+    start: Instant,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    if report_level >= 1 {
+        println!("⏱️ Telemetry: {}\n", locks[0].last_telemetry());
+    }
+
+    let mut pems = Vec::new();
+    for (lock, d) in locks.iter().zip(results) {
+        let reported = match &d {
+            Ok(d) => report_finding(attack, lock, d, report_level, audit, gate)?,
+            Err(_) => false,
+        };
+        if let Some(audit) = audit {
+            audit.attack_finished(attack, lock, d.is_ok(), start.elapsed())?;
+        }
+        match d {
+            Ok(d) if reported => pems.push(lock.to_cracked_key(&d)?.to_pkcs1_pem()),
+            Ok(_) => (),
+            Err(e) => {
+                if report_level >= 1 {
+                    println!("🔒 The lock {} holds: {e}\n", lock.modulus_fingerprint());
+                }
+            }
+        }
+    }
+
+    Ok((!pems.is_empty()).then(|| pems.join("\n")))
+}
+
+#[inline(always)]
+fn run_certscan(
+    path: Option<&PathBuf>,
+    resolver: Option<&String>,
+    strong: Option<&u32>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+    cache: &Cache,
+) -> Result<Option<String>, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(path) = path else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect urls file path".to_string(),
+        ));
+    };
+    let urls = read_urls(BufReader::new(File::open(path)?))?;
+    let dialer = Dialer::new(resolver_of(resolver)?);
+    let inventory = grab_certificates(&urls, &dialer, cache);
+    if report_level >= 1 {
+        println!(
+            "🌐 {} unique certificates served by {} URLs.\n",
+            inventory.certificates().len(),
+            urls.len()
+        );
+        for (url, reason) in inventory.failures() {
+            println!("🚫 {url}: {reason}\n");
+        }
+        for transport in inventory.transports() {
+            println!("🔌 {transport}\n");
+        }
+    }
+
+    if let Some(audit) = audit {
+        let mut resolved = BTreeMap::new();
+        for endpoint in inventory
+            .certificates()
+            .iter()
+            .flat_map(|c| c.endpoints.iter())
+        {
+            resolved.insert(&endpoint.host, &endpoint.addrs);
+        }
+        for (host, addrs) in resolved {
+            audit.host_resolved(host, dialer.resolver(), addrs)?;
+        }
+        for transport in inventory.transports() {
+            audit.host_probed(&transport.host, transport.port, &transport.dual_stack)?;
+        }
+    }
+
+    let mut weak_transports = Vec::new();
+    let mut pems = Vec::new();
+    for cert in inventory.certificates() {
+        if report_level >= 1 {
+            let endpoints: Vec<String> = cert.endpoints.iter().map(|e| e.to_string()).collect();
+            println!(
+                "📜 {} {} served by:\n  {}\n",
+                cert.fingerprint,
+                cert.subject,
+                endpoints.join("\n  ")
+            );
+        }
+        let pl = cert.pick_lock()?;
+        if let (Some(audit), Some(pl)) = (audit, &pl) {
+            audit.key_loaded(&cert.endpoints[0].to_string(), pl)?;
+        }
+        for endpoint in cert.endpoints.iter() {
+            let Some(transport) = inventory.transport(&endpoint.host, endpoint.port) else {
+                continue;
+            };
+            for weakness in transport.offered.iter() {
+                let detail = format!("{}:{} offers {weakness}", transport.host, transport.port);
+                // Transport of endpoints serving other than RSA keys is reported without the gate.
+                let reported = match &pl {
+                    Some(pl) => gate_finding(
+                        TRANSPORT_ATTACK,
+                        pl,
+                        &detail,
+                        None,
+                        report_level,
+                        audit,
+                        gate,
+                    )?,
+                    None => true,
+                };
+                let detail = with_families(detail, &transport.dual_stack);
+                if reported && !weak_transports.contains(&detail) {
+                    weak_transports.push(detail);
+                }
+            }
+        }
+        let Some(pl) = pl else {
+            if report_level >= 1 {
+                println!("🔏 The key of {} is not RSA, skipped.\n", cert.fingerprint);
+            }
+            continue;
+        };
+        if let Some(roca) = flag_roca(&pl, report_level, audit, gate)? {
+            pems.push(roca);
+        }
+        let Some(pem) = pick_scanned_key(pl, strong, report_level, audit, gate)? else {
+            continue;
+        };
+        // Cracked key of a revoked certificate is no longer trusted by the clients checking revocation.
+        let revocation = cert.revocation(cache);
+        if let (Some(audit), Some(pl)) = (audit, cert.pick_lock()?) {
+            audit.revocation_checked(&pl, &cert.fingerprint, &revocation)?;
+        }
+        pems.push(format!("🪪 {} {revocation}\n{pem}", cert.fingerprint));
+    }
+
+    let findings: Vec<String> = weak_transports
+        .into_iter()
+        .map(|t| format!("⚠️ {t}"))
+        .chain(pems)
+        .collect();
+
+    Ok((!findings.is_empty()).then(|| findings.join("\n")))
+}
+
+// Flags the key found by a scanner carrying the ROCA fingerprint, no lock pick breaks it in reasonable time.
+// Returns the reported finding.
+#[inline(always)]
+fn flag_roca(
+    pl: &PickLock,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    if !pl.is_roca_vulnerable() {
+        return Ok(None);
+    }
+    let detail = format!(
+        "key {} carries the ROCA fingerprint, CVE-2017-15361, of Infineon generated keys",
+        pl.modulus_fingerprint()
+    );
+    if !gate_finding(ROCA_ATTACK, pl, &detail, None, report_level, audit, gate)? {
+        return Ok(None);
+    }
+
+    Ok(Some(format!("⚠️ {detail}")))
+}
+
+// Picks the lock of the key found by a scanner, with the strong pipeline when it has a budget, the weak lock pick otherwise.
+// Returns private PEM key of the reported finding.
+#[inline(always)]
+fn pick_scanned_key(
+    mut pl: PickLock,
+    strong: Option<&u32>,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    let attack = if strong.is_some() { "strong" } else { "weak" };
+    if let Some(audit) = audit {
+        audit.attack_started(attack, &pl)?;
+    }
+    let start = Instant::now();
+    let d = match strong {
+        Some(iter) => {
+            if *iter != 0 {
+                pl.alter_iteration_limit(IterationLimit::bounded(*iter as u64)?);
+            }
+            pl.try_lock_pick_strong_private(&mut SilentSink)
+        }
+        None => pl.try_lock_pick_weak_private(),
+    };
+    let reported = match &d {
+        Ok(d) => report_finding(attack, &pl, d, report_level, audit, gate)?,
+        Err(_) => false,
+    };
+    if let Some(audit) = audit {
+        audit.attack_finished(attack, &pl, d.is_ok(), start.elapsed())?;
+    }
+
+    match d {
+        Ok(d) if reported => Ok(Some(pl.to_cracked_key(&d)?.to_pkcs1_pem())),
+        Ok(_) => Ok(None),
+        Err(e) => {
+            if report_level >= 1 {
+                println!("🔒 The lock {} holds: {e}\n", pl.modulus_fingerprint());
+            }
+            Ok(None)
+        }
+    }
+}
+
+#[inline(always)]
+fn run_sshscan(
+    path: Option<&PathBuf>,
+    resolver: Option<&String>,
+    strong: Option<&u32>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+    cache: &Cache,
+) -> Result<Option<String>, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(path) = path else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect hosts file path".to_string(),
+        ));
+    };
+    let hosts = read_hosts(BufReader::new(File::open(path)?))?;
+    let dialer = Dialer::new(resolver_of(resolver)?);
+    let mut findings = Vec::new();
+    for (host, port) in hosts.iter() {
+        let scanned = match ssh::scan(host, *port, &dialer, cache) {
+            Ok(scanned) => scanned,
+            Err(e) => {
+                if report_level >= 1 {
+                    println!("🚫 {host}:{port}: {e}\n");
+                }
+                continue;
+            }
+        };
+        if let Some(audit) = audit {
+            audit.host_resolved(host, dialer.resolver(), &scanned.addrs)?;
+            audit.host_probed(host, *port, &scanned.dual_stack)?;
+        }
+        if report_level >= 1 {
+            let addrs: Vec<String> = scanned.addrs.iter().map(|a| a.to_string()).collect();
+            println!(
+                "🔑 {host}:{port} at {} ({}) {}, host key {}\n  {}\n",
+                addrs.join(", "),
+                scanned.dual_stack,
+                scanned.banner,
+                scanned
+                    .host_key_type()
+                    .unwrap_or("not exchanged".to_string()),
+                scanned.algorithms
+            );
+        }
+        let pl = scanned.pick_lock()?;
+        if let (Some(audit), Some(pl)) = (audit, &pl) {
+            audit.key_loaded(&format!("ssh://{host}:{port}"), pl)?;
+        }
+        for weakness in scanned.algorithms.weaknesses() {
+            let detail = format!("{host}:{port} offers {weakness}");
+            // Algorithms of servers with other than RSA host keys are reported without the gate.
+            let reported = match &pl {
+                Some(pl) => gate_finding(
+                    SSH_ALGORITHMS_ATTACK,
+                    pl,
+                    &detail,
+                    None,
+                    report_level,
+                    audit,
+                    gate,
+                )?,
+                None => true,
+            };
+            if reported {
+                findings.push(format!("⚠️ {}", with_families(detail, &scanned.dual_stack)));
+            }
+        }
+        let Some(pl) = pl else {
+            continue;
+        };
+        if let Some(roca) = flag_roca(&pl, report_level, audit, gate)? {
+            findings.push(roca);
+        }
+        if let Some(pem) = pick_scanned_key(pl, strong, report_level, audit, gate)? {
+            findings.push(pem);
+        }
+    }
+
+    Ok((!findings.is_empty()).then(|| findings.join("\n")))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[inline(always)]
+fn run_dkimscan(
+    domains: Option<&PathBuf>,
+    selectors: Option<&PathBuf>,
+    resolver: Option<&String>,
+    strong: Option<&u32>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+    cache: &Cache,
+) -> Result<Option<String>, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(domains) = domains else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect domains file path".to_string(),
+        ));
+    };
+    let domains = read_domains(BufReader::new(File::open(domains)?))?;
+    let selectors: Vec<String> = match selectors {
+        Some(path) => read_to_string(path)?
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .collect(),
+        None => COMMON_SELECTORS.iter().map(|s| s.to_string()).collect(),
+    };
+    let resolver = resolver_of(resolver)?;
+    let mut findings = Vec::new();
+    for (domain, own) in domains.iter() {
+        let domain_selectors = if own.is_empty() { &selectors } else { own };
+        for selector in domain_selectors.iter() {
+            let key = match dkim::scan(domain, selector, &resolver, cache) {
+                Ok(Some(key)) => key,
+                Ok(None) => continue,
+                Err(e) => {
+                    if report_level >= 1 {
+                        println!("🚫 {selector}._domainkey.{domain}: {e}\n");
+                    }
+                    continue;
+                }
+            };
+            if report_level >= 1 {
+                println!("✉️ {key}\n");
+            }
+            let pl = key.pick_lock()?;
+            if let (Some(audit), Some(pl)) = (audit, &pl) {
+                audit.key_loaded(&format!("dkim://{}", key.name()), pl)?;
+            }
+            for weakness in key.weaknesses() {
+                let detail = format!("{} publishes {weakness}", key.name());
+                // Records of revoked and other than RSA keys are reported without the gate.
+                let reported = match &pl {
+                    Some(pl) => gate_finding(
+                        DKIM_KEY_ATTACK,
+                        pl,
+                        &detail,
+                        None,
+                        report_level,
+                        audit,
+                        gate,
+                    )?,
+                    None => true,
+                };
+                if reported {
+                    findings.push(format!("⚠️ {detail}"));
+                }
+            }
+            let Some(pl) = pl else {
+                continue;
+            };
+            if let Some(roca) = flag_roca(&pl, report_level, audit, gate)? {
+                findings.push(roca);
+            }
+            if let Some(pem) = pick_scanned_key(pl, strong, report_level, audit, gate)? {
+                findings.push(pem);
+            }
+        }
+    }
+
+    Ok((!findings.is_empty()).then(|| findings.join("\n")))
+}
+
+#[inline(always)]
+fn run_fedscan(
+    metadata: Option<&PathBuf>,
+    strong: Option<&u32>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+    cache: &Cache,
+) -> Result<Option<String>, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(metadata) = metadata else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect metadata file path".to_string(),
+        ));
     };
-    let pem_priv = to_pem(d, KeyType::Private)?;
+    let mut inventory = FederationInventory::default();
+    for url in read_metadata_urls(BufReader::new(File::open(metadata)?))?.iter() {
+        if let Err(e) = federation::scan_metadata(url, &mut inventory, cache) {
+            inventory.fail(url, &e);
+        }
+    }
+    if report_level >= 1 {
+        for (url, reason) in inventory.failures() {
+            println!("🚫 {url}: {reason}\n");
+        }
+        println!(
+            "🏛 {} unique keys published by the entities.\n",
+            inventory.keys().len()
+        );
+    }
+    let mut findings = Vec::new();
+    for key in inventory.keys() {
+        if report_level >= 1 {
+            for holder in key.holders.iter() {
+                println!("🔑 {} {holder}\n", key.fingerprint);
+            }
+        }
+        let Some(pl) = key.pick_lock()? else {
+            if report_level >= 1 {
+                println!("🔏 The key {} is not RSA, skipped.\n", key.fingerprint);
+            }
+            continue;
+        };
+        if let Some(audit) = audit {
+            audit.key_loaded(&format!("federation://{}", key.holders[0].entity), &pl)?;
+        }
+        if key.is_short() {
+            for holder in key.holders.iter() {
+                let detail = format!(
+                    "{holder} is {} bit RSA shorter than {MIN_FEDERATION_BITS} bits",
+                    key.public_key.bits()
+                );
+                if gate_finding(
+                    FEDERATION_KEY_ATTACK,
+                    &pl,
+                    &detail,
+                    None,
+                    report_level,
+                    audit,
+                    gate,
+                )? {
+                    findings.push(format!("⚠️ {detail}"));
+                }
+            }
+        }
+        if let Some(roca) = flag_roca(&pl, report_level, audit, gate)? {
+            findings.push(roca);
+        }
+        if let Some(pem) = pick_scanned_key(pl, strong, report_level, audit, gate)? {
+            for holder in key.holders.iter() {
+                findings.push(format!("🏛 {holder}"));
+            }
+            findings.push(pem);
+        }
+    }
 
-    Ok(pem_priv)
+    Ok((!findings.is_empty()).then(|| findings.join("\n")))
+}
+
+#[inline(always)]
+fn run_storescan(
+    strong: Option<&u32>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<Option<String>, BilboError> {
+    let report_level = check_level(report_level)?;
+    let inventory = scan_stores()?;
+    if report_level >= 1 {
+        println!(
+            "🗄 {} unique certificates and keys resident in the stores.\n",
+            inventory.residents().len()
+        );
+    }
+    let mut pems = Vec::new();
+    for resident in inventory.residents() {
+        if report_level >= 1 {
+            println!("📜 {resident}\n");
+        }
+        let Some(pl) = resident.pick_lock()? else {
+            if report_level >= 1 {
+                println!(
+                    "🔏 The key of {} is not RSA, skipped.\n",
+                    resident.fingerprint
+                );
+            }
+            continue;
+        };
+        if let Some(audit) = audit {
+            audit.key_loaded(&format!("store://{}", resident.stores[0]), &pl)?;
+        }
+        if let Some(roca) = flag_roca(&pl, report_level, audit, gate)? {
+            pems.push(roca);
+        }
+        if let Some(pem) = pick_scanned_key(pl, strong, report_level, audit, gate)? {
+            pems.push(pem);
+        }
+    }
+
+    Ok((!pems.is_empty()).then(|| pems.join("\n")))
+}
+
+// Prints the audit report of the subcommand in its --format, or writes it to its --out file.
+// Returns exit code 1 for medium or more severe findings.
+#[inline(always)]
+fn print_report(
+    report: Result<AuditReport, BilboError>,
+    matches: &ArgMatches,
+    quiet: bool,
+    name: &str,
+) -> u8 {
+    let written = report.and_then(|report| {
+        let rendered = match matches.get_one::<String>("format").map(|f| f.as_str()) {
+            Some("json") => report.to_json()?,
+            Some("sarif") => report.to_sarif()?,
+            Some("csv") => report.to_csv(),
+            Some("junit") => report.to_junit(),
+            _ => format!("🚨 Audit report:\n{report}\n"),
+        };
+        match matches.get_one::<PathBuf>("out") {
+            Some(path) => {
+                write(path, rendered)?;
+                if !quiet {
+                    println!("📝 Audit report written to {}", path.display());
+                }
+            }
+            None if !quiet => println!("{rendered}"),
+            None => (),
+        }
+        Ok(report)
+    });
+    match written {
+        Ok(report) if report.at_least(GATE_SEVERITY).is_empty() => EXIT_CLEAN,
+        Ok(_) => EXIT_FINDINGS,
+        Err(e) => {
+            eprintln!("🤷 {name} Failure: {e}");
+            EXIT_ERROR
+        }
+    }
+}
+
+#[inline(always)]
+fn run_crack(
+    path: Option<&PathBuf>,
+    budget: Option<&u64>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<AuditReport, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(path) = path else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect key file path".to_string(),
+        ));
+    };
+    let pl = load_lock(path)?;
+    let origin = path.to_string_lossy();
+    if let Some(audit) = audit {
+        audit.key_loaded(&origin, &pl)?;
+    }
+
+    crack_report(&pl, &origin, budget, report_level, audit, gate)
+}
+
+#[inline(always)]
+fn run_tls(
+    target: Option<&String>,
+    resolver: Option<&String>,
+    budget: Option<&u64>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+    cache: &Cache,
+) -> Result<AuditReport, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(target) = target else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect server".to_string(),
+        ));
+    };
+    let (host, port) = split_host_port(target, HTTPS_PORT)?;
+    let dialer = Dialer::new(resolver_of(resolver)?);
+    let addrs = dialer.resolve(&host, cache)?;
+    let dual_stack = dialer.probe(&host, port, cache)?;
+    if let Some(audit) = audit {
+        audit.host_resolved(&host, dialer.resolver(), &addrs)?;
+        audit.host_probed(&host, port, &dual_stack)?;
+    }
+    let pl = fetch_tls_public_key_with(&host, port, &dialer)?;
+    if let Some(audit) = audit {
+        audit.key_loaded(target, &pl)?;
+    }
+
+    let mut report = crack_report(&pl, target, budget, report_level, audit, gate)?;
+    for finding in report.findings.iter_mut() {
+        finding.addrs = addrs.clone();
+        finding.families = dual_stack.outcomes();
+    }
+
+    Ok(report)
+}
+
+// Runs every attack on the key within its budget and reports the ROCA fingerprint and the recovered private key,
+// unless the gate lets them pass.
+#[inline(always)]
+fn crack_report(
+    pl: &PickLock,
+    origin: &str,
+    budget: Option<&u64>,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<AuditReport, BilboError> {
+    let mut report = AuditReport::new();
+    if flag_roca(pl, report_level, audit, gate)?.is_some() {
+        report.push(Finding::roca(pl).with_origin(origin));
+    }
+    // The findings of the lint like the scanner reports them, the small primes are the quick factor attack's.
+    for weakness in pl.lint() {
+        if weakness == KeyWeakness::SmallPrime {
+            continue;
+        }
+        let finding = weakness
+            .finding(Some(pl), pl.bits())
+            .with_fingerprint(&pl.modulus_fingerprint())
+            .with_origin(origin);
+        if gate_finding(
+            &finding.rule_id(),
+            pl,
+            &finding.detail,
+            None,
+            report_level,
+            audit,
+            gate,
+        )? {
+            report.push(finding);
+        }
+    }
+    let attack = Attack::All(budget.copied());
+    if let Some(audit) = audit {
+        audit.attack_started(attack.name(), pl)?;
+    }
+    let mut strategy = AttackStrategy::new();
+    if let Some(seconds) = budget {
+        strategy = strategy.with_default_budget(Duration::from_secs(*seconds));
+    }
+    let start = Instant::now();
+    let outcome = pl.try_all(strategy);
+    if report_level >= 1 {
+        for (technique, telemetry) in outcome.telemetry.iter() {
+            println!("🧰 {technique}: {telemetry}");
+        }
+        println!();
+    }
+    if let (Some(technique), Ok(d)) = (outcome.technique, &outcome.result) {
+        if report_finding(&technique.to_string(), pl, d, report_level, audit, gate)? {
+            report.push(Finding::recovered(technique, pl).with_origin(origin));
+        }
+    }
+    if let Some(audit) = audit {
+        audit.attack_finished(attack.name(), pl, outcome.result.is_ok(), start.elapsed())?;
+    }
+    if let (Err(e), true) = (&outcome.result, report_level >= 1) {
+        println!("🔒 The lock holds: {e}\n");
+    }
+
+    Ok(report)
+}
+
+#[inline(always)]
+fn run_scan(
+    matches: &ArgMatches,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<AuditReport, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(path) = matches.get_one::<PathBuf>("PATH") else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect path to scan".to_string(),
+        ));
+    };
+    let mut scanner = KeyScanner::new();
+    for pattern in matches.get_many::<String>("include").unwrap_or_default() {
+        scanner = scanner.with_include(pattern);
+    }
+    for pattern in matches.get_many::<String>("exclude").unwrap_or_default() {
+        scanner = scanner.with_exclude(pattern);
+    }
+    if let Some(bytes) = matches.get_one::<u64>("max-file-size") {
+        scanner = scanner.with_max_file_size(*bytes);
+    }
+    if let Some(depth) = matches.get_one::<usize>("archive-depth") {
+        scanner = scanner.with_archive_depth(*depth);
+    }
+    let scanned = match matches.get_flag("git") {
+        true => scanner.scan_git_repo(path)?,
+        false => scanner.scan(path)?,
+    };
+    if report_level >= 1 {
+        println!(
+            "🔎 Scanned {} files, skipped {} too large, found {} keys.\n",
+            scanned.files,
+            scanned.skipped,
+            scanned.keys.len()
+        );
+        for key in scanned.keys.iter() {
+            println!("🔑 {key}");
+        }
+        println!();
+    }
+
+    let mut locks = BTreeMap::new();
+    for key in scanned.keys.iter() {
+        let Some(pl) = &key.pick_lock else {
+            continue;
+        };
+        if let Some(audit) = audit {
+            audit.key_loaded(&key.origin, pl)?;
+        }
+        locks.insert(pl.modulus_fingerprint(), pl);
+    }
+    let mut report = AuditReport::new();
+    for finding in scanned.audit_report().findings {
+        let counted = match finding.fingerprint.as_ref().and_then(|f| locks.get(f)) {
+            Some(pl) => gate_finding(
+                &finding.rule_id(),
+                pl,
+                &finding.detail,
+                None,
+                report_level,
+                audit,
+                gate,
+            )?,
+            None => true,
+        };
+        if counted {
+            report.push(finding);
+        }
+    }
+
+    Ok(report)
+}
+
+#[inline(always)]
+fn run_batch_gcd(
+    dir: Option<&PathBuf>,
+    report_level: Option<&u8>,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<AuditReport, BilboError> {
+    let report_level = check_level(report_level)?;
+    let Some(dir) = dir else {
+        return Err(BilboError::GenericError(
+            "empty or incorrect directory path".to_string(),
+        ));
+    };
+    let scanned = KeyScanner::new().scan(dir)?;
+    // The same key found in many files is attacked once, duplicate moduli would hide each other.
+    let mut origins: BTreeMap<String, String> = BTreeMap::new();
+    let mut locks = Vec::new();
+    for key in scanned.keys.into_iter() {
+        let Some(pl) = key.pick_lock else {
+            continue;
+        };
+        if origins.contains_key(&pl.modulus_fingerprint()) {
+            continue;
+        }
+        if let Some(audit) = audit {
+            audit.key_loaded(&key.origin, &pl)?;
+            audit.attack_started(BATCH_GCD_ATTACK, &pl)?;
+        }
+        origins.insert(pl.modulus_fingerprint(), key.origin);
+        locks.push(pl);
+    }
+    if locks.len() < 2 {
+        return Err(BilboError::GenericError(format!(
+            "batch gcd needs at least two RSA keys, {} found in {}",
+            locks.len(),
+            dir.display()
+        )));
+    }
+    let batch = BatchPickLock::new(locks);
+    if report_level >= 1 {
+        println!(
+            "🔐 Starting lock picking {} RSA private keys by batch gcd of their moduli.\n",
+            batch.locks().len()
+        );
+    }
+    let start = Instant::now();
+    let results = batch.try_lock_pick_shared_primes();
+    if report_level >= 1 {
+        println!("⏱️ Telemetry: {}\n", batch.locks()[0].last_telemetry());
+    }
+
+    let mut report = AuditReport::new();
+    for (pl, d) in batch.locks().iter().zip(results) {
+        let reported = match &d {
+            Ok(d) => report_finding(BATCH_GCD_ATTACK, pl, d, report_level, audit, gate)?,
+            Err(_) => false,
+        };
+        if let Some(audit) = audit {
+            audit.attack_finished(BATCH_GCD_ATTACK, pl, d.is_ok(), start.elapsed())?;
+        }
+        if reported {
+            let finding =
+                Finding::recovered_by(BATCH_GCD_ATTACK, "a prime shared with another key", pl);
+            report.push(finding.with_origin(&origins[&pl.modulus_fingerprint()]));
+        }
+    }
+
+    Ok(report)
+}
+
+// Reports the findings of the audit log, the ones the allowlist suppresses or the baseline knows left out.
+#[inline(always)]
+fn run_report(log: Option<&PathBuf>, gate: &Gate) -> Result<AuditReport, BilboError> {
+    let Some(log) = log else {
+        return Err(BilboError::GenericError(
+            "The --log audit log of the run is required.".to_string(),
+        ));
+    };
+    let records = gate
+        .allowlist
+        .filter(read_records(BufReader::new(File::open(log)?))?);
+    let mut report = AuditReport::from_records(&records);
+    report.findings.retain(|f| {
+        let key = f.fingerprint.as_deref().unwrap_or_default();
+        !gate.baseline.contains(key, &f.detail)
+    });
+
+    Ok(report)
+}
+
+// Gate decides which findings fail the run, suppressed by the allowlist and known by the baseline don't.
+struct Gate {
+    allowlist: Allowlist,
+    baseline: Baseline,
+}
+
+impl Gate {
+    #[inline(always)]
+    fn load(allowlist: Option<&PathBuf>, baseline: Option<&PathBuf>) -> Result<Self, BilboError> {
+        Ok(Self {
+            allowlist: match allowlist {
+                Some(path) => Allowlist::read(BufReader::new(File::open(path)?))?,
+                None => Allowlist::default(),
+            },
+            baseline: match baseline {
+                Some(path) => Baseline::read(BufReader::new(File::open(path)?))?,
+                None => Baseline::default(),
+            },
+        })
+    }
+}
+
+// Records the recovered key finding with its evidence, unless the allowlist suppresses it.
+// Returns false for suppressed findings and findings known by the baseline, those don't fail the run.
+#[inline(always)]
+fn report_finding(
+    attack: &str,
+    pl: &PickLock,
+    d: &BigInt,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<bool, BilboError> {
+    let evidence = evidence(attack, pl, d)?;
+    gate_finding(
+        attack,
+        pl,
+        "private exponent recovered",
+        Some(evidence),
+        report_level,
+        audit,
+        gate,
+    )
+}
+
+// Records the finding of the key, unless the allowlist suppresses it, see report_finding.
+#[inline(always)]
+fn gate_finding(
+    attack: &str,
+    pl: &PickLock,
+    detail: &str,
+    evidence: Option<EvidenceBundle>,
+    report_level: u8,
+    audit: Option<&AuditLog>,
+    gate: &Gate,
+) -> Result<bool, BilboError> {
+    if let Some(suppression) = gate
+        .allowlist
+        .suppression(&pl.modulus_fingerprint(), attack)
+    {
+        if report_level >= 1 {
+            println!(
+                "🙈 Finding of the key {} suppressed by the allowlist: {}\n",
+                suppression.key, suppression.reason
+            );
+        }
+        if let Some(audit) = audit {
+            audit.finding_suppressed(attack, pl, detail, suppression)?;
+        }
+        return Ok(false);
+    }
+    if let Some(audit) = audit {
+        match evidence {
+            Some(evidence) => audit.finding_with_evidence(attack, pl, detail, evidence)?,
+            None => audit.finding(attack, pl, detail)?,
+        }
+    }
+    if gate.baseline.contains(&pl.modulus_fingerprint(), detail) {
+        if report_level >= 1 {
+            println!(
+                "📌 Finding of the key {} is known by the baseline.\n",
+                pl.modulus_fingerprint()
+            );
+        }
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+// Builds redacted evidence of the recovered key with the command line and configuration reproducing it.
+#[inline(always)]
+fn evidence(attack: &str, pl: &PickLock, d: &BigInt) -> Result<EvidenceBundle, BilboError> {
+    let config = BTreeMap::from([
+        ("max_iter".to_string(), pl.iteration_limit().to_string()),
+        ("primality".to_string(), policy().to_string()),
+    ]);
+    EvidenceBundle::new(
+        attack,
+        &pl.to_private_key(d)?,
+        true,
+        std::env::args().collect(),
+        config,
+    )
 }
 
 #[inline(always)]
@@ -344,28 +2586,55 @@ fn run_entropy(path: Option<&PathBuf>, report_level: Option<&u8>) -> Result<Stri
     Ok(result)
 }
 
+#[inline(always)]
+fn cache(dir: Option<&PathBuf>, ttl: Option<&u64>) -> Result<Cache, BilboError> {
+    let cache = match ttl {
+        Some(ttl) => Cache::new(Duration::from_secs(*ttl)),
+        None => Cache::default(),
+    };
+    match dir {
+        Some(dir) => cache.with_dir(dir.clone()),
+        None => Ok(cache),
+    }
+}
+
 #[inline(always)]
 fn smuggle_file_via_ping(
     file: Option<&PathBuf>,
     ip: Option<&Ipv4Addr>,
+    host: Option<&String>,
+    resolver: Option<&String>,
     key: Option<&Vec<u8>>,
+    audit: Option<&AuditLog>,
+    cache: &Cache,
 ) -> Result<String, BilboError> {
     let Some(path) = file else {
         return Err(BilboError::GenericError(
             "empty or incorrect file path".to_string(),
         ));
     };
-    let Some(ip) = ip else {
-        return Err(BilboError::GenericError(
-            "empty or incorrect ip address".to_string(),
-        ));
+    let ip = match (ip, host) {
+        (Some(ip), _) => IpAddr::V4(*ip),
+        (None, Some(host)) => {
+            let resolver = resolver_of(resolver)?;
+            let addrs = resolver.resolve_cached(host, cache)?;
+            if let Some(audit) = audit {
+                audit.host_resolved(host, &resolver, &addrs)?;
+            }
+            addrs[0]
+        }
+        (None, None) => {
+            return Err(BilboError::GenericError(
+                "empty or incorrect ip address or host".to_string(),
+            ));
+        }
     };
 
     let data = read_to_string(path)?;
 
     match key {
         None => {
-            ping_plain(IpAddr::V4(*ip), data.as_bytes(), &Config::default())?;
+            ping_plain(ip, data.as_bytes(), &Config::default())?;
             Ok(format!("File {:?} smuggled to {}\n", path.as_os_str(), ip).to_string())
         }
         Some(k) => {
@@ -380,7 +2649,6 @@ fn smuggle_file_via_ping(
             let mut enc_key: [u8; 16] = [0; 16];
             enc_key.copy_from_slice(&k[..16]);
 
-            let ip = IpAddr::V4(*ip);
             let vi = ping_cipher(ip, data.as_bytes(), &enc_key, &cfg)?;
             ping_plain(ip, &vi, &cfg)?;
 
@@ -395,6 +2663,25 @@ fn smuggle_file_via_ping(
     }
 }
 
+// Appends the outcome per address family to the finding of the live server.
+#[inline(always)]
+fn with_families(detail: String, dual_stack: &DualStackReport) -> String {
+    if dual_stack.attempts.is_empty() {
+        return detail;
+    }
+
+    format!("{detail} ({dual_stack})")
+}
+
+// Returns the resolver of the --resolver, the system one by default.
+#[inline(always)]
+fn resolver_of(resolver: Option<&String>) -> Result<Resolver, BilboError> {
+    match resolver {
+        Some(r) => r.parse(),
+        None => Ok(Resolver::System),
+    }
+}
+
 #[inline(always)]
 fn check_level(level: Option<&u8>) -> Result<u8, BilboError> {
     let level = *level.unwrap_or(&0);
@@ -405,3 +2692,138 @@ fn check_level(level: Option<&u8>) -> Result<u8, BilboError> {
         ))),
     }
 }
+
+// Level 2 renders progress of the long running attacks in the terminal, lower levels drop it.
+#[inline(always)]
+fn progress_sink(report_level: u8) -> Box<dyn ProgressSink> {
+    match report_level {
+        2 => Box::new(ConsoleSink::new()),
+        _ => Box::new(SilentSink),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bilbo::report::Finding;
+    use std::env::temp_dir;
+    use std::fs::remove_dir_all;
+
+    fn matches(args: &[&str]) -> ArgMatches {
+        cli()
+            .try_get_matches_from([&["bilbo"], args].concat())
+            .unwrap()
+    }
+
+    #[test]
+    fn it_should_gate_the_exit_code_on_the_severity_of_findings() {
+        let matches = matches(&["report", "-q"]);
+        let (_, matches) = matches.subcommand().unwrap();
+        let report = |severity| {
+            let mut report = AuditReport::new();
+            report.push(Finding::new(severity, "weak", "Replace it."));
+            report
+        };
+
+        assert_eq!(
+            print_report(Ok(AuditReport::new()), matches, true, "Report"),
+            EXIT_CLEAN
+        );
+        assert_eq!(
+            print_report(Ok(report(Severity::Low)), matches, true, "Report"),
+            EXIT_CLEAN
+        );
+        assert_eq!(
+            print_report(Ok(report(GATE_SEVERITY)), matches, true, "Report"),
+            EXIT_FINDINGS
+        );
+        assert_eq!(
+            print_report(Ok(report(Severity::Critical)), matches, true, "Report"),
+            EXIT_FINDINGS
+        );
+        assert_eq!(
+            print_report(
+                Err(BilboError::GenericError("failed".to_string())),
+                matches,
+                true,
+                "Report"
+            ),
+            EXIT_ERROR
+        );
+    }
+
+    #[test]
+    fn it_should_exit_alike_cracking_and_scanning_the_weak_key() -> Result<(), BilboError> {
+        let dir = temp_dir().join(format!("bilbo-main-short-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let key = dir.join("short.pem");
+        let rsa = openssl::rsa::Rsa::generate(1024)?;
+        write(&key, rsa.public_key_to_pem()?)?;
+        let key = key.to_string_lossy().to_string();
+
+        let crack = run(&matches(&["crack", "-q", &key, "--budget", "0"]));
+        assert_eq!(crack, EXIT_FINDINGS);
+        assert_eq!(run(&matches(&["scan", "-q", &key])), crack);
+        std::fs::remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_exit_with_the_code_of_the_subcommand_outcome() -> Result<(), BilboError> {
+        let dir = temp_dir().join(format!("bilbo-main-{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let pl = PickLock::from_exponent_and_modulus(
+            BigInt::from(65_537),
+            BigInt::from(1_000_003_u64 * 999_983),
+        );
+        let log = AuditLog::to_file(&dir.join("run.jsonl"))?;
+        log.key_loaded("weak.pem", &pl)?;
+        log.finding("weak", &pl, "private exponent recovered")?;
+        write(dir.join("empty.jsonl"), "")?;
+        write(
+            dir.join("allowlist.txt"),
+            format!("{} weak # test key\n", pl.modulus_fingerprint()),
+        )?;
+
+        assert_eq!(
+            run(&matches(&["report", "-q", "--log", &path("empty.jsonl")])),
+            EXIT_CLEAN
+        );
+        assert_eq!(
+            run(&matches(&["report", "-q", "--log", &path("run.jsonl")])),
+            EXIT_FINDINGS
+        );
+        assert_eq!(
+            run(&matches(&[
+                "report",
+                "-q",
+                "--log",
+                &path("run.jsonl"),
+                "--allowlist",
+                &path("allowlist.txt"),
+            ])),
+            EXIT_CLEAN,
+            "allowlisted finding doesn't fail the gate"
+        );
+        assert_eq!(
+            run(&matches(&["report", "-q", "--log", &path("missing.jsonl")])),
+            EXIT_ERROR
+        );
+        assert_eq!(
+            run(&matches(&[
+                "report",
+                "-q",
+                "--log",
+                &path("run.jsonl"),
+                "--allowlist",
+                &path("missing.txt"),
+            ])),
+            EXIT_ERROR
+        );
+        remove_dir_all(&dir)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use num_bigint::BigInt;
+use num_integer::Integer;
+
+use crate::cpu::Montgomery;
+
+// Widest window of the exponentiation, the table of 2^window powers outgrows the multiplications it saves.
+const MAX_WINDOW: u64 = 6;
+// Above 512 bits the exponentiation of num-bigint, Montgomery too, is as fast as the kernel's.
+const MAX_POW_LIMBS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Repr {
+    Montgomery(Vec<u64>),
+    Plain(BigInt),
+}
+
+/// Residue is a number modulo the Modulus it was created by, kept in the form its arithmetic works on,
+/// Montgomery form for odd n. Residues of the same Modulus are equal when the numbers are congruent.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Residue(Repr);
+
+/// Modulus is the arithmetic modulo fixed n repeated by the attacks and the primality checks,
+/// residues stay in Montgomery form on the multiplication kernel selected for the running CPU,
+/// so the long chains of multiplications and exponentiations modulo n never divide.
+/// Even n and n above 8192 bits fall back to the plain reduction of num-bigint.
+///
+#[derive(Debug, Clone)]
+pub struct Modulus {
+    n: BigInt,
+    mont: Option<Montgomery>,
+}
+
+impl Modulus {
+    /// Creates the arithmetic modulo n, n must be positive.
+    ///
+    #[inline(always)]
+    pub fn new(n: &BigInt) -> Self {
+        Self {
+            n: n.clone(),
+            mont: Montgomery::new(n),
+        }
+    }
+
+    /// Returns n.
+    ///
+    #[inline(always)]
+    pub fn n(&self) -> &BigInt {
+        &self.n
+    }
+
+    /// Returns the residue of a, any integer.
+    ///
+    #[inline(always)]
+    pub fn residue(&self, a: &BigInt) -> Residue {
+        match &self.mont {
+            Some(mont) => Residue(Repr::Montgomery(mont.to_montgomery(a))),
+            None => Residue(Repr::Plain(a.mod_floor(&self.n))),
+        }
+    }
+
+    /// Returns the residue as number from 0 to n − 1.
+    ///
+    #[inline(always)]
+    pub fn value(&self, a: &Residue) -> BigInt {
+        match (&self.mont, &a.0) {
+            (Some(mont), Repr::Montgomery(a)) => mont.from_montgomery(a),
+            (_, Repr::Plain(a)) => a.clone(),
+            (None, Repr::Montgomery(_)) => unreachable!("residue of another modulus"),
+        }
+    }
+
+    /// Returns the residue of 1.
+    ///
+    #[inline(always)]
+    pub fn one(&self) -> Residue {
+        self.residue(&BigInt::from(1))
+    }
+
+    /// Returns a · b mod n.
+    ///
+    #[inline(always)]
+    pub fn mul(&self, a: &Residue, b: &Residue) -> Residue {
+        match (&self.mont, &a.0, &b.0) {
+            (Some(mont), Repr::Montgomery(a), Repr::Montgomery(b)) => {
+                let mut out = vec![0; a.len()];
+                mont.mul(a, b, &mut out);
+                Residue(Repr::Montgomery(out))
+            }
+            (_, Repr::Plain(a), Repr::Plain(b)) => Residue(Repr::Plain((a * b) % &self.n)),
+            _ => unreachable!("residues of another modulus"),
+        }
+    }
+
+    /// Returns a² mod n.
+    ///
+    #[inline(always)]
+    pub fn square(&self, a: &Residue) -> Residue {
+        self.mul(a, a)
+    }
+
+    /// Returns a − b mod n.
+    ///
+    #[inline(always)]
+    pub fn sub(&self, a: &Residue, b: &Residue) -> Residue {
+        match (&self.mont, &a.0, &b.0) {
+            (Some(mont), Repr::Montgomery(a), Repr::Montgomery(b)) => {
+                let mut out = vec![0; a.len()];
+                mont.sub(a, b, &mut out);
+                Residue(Repr::Montgomery(out))
+            }
+            (_, Repr::Plain(a), Repr::Plain(b)) => Residue(Repr::Plain((a - b).mod_floor(&self.n))),
+            _ => unreachable!("residues of another modulus"),
+        }
+    }
+
+    /// Returns a^exp mod n of non negative exp, by fixed window exponentiation, a squaring per bit
+    /// and a multiplication per window, the window is as wide as pays off for the size of exp.
+    /// Above 512 bits n it is the exponentiation of num-bigint.
+    ///
+    pub fn pow(&self, a: &Residue, exp: &BigInt) -> Residue {
+        let (Some(mont), Repr::Montgomery(a)) = (&self.mont, &a.0) else {
+            let Repr::Plain(a) = &a.0 else {
+                unreachable!("residue of another modulus");
+            };
+            return Residue(Repr::Plain(a.modpow(exp, &self.n)));
+        };
+        if a.len() > MAX_POW_LIMBS {
+            return self.residue(&mont.from_montgomery(a).modpow(exp, &self.n));
+        }
+        // Squarings don't depend on the window, it minimizes the multiplications, one per window and 2^window for the table.
+        let bits = exp.bits();
+        let window = (1..=MAX_WINDOW)
+            .min_by_key(|w| bits.div_ceil(*w) + (1 << w))
+            .unwrap_or(1);
+        let Repr::Montgomery(one) = self.one().0 else {
+            unreachable!("residue of another modulus");
+        };
+        let mut table = vec![one.clone(), a.clone()];
+        for i in 2..1 << window {
+            let mut next = vec![0; a.len()];
+            mont.mul(&table[i - 1], a, &mut next);
+            table.push(next);
+        }
+        let (mut acc, mut scratch) = (one, vec![0; a.len()]);
+        for at in (0..bits.div_ceil(window)).rev() {
+            for _ in 0..window {
+                mont.sqr(&acc, &mut scratch);
+                std::mem::swap(&mut acc, &mut scratch);
+            }
+            let digit = (0..window).rev().fold(0, |digit, bit| {
+                digit << 1 | usize::from(exp.bit(at * window + bit))
+            });
+            if digit != 0 {
+                mont.mul(&acc, &table[digit], &mut scratch);
+                std::mem::swap(&mut acc, &mut scratch);
+            }
+        }
+
+        Residue(Repr::Montgomery(acc))
+    }
+
+    /// Returns base^exp mod n of non negative exp.
+    ///
+    #[inline(always)]
+    pub fn pow_mod(&self, base: &BigInt, exp: &BigInt) -> BigInt {
+        self.value(&self.pow(&self.residue(base), exp))
+    }
+
+    /// Returns a · b mod n.
+    ///
+    #[inline(always)]
+    pub fn mul_mod(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        self.value(&self.mul(&self.residue(a), &self.residue(b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_agree_with_plain_modular_arithmetic() {
+        let odd: BigInt = "340282366920938463463374607431768211507".parse().unwrap();
+        let even = &odd * 2;
+        let base: BigInt = "-123456789012345678901234567890".parse().unwrap();
+        let exp: BigInt = "98765432109876543210987654321".parse().unwrap();
+        for n in [odd, even] {
+            let m = Modulus::new(&n);
+            assert_eq!(m.pow_mod(&base, &exp), base.mod_floor(&n).modpow(&exp, &n));
+            assert_eq!(m.pow_mod(&base, &BigInt::from(0)), BigInt::from(1));
+            assert_eq!(m.mul_mod(&base, &exp), (&base * &exp).mod_floor(&n));
+            let (a, b) = (m.residue(&base), m.residue(&exp));
+            assert_eq!(m.value(&m.sub(&a, &b)), (&base - &exp).mod_floor(&n));
+            assert_eq!(m.square(&a), m.mul(&a, &a));
+            assert_eq!(m.residue(&(&base + &n)), a);
+            assert_eq!(m.value(&m.one()), BigInt::from(1));
+        }
+    }
+}
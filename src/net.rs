@@ -0,0 +1,1166 @@
+use crossbeam::channel::unbounded;
+use openssl::ssl::{ErrorCode, HandshakeError};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::read_to_string;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+use std::thread::{scope, sleep, spawn};
+use std::time::{Duration, Instant};
+
+use crate::cache::{Cache, Material};
+use crate::errors::BilboError;
+use crate::http::{get, Url};
+
+/// DNS record type of IPv4 address.
+pub const DNS_TYPE_A: u16 = 1;
+/// DNS record type of IPv6 address.
+pub const DNS_TYPE_AAAA: u16 = 28;
+/// DNS record type of text record.
+pub const DNS_TYPE_TXT: u16 = 16;
+/// Delay before the next happy eyeballs connection attempt is started, as recommended by RFC 8305.
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+/// Timeout of every connection attempt of the dual stack probe.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const DNS_PORT: u16 = 53;
+const DNS_TIMEOUT: Duration = Duration::from_secs(5);
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+// UDP payload size advertised with EDNS0, long DKIM keys don't fit the classic 512 bytes.
+const EDNS_PAYLOAD: u16 = 4096;
+const DNS_RCODE_NXDOMAIN: u8 = 3;
+
+/// NetOutcome classifies the outcome of a network operation.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum NetOutcome {
+    Success,
+    Refused,
+    Timeout,
+    TlsAlert,
+    Protocol,
+    Unreachable,
+    Denied,
+    Other,
+}
+
+impl Display for NetOutcome {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                NetOutcome::Success => "success",
+                NetOutcome::Refused => "connection refused",
+                NetOutcome::Timeout => "timeout",
+                NetOutcome::TlsAlert => "tls alert",
+                NetOutcome::Protocol => "protocol error",
+                NetOutcome::Unreachable => "unreachable",
+                NetOutcome::Denied => "permission denied",
+                NetOutcome::Other => "other",
+            }
+        )
+    }
+}
+
+/// Classifies the error of a network operation.
+///
+#[inline(always)]
+pub fn classify(err: &BilboError) -> NetOutcome {
+    match err {
+        BilboError::IoErrro(e) => classify_io(e.kind()),
+        BilboError::PingErrro(ping::Error::IoError { error }) => classify_io(error.kind()),
+        BilboError::PingErrro(_) => NetOutcome::Protocol,
+        BilboError::OpensslStackError(stack) => {
+            let alert = stack
+                .errors()
+                .iter()
+                .any(|e| e.reason().is_some_and(|r| r.contains("alert")));
+            match alert {
+                true => NetOutcome::TlsAlert,
+                false => NetOutcome::Other,
+            }
+        }
+        BilboError::NetworkError { outcome, .. } => *outcome,
+        _ => NetOutcome::Other,
+    }
+}
+
+/// Converts the failed TLS handshake to the network error of its class,
+/// alerts and other TLS failures as tls alert, broken connections by their IO error.
+///
+#[inline(always)]
+pub fn handshake_error<S>(err: HandshakeError<S>) -> BilboError {
+    match err {
+        HandshakeError::SetupFailure(stack) => BilboError::OpensslStackError(stack),
+        HandshakeError::Failure(mid) | HandshakeError::WouldBlock(mid) => {
+            let e = mid.error();
+            let outcome = match e.io_error() {
+                Some(io) => classify_io(io.kind()),
+                None if e.code() == ErrorCode::SSL => NetOutcome::TlsAlert,
+                None => NetOutcome::Protocol,
+            };
+            BilboError::NetworkError {
+                outcome,
+                attempts: 1,
+                message: format!("tls handshake failed: {e}"),
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn classify_io(kind: ErrorKind) -> NetOutcome {
+    match kind {
+        ErrorKind::ConnectionRefused => NetOutcome::Refused,
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => NetOutcome::Timeout,
+        ErrorKind::HostUnreachable
+        | ErrorKind::NetworkUnreachable
+        | ErrorKind::AddrNotAvailable => NetOutcome::Unreachable,
+        ErrorKind::PermissionDenied => NetOutcome::Denied,
+        ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::UnexpectedEof
+        | ErrorKind::InvalidData => NetOutcome::Protocol,
+        _ => NetOutcome::Other,
+    }
+}
+
+/// Backoff describes how many times and how long to wait before retrying a failed network operation.
+/// The delay is multiplied by the factor after every retry.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    pub retries: u32,
+    pub delay: Duration,
+    pub factor: u32,
+}
+
+impl Backoff {
+    /// Creates new backoff from given parameters.
+    ///
+    #[inline(always)]
+    pub fn new(retries: u32, delay: Duration, factor: u32) -> Self {
+        Self {
+            retries,
+            delay,
+            factor,
+        }
+    }
+}
+
+/// RetryPolicy holds the backoff per outcome class.
+/// Classes without the backoff are not retried.
+///
+/// By default timeouts are retried twice and protocol errors and unreachable hosts once,
+/// refused connections, tls alerts and denied permissions are final.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    backoffs: HashMap<NetOutcome, Backoff>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+            .with(
+                NetOutcome::Timeout,
+                Backoff::new(2, Duration::from_millis(500), 2),
+            )
+            .with(
+                NetOutcome::Protocol,
+                Backoff::new(1, Duration::from_millis(250), 1),
+            )
+            .with(
+                NetOutcome::Unreachable,
+                Backoff::new(1, Duration::from_secs(1), 1),
+            )
+    }
+}
+
+impl RetryPolicy {
+    /// Creates the policy that never retries.
+    ///
+    #[inline(always)]
+    pub fn none() -> Self {
+        Self {
+            backoffs: HashMap::new(),
+        }
+    }
+
+    /// Sets the backoff for given outcome class.
+    ///
+    #[inline(always)]
+    pub fn with(mut self, outcome: NetOutcome, backoff: Backoff) -> Self {
+        self.backoffs.insert(outcome, backoff);
+        self
+    }
+
+    /// Returns the backoff for given outcome class if the class is retried.
+    ///
+    #[inline(always)]
+    pub fn backoff(&self, outcome: NetOutcome) -> Option<&Backoff> {
+        self.backoffs.get(&outcome)
+    }
+
+    /// Runs the operation retrying according to the policy.
+    /// The final failure is returned as BilboError::NetworkError carrying the outcome class and number of attempts.
+    ///
+    pub fn run<T>(
+        &self,
+        mut operation: impl FnMut() -> Result<T, BilboError>,
+    ) -> Result<T, BilboError> {
+        let mut attempts = 0;
+        let mut delays: HashMap<NetOutcome, (u32, Duration)> = HashMap::new();
+        loop {
+            attempts += 1;
+            let err = match operation() {
+                Ok(v) => return Ok(v),
+                Err(e) => e,
+            };
+            let outcome = classify(&err);
+            let Some(backoff) = self.backoff(outcome) else {
+                return Err(network_error(outcome, attempts, err));
+            };
+            let (retried, delay) = delays.entry(outcome).or_insert((0, backoff.delay));
+            if *retried >= backoff.retries {
+                return Err(network_error(outcome, attempts, err));
+            }
+            *retried += 1;
+            sleep(*delay);
+            *delay *= backoff.factor;
+        }
+    }
+}
+
+#[inline(always)]
+fn network_error(outcome: NetOutcome, attempts: u32, err: BilboError) -> BilboError {
+    match err {
+        BilboError::NetworkError { message, .. } => BilboError::NetworkError {
+            outcome,
+            attempts,
+            message,
+        },
+        err => BilboError::NetworkError {
+            outcome,
+            attempts,
+            message: err.to_string(),
+        },
+    }
+}
+
+/// Resolver resolves host names to IP addresses.
+///
+///  - System uses the operating system resolver.
+///  - Static answers from the given host to addresses map, useful to pin split-horizon names.
+///  - DnsOverHttps queries given DoH JSON API endpoint, e.g. https://cloudflare-dns.com/dns-query.
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Resolver {
+    #[default]
+    System,
+    Static(HashMap<String, Vec<IpAddr>>),
+    DnsOverHttps(String),
+}
+
+impl Display for Resolver {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            Resolver::System => write!(f, "system"),
+            Resolver::Static(_) => write!(f, "static"),
+            Resolver::DnsOverHttps(url) => write!(f, "doh {url}"),
+        }
+    }
+}
+
+impl FromStr for Resolver {
+    type Err = BilboError;
+
+    /// Parses "system" or DoH endpoint url.
+    ///
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "system" => Ok(Resolver::System),
+            url if url.starts_with("https://") => Ok(Resolver::DnsOverHttps(url.to_string())),
+            _ => Err(BilboError::GenericError(format!(
+                "unknown resolver {s}, expected 'system' or DoH https url"
+            ))),
+        }
+    }
+}
+
+/// Dialer connects the network scanners to their targets, host names resolved by its resolver,
+/// so split-horizon and CDN fronted services are audited at the addresses the caller means.
+/// Failed connections and handshakes are retried according to its retry policy.
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Dialer {
+    resolver: Resolver,
+    retry: RetryPolicy,
+}
+
+impl Dialer {
+    /// Creates the dialer resolving host names by the resolver.
+    ///
+    #[inline(always)]
+    pub fn new(resolver: Resolver) -> Self {
+        Self {
+            resolver,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the policy for retrying failed connections and handshakes per failure class.
+    ///
+    #[inline(always)]
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Returns the policy the scanners retry their connections and handshakes with.
+    ///
+    #[inline(always)]
+    pub fn retry(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Returns the resolver of the host names.
+    ///
+    #[inline(always)]
+    pub fn resolver(&self) -> &Resolver {
+        &self.resolver
+    }
+
+    /// Resolves the host to all its addresses, answers of the system and DoH resolvers cached as DNS material.
+    ///
+    #[inline(always)]
+    pub fn resolve(&self, host: &str, cache: &Cache) -> Result<Vec<IpAddr>, BilboError> {
+        self.resolver.resolve_cached(host, cache)
+    }
+
+    /// Connects to the first address of the host that accepts the connection, happy eyeballs style,
+    /// the read and write timeouts of the stream set to the timeout.
+    ///
+    pub fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        timeout: Duration,
+        cache: &Cache,
+    ) -> Result<TcpStream, BilboError> {
+        let (tcp, _) = happy_eyeballs(&self.resolve(host, cache)?, port, timeout)?;
+        tcp.set_read_timeout(Some(timeout))?;
+        tcp.set_write_timeout(Some(timeout))?;
+
+        Ok(tcp)
+    }
+
+    /// Attempts connection to every address of the host and reports the outcome per address family.
+    ///
+    #[inline(always)]
+    pub fn probe(
+        &self,
+        host: &str,
+        port: u16,
+        cache: &Cache,
+    ) -> Result<DualStackReport, BilboError> {
+        Ok(probe_dual_stack(
+            &self.resolve(host, cache)?,
+            port,
+            PROBE_TIMEOUT,
+        ))
+    }
+}
+
+impl Resolver {
+    /// Resolves the host to all its IPv4 and IPv6 addresses.
+    /// Host given as an IP address is returned as is.
+    ///
+    pub fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, BilboError> {
+        if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse() {
+            return Ok(vec![ip]);
+        }
+        let addrs: Vec<IpAddr> = match self {
+            Resolver::System => (host, 0).to_socket_addrs()?.map(|a| a.ip()).collect(),
+            Resolver::Static(hosts) => hosts.get(host).cloned().unwrap_or_default(),
+            Resolver::DnsOverHttps(url) => {
+                let mut addrs = Vec::new();
+                for record_type in [DNS_TYPE_A, DNS_TYPE_AAAA] {
+                    for (_, data) in doh_query(url, host, record_type)? {
+                        if let Ok(ip) = data.parse() {
+                            addrs.push(ip);
+                        }
+                    }
+                }
+                addrs
+            }
+        };
+        if addrs.is_empty() {
+            return Err(BilboError::NetworkError {
+                outcome: NetOutcome::Unreachable,
+                attempts: 1,
+                message: format!("{self} resolver has no address for host {host}"),
+            });
+        }
+
+        Ok(addrs)
+    }
+
+    /// Resolves the host like resolve, answers of the system and DoH resolvers are cached as DNS material.
+    ///
+    pub fn resolve_cached(&self, host: &str, cache: &Cache) -> Result<Vec<IpAddr>, BilboError> {
+        if let Resolver::Static(_) = self {
+            return self.resolve(host);
+        }
+        let answer = cache.fetch(Material::Dns, &format!("{self} {host}"), || {
+            let addrs = self.resolve(host)?;
+            Ok(addrs
+                .iter()
+                .map(|a| a.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into_bytes())
+        })?;
+
+        Ok(String::from_utf8_lossy(&answer)
+            .lines()
+            .filter_map(|a| a.parse().ok())
+            .collect())
+    }
+}
+
+impl Resolver {
+    /// Queries the text records of the name, strings of each record concatenated, none if the name doesn't exist.
+    /// System resolver asks the first name server of /etc/resolv.conf over UDP, and over TCP when the answer is truncated.
+    /// Static resolver holds no text records.
+    ///
+    pub fn txt(&self, name: &str) -> Result<Vec<String>, BilboError> {
+        match self {
+            Resolver::System => Ok(dns_query(system_name_server()?, name, DNS_TYPE_TXT)?
+                .iter()
+                .map(|rdata| txt_strings(rdata))
+                .collect()),
+            Resolver::Static(_) => Ok(Vec::new()),
+            Resolver::DnsOverHttps(url) => Ok(doh_query(url, name, DNS_TYPE_TXT)?
+                .into_iter()
+                .map(|(_, data)| unquote_txt(&data))
+                .collect()),
+        }
+    }
+
+    /// Queries the text records like txt, answers are cached as DNS material.
+    ///
+    pub fn txt_cached(&self, name: &str, cache: &Cache) -> Result<Vec<String>, BilboError> {
+        if let Resolver::Static(_) = self {
+            return self.txt(name);
+        }
+        let answer = cache.fetch(Material::Dns, &format!("{self} TXT {name}"), || {
+            Ok(serde_json::to_vec(&self.txt(name)?)?)
+        })?;
+
+        Ok(serde_json::from_slice(&answer)?)
+    }
+}
+
+/// Sends DNS query for the records of given name and type to the name server over UDP,
+/// repeated over TCP if the answer is truncated. Returns record data of every answer, none if the name doesn't exist.
+///
+pub fn dns_query(
+    server: SocketAddr,
+    name: &str,
+    record_type: u16,
+) -> Result<Vec<Vec<u8>>, BilboError> {
+    let id = rand::random::<u16>();
+    let query = dns_message(id, name, record_type)?;
+    let socket = UdpSocket::bind(match server {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    })?;
+    socket.set_read_timeout(Some(DNS_TIMEOUT))?;
+    socket.connect(server)?;
+    socket.send(&query)?;
+    let mut buf = vec![0u8; EDNS_PAYLOAD as usize];
+    let len = socket.recv(&mut buf)?;
+    let (truncated, answers) = parse_dns_answer(id, &buf[..len], record_type)?;
+    if !truncated {
+        return Ok(answers);
+    }
+
+    let mut tcp = TcpStream::connect_timeout(&server, DNS_TIMEOUT)?;
+    tcp.set_read_timeout(Some(DNS_TIMEOUT))?;
+    let mut framed = (query.len() as u16).to_be_bytes().to_vec();
+    framed.extend(query);
+    tcp.write_all(&framed)?;
+    let mut len = [0u8; 2];
+    tcp.read_exact(&mut len)?;
+    let mut answer = vec![0u8; u16::from_be_bytes(len) as usize];
+    tcp.read_exact(&mut answer)?;
+
+    Ok(parse_dns_answer(id, &answer, record_type)?.1)
+}
+
+/// Parses DNS answer message to the query of given id, returns whether it is truncated
+/// and record data of the answers of given type.
+///
+pub fn parse_dns_answer(
+    id: u16,
+    message: &[u8],
+    record_type: u16,
+) -> Result<(bool, Vec<Vec<u8>>), BilboError> {
+    let malformed = || BilboError::NetworkError {
+        outcome: NetOutcome::Protocol,
+        attempts: 1,
+        message: "malformed DNS answer".to_string(),
+    };
+    if message.len() < 12 || u16::from_be_bytes([message[0], message[1]]) != id {
+        return Err(malformed());
+    }
+    let truncated = message[2] & 0x02 != 0;
+    match message[3] & 0x0f {
+        0 => (),
+        DNS_RCODE_NXDOMAIN => return Ok((truncated, Vec::new())),
+        rcode => {
+            return Err(BilboError::NetworkError {
+                outcome: NetOutcome::Protocol,
+                attempts: 1,
+                message: format!("DNS query failed with rcode {rcode}"),
+            })
+        }
+    }
+    let questions = u16::from_be_bytes([message[4], message[5]]);
+    let answers = u16::from_be_bytes([message[6], message[7]]);
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_dns_name(message, pos).ok_or_else(malformed)? + 4;
+    }
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        pos = skip_dns_name(message, pos).ok_or_else(malformed)?;
+        let header = message.get(pos..pos + 10).ok_or_else(malformed)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let rdata = message
+            .get(pos + 10..pos + 10 + len)
+            .ok_or_else(malformed)?;
+        if rtype == record_type {
+            records.push(rdata.to_vec());
+        }
+        pos += 10 + len;
+    }
+
+    Ok((truncated, records))
+}
+
+/// Concatenates the character strings of text record data.
+///
+#[inline(always)]
+pub fn txt_strings(rdata: &[u8]) -> String {
+    let mut text = Vec::new();
+    let mut rest = rdata;
+    while let Some((len, tail)) = rest.split_first() {
+        let len = (*len as usize).min(tail.len());
+        text.extend_from_slice(&tail[..len]);
+        rest = &tail[len..];
+    }
+
+    String::from_utf8_lossy(&text).to_string()
+}
+
+// Builds recursive query message with EDNS0 record advertising the larger UDP payload.
+#[inline(always)]
+fn dns_message(id: u16, name: &str, record_type: u16) -> Result<Vec<u8>, BilboError> {
+    let mut message = id.to_be_bytes().to_vec();
+    message.extend([0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 1]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(BilboError::GenericError(format!("invalid DNS name {name}")));
+        }
+        message.push(label.len() as u8);
+        message.extend(label.as_bytes());
+    }
+    message.push(0);
+    message.extend(record_type.to_be_bytes());
+    message.extend(1u16.to_be_bytes());
+    // OPT pseudo record of the root name.
+    message.extend([0, 0, 41]);
+    message.extend(EDNS_PAYLOAD.to_be_bytes());
+    message.extend([0, 0, 0, 0, 0, 0]);
+
+    Ok(message)
+}
+
+// Returns position after the possibly compressed name starting at given position.
+#[inline(always)]
+fn skip_dns_name(message: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            l if l & 0xc0 == 0xc0 => return Some(pos + 2),
+            l => pos += 1 + l as usize,
+        }
+    }
+}
+
+// Reads the first name server of the system resolver configuration.
+#[inline(always)]
+fn system_name_server() -> Result<SocketAddr, BilboError> {
+    let conf = read_to_string(RESOLV_CONF).map_err(|e| {
+        BilboError::GenericError(format!(
+            "system resolver configuration {RESOLV_CONF} can't be read, use DoH resolver: {e}"
+        ))
+    })?;
+    conf.lines()
+        .filter_map(|l| l.trim().strip_prefix("nameserver"))
+        .find_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        .ok_or_else(|| {
+            BilboError::GenericError(format!("no name server configured in {RESOLV_CONF}"))
+        })
+}
+
+// DoH JSON API returns text record data as quoted character strings, e.g. "v=DKIM1; k=rsa; " "p=MIIB...".
+#[inline(always)]
+fn unquote_txt(data: &str) -> String {
+    if !data.starts_with('"') {
+        return data.to_string();
+    }
+    let mut text = String::new();
+    let mut quoted = false;
+    let mut chars = data.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => text.extend(chars.next()),
+            c if quoted => text.push(c),
+            _ => (),
+        }
+    }
+
+    text
+}
+
+/// Queries DoH JSON API endpoint for the records of given name and type.
+/// Returns record type and data of every answer.
+///
+pub fn doh_query(
+    url: &str,
+    name: &str,
+    record_type: u16,
+) -> Result<Vec<(u16, String)>, BilboError> {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let url = Url::parse(&format!("{url}{separator}name={name}&type={record_type}"))?;
+    let response = get(&url, &[("Accept", "application/dns-json")])?;
+    if response.status != 200 {
+        return Err(BilboError::NetworkError {
+            outcome: NetOutcome::Protocol,
+            attempts: 1,
+            message: format!("DoH server responded with status {}", response.status),
+        });
+    }
+    let answer: DohResponse = serde_json::from_slice(&response.body)?;
+    if answer.status == DNS_RCODE_NXDOMAIN as u32 {
+        return Ok(Vec::new());
+    }
+    if answer.status != 0 {
+        return Err(BilboError::NetworkError {
+            outcome: NetOutcome::Protocol,
+            attempts: 1,
+            message: format!("DoH query for {name} failed with rcode {}", answer.status),
+        });
+    }
+
+    Ok(answer
+        .answer
+        .into_iter()
+        .filter(|a| a.record_type == record_type)
+        .map(|a| (a.record_type, a.data))
+        .collect())
+}
+
+#[derive(Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    data: String,
+}
+
+/// AddrFamily is the IP address family, A records resolve to IPv4 and AAAA records to IPv6.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AddrFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl Display for AddrFamily {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            AddrFamily::Ipv4 => write!(f, "IPv4"),
+            AddrFamily::Ipv6 => write!(f, "IPv6"),
+        }
+    }
+}
+
+impl AddrFamily {
+    /// Returns the family of given address.
+    ///
+    #[inline(always)]
+    pub fn of(addr: &IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => AddrFamily::Ipv4,
+            IpAddr::V6(_) => AddrFamily::Ipv6,
+        }
+    }
+}
+
+/// ConnectAttempt is the result of a single TCP connection attempt.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ConnectAttempt {
+    pub addr: SocketAddr,
+    pub family: AddrFamily,
+    pub outcome: NetOutcome,
+    pub elapsed_ms: u64,
+    pub message: Option<String>,
+}
+
+/// DualStackReport holds connection attempts to every address of the host.
+/// Legacy services often listen only on one family or serve different software on each,
+/// so results are reported per address family.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct DualStackReport {
+    pub attempts: Vec<ConnectAttempt>,
+}
+
+impl Display for DualStackReport {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let outcomes: Vec<String> = self
+            .outcomes()
+            .iter()
+            .map(|(family, outcome)| format!("{family} {outcome}"))
+            .collect();
+        write!(f, "{}", outcomes.join(", "))
+    }
+}
+
+impl DualStackReport {
+    /// Returns attempts to addresses of given family.
+    ///
+    #[inline(always)]
+    pub fn family(&self, family: AddrFamily) -> impl Iterator<Item = &ConnectAttempt> {
+        self.attempts.iter().filter(move |a| a.family == family)
+    }
+
+    /// Returns the outcome of the family, success if any of its addresses accepted the connection,
+    /// otherwise the outcome of its first address. None when the host has no address of the family.
+    ///
+    #[inline(always)]
+    pub fn outcome(&self, family: AddrFamily) -> Option<NetOutcome> {
+        let mut first = None;
+        for attempt in self.family(family) {
+            if attempt.outcome == NetOutcome::Success {
+                return Some(NetOutcome::Success);
+            }
+            first = first.or(Some(attempt.outcome));
+        }
+        first
+    }
+
+    /// Returns the outcome of every family the host has an address of.
+    ///
+    #[inline(always)]
+    pub fn outcomes(&self) -> BTreeMap<AddrFamily, NetOutcome> {
+        [AddrFamily::Ipv4, AddrFamily::Ipv6]
+            .into_iter()
+            .filter_map(|family| Some((family, self.outcome(family)?)))
+            .collect()
+    }
+
+    /// Returns true if the host is reachable over both families with different outcomes.
+    ///
+    #[inline(always)]
+    pub fn differs(&self) -> bool {
+        match (
+            self.outcome(AddrFamily::Ipv4),
+            self.outcome(AddrFamily::Ipv6),
+        ) {
+            (Some(v4), Some(v6)) => v4 != v6,
+            _ => false,
+        }
+    }
+}
+
+/// Splits the host[:port] target to its host and port, the default port if it has none,
+/// IPv6 address with port in square brackets.
+///
+pub fn split_host_port(target: &str, default_port: u16) -> Result<(String, u16), BilboError> {
+    let (host, port) = match target.strip_prefix('[') {
+        Some(v6) => match v6.split_once(']') {
+            Some((host, rest)) => (host, rest.strip_prefix(':')),
+            None => {
+                return Err(BilboError::GenericError(format!(
+                    "unclosed ipv6 host {target}"
+                )))
+            }
+        },
+        None if target.matches(':').count() == 1 => match target.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (target, None),
+        },
+        None => (target, None),
+    };
+    let port = match port {
+        Some(port) => port
+            .parse::<u16>()
+            .map_err(|e| BilboError::GenericError(format!("invalid port in {target}: {e}")))?,
+        None => default_port,
+    };
+
+    Ok((host.to_string(), port))
+}
+
+/// Orders addresses for connection attempts alternating the families, starting with IPv6
+/// as described in RFC 8305 section 4.
+///
+#[inline(always)]
+pub fn interleave(addrs: &[IpAddr]) -> Vec<IpAddr> {
+    let mut v6 = addrs.iter().filter(|a| a.is_ipv6());
+    let mut v4 = addrs.iter().filter(|a| a.is_ipv4());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b).copied()),
+        }
+    }
+
+    ordered
+}
+
+/// Connects to the first address that accepts the connection, happy eyeballs style.
+/// Attempts are started in interleaved family order, next one after the previous failed
+/// or after the CONNECTION_ATTEMPT_DELAY, so a broken family does not stall the connection.
+/// Returns the stream with all attempts that finished before it was established, the last one is the winner.
+///
+pub fn happy_eyeballs(
+    addrs: &[IpAddr],
+    port: u16,
+    timeout: Duration,
+) -> Result<(TcpStream, Vec<ConnectAttempt>), BilboError> {
+    let ordered = interleave(addrs);
+    let (tx, rx) = unbounded();
+    let mut next = ordered.iter();
+    let mut attempts = Vec::new();
+    let mut pending = 0;
+    loop {
+        if let Some(ip) = next.next() {
+            let tx = tx.clone();
+            let addr = SocketAddr::new(*ip, port);
+            spawn(move || {
+                let _ = tx.send(connect(addr, timeout));
+            });
+            pending += 1;
+        }
+        if pending == 0 {
+            break;
+        }
+        let received = if next.len() > 0 {
+            match rx.recv_timeout(CONNECTION_ATTEMPT_DELAY) {
+                Ok(received) => received,
+                Err(_) => continue,
+            }
+        } else {
+            rx.recv()
+                .map_err(|e| BilboError::GenericError(format!("connection attempt lost: {e}")))?
+        };
+        pending -= 1;
+        let (attempt, stream) = received;
+        attempts.push(attempt);
+        if let Some(stream) = stream {
+            return Ok((stream, attempts));
+        }
+    }
+
+    Err(BilboError::NetworkError {
+        outcome: attempts
+            .last()
+            .map(|a| a.outcome)
+            .unwrap_or(NetOutcome::Unreachable),
+        attempts: attempts.len() as u32,
+        message: format!(
+            "no address accepted connection on port {port}: {}",
+            attempts
+                .iter()
+                .map(|a| format!("{} {}", a.addr, a.outcome))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    })
+}
+
+/// Attempts connection to every address concurrently and reports the outcome of each of them.
+///
+pub fn probe_dual_stack(addrs: &[IpAddr], port: u16, timeout: Duration) -> DualStackReport {
+    let attempts = scope(|s| {
+        let handles: Vec<_> = interleave(addrs)
+            .into_iter()
+            .map(|ip| s.spawn(move || connect(SocketAddr::new(ip, port), timeout).0))
+            .collect();
+        handles.into_iter().filter_map(|h| h.join().ok()).collect()
+    });
+
+    DualStackReport { attempts }
+}
+
+#[inline(always)]
+fn connect(addr: SocketAddr, timeout: Duration) -> (ConnectAttempt, Option<TcpStream>) {
+    let start = Instant::now();
+    let (outcome, message, stream) = match TcpStream::connect_timeout(&addr, timeout) {
+        Ok(stream) => (NetOutcome::Success, None, Some(stream)),
+        Err(e) => {
+            let err = BilboError::from(e);
+            (classify(&err), Some(err.to_string()), None)
+        }
+    };
+    let attempt = ConnectAttempt {
+        addr,
+        family: AddrFamily::of(&addr.ip()),
+        outcome,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        message,
+    };
+
+    (attempt, stream)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::net::TcpListener;
+
+    #[test]
+    fn it_should_connect_to_the_family_that_accepts() -> Result<(), BilboError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let given: Vec<IpAddr> = vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()];
+        assert_eq!(interleave(&given), vec![given[1], given[0]]);
+
+        let (stream, attempts) = happy_eyeballs(&given, port, Duration::from_secs(1))?;
+        assert_eq!(stream.peer_addr()?, SocketAddr::new(given[0], port));
+        assert_eq!(attempts.last().map(|a| a.family), Some(AddrFamily::Ipv4));
+
+        let report = probe_dual_stack(&given, port, Duration::from_secs(1));
+        assert_eq!(report.attempts.len(), 2);
+        assert_eq!(report.outcome(AddrFamily::Ipv4), Some(NetOutcome::Success));
+        assert_ne!(report.outcome(AddrFamily::Ipv6), Some(NetOutcome::Success));
+        assert_eq!(report.outcomes().len(), 2);
+        assert!(report.to_string().starts_with("IPv4 success, IPv6 "));
+        assert!(report.differs());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_resolve_with_static_resolver() -> Result<(), BilboError> {
+        let addr: IpAddr = "10.0.0.1".parse().unwrap();
+        let resolver = Resolver::Static(HashMap::from([(
+            "internal.example".to_string(),
+            vec![addr],
+        )]));
+        assert_eq!(resolver.resolve("internal.example")?, vec![addr]);
+        assert_eq!(
+            resolver.resolve("::1")?,
+            vec!["::1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(
+            classify(&resolver.resolve("other.example").unwrap_err()),
+            NetOutcome::Unreachable
+        );
+        assert_eq!(
+            "https://dns.google/resolve".parse::<Resolver>()?,
+            Resolver::DnsOverHttps("https://dns.google/resolve".to_string())
+        );
+
+        let cache = Cache::default();
+        cache.put(Material::Dns, "system cached.example", b"10.0.0.2\n::2")?;
+        assert_eq!(
+            Resolver::System.resolve_cached("cached.example", &cache)?,
+            vec![
+                "10.0.0.2".parse::<IpAddr>().unwrap(),
+                "::2".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(
+            resolver.resolve_cached("internal.example", &cache)?,
+            vec![addr]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_query_txt_records_split_in_strings() -> Result<(), BilboError> {
+        let server = UdpSocket::bind("127.0.0.1:0")?;
+        let addr = server.local_addr()?;
+        let answering = spawn(move || -> Result<(), BilboError> {
+            let mut query = [0u8; 512];
+            let (len, client) = server.recv_from(&mut query)?;
+            // Question follows the header, the OPT record of 11 bytes ends the query.
+            let mut answer = query[..2].to_vec();
+            answer.extend([0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0]);
+            answer.extend(&query[12..len - 11]);
+            answer.extend([0xc0, 0x0c, 0, 16, 0, 1, 0, 0, 0x0e, 0x10, 0, 20]);
+            answer.extend(b"\x0av=DKIM1; k");
+            answer.extend(b"\x08=rsa; p=");
+            server.send_to(&answer, client)?;
+            Ok(())
+        });
+
+        let records = dns_query(addr, "s1._domainkey.example.com", DNS_TYPE_TXT)?;
+        assert!(answering.join().is_ok_and(|r| r.is_ok()));
+        assert_eq!(records.len(), 1);
+        assert_eq!(txt_strings(&records[0]), "v=DKIM1; k=rsa; p=");
+        assert_eq!(
+            unquote_txt("\"v=DKIM1; k=rsa; \" \"p=MIIB\\\"\""),
+            "v=DKIM1; k=rsa; p=MIIB\""
+        );
+        assert!(
+            parse_dns_answer(1, &[0, 1, 0x81, 0x83, 0, 0, 0, 0, 0, 0, 0, 0], DNS_TYPE_TXT)?
+                .1
+                .is_empty()
+        );
+        assert!(dns_message(1, "bad..example", DNS_TYPE_TXT).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_classify_network_errors() {
+        let given = [
+            BilboError::IoErrro(io::Error::from(ErrorKind::ConnectionRefused)),
+            BilboError::IoErrro(io::Error::from(ErrorKind::WouldBlock)),
+            BilboError::PingErrro(ping::Error::IoError {
+                error: io::Error::from(ErrorKind::PermissionDenied),
+            }),
+            BilboError::PingErrro(ping::Error::DecodeEchoReplyError),
+            BilboError::IoErrro(io::Error::from(ErrorKind::HostUnreachable)),
+            BilboError::GenericError("unknown".to_string()),
+            BilboError::from(openssl::rsa::Rsa::private_key_from_pem(b"not a key").unwrap_err()),
+        ];
+        let expected = [
+            NetOutcome::Refused,
+            NetOutcome::Timeout,
+            NetOutcome::Denied,
+            NetOutcome::Protocol,
+            NetOutcome::Unreachable,
+            NetOutcome::Other,
+            NetOutcome::Other,
+        ];
+        for (g, e) in given.iter().zip(expected) {
+            assert_eq!(classify(g), e);
+        }
+    }
+
+    #[test]
+    fn it_should_classify_failed_tls_handshakes() -> Result<(), BilboError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = spawn(move || -> Result<(), BilboError> {
+            // Plain text server answers the client hello with garbage, the next one hangs up.
+            let (mut tcp, _) = listener.accept()?;
+            tcp.write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")?;
+            listener.accept()?;
+            Ok(())
+        });
+        let handshake = || -> BilboError {
+            let connector =
+                openssl::ssl::SslConnector::builder(openssl::ssl::SslMethod::tls_client())
+                    .unwrap()
+                    .build();
+            let tcp = TcpStream::connect(addr).unwrap();
+            handshake_error(connector.connect("localhost", tcp).unwrap_err())
+        };
+
+        let garbage = handshake();
+        assert_eq!(classify(&garbage), NetOutcome::TlsAlert);
+        assert!(garbage.to_string().contains("tls handshake failed"));
+        let hung_up = handshake();
+        assert_eq!(classify(&hung_up), NetOutcome::Protocol);
+        server.join().unwrap()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_retry_according_to_the_class_backoff() -> Result<(), BilboError> {
+        let policy = RetryPolicy::none().with(
+            NetOutcome::Timeout,
+            Backoff::new(2, Duration::from_millis(1), 2),
+        );
+
+        let mut calls = 0;
+        let v = policy.run(|| {
+            calls += 1;
+            if calls < 3 {
+                return Err(BilboError::IoErrro(io::Error::from(ErrorKind::TimedOut)));
+            }
+            Ok(calls)
+        })?;
+        assert_eq!(v, 3);
+
+        let mut calls = 0;
+        let Err(err) = policy.run(|| -> Result<(), BilboError> {
+            calls += 1;
+            Err(BilboError::IoErrro(io::Error::from(ErrorKind::TimedOut)))
+        }) else {
+            panic!("expected timeout");
+        };
+        assert_eq!(calls, 3);
+        assert!(matches!(
+            err,
+            BilboError::NetworkError {
+                outcome: NetOutcome::Timeout,
+                attempts: 3,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_not_retry_final_classes() {
+        let mut calls = 0;
+        let Err(err) = RetryPolicy::default().run(|| -> Result<(), BilboError> {
+            calls += 1;
+            Err(BilboError::IoErrro(io::Error::from(
+                ErrorKind::ConnectionRefused,
+            )))
+        }) else {
+            panic!("expected refused");
+        };
+        assert_eq!(calls, 1);
+        assert_eq!(classify(&err), NetOutcome::Refused);
+    }
+}
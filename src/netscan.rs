@@ -0,0 +1,145 @@
+use crate::cache::Cache;
+use crate::endpoint::grab_certificate;
+use crate::errors::BilboError;
+use crate::net::Dialer;
+use crate::rsa::PickLock;
+use crate::ssh::{parse_host_key, scan, HostKey, CLIENT_KEX};
+
+/// Handshakes TLS with the host and returns PickLock of the RSA key of the leaf certificate it serves,
+/// live servers are audited without exporting their certificates first.
+/// The certificate is not verified and not cached across calls, fails if its key is not RSA.
+///
+#[inline(always)]
+pub fn fetch_tls_public_key(host: &str, port: u16) -> Result<PickLock, BilboError> {
+    fetch_tls_public_key_with(host, port, &Dialer::default())
+}
+
+/// Fetches the RSA key of the leaf certificate like fetch_tls_public_key, the host name resolved by the dialer.
+///
+#[inline(always)]
+pub fn fetch_tls_public_key_with(
+    host: &str,
+    port: u16,
+    dialer: &Dialer,
+) -> Result<PickLock, BilboError> {
+    let (leaf, _) = grab_certificate(host, port, dialer, &Cache::default())?;
+
+    PickLock::from_x509_der(&leaf)
+}
+
+/// Connects to the SSH server and exchanges keys far enough to retrieve its host key, RSA host keys are preferred.
+/// Fails if the server offers none of the key exchanges the client implements.
+///
+#[inline(always)]
+pub fn fetch_ssh_host_key(host: &str, port: u16) -> Result<HostKey, BilboError> {
+    fetch_ssh_host_key_with(host, port, &Dialer::default())
+}
+
+/// Retrieves the host key like fetch_ssh_host_key, the host name resolved by the dialer.
+///
+#[inline(always)]
+pub fn fetch_ssh_host_key_with(
+    host: &str,
+    port: u16,
+    dialer: &Dialer,
+) -> Result<HostKey, BilboError> {
+    let scan = scan(host, port, dialer, &Cache::default())?;
+    let Some(blob) = scan.host_key else {
+        return Err(BilboError::GenericError(format!(
+            "{host}:{port} offers none of the key exchanges {CLIENT_KEX}"
+        )));
+    };
+
+    parse_host_key(&blob)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::{
+        kexinit, put_mpint, put_string, read_banner, read_packet, write_packet, MSG_KEXDH_INIT,
+        MSG_KEXDH_REPLY,
+    };
+    use num_bigint::{BigInt, Sign};
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::ssl::{SslAcceptor, SslMethod};
+    use openssl::x509::{X509NameBuilder, X509};
+    use std::io::{BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn it_should_fetch_public_key_of_live_tls_server() -> Result<(), BilboError> {
+        let rsa = Rsa::generate(1024)?;
+        let n = BigInt::from_bytes_be(Sign::Plus, &rsa.n().to_vec());
+        let key = PKey::from_rsa(rsa)?;
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", "localhost")?;
+        let name = name.build();
+        let mut cert = X509::builder()?;
+        cert.set_subject_name(&name)?;
+        cert.set_issuer_name(&name)?;
+        cert.set_pubkey(&key)?;
+        let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(1)?);
+        cert.set_not_before(&not_before)?;
+        cert.set_not_after(&not_after)?;
+        cert.sign(&key, MessageDigest::sha256())?;
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+        // Weak keys of the audited servers are below the default security level.
+        acceptor.set_security_level(0);
+        acceptor.set_private_key(&key)?;
+        acceptor.set_certificate(&cert.build())?;
+        let acceptor = acceptor.build();
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let server = std::thread::spawn(move || {
+            if let Ok((tcp, _)) = listener.accept() {
+                let _ = acceptor.accept(tcp);
+            }
+        });
+
+        let pl = fetch_tls_public_key("127.0.0.1", port)?;
+        assert_eq!(pl.modulus(), &n);
+        let _ = server.join();
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_fetch_host_key_of_live_ssh_server() -> Result<(), BilboError> {
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-rsa");
+        put_mpint(&mut blob, &BigInt::from(65_537));
+        put_mpint(&mut blob, &BigInt::from(1_000_003_u64 * 999_983));
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let server = std::thread::spawn(move || -> Result<(), BilboError> {
+            // The first connection is the dual stack probe.
+            listener.accept()?;
+            let (tcp, _) = listener.accept()?;
+            let mut writer = tcp.try_clone()?;
+            let mut reader = BufReader::new(tcp);
+            writer.write_all(b"SSH-2.0-OpenSSH_9.6\r\n")?;
+            // The key exchange init of the client offers what the server needs to.
+            write_packet(&mut writer, &kexinit())?;
+            read_banner(&mut reader)?;
+            read_packet(&mut reader)?;
+            assert_eq!(read_packet(&mut reader)?[0], MSG_KEXDH_INIT);
+            let mut reply = vec![MSG_KEXDH_REPLY];
+            put_string(&mut reply, &blob);
+            put_mpint(&mut reply, &BigInt::from(2));
+            put_string(&mut reply, &[]);
+            write_packet(&mut writer, &reply)
+        });
+
+        let HostKey::Rsa(pl) = fetch_ssh_host_key("127.0.0.1", port)? else {
+            panic!("expected ssh-rsa host key");
+        };
+        assert_eq!(pl.bits(), 40);
+        server.join().unwrap()?;
+
+        Ok(())
+    }
+}
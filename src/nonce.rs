@@ -0,0 +1,253 @@
+use num_bigint::{BigInt, Sign};
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcKeyRef, EcPoint};
+use openssl::pkey::HasPublic;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::errors::BilboError;
+use crate::signature::SignedMessage;
+
+/// NonceRelation is the known affine relation between two nonces, k2 = a·k1 + b mod n.
+///
+///  - Exact nonce reuse is a = 1, b = 0.
+///  - Counter based nonces are a = 1, b = step.
+///  - Flawed deterministic nonces often produce other small a and b.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceRelation {
+    pub a: BigInt,
+    pub b: BigInt,
+}
+
+impl Display for NonceRelation {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "k2 = {}·k1 + {}", self.a, self.b)
+    }
+}
+
+impl NonceRelation {
+    /// Creates new relation k2 = a·k1 + b.
+    ///
+    #[inline(always)]
+    pub fn new(a: BigInt, b: BigInt) -> Self {
+        Self { a, b }
+    }
+
+    /// Relation of the reused nonce, k2 = k1.
+    ///
+    #[inline(always)]
+    pub fn reuse() -> Self {
+        Self::new(BigInt::from(1), BigInt::from(0))
+    }
+
+    /// Relation of the counter based nonce, k2 = k1 + step.
+    ///
+    #[inline(always)]
+    pub fn counter(step: i64) -> Self {
+        Self::new(BigInt::from(1), BigInt::from(step))
+    }
+}
+
+/// NonceRecovery is the private key recovered from the pair of signatures of the corpus.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonceRecovery {
+    pub private_key: BigInt,
+    pub first: usize,
+    pub second: usize,
+    pub relation: NonceRelation,
+}
+
+/// Recovers the private key from two signatures whose nonces satisfy given relation.
+///
+/// From s = k⁻¹(z + r·x) follows k = (z + r·x)/s, substituting to k2 = a·k1 + b gives
+/// x = (a·z1/s1 + b − z2/s2) / (r2/s2 − a·r1/s1) mod n.
+///
+/// The result is only a candidate, it is the private key if the relation holds,
+/// verify it against the public key.
+///
+pub fn recover_private_key(
+    first: &SignedMessage,
+    second: &SignedMessage,
+    relation: &NonceRelation,
+    order: &BigInt,
+) -> Result<BigInt, BilboError> {
+    let (r1, s1) = (&first.signature.r, &first.signature.s);
+    let (r2, s2) = (&second.signature.r, &second.signature.s);
+    let z1 = first.message.to_int(order)?;
+    let z2 = second.message.to_int(order)?;
+    let s1_inv = mod_inverse(s1, order)?;
+    let s2_inv = mod_inverse(s2, order)?;
+
+    let numerator = &relation.a * &z1 * &s1_inv + &relation.b - &z2 * &s2_inv;
+    let denominator = r2 * &s2_inv - &relation.a * r1 * &s1_inv;
+    let denominator_inv = mod_inverse(&denominator, order).map_err(|_| {
+        BilboError::GenericError(format!(
+            "signatures are degenerate for relation {relation}, r2/s2 = a·r1/s1"
+        ))
+    })?;
+
+    Ok(reduce(numerator * denominator_inv, order))
+}
+
+/// Recovers the nonce of the signature from the private key, k = (z + r·x)/s mod n.
+///
+#[inline(always)]
+pub fn recover_nonce(
+    signed: &SignedMessage,
+    private_key: &BigInt,
+    order: &BigInt,
+) -> Result<BigInt, BilboError> {
+    let z = signed.message.to_int(order)?;
+    let s_inv = mod_inverse(&signed.signature.s, order)?;
+
+    Ok(reduce(
+        (z + &signed.signature.r * private_key) * s_inv,
+        order,
+    ))
+}
+
+/// Tries every relation on every ordered pair of signatures of the corpus, returns the first
+/// candidate accepted by is_private_key. Pairs sharing r are tried first, they reveal exact reuse.
+///
+pub fn scan_related_nonces(
+    corpus: &[SignedMessage],
+    relations: &[NonceRelation],
+    order: &BigInt,
+    is_private_key: impl Fn(&BigInt) -> bool,
+) -> Option<NonceRecovery> {
+    let mut pairs: Vec<(usize, usize)> = (0..corpus.len())
+        .flat_map(|i| {
+            (0..corpus.len())
+                .filter(move |j| *j != i)
+                .map(move |j| (i, j))
+        })
+        .collect();
+    pairs.sort_by_key(|(i, j)| corpus[*i].signature.r != corpus[*j].signature.r);
+
+    for (first, second) in pairs {
+        for relation in relations.iter() {
+            let Ok(candidate) =
+                recover_private_key(&corpus[first], &corpus[second], relation, order)
+            else {
+                continue;
+            };
+            if is_private_key(&candidate) {
+                return Some(NonceRecovery {
+                    private_key: candidate,
+                    first,
+                    second,
+                    relation: relation.clone(),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the order of the curve group of given EC key.
+///
+#[inline(always)]
+pub fn ec_order<T: HasPublic>(key: &EcKeyRef<T>) -> Result<BigInt, BilboError> {
+    let mut ctx = BigNumContext::new()?;
+    let mut order = BigNum::new()?;
+    key.group().order(&mut order, &mut ctx)?;
+
+    Ok(BigInt::from_bytes_be(Sign::Plus, &order.to_vec()))
+}
+
+/// Returns true if x·G equals the public point of given EC key.
+///
+#[inline(always)]
+pub fn ec_private_key_matches<T: HasPublic>(
+    key: &EcKeyRef<T>,
+    x: &BigInt,
+) -> Result<bool, BilboError> {
+    if x.sign() != Sign::Plus {
+        return Ok(false);
+    }
+    let mut ctx = BigNumContext::new()?;
+    let scalar = BigNum::from_slice(&x.to_bytes_be().1)?;
+    let mut point = EcPoint::new(key.group())?;
+    point.mul_generator(key.group(), &scalar, &ctx)?;
+
+    Ok(point.eq(key.group(), key.public_key(), &mut ctx)?)
+}
+
+#[inline(always)]
+fn mod_inverse(v: &BigInt, order: &BigInt) -> Result<BigInt, BilboError> {
+    reduce(v.clone(), order)
+        .modinv(order)
+        .ok_or_else(|| BilboError::GenericError(format!("{v} has no inverse modulo group order")))
+}
+
+#[inline(always)]
+fn reduce(v: BigInt, order: &BigInt) -> BigInt {
+    ((v % order) + order) % order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature::{HashAlgorithm, Message, Signature};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+
+    fn sign(key: &EcKey<Private>, message: &[u8], k: &BigInt) -> SignedMessage {
+        let order = ec_order(key).unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let mut point = EcPoint::new(key.group()).unwrap();
+        let scalar = BigNum::from_slice(&k.to_bytes_be().1).unwrap();
+        point.mul_generator(key.group(), &scalar, &ctx).unwrap();
+        let (mut x, mut y) = (BigNum::new().unwrap(), BigNum::new().unwrap());
+        point
+            .affine_coordinates(key.group(), &mut x, &mut y, &mut ctx)
+            .unwrap();
+        let r = reduce(BigInt::from_bytes_be(Sign::Plus, &x.to_vec()), &order);
+        let d = BigInt::from_bytes_be(Sign::Plus, &key.private_key().to_vec());
+        let message = Message::Raw(message.to_vec(), HashAlgorithm::Sha256);
+        let z = message.to_int(&order).unwrap();
+        let s = reduce((z + &r * d) * k.modinv(&order).unwrap(), &order);
+
+        SignedMessage::new(Signature { r, s }, message)
+    }
+
+    #[test]
+    fn it_should_recover_private_key_from_linearly_related_nonces() -> Result<(), BilboError> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = EcKey::generate(&group)?;
+        let d = BigInt::from_bytes_be(Sign::Plus, &key.private_key().to_vec());
+        let order = ec_order(&key)?;
+        let k1 = BigInt::from(0x1234_5678_9abc_u64) << 128;
+        let relation = NonceRelation::new(BigInt::from(3), BigInt::from(7));
+        let k2 = reduce(&relation.a * &k1 + &relation.b, &order);
+
+        let first = sign(&key, b"first", &k1);
+        let second = sign(&key, b"second", &k2);
+        assert_eq!(recover_private_key(&first, &second, &relation, &order)?, d);
+        assert_eq!(recover_nonce(&second, &d, &order)?, k2);
+
+        let reused = sign(&key, b"third", &k1);
+        let corpus = vec![second, first, reused];
+        let found = scan_related_nonces(
+            &corpus,
+            &[NonceRelation::counter(1), NonceRelation::reuse()],
+            &order,
+            |x| ec_private_key_matches(&key, x).unwrap_or(false),
+        );
+        assert_eq!(
+            found,
+            Some(NonceRecovery {
+                private_key: d,
+                first: 1,
+                second: 2,
+                relation: NonceRelation::reuse(),
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use num_bigint::{BigInt, Sign};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use crate::errors::BilboError;
+
+/// Secret is the part of the RSA private key whose bits were recovered.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Secret {
+    D,
+    P,
+    Q,
+    Dp,
+    Dq,
+}
+
+impl Display for Secret {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Secret::D => "d",
+                Secret::P => "p",
+                Secret::Q => "q",
+                Secret::Dp => "dp",
+                Secret::Dq => "dq",
+            }
+        )
+    }
+}
+
+impl FromStr for Secret {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "d" => Ok(Secret::D),
+            "p" => Ok(Secret::P),
+            "q" => Ok(Secret::Q),
+            "dp" => Ok(Secret::Dp),
+            "dq" => Ok(Secret::Dq),
+            _ => Err(BilboError::GenericError(format!(
+                "unknown secret {s}, expected d, p, q, dp or dq"
+            ))),
+        }
+    }
+}
+
+/// KnownBits holds recovered bits of a single secret, bit i of value is meaningful if bit i of mask is set.
+/// Bits are numbered from the least significant bit 0.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KnownBits {
+    pub value: BigInt,
+    pub mask: BigInt,
+}
+
+impl KnownBits {
+    /// Sets bits lo..hi (hi exclusive) to given value, fails if they contradict already known bits.
+    ///
+    pub fn set(&mut self, lo: u64, hi: u64, value: &BigInt) -> Result<(), BilboError> {
+        if hi <= lo {
+            return Err(BilboError::GenericError(format!(
+                "empty bit range {lo}..{hi}"
+            )));
+        }
+        let width_mask = (BigInt::from(1) << (hi - lo)) - 1;
+        if value > &width_mask || value.sign() == Sign::Minus {
+            return Err(BilboError::GenericError(format!(
+                "value {value:#x} does not fit in bits {lo}..{hi}"
+            )));
+        }
+        let mask = width_mask << lo;
+        let value = value << lo;
+        let overlap = &mask & &self.mask;
+        if (&value & &overlap) != (&self.value & &overlap) {
+            return Err(BilboError::GenericError(format!(
+                "bits {lo}..{hi} contradict previously imported bits"
+            )));
+        }
+        self.value |= value;
+        self.mask |= mask;
+
+        Ok(())
+    }
+
+    /// Returns the number of known bits.
+    ///
+    #[inline(always)]
+    pub fn count(&self) -> u64 {
+        self.mask.magnitude().count_ones()
+    }
+
+    /// Returns the value of the contiguous run of known bits ending at the most significant bit
+    /// of the secret of given bit length and the length of the run, as used by Coppersmith attacks.
+    ///
+    #[inline(always)]
+    pub fn high_bits(&self, bits: u64) -> Option<(BigInt, u64)> {
+        let run = (0..bits).rev().take_while(|i| self.mask.bit(*i)).count() as u64;
+        if run == 0 {
+            return None;
+        }
+
+        Some((&self.value >> (bits - run), run))
+    }
+
+    /// Returns the value of the contiguous run of known bits starting at the least significant bit
+    /// and the length of the run, as used by partial key exposure attacks.
+    ///
+    #[inline(always)]
+    pub fn low_bits(&self) -> Option<(BigInt, u64)> {
+        let run = (0..).take_while(|i| self.mask.bit(*i)).count() as u64;
+        if run == 0 {
+            return None;
+        }
+
+        Some((&self.value & ((BigInt::from(1) << run) - 1), run))
+    }
+}
+
+/// PartialKey holds bits of the RSA private key recovered by side channel trace analysis.
+///
+/// It is imported from the text format, one statement per line, `#` starts a comment:
+///
+/// ```text
+/// bits 1024..1200 of d are 0x1f3a...
+/// bits 0..8 of p are 0b10110011
+/// bit 5 of q is 1
+/// ```
+///
+/// Ranges are half open and bits are numbered from the least significant bit 0.
+/// Values are hex with 0x prefix, binary with 0b prefix written most significant bit first, or decimal.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PartialKey {
+    pub secrets: BTreeMap<Secret, KnownBits>,
+}
+
+impl FromStr for PartialKey {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl PartialKey {
+    /// Parses the partial key from the text format.
+    ///
+    pub fn parse(text: &str) -> Result<Self, BilboError> {
+        let mut key = Self::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            key.parse_statement(line)
+                .map_err(|e| BilboError::GenericError(format!("line {}: {e}", number + 1)))?;
+        }
+
+        Ok(key)
+    }
+
+    /// Returns known bits of given secret.
+    ///
+    #[inline(always)]
+    pub fn get(&self, secret: Secret) -> Option<&KnownBits> {
+        self.secrets.get(&secret)
+    }
+
+    #[inline(always)]
+    fn parse_statement(&mut self, line: &str) -> Result<(), BilboError> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (range, secret, value) = match words.as_slice() {
+            ["bits", range, "of", secret, "are", value] => (*range, *secret, *value),
+            ["bit", bit, "of", secret, "is", value] => (*bit, *secret, *value),
+            _ => {
+                return Err(BilboError::GenericError(format!(
+                    "expected 'bits <lo>..<hi> of <secret> are <value>' or 'bit <i> of <secret> is <value>', found {line:?}"
+                )))
+            }
+        };
+        let (lo, hi) = match range.split_once("..") {
+            Some((lo, hi)) => (parse_index(lo)?, parse_index(hi)?),
+            None => {
+                let bit = parse_index(range)?;
+                (bit, bit + 1)
+            }
+        };
+        let value = parse_value(value, hi.saturating_sub(lo))?;
+        self.secrets
+            .entry(secret.parse()?)
+            .or_default()
+            .set(lo, hi, &value)
+    }
+}
+
+#[inline(always)]
+pub(crate) fn parse_index(s: &str) -> Result<u64, BilboError> {
+    s.parse::<u64>()
+        .map_err(|e| BilboError::GenericError(format!("invalid bit index {s}: {e}")))
+}
+
+// Parses hex, binary or decimal value, binary values must have exactly width digits
+// so leading zeros recovered from the trace are not silently dropped.
+#[inline(always)]
+pub(crate) fn parse_value(s: &str, width: u64) -> Result<BigInt, BilboError> {
+    let invalid = || BilboError::GenericError(format!("invalid value {s}"));
+    let s = s.replace('_', "");
+    if let Some(hex) = s.strip_prefix("0x") {
+        return BigInt::parse_bytes(hex.as_bytes(), 16).ok_or_else(invalid);
+    }
+    if let Some(bin) = s.strip_prefix("0b") {
+        if bin.len() as u64 != width {
+            return Err(BilboError::GenericError(format!(
+                "binary value has {} digits, range has {width} bits",
+                bin.len()
+            )));
+        }
+        return BigInt::parse_bytes(bin.as_bytes(), 2).ok_or_else(invalid);
+    }
+
+    BigInt::parse_bytes(s.as_bytes(), 10).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_import_side_channel_bits() -> Result<(), BilboError> {
+        let given = "
+# trace 42, high bits of p
+bits 12..16 of p are 0xb
+bits 8..12 of p are 0b0110   # window recovered from power trace
+bit 0 of d is 1
+bits 1..3 of d are 2
+";
+        let key: PartialKey = given.parse()?;
+        let p = key.get(Secret::P).unwrap();
+        assert_eq!(p.count(), 8);
+        assert_eq!(p.high_bits(16), Some((BigInt::from(0xb6), 8)));
+        assert_eq!(p.low_bits(), None);
+        let d = key.get(Secret::D).unwrap();
+        assert_eq!(d.low_bits(), Some((BigInt::from(0b101), 3)));
+        assert_eq!(key.get(Secret::Q), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_contradicting_bits() {
+        let given = "bits 0..4 of q are 0xf\nbit 2 of q is 0";
+        assert!(PartialKey::parse(given)
+            .unwrap_err()
+            .to_string()
+            .contains("line 2"));
+        assert!(PartialKey::parse("bits 0..4 of q are 0b101").is_err());
+        assert!(PartialKey::parse("bits 0..4 of x are 1").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use crossbeam::channel::{bounded, Receiver};
+use std::collections::{HashMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+use std::thread::{available_parallelism, spawn};
+
+static GLOBAL: OnceLock<Pool> = OnceLock::new();
+static OWNERS: AtomicU64 = AtomicU64::new(0);
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// Jobs queued per owner, the round robin order of the owners with queued jobs
+// and the number of jobs of every owner queued or running.
+#[derive(Default)]
+struct Queues {
+    jobs: HashMap<u64, VecDeque<Job>>,
+    order: VecDeque<u64>,
+    active: HashMap<u64, usize>,
+}
+
+// Counts the job as active until it is dropped, after the job returned or panicked.
+struct Active {
+    queues: Arc<(Mutex<Queues>, Condvar)>,
+    owner: u64,
+}
+
+impl Drop for Active {
+    #[inline(always)]
+    fn drop(&mut self) {
+        if let Ok(mut queues) = self.queues.0.lock() {
+            if let Some(active) = queues.active.get_mut(&self.owner) {
+                *active -= 1;
+                if *active == 0 {
+                    queues.active.remove(&self.owner);
+                }
+            }
+        }
+    }
+}
+
+/// Pool is a fixed set of worker threads shared by all the PickLocks of the process,
+/// so batch analysis of thousands of keys runs on as many threads as there are cores.
+///
+/// Jobs are scheduled fairly: each owner, usually a single PickLock, has its own queue
+/// and the workers take one job from every owner in turn, so a key with many queued jobs
+/// doesn't starve the others.
+/// Jobs shall not wait for other jobs of the pool, the workers would deadlock.
+///
+pub struct Pool {
+    queues: Arc<(Mutex<Queues>, Condvar)>,
+    workers: usize,
+}
+
+/// Task is the handle of a job queued to the pool.
+///
+pub struct Task<T> {
+    result: Receiver<T>,
+}
+
+impl<T> Task<T> {
+    /// Waits for the job to finish and returns its result, None if the job panicked.
+    /// The job no longer counts as active once joined.
+    ///
+    #[inline(always)]
+    pub fn join(self) -> Option<T> {
+        self.result.recv().ok()
+    }
+}
+
+impl Pool {
+    /// Creates new pool of given number of worker threads, at least one.
+    ///
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        let queues = Arc::new((Mutex::new(Queues::default()), Condvar::new()));
+        for _ in 0..workers {
+            let queues = queues.clone();
+            spawn(move || loop {
+                let job = {
+                    let (lock, ready) = &*queues;
+                    let Ok(mut queues) = lock.lock() else {
+                        return;
+                    };
+                    loop {
+                        if let Some(job) = queues.next() {
+                            break job;
+                        }
+                        queues = match ready.wait(queues) {
+                            Ok(queues) => queues,
+                            Err(_) => return,
+                        };
+                    }
+                };
+                // A panicking job drops its result sender, the task joins to None.
+                let _ = catch_unwind(AssertUnwindSafe(job));
+            });
+        }
+
+        Self { queues, workers }
+    }
+
+    /// Returns the pool shared by the whole crate, one worker per available core.
+    ///
+    #[inline(always)]
+    pub fn global() -> &'static Pool {
+        GLOBAL.get_or_init(|| Pool::new(available_parallelism().map_or(1, |n| n.get())))
+    }
+
+    /// Returns new owner id, jobs of the same owner share a queue.
+    ///
+    #[inline(always)]
+    pub fn owner() -> u64 {
+        OWNERS.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Returns the number of worker threads.
+    ///
+    #[inline(always)]
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Returns the number of jobs of the owner queued or running,
+    /// zero once the attacks of the owner returned, they join all the jobs they queued.
+    ///
+    #[inline(always)]
+    pub fn active(&self, owner: u64) -> usize {
+        self.queues
+            .0
+            .lock()
+            .map_or(0, |queues| queues.active.get(&owner).copied().unwrap_or(0))
+    }
+
+    /// Queues the job of given owner and returns its task handle.
+    ///
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        owner: u64,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> Task<T> {
+        let (tx, rx) = bounded(1);
+        let active = Active {
+            queues: self.queues.clone(),
+            owner,
+        };
+        let job: Job = Box::new(move || {
+            let result = job();
+            drop(active);
+            let _ = tx.send(result);
+        });
+        let (lock, ready) = &*self.queues;
+        if let Ok(mut queues) = lock.lock() {
+            *queues.active.entry(owner).or_default() += 1;
+            let jobs = queues.jobs.entry(owner).or_default();
+            let idle = jobs.is_empty();
+            jobs.push_back(job);
+            if idle {
+                queues.order.push_back(owner);
+            }
+            ready.notify_one();
+        }
+
+        Task { result: rx }
+    }
+}
+
+impl Queues {
+    // Takes the next job of the owner in turn, the owner goes to the back if it has more jobs queued.
+    #[inline(always)]
+    fn next(&mut self) -> Option<Job> {
+        let owner = self.order.pop_front()?;
+        let jobs = self.jobs.get_mut(&owner)?;
+        let job = jobs.pop_front();
+        if jobs.is_empty() {
+            self.jobs.remove(&owner);
+        } else {
+            self.order.push_back(owner);
+        }
+
+        job
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_schedule_jobs_of_owners_in_turn() {
+        let pool = Pool::new(1);
+        let (gate_tx, gate_rx) = bounded::<()>(0);
+        let gate = pool.spawn(Pool::owner(), move || gate_rx.recv().is_ok());
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (a, b) = (Pool::owner(), Pool::owner());
+        let mut tasks = Vec::new();
+        for (owner, name) in [(a, "a"), (a, "a"), (a, "a"), (b, "b"), (b, "b")] {
+            let order = order.clone();
+            tasks.push(pool.spawn(owner, move || order.lock().unwrap().push(name)));
+        }
+        let _ = gate_tx.send(());
+
+        assert_eq!(gate.join(), Some(true));
+        tasks.into_iter().for_each(|t| assert!(t.join().is_some()));
+        assert_eq!(*order.lock().unwrap(), vec!["a", "b", "a", "b", "a"]);
+        assert!(pool.spawn(a, || panic!("job failure")).join().is_none());
+        assert_eq!(pool.spawn(b, || 42).join(), Some(42));
+        assert_eq!((pool.active(a), pool.active(b)), (0, 0));
+        assert!(Pool::global().workers() >= 1);
+    }
+}
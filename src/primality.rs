@@ -0,0 +1,230 @@
+use num_bigint::{BigInt, Sign};
+use num_prime::nt_funcs::is_prime;
+use num_prime::PrimalityTestConfig;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use crate::errors::BilboError;
+use crate::modmath::Modulus;
+use crate::sieve;
+
+// Miller–Rabin with the first 12 prime bases, 2 to 37, is exact for all n < 3.3 · 10^24.
+const DETERMINISTIC_U64_BASES: usize = 12;
+// Bound of the prime bases, there are 168 primes below it.
+const BASES_BOUND: u64 = 1_000;
+
+static POLICY: RwLock<PrimalityPolicy> = RwLock::new(PrimalityPolicy::bpsw());
+
+/// PrimalityPolicy is the confidence and speed trade-off of the primality checks in validation paths.
+///
+///  - miller_rabin_rounds, Miller–Rabin rounds with the first prime bases 2, 3, 5, … up to 168 rounds.
+///  - bpsw, adds strong Lucas test to the base 2 round, Baillie–PSW has no known pseudoprime.
+///  - deterministic_u64, inputs below 2^64 are decided exactly with the first 12 prime bases instead.
+///
+/// Presets, also accepted by name from the command line:
+///  - fast, a single base 2 round, cheap filter with rare false positives on adversarial input.
+///  - bpsw, the default, Baillie–PSW.
+///  - strict, Baillie–PSW followed by 8 more Miller–Rabin rounds.
+///  - a number N, N Miller–Rabin rounds without Lucas test.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrimalityPolicy {
+    pub miller_rabin_rounds: usize,
+    pub bpsw: bool,
+    pub deterministic_u64: bool,
+}
+
+impl Default for PrimalityPolicy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::bpsw()
+    }
+}
+
+impl Display for PrimalityPolicy {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (self.miller_rabin_rounds, self.bpsw) {
+            (1, false) if *self == Self::fast() => write!(f, "fast"),
+            (1, true) if *self == Self::bpsw() => write!(f, "bpsw"),
+            _ if *self == Self::strict() => write!(f, "strict"),
+            (rounds, false) => write!(f, "{rounds} miller-rabin rounds"),
+            (rounds, true) => write!(f, "bpsw with {rounds} miller-rabin rounds"),
+        }
+    }
+}
+
+impl FromStr for PrimalityPolicy {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fast" => Ok(Self::fast()),
+            "bpsw" => Ok(Self::bpsw()),
+            "strict" => Ok(Self::strict()),
+            _ => match s.parse::<usize>() {
+                Ok(rounds) if rounds > 0 => Ok(Self {
+                    miller_rabin_rounds: rounds,
+                    bpsw: false,
+                    deterministic_u64: true,
+                }),
+                _ => Err(BilboError::GenericError(format!(
+                    "unknown primality policy {s}, expected fast, bpsw, strict or number of Miller-Rabin rounds"
+                ))),
+            },
+        }
+    }
+}
+
+impl PrimalityPolicy {
+    /// Single Miller–Rabin round with base 2.
+    ///
+    #[inline(always)]
+    pub const fn fast() -> Self {
+        Self {
+            miller_rabin_rounds: 1,
+            bpsw: false,
+            deterministic_u64: true,
+        }
+    }
+
+    /// Baillie–PSW, Miller–Rabin round with base 2 and strong Lucas test.
+    ///
+    #[inline(always)]
+    pub const fn bpsw() -> Self {
+        Self {
+            miller_rabin_rounds: 1,
+            bpsw: true,
+            deterministic_u64: true,
+        }
+    }
+
+    /// Baillie–PSW with additional Miller–Rabin rounds, 9 in total.
+    ///
+    #[inline(always)]
+    pub const fn strict() -> Self {
+        Self {
+            miller_rabin_rounds: 9,
+            bpsw: true,
+            deterministic_u64: true,
+        }
+    }
+
+    /// Returns true if n is prime according to this policy, negative numbers, 0 and 1 are not.
+    ///
+    #[inline(always)]
+    pub fn is_prime(&self, n: &BigInt) -> bool {
+        if n.sign() != Sign::Plus || n == &BigInt::from(1) {
+            return false;
+        }
+        let deterministic = self.deterministic_u64 && n.bits() <= u64::BITS as u64;
+        let rounds = if deterministic {
+            DETERMINISTIC_U64_BASES
+        } else {
+            self.miller_rabin_rounds.max(1)
+        };
+        let bases = sieve::primes(BASES_BOUND);
+        let modulus = Modulus::new(n);
+        for base in bases.iter().take(rounds) {
+            if n == &BigInt::from(*base) {
+                return true;
+            }
+            if !strong_probable_prime(&modulus, *base) {
+                return false;
+            }
+        }
+        if deterministic || !self.bpsw {
+            return true;
+        }
+        let Some(n) = n.to_biguint() else {
+            return false;
+        };
+
+        is_prime(&n, Some(PrimalityTestConfig::bpsw())).probably()
+    }
+}
+
+// Miller–Rabin round, n − 1 = d · 2^s and n is strong probable prime to base a
+// if a^d ≡ 1 or a^(d·2^r) ≡ −1 mod n for some r < s. The squarings stay in Montgomery form of n.
+#[inline(always)]
+fn strong_probable_prime(modulus: &Modulus, base: u64) -> bool {
+    let n = modulus.n();
+    let n_minus_one: BigInt = n - 1;
+    if n_minus_one.bits() == 0 || (n % 2_u32).bits() == 0 {
+        return n == &BigInt::from(2);
+    }
+    let s = n_minus_one.trailing_zeros().unwrap_or_default();
+    let d = &n_minus_one >> s;
+    let (one, minus_one) = (modulus.one(), modulus.residue(&n_minus_one));
+    let mut x = modulus.pow(&modulus.residue(&BigInt::from(base)), &d);
+    if x == one || x == minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = modulus.square(&x);
+        if x == minus_one {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Returns the crate wide primality policy used by every validation path.
+///
+#[inline(always)]
+pub fn policy() -> PrimalityPolicy {
+    POLICY.read().map(|p| *p).unwrap_or_default()
+}
+
+/// Sets the crate wide primality policy used by every validation path.
+///
+#[inline(always)]
+pub fn set_policy(policy: PrimalityPolicy) {
+    if let Ok(mut p) = POLICY.write() {
+        *p = policy;
+    }
+}
+
+/// Returns true if n is prime according to the crate wide policy.
+///
+#[inline(always)]
+pub fn is_probable_prime(n: &BigInt) -> bool {
+    policy().is_prime(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_check_primality_by_configured_policy() -> Result<(), BilboError> {
+        // 3215031751 = 151 · 751 · 28351 is strong pseudoprime to bases 2, 3, 5 and 7.
+        let pseudoprime = BigInt::from(3_215_031_751_u64);
+        let mersenne = (BigInt::from(1) << 127) - 1;
+
+        for name in ["fast", "bpsw", "strict", "20"] {
+            let policy = name.parse::<PrimalityPolicy>()?;
+            assert!(!policy.is_prime(&pseudoprime));
+            assert!(policy.is_prime(&mersenne));
+            assert!(!policy.is_prime(&(&mersenne * 3_u32)));
+            assert!(!policy.is_prime(&BigInt::from(1)));
+            assert!(!policy.is_prime(&BigInt::from(-7)));
+        }
+        let probabilistic = PrimalityPolicy {
+            miller_rabin_rounds: 4,
+            bpsw: false,
+            deterministic_u64: false,
+        };
+        assert!(probabilistic.is_prime(&pseudoprime));
+        assert_eq!(PrimalityPolicy::strict().to_string(), "strict");
+        assert!("0".parse::<PrimalityPolicy>().is_err());
+
+        assert_eq!(policy(), PrimalityPolicy::bpsw());
+
+        Ok(())
+    }
+}
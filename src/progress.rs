@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tui")]
+use crate::dashboard::Dashboard;
+use crate::telemetry::Telemetry;
+
+/// Effort spent by the running attack so far.
+///
+///  - iterations, steps of the attack main loop done so far.
+///  - primes_tested, candidate primes checked for dividing the modulus so far.
+///  - elapsed, wall clock time since the attack started.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Effort {
+    pub iterations: u64,
+    pub primes_tested: u64,
+    pub elapsed: Duration,
+}
+
+impl Effort {
+    /// Takes the counters of the telemetry collected so far and the time elapsed since given start.
+    ///
+    #[inline(always)]
+    pub fn of(telemetry: &Telemetry, start: Instant) -> Self {
+        Self {
+            iterations: telemetry.iterations,
+            primes_tested: telemetry.primes_tested,
+            elapsed: start.elapsed(),
+        }
+    }
+}
+
+/// ProgressEvent is sent by the long running attacks to the ProgressSink given to them.
+///
+///  - Started, the attack of given name started, it takes total units, stages or relations.
+///  - Advanced, the attack reached done of total units, step names the unit reached.
+///  - Finished, the attack ended, cracked or not, summary tells what was covered.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    Started {
+        attack: &'static str,
+        total: usize,
+    },
+    Advanced {
+        attack: &'static str,
+        step: String,
+        done: usize,
+        total: usize,
+        effort: Effort,
+    },
+    Finished {
+        attack: &'static str,
+        cracked: bool,
+        summary: String,
+        effort: Effort,
+    },
+}
+
+/// ProgressSink receives progress of the long running attacks, so GUIs and services render it their own way.
+/// Attacks call it on the thread they were called on.
+///
+pub trait ProgressSink {
+    /// Receives the progress event.
+    ///
+    fn progress(&mut self, event: ProgressEvent);
+}
+
+/// SilentSink drops all the progress events.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SilentSink;
+
+impl ProgressSink for SilentSink {
+    #[inline(always)]
+    fn progress(&mut self, _event: ProgressEvent) {}
+}
+
+impl ProgressSink for Sender<ProgressEvent> {
+    // Progress is best effort, a receiver that hung up doesn't stop the attack.
+    #[inline(always)]
+    fn progress(&mut self, event: ProgressEvent) {
+        let _ = self.send(event);
+    }
+}
+
+/// ConsoleSink renders the progress in the terminal,
+/// as a live dashboard when tui feature is enabled or as a table printed to stdout otherwise,
+/// a row every stage or every tenth of the total units.
+///
+#[derive(Default)]
+pub struct ConsoleSink {
+    #[cfg(feature = "tui")]
+    dashboard: Option<Dashboard>,
+    #[cfg(feature = "tui")]
+    ids: HashMap<&'static str, (usize, usize)>,
+    #[cfg(not(feature = "tui"))]
+    tenths: HashMap<&'static str, usize>,
+}
+
+impl ConsoleSink {
+    /// Creates new console sink.
+    ///
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProgressSink for ConsoleSink {
+    #[cfg(feature = "tui")]
+    fn progress(&mut self, event: ProgressEvent) {
+        let dashboard = self.dashboard.get_or_insert_with(Dashboard::default);
+        match event {
+            ProgressEvent::Started { attack, total } => {
+                let id = dashboard.add_attack(&attack.replace('_', " "), total);
+                self.ids.insert(attack, (id, 0));
+            }
+            ProgressEvent::Advanced { attack, done, .. } => {
+                if let Some((id, shown)) = self.ids.get_mut(attack) {
+                    let _ = dashboard.advance(*id, done.saturating_sub(*shown));
+                    *shown = done;
+                }
+            }
+            ProgressEvent::Finished {
+                attack,
+                cracked,
+                summary,
+                ..
+            } => {
+                if cracked {
+                    let _ = dashboard.finding("private exponent recovered");
+                } else {
+                    let _ = dashboard.finding(&summary);
+                }
+                if let Some((id, _)) = self.ids.remove(attack) {
+                    let _ = dashboard.finish(id);
+                }
+            }
+        }
+    }
+
+    #[cfg(not(feature = "tui"))]
+    fn progress(&mut self, event: ProgressEvent) {
+        match event {
+            ProgressEvent::Started { attack, .. } => {
+                println!("[ {0: <14} ]", attack.to_uppercase());
+                self.tenths.insert(attack, 0);
+            }
+            ProgressEvent::Advanced {
+                attack,
+                step,
+                done,
+                total,
+                ..
+            } => {
+                let tenths = done * 10 / total.max(1);
+                if let Some(shown) = self.tenths.get_mut(attack).filter(|shown| tenths > **shown) {
+                    *shown = tenths;
+                    println!("| {0: <14} |", step);
+                }
+            }
+            ProgressEvent::Finished {
+                attack,
+                summary,
+                effort,
+                ..
+            } => {
+                self.tenths.remove(attack);
+                println!("| {0: <14} |", "----FINAL-----");
+                println!(
+                    "{summary}, {} iterations, {} primes tested in {} ms",
+                    effort.iterations,
+                    effort.primes_tested,
+                    effort.elapsed.as_millis()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn it_should_send_progress_events_over_channel() {
+        let (mut tx, rx) = channel();
+        let telemetry = Telemetry {
+            iterations: 7,
+            primes_tested: 3,
+            ..Telemetry::default()
+        };
+        tx.progress(ProgressEvent::Started {
+            attack: "strong",
+            total: 2,
+        });
+        tx.progress(ProgressEvent::Advanced {
+            attack: "strong",
+            step: "rho".to_string(),
+            done: 1,
+            total: 2,
+            effort: Effort::of(&telemetry, Instant::now()),
+        });
+        drop(tx);
+
+        let events: Vec<ProgressEvent> = rx.iter().collect();
+        assert_eq!(events.len(), 2);
+        let ProgressEvent::Advanced { effort, .. } = &events[1] else {
+            panic!("expected advanced event, got {:?}", events[1]);
+        };
+        assert_eq!((effort.iterations, effort.primes_tested), (7, 3));
+    }
+}
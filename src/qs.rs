@@ -0,0 +1,724 @@
+use crossbeam::channel::unbounded;
+use num_bigint::BigInt;
+use num_integer::Integer;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::factor::trial_division;
+use crate::modmath::Modulus;
+use crate::pool::Pool;
+use crate::race::Cancel;
+use crate::sieve;
+use crate::telemetry::Telemetry;
+
+/// Smallest modulus, in bits, factored by the quadratic sieve, smaller ones are the domain of SQUFOF and Lehman's method.
+///
+pub const MIN_QS_BITS: u64 = 64;
+
+/// Largest modulus, in bits, the quadratic sieve is tuned for, about 100 decimal digits.
+///
+pub const MAX_QS_BITS: u64 = 332;
+
+// Sieve parameters by modulus bits, up to: factor base size and half width M of the sieve interval [-M, M).
+const PARAMETERS: [(u64, usize, usize); 11] = [
+    (80, 100, 16_384),
+    (100, 200, 32_768),
+    (130, 450, 32_768),
+    (160, 900, 32_768),
+    (185, 2_000, 32_768),
+    (200, 3_000, 65_536),
+    (215, 5_400, 98_304),
+    (235, 10_000, 98_304),
+    (250, 16_000, 131_072),
+    (280, 24_000, 196_608),
+    (MAX_QS_BITS, 30_000, 327_680),
+];
+// Knuth-Schroeppel candidates, odd square free multipliers k of the sieved k · n.
+const MULTIPLIERS: [u64; 24] = [
+    1, 3, 5, 7, 11, 13, 15, 17, 19, 21, 23, 29, 31, 33, 35, 37, 39, 41, 43, 47, 51, 53, 55, 57,
+];
+// Bytes of the sieve interval sieved at once, the L1 data cache.
+const SIEVE_BLOCK: usize = 32_768;
+// Primes below it are not sieved, their small logarithms cost more than they help, the threshold accounts for them.
+const SIEVE_MIN_PRIME: u32 = 30;
+// Bits of the sieve threshold left for the skipped small primes and the rounding of the logarithms.
+const THRESHOLD_SLACK: u64 = 18;
+// Cofactor left by trial division up to this multiple of the largest prime of the factor base is kept
+// as the large prime of a partial relation, two partials of the same large prime combine into a full one.
+const LARGE_PRIME_MULTIPLIER: u64 = 64;
+// Relations collected beyond the number of columns, each of them almost surely adds a dependency.
+const EXTRA_RELATIONS: usize = 64;
+// Ideal bits of the primes of coefficient A, small enough for many combinations, large enough to sieve well.
+const A_PRIME_BITS: f64 = 11.0;
+// Primes near the ideal size the factors of A but the last one are drawn from.
+const A_POOL: usize = 40;
+// Draws of A in a row a worker tries before it decides all the combinations were used.
+const A_ATTEMPTS: usize = 100;
+
+// Relation (A · x + B)² ≡ product of the primes of its columns · large² mod n, column 0 is the sign.
+// Partial relation, not yet combined, has the single large prime instead of its square.
+struct Relation {
+    y: BigInt,
+    columns: Vec<u32>,
+    large: u64,
+}
+
+// Factor base and parameters of the sieve shared by the workers.
+struct Sieve {
+    kn: BigInt,
+    primes: Vec<u32>,
+    roots: Vec<u32>,
+    logs: Vec<u8>,
+    m: usize,
+    threshold: u8,
+    large_prime_bound: u64,
+    target_ln: f64,
+    a_factors: usize,
+    a_pool: Vec<usize>,
+}
+
+impl Sieve {
+    // Builds the factor base of k · n, the primes p with k · n a quadratic residue modulo p and the square roots of it.
+    // Returns Err with a prime of the factor base range dividing n.
+    #[inline(always)]
+    fn new(n: &BigInt, telemetry: &mut Telemetry, cancel: &Cancel) -> Result<Self, BigInt> {
+        let bits = n.bits();
+        let (_, size, m) = PARAMETERS
+            .iter()
+            .find(|(max, ..)| bits <= *max)
+            .copied()
+            .unwrap_or(PARAMETERS[PARAMETERS.len() - 1]);
+        let kn = n * knuth_schroeppel(n);
+
+        let mut bound = ((2 * size) as f64 * ((2 * size) as f64).ln() * 1.5) as u64;
+        let (primes, roots) = loop {
+            if let Some(p) = trial_division(n, bound, telemetry, cancel) {
+                return Err(p);
+            }
+            let mut primes = Vec::with_capacity(size);
+            let mut roots = Vec::with_capacity(size);
+            for p in sieve::primes(bound).iter().copied() {
+                let residue = u64::try_from(&kn % p).unwrap_or_default();
+                if let Some(root) = sqrt_mod(residue, p) {
+                    primes.push(p as u32);
+                    roots.push(root as u32);
+                }
+                if primes.len() == size {
+                    break;
+                }
+            }
+            if primes.len() == size {
+                break (primes, roots);
+            }
+            bound *= 2;
+        };
+        telemetry.primes_tested += primes.len() as u64;
+
+        let largest = u64::from(primes[primes.len() - 1]);
+        let large_prime_bound = largest.saturating_mul(LARGE_PRIME_MULTIPLIER.min(largest));
+        let interval_bits = kn.bits() / 2 + (usize::BITS - m.leading_zeros()) as u64;
+        let large_prime_bits = 64 - u64::from(large_prime_bound.leading_zeros());
+        let threshold = interval_bits
+            .saturating_sub(large_prime_bits + THRESHOLD_SLACK)
+            .clamp(1, u64::from(u8::MAX)) as u8;
+        let logs = primes
+            .iter()
+            .map(|p| f64::from(*p).log2().round() as u8)
+            .collect();
+
+        // A ≈ √(2 · k · n) / M keeps the polynomial values small over the whole interval.
+        let target_ln = ((&kn * 2_u32).sqrt() / m).bits() as f64 * std::f64::consts::LN_2;
+        let ideal_bits = target_ln / std::f64::consts::LN_2;
+        let mut a_factors = ((ideal_bits / A_PRIME_BITS).round() as usize).max(2);
+        while ideal_bits / (a_factors as f64) > (largest as f64 / 2.0).log2() {
+            a_factors += 1;
+        }
+        let ideal_ln = target_ln / a_factors as f64;
+        let mut a_pool: Vec<usize> = (0..primes.len())
+            .filter(|i| primes[*i] > 3 && roots[*i] != 0)
+            .collect();
+        a_pool.sort_by(|i, j| {
+            let distance = |p: u32| (f64::from(p).ln() - ideal_ln).abs();
+            distance(primes[*i]).total_cmp(&distance(primes[*j]))
+        });
+        a_pool.truncate(A_POOL.max(2 * a_factors));
+
+        Ok(Self {
+            kn,
+            primes,
+            roots,
+            logs,
+            m,
+            threshold,
+            large_prime_bound,
+            target_ln,
+            a_factors,
+            a_pool,
+        })
+    }
+
+    // Number of columns of the relations, the sign and the primes of the factor base.
+    #[inline(always)]
+    fn columns(&self) -> usize {
+        self.primes.len() + 1
+    }
+
+    // Draws new coefficient A as indices of its primes in the factor base, all but the last from the pool
+    // and the last one bringing the product closest to the target, None if no new combination is found.
+    #[inline(always)]
+    fn choose_a(&self, rng: &mut impl Rng, used: &mut HashSet<Vec<usize>>) -> Option<Vec<usize>> {
+        for _ in 0..A_ATTEMPTS {
+            let mut chosen = Vec::with_capacity(self.a_factors);
+            while chosen.len() + 1 < self.a_factors {
+                let i = self.a_pool[rng.gen_range(0..self.a_pool.len())];
+                if !chosen.contains(&i) {
+                    chosen.push(i);
+                }
+            }
+            let rest_ln = self.target_ln
+                - chosen
+                    .iter()
+                    .map(|i| f64::from(self.primes[*i]).ln())
+                    .sum::<f64>();
+            let last = (0..self.primes.len())
+                .filter(|i| self.primes[*i] > 3 && self.roots[*i] != 0 && !chosen.contains(i))
+                .min_by(|i, j| {
+                    let distance = |p: u32| (f64::from(p).ln() - rest_ln).abs();
+                    distance(self.primes[*i]).total_cmp(&distance(self.primes[*j]))
+                })?;
+            chosen.push(last);
+            chosen.sort_unstable();
+            if used.insert(chosen.clone()) {
+                return Some(chosen);
+            }
+        }
+
+        None
+    }
+
+    // Sieves the 2^(s - 1) polynomials (A · x + B)² − k · n of coefficient A of s primes, switching B in Gray code order
+    // so the roots of every next polynomial are updated by a single addition per prime, and collects their relations.
+    // Gives up the rest of the polynomials when cancelled.
+    fn sieve_family(
+        &self,
+        a_indices: &[usize],
+        telemetry: &mut Telemetry,
+        cancel: &Cancel,
+    ) -> Vec<Relation> {
+        let a: BigInt = a_indices
+            .iter()
+            .map(|i| BigInt::from(self.primes[*i]))
+            .product();
+        let mut b_terms = Vec::with_capacity(a_indices.len());
+        for i in a_indices {
+            let q = u64::from(self.primes[*i]);
+            let rest = &a / q;
+            let inverse = mod_pow(u64::try_from(&rest % q).unwrap_or_default(), q - 2, q);
+            let mut gamma = u64::from(self.roots[*i]) * inverse % q;
+            if gamma > q / 2 {
+                gamma = q - gamma;
+            }
+            b_terms.push(rest * gamma);
+        }
+        let mut b: BigInt = b_terms.iter().sum();
+
+        let width = 2 * self.m;
+        let mut in_a = vec![false; self.primes.len()];
+        a_indices.iter().for_each(|i| in_a[*i] = true);
+        let mut roots1 = vec![0_u32; self.primes.len()];
+        let mut roots2 = vec![0_u32; self.primes.len()];
+        let mut deltas = vec![vec![0_u32; self.primes.len()]; b_terms.len()];
+        for (i, p) in self.primes.iter().enumerate() {
+            if in_a[i] {
+                continue;
+            }
+            let p = u64::from(*p);
+            let a_inverse = mod_pow(u64::try_from(&a % p).unwrap_or_default(), p - 2, p);
+            for (delta, term) in deltas.iter_mut().zip(b_terms.iter()) {
+                delta[i] = (2 * u64::try_from(term % p).unwrap_or_default() * a_inverse % p) as u32;
+            }
+            let b_mod = u64::try_from(&b % p).unwrap_or_default();
+            let t = u64::from(self.roots[i]);
+            let shift = self.m as u64 % p;
+            roots1[i] = ((a_inverse * ((t + p - b_mod) % p) + shift) % p) as u32;
+            roots2[i] = ((a_inverse * ((2 * p - t - b_mod) % p) + shift) % p) as u32;
+        }
+
+        let mut relations = Vec::new();
+        let mut sieve = vec![0_u8; SIEVE_BLOCK];
+        let (mut next1, mut next2) = (roots1.clone(), roots2.clone());
+        let first_sieved = self.primes.partition_point(|p| *p < SIEVE_MIN_PRIME);
+        let polynomials = 1_usize << (a_indices.len() - 1);
+        for index in 0..polynomials {
+            if cancel.is_cancelled() {
+                break;
+            }
+            if index > 0 {
+                // Gray code step flips the sign of term v of B.
+                let v = index.trailing_zeros() as usize;
+                let minus = (index.div_ceil(1 << (v + 1))) % 2 == 1;
+                if minus {
+                    b -= &b_terms[v] * 2_u32;
+                } else {
+                    b += &b_terms[v] * 2_u32;
+                }
+                for (i, p) in self.primes.iter().enumerate() {
+                    let delta = deltas[v][i];
+                    let step = if minus || delta == 0 {
+                        delta
+                    } else {
+                        p - delta
+                    };
+                    roots1[i] = add_mod(roots1[i], step, *p);
+                    roots2[i] = add_mod(roots2[i], step, *p);
+                }
+            }
+            let c = (&b * &b - &self.kn) / &a;
+            telemetry.iterations += 1;
+            telemetry.candidates_sieved += width as u64;
+
+            // Blocks of the interval fit the cache, every prime carries its next position over to the next block.
+            next1.copy_from_slice(&roots1);
+            next2.copy_from_slice(&roots2);
+            for block in (0..width).step_by(SIEVE_BLOCK) {
+                let end = (block + SIEVE_BLOCK).min(width);
+                sieve.fill(0);
+                for (i, p) in self.primes.iter().enumerate().skip(first_sieved) {
+                    if in_a[i] {
+                        continue;
+                    }
+                    let (log, p) = (self.logs[i], *p as usize);
+                    let mut pos = next1[i] as usize;
+                    while pos < end {
+                        sieve[pos - block] = sieve[pos - block].saturating_add(log);
+                        pos += p;
+                    }
+                    next1[i] = pos as u32;
+                    if roots2[i] == roots1[i] {
+                        continue;
+                    }
+                    let mut pos = next2[i] as usize;
+                    while pos < end {
+                        sieve[pos - block] = sieve[pos - block].saturating_add(log);
+                        pos += p;
+                    }
+                    next2[i] = pos as u32;
+                }
+
+                for (offset, _) in sieve[..end - block]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, log)| **log >= self.threshold)
+                {
+                    let pos = block + offset;
+                    let x = pos as i64 - self.m as i64;
+                    let value = (&a * x + &b * 2_u32) * x + &c;
+                    if let Some(relation) =
+                        self.trial_divide(value, pos, a_indices, &in_a, &roots1, &roots2)
+                    {
+                        relations.push(Relation {
+                            y: &a * x + &b,
+                            ..relation
+                        });
+                    }
+                }
+            }
+        }
+
+        relations
+    }
+
+    // Factors the value (A · x + B)² / A − k · n / A of the sieve position over the factor base, the primes dividing it
+    // are the ones whose roots meet the position. Returns the relation, y left to the caller,
+    // if the value is smooth or its cofactor a large prime below the bound.
+    #[inline(always)]
+    fn trial_divide(
+        &self,
+        mut value: BigInt,
+        pos: usize,
+        a_indices: &[usize],
+        in_a: &[bool],
+        roots1: &[u32],
+        roots2: &[u32],
+    ) -> Option<Relation> {
+        let mut columns: Vec<u32> = a_indices.iter().map(|i| *i as u32 + 1).collect();
+        if value < BigInt::from(0) {
+            columns.push(0);
+            value = -value;
+        }
+        for (i, p) in self.primes.iter().enumerate() {
+            let offset = (pos % *p as usize) as u32;
+            if !in_a[i] && offset != roots1[i] && offset != roots2[i] {
+                continue;
+            }
+            let (mut quotient, mut remainder) = value.div_rem(&BigInt::from(*p));
+            while remainder == BigInt::from(0) {
+                columns.push(i as u32 + 1);
+                value = quotient;
+                (quotient, remainder) = value.div_rem(&BigInt::from(*p));
+            }
+        }
+
+        let large = u64::try_from(&value)
+            .ok()
+            .filter(|l| *l < self.large_prime_bound)?;
+        Some(Relation {
+            y: BigInt::from(0),
+            columns,
+            large,
+        })
+    }
+}
+
+/// Factors n of MIN_QS_BITS up to MAX_QS_BITS by the self-initializing quadratic sieve and returns a non trivial factor.
+/// Polynomials (A · x + B)² − k · n of A made of several primes of the factor base share the costly initialization,
+/// every B of the same A is switched to in Gray code order by a single addition per prime,
+/// and values of the polynomials smooth over the factor base are found by sieving with logarithms.
+/// Sieving runs in parallel on the shared pool with given owner, in given number of jobs, each on its own coefficients A,
+/// relations with a single large prime are kept and combined in pairs of the same large prime.
+/// Once there are more relations than primes of the factor base, Gaussian elimination over GF(2)
+/// finds subsets of them whose product is a square, x² ≡ y² mod n, and gcd(x − y, n) splits n.
+///
+/// Progress is called with the number of collected relations, the number needed and the telemetry so far
+/// after every batch of them.
+/// Returns None for moduli out of range, primes and when cancelled.
+///
+pub fn quadratic_sieve(
+    n: &BigInt,
+    telemetry: &mut Telemetry,
+    cancel: &Cancel,
+    owner: u64,
+    jobs: usize,
+    mut progress: impl FnMut(usize, usize, &Telemetry),
+) -> Option<BigInt> {
+    if n.bits() < MIN_QS_BITS || n.bits() > MAX_QS_BITS {
+        return None;
+    }
+    let root = n.sqrt();
+    if &root * &root == *n {
+        return Some(root);
+    }
+    let sieve = match Sieve::new(n, telemetry, cancel) {
+        Ok(sieve) => Arc::new(sieve),
+        Err(p) => return Some(p),
+    };
+    let needed = sieve.columns() + EXTRA_RELATIONS;
+
+    let (tx, rx) = unbounded();
+    let stop = Arc::new(AtomicBool::new(false));
+    let mut tasks = Vec::with_capacity(jobs.max(1));
+    for _ in 0..jobs.max(1) {
+        let (tx, sieve, cancel, stop) = (tx.clone(), sieve.clone(), cancel.clone(), stop.clone());
+        tasks.push(Pool::global().spawn(owner, move || {
+            let mut rng = rand::thread_rng();
+            let mut used = HashSet::new();
+            while !cancel.is_cancelled() && !stop.load(Ordering::Relaxed) {
+                let Some(a) = sieve.choose_a(&mut rng, &mut used) else {
+                    break;
+                };
+                let mut telemetry = Telemetry::default();
+                let relations = sieve.sieve_family(&a, &mut telemetry, &cancel);
+                // Collector hung up, enough relations were found.
+                if tx.send((relations, telemetry)).is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+    drop(tx);
+
+    let mut full = Vec::with_capacity(needed);
+    let mut seen = HashSet::new();
+    let mut partials: HashMap<u64, Relation> = HashMap::new();
+    for (relations, worker) in rx.iter() {
+        telemetry.merge(&worker);
+        for relation in relations {
+            if !seen.insert(relation.y.clone()) {
+                continue;
+            }
+            if relation.large == 1 {
+                full.push(relation);
+                continue;
+            }
+            match partials.get(&relation.large) {
+                Some(other) => full.push(Relation {
+                    y: (&other.y * &relation.y).mod_floor(n),
+                    columns: [other.columns.as_slice(), relation.columns.as_slice()].concat(),
+                    large: relation.large,
+                }),
+                None => {
+                    partials.insert(relation.large, relation);
+                }
+            }
+        }
+        progress(full.len().min(needed), needed, telemetry);
+        if full.len() >= needed {
+            break;
+        }
+    }
+    // The jobs finish the family they sieve and stop, joined so none keeps sieving after the sieve returned.
+    stop.store(true, Ordering::Relaxed);
+    drop(rx);
+    tasks.into_iter().for_each(|task| {
+        task.join();
+    });
+    if full.len() < needed {
+        return None;
+    }
+
+    let one = BigInt::from(1);
+    dependencies(&full, sieve.columns())
+        .into_iter()
+        .find_map(|subset| {
+            telemetry.iterations += 1;
+            let (x, y) = square_root(n, &sieve.primes, &full, &subset);
+            let g = (x - y).gcd(n);
+            (g != one && &g != n).then_some(g)
+        })
+}
+
+// Returns subsets of the relations whose products are squares, the null space of their exponent parities over GF(2)
+// found by Gaussian elimination of the relations, each row tracking the relations it was combined of.
+fn dependencies(relations: &[Relation], columns: usize) -> Vec<Vec<usize>> {
+    let words = columns.div_ceil(64);
+    let history_words = relations.len().div_ceil(64);
+    let mut rows: Vec<(Vec<u64>, Vec<u64>)> = relations
+        .iter()
+        .enumerate()
+        .map(|(r, relation)| {
+            let mut bits = vec![0_u64; words];
+            for column in relation.columns.iter() {
+                bits[*column as usize / 64] ^= 1 << (column % 64);
+            }
+            let mut history = vec![0_u64; history_words];
+            history[r / 64] |= 1 << (r % 64);
+            (bits, history)
+        })
+        .collect();
+
+    let mut pivot = 0;
+    for column in 0..columns {
+        let (word, bit) = (column / 64, 1 << (column % 64));
+        let Some(found) = (pivot..rows.len()).find(|r| rows[*r].0[word] & bit != 0) else {
+            continue;
+        };
+        rows.swap(pivot, found);
+        let (head, tail) = rows.split_at_mut(pivot + 1);
+        let (pivot_bits, pivot_history) = &head[pivot];
+        for (bits, history) in tail.iter_mut().filter(|(bits, _)| bits[word] & bit != 0) {
+            bits.iter_mut().zip(pivot_bits).for_each(|(b, p)| *b ^= p);
+            history
+                .iter_mut()
+                .zip(pivot_history)
+                .for_each(|(h, p)| *h ^= p);
+        }
+        pivot += 1;
+    }
+
+    rows[pivot..]
+        .iter()
+        .map(|(_, history)| {
+            (0..relations.len())
+                .filter(|r| history[r / 64] & (1 << (r % 64)) != 0)
+                .collect()
+        })
+        .collect()
+}
+
+// Returns x, the product of y of the relations, and y, the square root of the product of their smooth values, mod n.
+fn square_root(
+    n: &BigInt,
+    primes: &[u32],
+    relations: &[Relation],
+    subset: &[usize],
+) -> (BigInt, BigInt) {
+    let mut x = BigInt::from(1);
+    let mut y = BigInt::from(1);
+    let mut exponents = vec![0_u32; primes.len() + 1];
+    for relation in subset.iter().map(|r| &relations[*r]) {
+        x = (x * &relation.y).mod_floor(n);
+        y = (y * relation.large) % n;
+        for column in relation.columns.iter() {
+            exponents[*column as usize] += 1;
+        }
+    }
+    let modulus = Modulus::new(n);
+    let mut root = modulus.residue(&y);
+    for (p, exponent) in primes.iter().zip(exponents.iter().skip(1)) {
+        if *exponent > 0 {
+            let power = modulus.pow(
+                &modulus.residue(&BigInt::from(*p)),
+                &BigInt::from(exponent / 2),
+            );
+            root = modulus.mul(&root, &power);
+        }
+    }
+    let y = modulus.value(&root);
+
+    (x, y)
+}
+
+// Returns the Knuth-Schroeppel multiplier k making k · n a quadratic residue modulo most of the small primes,
+// so the factor base of k · n is denser in small primes and its polynomial values smooth more often.
+#[inline(always)]
+fn knuth_schroeppel(n: &BigInt) -> u64 {
+    let primes = sieve::primes(1_000);
+    let mut best = (f64::MIN, 1);
+    for k in MULTIPLIERS {
+        let kn = n * k;
+        let ln2 = std::f64::consts::LN_2;
+        let mut score = -0.5 * (k as f64).ln()
+            + match u64::try_from(&kn % 8_u32).unwrap_or_default() {
+                1 => 2.0 * ln2,
+                5 => ln2,
+                _ => 0.5 * ln2,
+            };
+        for p in primes.iter().skip(1) {
+            let ln = (*p as f64).ln();
+            if k.is_multiple_of(*p) {
+                score += ln / *p as f64;
+            } else if sqrt_mod(u64::try_from(&kn % *p).unwrap_or_default(), *p).is_some() {
+                score += 2.0 * ln / (*p - 1) as f64;
+            }
+        }
+        if score > best.0 {
+            best = (score, k);
+        }
+    }
+
+    best.1
+}
+
+// Returns square root of a modulo prime p by Tonelli-Shanks, None if a is not a quadratic residue.
+#[inline(always)]
+fn sqrt_mod(a: u64, p: u64) -> Option<u64> {
+    let a = a % p;
+    if p == 2 || a == 0 {
+        return Some(a);
+    }
+    if mod_pow(a, (p - 1) / 2, p) != 1 {
+        return None;
+    }
+    if p % 4 == 3 {
+        return Some(mod_pow(a, (p + 1) / 4, p));
+    }
+    let (mut q, mut s) = (p - 1, 0);
+    while q.is_multiple_of(2) {
+        q /= 2;
+        s += 1;
+    }
+    let z = (2..p).find(|z| mod_pow(*z, (p - 1) / 2, p) == p - 1)?;
+    let (mut m, mut c, mut t, mut r) = (
+        s,
+        mod_pow(z, q, p),
+        mod_pow(a, q, p),
+        mod_pow(a, q.div_ceil(2), p),
+    );
+    while t != 1 {
+        let mut i = 0;
+        let mut square = t;
+        while square != 1 {
+            square = mul_mod(square, square, p);
+            i += 1;
+        }
+        let b = mod_pow(c, 1 << (m - i - 1), p);
+        m = i;
+        c = mul_mod(b, b, p);
+        t = mul_mod(t, c, p);
+        r = mul_mod(r, b, p);
+    }
+
+    Some(r)
+}
+
+#[inline(always)]
+fn add_mod(a: u32, b: u32, p: u32) -> u32 {
+    let sum = a + b;
+    if sum >= p {
+        sum - p
+    } else {
+        sum
+    }
+}
+
+#[inline(always)]
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (u128::from(a) * u128::from(b) % u128::from(m)) as u64
+}
+
+#[inline(always)]
+fn mod_pow(mut base: u64, mut exponent: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_factor_medium_moduli_with_the_quadratic_sieve() {
+        for p in [3_u64, 5, 13, 17, 1_000_003, 4_294_967_291] {
+            for a in 1..p.min(200) {
+                if let Some(root) = sqrt_mod(a, p) {
+                    assert_eq!(mul_mod(root, root, p), a % p);
+                }
+            }
+        }
+        assert_eq!(sqrt_mod(3, 7), None);
+
+        // 40 digits, primes of 20 digits.
+        let p: BigInt = "10000000000000000051".parse().unwrap();
+        let q: BigInt = "10000000000000000087".parse().unwrap();
+        let n = &p * &q;
+        let mut telemetry = Telemetry::default();
+        let mut reported = (0, 0);
+        let owner = Pool::owner();
+        let factor = quadratic_sieve(
+            &n,
+            &mut telemetry,
+            &Cancel::default(),
+            owner,
+            Pool::global().workers(),
+            |found, needed, _| reported = (found, needed),
+        )
+        .unwrap();
+        assert!(factor == p || factor == q, "{n} split into {factor}");
+        assert!(reported.0 > 0 && reported.0 == reported.1);
+        assert_eq!(Pool::global().active(owner), 0);
+        assert!(telemetry.iterations > 0 && telemetry.candidates_sieved > 0);
+
+        let small = BigInt::from(1_000_003_u64) * &p;
+        let factor = quadratic_sieve(
+            &small,
+            &mut telemetry,
+            &Cancel::default(),
+            Pool::owner(),
+            Pool::global().workers(),
+            |_, _, _| {},
+        );
+        assert!(factor == Some(BigInt::from(1_000_003_u64)) || factor == Some(p));
+        assert_eq!(
+            quadratic_sieve(
+                &BigInt::from(1_000_003_u64),
+                &mut telemetry,
+                &Cancel::default(),
+                Pool::owner(),
+                Pool::global().workers(),
+                |_, _, _| {}
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,592 @@
+use num_bigint::BigInt;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{read_link, read_to_string};
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, OnceLock};
+use std::thread::scope;
+use std::time::{Duration, Instant};
+
+use crate::errors::BilboError;
+use crate::rsa::PickLock;
+use crate::telemetry::{resident_memory_bytes, Telemetry};
+
+// Quota is measured on every this many checks, reading procfs on every loop step would dominate cheap attacks.
+const QUOTA_CHECK_INTERVAL: u64 = 64;
+// Linux reports thread CPU time in clock ticks of USER_HZ, fixed to 100 on all supported architectures.
+const CLOCK_TICKS_PER_SECOND: u64 = 100;
+const BYTES_IN_KIB: u64 = 1024;
+const ANY_ATTACK: &str = "*";
+
+/// Cancel is a flag shared by the attacks of a single race, long running loops check it
+/// and give up with BilboError::Cancelled once any attack succeeded.
+/// Every contender's Cancel also enforces its own quota, it stops the attack once the quota is exceeded.
+/// Callers hand their own Cancel, or Arc<AtomicBool> converted to it, to PickLock::alter_cancel
+/// to abort a running attack from another thread.
+/// Cancel with time limit stops the attack once the limit elapsed since the attack started,
+/// the deadline is shared by its clones handed to the jobs of the attack.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Cancel {
+    flag: Arc<AtomicBool>,
+    guard: Option<Arc<QuotaGuard>>,
+    time_limit: Option<Duration>,
+    // Nanoseconds since the process epoch the running attack times out at, 0 while no attack runs.
+    deadline: Arc<AtomicU64>,
+}
+
+impl Cancel {
+    /// Signals all the attacks sharing the flag to stop.
+    ///
+    #[inline(always)]
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the attacks sharing the flag were signaled to stop or this attack exceeded its quota.
+    ///
+    #[inline(always)]
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed) || self.timed_out() || self.exceeded().is_some()
+    }
+
+    /// Returns true if the running attack spent its time limit.
+    ///
+    #[inline(always)]
+    pub fn timed_out(&self) -> bool {
+        let deadline = self.deadline.load(Ordering::Relaxed);
+        deadline != 0 && since_epoch() >= deadline
+    }
+
+    /// Returns the time limit of every attack, None if unlimited.
+    ///
+    #[inline(always)]
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.time_limit
+    }
+
+    /// Shares the flag and the quota and limits the time of every attack started afterwards.
+    ///
+    #[inline(always)]
+    pub fn with_time_limit(&self, limit: Option<Duration>) -> Self {
+        Self {
+            flag: self.flag.clone(),
+            guard: self.guard.clone(),
+            time_limit: limit,
+            deadline: Arc::default(),
+        }
+    }
+
+    // Starts the clock of the time limit, the attack started.
+    #[inline(always)]
+    pub(crate) fn start(&self) {
+        let deadline = self.time_limit.map_or(0, |limit| {
+            since_epoch()
+                .saturating_add(limit.as_nanos().min(u64::MAX as u128) as u64)
+                .max(1)
+        });
+        self.deadline.store(deadline, Ordering::Relaxed);
+    }
+
+    // Stops the clock of the time limit, the attack finished.
+    #[inline(always)]
+    pub(crate) fn stop(&self) {
+        self.deadline.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the description of the exceeded quota, if the attack exceeded it.
+    ///
+    #[inline(always)]
+    pub fn exceeded(&self) -> Option<&str> {
+        self.guard.as_ref().and_then(|guard| guard.check())
+    }
+
+    // Binds the quota to the current thread, the attack thread.
+    #[inline(always)]
+    pub(crate) fn bind(&self) {
+        if let Some(guard) = self.guard.as_ref() {
+            guard.bind();
+        }
+    }
+
+    // Shares the flag and enforces the quota of a single attack.
+    #[inline(always)]
+    pub(crate) fn with_quota(&self, quota: Quota) -> Self {
+        Self {
+            flag: self.flag.clone(),
+            guard: (!quota.is_unlimited()).then(|| Arc::new(QuotaGuard::new(quota))),
+            time_limit: self.time_limit,
+            deadline: Arc::default(),
+        }
+    }
+}
+
+impl From<Arc<AtomicBool>> for Cancel {
+    #[inline(always)]
+    fn from(flag: Arc<AtomicBool>) -> Self {
+        Self {
+            flag,
+            ..Self::default()
+        }
+    }
+}
+
+// Returns nanoseconds since the first call, the epoch deadlines are measured from, never 0.
+#[inline(always)]
+fn since_epoch() -> u64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64 + 1
+}
+
+/// Quota is the share of the resources a single attack may consume, unlimited where None.
+///
+///  - cpu_time_ms, CPU time of the attack thread, jobs it queues to the shared pool are not counted.
+///  - wall_time_ms, wall clock time since the attack started, its jobs on the shared pool stop with it.
+///  - memory_bytes, resident memory of the whole process while the attack runs.
+///  - iterations, caps the iteration limit of the attack.
+///
+/// Parsed from space separated `cpu=30s wall=1m memory=512MiB iterations=1000`, time in ms, s or m
+/// and memory in B, KiB, MiB or GiB.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Quota {
+    pub cpu_time_ms: Option<u64>,
+    pub wall_time_ms: Option<u64>,
+    pub memory_bytes: Option<u64>,
+    pub iterations: Option<u64>,
+}
+
+impl Display for Quota {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut limits = Vec::new();
+        if let Some(ms) = self.cpu_time_ms {
+            limits.push(format!("cpu={ms}ms"));
+        }
+        if let Some(ms) = self.wall_time_ms {
+            limits.push(format!("wall={ms}ms"));
+        }
+        if let Some(bytes) = self.memory_bytes {
+            limits.push(format!("memory={bytes}B"));
+        }
+        if let Some(iterations) = self.iterations {
+            limits.push(format!("iterations={iterations}"));
+        }
+        if limits.is_empty() {
+            return write!(f, "unlimited");
+        }
+        write!(f, "{}", limits.join(" "))
+    }
+}
+
+impl FromStr for Quota {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quota = Quota::default();
+        for limit in s.split_whitespace() {
+            let parsed = match limit.split_once('=') {
+                Some(("cpu", value)) => {
+                    parse_unit(value, &[("ms", 1), ("s", 1_000), ("m", 60_000)])
+                        .map(|ms| quota.cpu_time_ms = Some(ms))
+                }
+                Some(("wall", value)) => {
+                    parse_unit(value, &[("ms", 1), ("s", 1_000), ("m", 60_000)])
+                        .map(|ms| quota.wall_time_ms = Some(ms))
+                }
+                Some(("memory", value)) => parse_unit(
+                    value,
+                    &[
+                        ("B", 1),
+                        ("KiB", BYTES_IN_KIB),
+                        ("MiB", BYTES_IN_KIB.pow(2)),
+                        ("GiB", BYTES_IN_KIB.pow(3)),
+                    ],
+                )
+                .map(|bytes| quota.memory_bytes = Some(bytes)),
+                Some(("iterations", value)) => value
+                    .parse::<u64>()
+                    .ok()
+                    .map(|iterations| quota.iterations = Some(iterations)),
+                _ => None,
+            };
+            if parsed.is_none() {
+                return Err(BilboError::GenericError(format!(
+                    "invalid quota {limit}, expected cpu=<time>, wall=<time>, memory=<size> or iterations=<count>"
+                )));
+            }
+        }
+
+        Ok(quota)
+    }
+}
+
+impl Quota {
+    /// Returns true if no resource is limited.
+    ///
+    #[inline(always)]
+    pub fn is_unlimited(&self) -> bool {
+        *self == Quota::default()
+    }
+}
+
+/// Quotas assigns quota to attacks by name, declared in the text file, one attack per line, `#` starts a comment:
+///
+/// ```text
+/// weak             cpu=2s iterations=100000
+/// strong           cpu=30s memory=512MiB
+/// *                cpu=60s
+/// ```
+///
+/// Attack `*` sets the quota of attacks without their own line.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quotas {
+    quotas: BTreeMap<String, Quota>,
+}
+
+impl Quotas {
+    /// Reads the quotas from the text format.
+    ///
+    pub fn read(reader: impl BufRead) -> Result<Self, BilboError> {
+        let mut quotas = BTreeMap::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((attack, quota)) = line.split_once(char::is_whitespace) else {
+                if line.is_empty() {
+                    continue;
+                }
+                return Err(BilboError::GenericError(format!(
+                    "line {}: expected '<attack> <quota>', found {line:?}",
+                    number + 1
+                )));
+            };
+            let quota = quota
+                .parse::<Quota>()
+                .map_err(|e| BilboError::GenericError(format!("line {}: {e}", number + 1)))?;
+            quotas.insert(attack.to_string(), quota);
+        }
+
+        Ok(Self { quotas })
+    }
+
+    /// Returns the quota of the attack, the `*` quota if the attack has none of its own.
+    ///
+    #[inline(always)]
+    pub fn quota(&self, attack: &str) -> Quota {
+        self.quotas
+            .get(attack)
+            .or_else(|| self.quotas.get(ANY_ATTACK))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+// Measures the resources of the attack against its quota, the first exceeded quota is kept.
+#[derive(Debug)]
+struct QuotaGuard {
+    quota: Quota,
+    started: Instant,
+    thread: OnceLock<Option<(String, u64)>>,
+    checks: AtomicU64,
+    exceeded: OnceLock<String>,
+}
+
+impl QuotaGuard {
+    #[inline(always)]
+    fn new(quota: Quota) -> Self {
+        Self {
+            quota,
+            started: Instant::now(),
+            thread: OnceLock::new(),
+            checks: AtomicU64::new(0),
+            exceeded: OnceLock::new(),
+        }
+    }
+
+    // Binds the guard to the current thread, its CPU time is measured from now on.
+    #[inline(always)]
+    fn bind(&self) {
+        self.thread.get_or_init(|| {
+            let thread = thread_id()?;
+            let start = thread_cpu_time_ms(&thread)?;
+            Some((thread, start))
+        });
+    }
+
+    #[inline(always)]
+    fn check(&self) -> Option<&str> {
+        if let Some(exceeded) = self.exceeded.get() {
+            return Some(exceeded);
+        }
+        // Reading the clock is cheap, wall time is measured on every check.
+        if let Some(limit) = self.quota.wall_time_ms {
+            let spent = self.started.elapsed().as_millis() as u64;
+            if spent > limit {
+                return Some(
+                    self.exceeded
+                        .get_or_init(|| format!("wall quota {limit} ms, spent {spent} ms")),
+                );
+            }
+        }
+        if !self
+            .checks
+            .fetch_add(1, Ordering::Relaxed)
+            .is_multiple_of(QUOTA_CHECK_INTERVAL)
+        {
+            return None;
+        }
+        if let Some(limit) = self.quota.memory_bytes {
+            let resident = resident_memory_bytes();
+            if resident > limit {
+                return Some(
+                    self.exceeded
+                        .get_or_init(|| format!("memory quota {limit} B, resident {resident} B")),
+                );
+            }
+        }
+        if let (Some(limit), Some(Some((thread, start)))) =
+            (self.quota.cpu_time_ms, self.thread.get())
+        {
+            let spent = thread_cpu_time_ms(thread)
+                .unwrap_or_default()
+                .saturating_sub(*start);
+            if spent > limit {
+                return Some(
+                    self.exceeded
+                        .get_or_init(|| format!("cpu quota {limit} ms, spent {spent} ms")),
+                );
+            }
+        }
+
+        None
+    }
+}
+
+// Parses number with one of the unit suffixes, returns it multiplied by the unit.
+#[inline(always)]
+fn parse_unit(value: &str, units: &[(&str, u64)]) -> Option<u64> {
+    units
+        .iter()
+        .rev()
+        .filter_map(|(unit, scale)| Some((value.strip_suffix(unit)?, scale)))
+        .find_map(|(number, scale)| number.parse::<u64>().ok()?.checked_mul(*scale))
+}
+
+// Returns the id of the current thread, task directory of /proc/thread-self links to <pid>/task/<tid>.
+#[inline(always)]
+fn thread_id() -> Option<String> {
+    let link = read_link("/proc/thread-self").ok()?;
+    Some(link.file_name()?.to_string_lossy().to_string())
+}
+
+// Returns CPU time, user and system, of the thread of the process, fields 14 and 15 of its stat.
+#[inline(always)]
+fn thread_cpu_time_ms(thread: &str) -> Option<u64> {
+    let stat = read_to_string(format!("/proc/self/task/{thread}/stat")).ok()?;
+    // The command name in field 2 may hold spaces, fields are counted after its closing parenthesis.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = fields.split_whitespace().collect();
+    let utime = fields.get(11)?.parse::<u64>().ok()?;
+    let stime = fields.get(12)?.parse::<u64>().ok()?;
+
+    Some((utime + stime) * 1_000 / CLOCK_TICKS_PER_SECOND)
+}
+
+/// Attack run by a contender of the race against its own PickLock.
+///
+pub type RaceAttack<'a> = Box<dyn Fn(&PickLock) -> Result<BigInt, BilboError> + Send + Sync + 'a>;
+
+/// Contender is a named attack entering the race with its quota.
+///
+pub struct Contender<'a> {
+    pub name: &'a str,
+    pub attack: RaceAttack<'a>,
+    pub quota: Quota,
+}
+
+impl<'a> Contender<'a> {
+    /// Creates new contender running given attack without quota.
+    ///
+    #[inline(always)]
+    pub fn new(
+        name: &'a str,
+        attack: impl Fn(&PickLock) -> Result<BigInt, BilboError> + Send + Sync + 'a,
+    ) -> Self {
+        Self {
+            name,
+            attack: Box::new(attack),
+            quota: Quota::default(),
+        }
+    }
+
+    /// Limits the resources the contender may consume.
+    ///
+    #[inline(always)]
+    pub fn with_quota(mut self, quota: Quota) -> Self {
+        self.quota = quota;
+        self
+    }
+}
+
+/// RaceOutcome is the result of the first contender that succeeded, or the failures of all of them.
+/// Every contender reports its own telemetry, the cancelled ones up to the moment they stopped.
+///
+#[derive(Debug)]
+pub struct RaceOutcome {
+    pub winner: Option<String>,
+    pub result: Result<BigInt, BilboError>,
+    pub telemetry: Vec<(String, Telemetry)>,
+}
+
+/// Races the contenders in parallel threads against the key, each on its own copy of the PickLock.
+/// As soon as any of them recovers the private exponent the rest is cancelled,
+/// minimizing wall clock time to the first break when it is unknown which weakness the key has.
+/// Each contender is stopped with BilboError::QuotaExceeded once it exceeds its own quota,
+/// so one pathological attack can't consume the whole assessment window.
+/// The PickLock records the telemetry of all the contenders merged.
+///
+pub fn race(pl: &PickLock, contenders: &[Contender]) -> RaceOutcome {
+    let start = Instant::now();
+    let cancel = Cancel::default();
+    let (tx, rx) = channel();
+    let mut winner = None;
+    let mut failures = Vec::new();
+    let mut telemetry = Vec::new();
+    scope(|s| {
+        for contender in contenders.iter() {
+            let (tx, lock) = (
+                tx.clone(),
+                pl.contender(
+                    cancel.with_quota(contender.quota),
+                    contender.quota.iterations,
+                ),
+            );
+            s.spawn(move || {
+                lock.bind_quota();
+                let (result, telemetry) = lock.with_telemetry(|lock| (contender.attack)(lock));
+                let _ = tx.send((contender.name, result, telemetry));
+            });
+        }
+        drop(tx);
+        for (name, result, spent) in rx.iter() {
+            telemetry.push((name.to_string(), spent));
+            match result {
+                Ok(d) if winner.is_none() => {
+                    cancel.cancel();
+                    winner = Some((name.to_string(), d));
+                }
+                Ok(_) | Err(BilboError::Cancelled { .. }) => (),
+                Err(e) => failures.push(format!("{name}: {e}")),
+            }
+        }
+    });
+
+    let mut merged = Telemetry::default();
+    for (_, spent) in telemetry.iter() {
+        merged.merge(spent);
+    }
+    merged.finish(start);
+    pl.record_telemetry(merged);
+
+    match winner {
+        Some((name, d)) => RaceOutcome {
+            winner: Some(name),
+            result: Ok(d),
+            telemetry,
+        },
+        None => RaceOutcome {
+            winner: None,
+            result: Err(BilboError::GenericError(format!(
+                "none of {} raced attacks succeeded, {}",
+                contenders.len(),
+                failures.join(", ")
+            ))),
+            telemetry,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rsa::{fermat_ratios, IterationLimit};
+    use num_bigint::Sign;
+
+    #[test]
+    fn it_should_race_attacks_and_cancel_the_rest_on_first_break() -> Result<(), BilboError> {
+        // q = 3·p + 44 is far from p and n is beyond the 64 bits routed to SQUFOF, only ratios break it.
+        let (p, q) = (
+            BigInt::from(1_099_511_627_791_u64),
+            BigInt::from(3_298_534_883_417_u64),
+        );
+        let mut pl =
+            PickLock::from_exponent_and_modulus(BigInt::new(Sign::Plus, vec![65537]), &p * &q);
+        pl.alter_iteration_limit(IterationLimit::bounded(10)?);
+        let ratios = fermat_ratios(3);
+        let contenders = [
+            Contender::new("weak", |pl| pl.try_lock_pick_weak_private()),
+            Contender::new("spin", |pl| loop {
+                pl.check_cancelled("spin")?;
+            }),
+            Contender::new("weighted_fermat", |pl| {
+                pl.try_lock_pick_weighted_fermat(&ratios)
+            }),
+        ];
+
+        let outcome = race(&pl, &contenders);
+        assert_eq!(outcome.winner.as_deref(), Some("weighted_fermat"));
+        assert!(outcome.result.is_ok());
+        assert_eq!(outcome.telemetry.len(), 3);
+        assert!(pl.last_telemetry().iterations > 0);
+
+        let outcome = race(&pl, &contenders[..1]);
+        assert!(outcome.winner.is_none());
+        assert!(outcome.result.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_stop_attacks_exceeding_their_quota() -> Result<(), BilboError> {
+        let given = "# quotas\nspin cpu=50ms  # pathological\n* iterations=5 memory=1GiB\n";
+        let quotas = Quotas::read(given.as_bytes())?;
+        assert_eq!(quotas.quota("spin").cpu_time_ms, Some(50));
+        assert_eq!(quotas.quota("weak").memory_bytes, Some(1 << 30));
+        assert_eq!(
+            quotas.quota("weak").to_string(),
+            "memory=1073741824B iterations=5"
+        );
+        assert!("cpu=fast".parse::<Quota>().is_err());
+        assert_eq!("wall=2s".parse::<Quota>()?.wall_time_ms, Some(2_000));
+        assert!(Quotas::read("weak".as_bytes()).is_err());
+
+        let pl = PickLock::from_exponent_and_modulus(
+            BigInt::from(65537),
+            BigInt::from(1_099_511_627_791_u64) * BigInt::from(3_298_534_883_417_u64),
+        );
+        let contenders = [
+            Contender::new("spin", |pl| loop {
+                pl.check_cancelled("spin")?;
+            })
+            .with_quota(quotas.quota("spin")),
+            Contender::new("weak", |pl| pl.try_lock_pick_weak_private())
+                .with_quota(quotas.quota("weak")),
+        ];
+
+        let outcome = race(&pl, &contenders);
+        let Err(BilboError::GenericError(failures)) = outcome.result else {
+            panic!("expected both contenders to fail");
+        };
+        assert!(failures.contains("spin: Attack spin exceeded cpu quota 50 ms"));
+        let weak = outcome.telemetry.iter().find(|(name, _)| name == "weak");
+        assert_eq!(weak.map(|(_, t)| t.iterations), Some(5));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,777 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::net::IpAddr;
+
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::errors::BilboError;
+use crate::net::{AddrFamily, DualStackReport, NetOutcome};
+use crate::rsa::PickLock;
+use crate::schema::SCHEMA_VERSION;
+use crate::strategy::Technique;
+
+/// Attack name of the ROCA fingerprint finding, CVE-2017-15361, the key is flagged without being broken.
+///
+pub const ROCA_ATTACK: &str = "roca";
+/// Attack name of the weak protocols and ciphers offered by the scanned endpoints.
+///
+pub const TRANSPORT_ATTACK: &str = "tls_transport";
+/// Attack name of the weak algorithms offered by the scanned SSH servers.
+///
+pub const SSH_ALGORITHMS_ATTACK: &str = "ssh_algorithms";
+/// Attack name of the short keys and weak parameters published in DKIM key records.
+///
+pub const DKIM_KEY_ATTACK: &str = "dkim_key";
+/// Attack name of the short keys published in the federation metadata.
+///
+pub const FEDERATION_KEY_ATTACK: &str = "federation_key";
+
+const RECOVERED_REMEDIATION: &str =
+    "Revoke the key and replace it, anyone holding the public key recovers the private one.";
+const TOOL_NAME: &str = "bilbo";
+// Columns of the CSV report, in the order they are written, new columns are appended.
+const CSV_COLUMNS: [&str; 7] = [
+    "severity",
+    "rule",
+    "fingerprint",
+    "origin",
+    "attack",
+    "detail",
+    "remediation",
+];
+// Leading characters spreadsheets evaluate as formula, such fields are prefixed with apostrophe.
+const CSV_FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+// Partial fingerprint of SARIF results, code scanning tracks the findings of the same key across runs by it.
+const SARIF_FINGERPRINT: &str = "modulusSha256/v1";
+const TRANSPORT_REMEDIATION: &str =
+    "Disable the weak protocol versions and cipher suites the endpoint offers.";
+const SSH_ALGORITHMS_REMEDIATION: &str =
+    "Disable the weak key exchange, host key, cipher and MAC algorithms the server offers.";
+const DKIM_KEY_REMEDIATION: &str =
+    "Rotate the signing key to RSA key of at least 2048 bits and publish its record.";
+const FEDERATION_KEY_REMEDIATION: &str =
+    "Replace the key in the metadata by RSA key of at least 2048 bits and roll it over with the relying parties.";
+const ROCA_REMEDIATION: &str = "Replace the key generated by the vulnerable Infineon library, update the firmware of the token or smart card first.";
+
+/// Severity of the finding, ordered from the least to the most severe.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    // SARIF level of the result, code scanning fails the check on errors.
+    #[inline(always)]
+    fn sarif_level(&self) -> &'static str {
+        match &self {
+            Severity::Critical | Severity::High => "error",
+            Severity::Medium => "warning",
+            Severity::Low | Severity::Info => "note",
+        }
+    }
+
+    // Score of the security-severity property GitHub code scanning ranks the rules by, CVSS ranges of the severities.
+    #[inline(always)]
+    fn security_severity(&self) -> &'static str {
+        match &self {
+            Severity::Critical => "9.5",
+            Severity::High => "8.0",
+            Severity::Medium => "5.5",
+            Severity::Low => "3.0",
+            Severity::Info => "0.0",
+        }
+    }
+}
+
+impl Display for Severity {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Severity::Info => "info",
+                Severity::Low => "low",
+                Severity::Medium => "medium",
+                Severity::High => "high",
+                Severity::Critical => "critical",
+            }
+        )
+    }
+}
+
+/// Finding is a single weakness of the key, its severity, the key fingerprint, the SHA-256 of the RSA modulus,
+/// where the key was found, the attack that succeeded against it and the hint how to remediate it.
+/// Fingerprint is missing for the keys other than RSA, origin for the keys given directly.
+/// Findings of live servers carry the addresses their host name was resolved to
+/// and the outcome of connections over every address family.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub detail: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attack: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub addrs: Vec<IpAddr>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub families: BTreeMap<AddrFamily, NetOutcome>,
+    pub remediation: String,
+}
+
+impl Display for Finding {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "[{}] ", self.severity)?;
+        if let Some(origin) = &self.origin {
+            write!(f, "{origin}")?;
+            if !self.addrs.is_empty() {
+                let addrs: Vec<String> = self.addrs.iter().map(|a| a.to_string()).collect();
+                write!(f, " at {}", addrs.join(", "))?;
+            }
+            if !self.families.is_empty() {
+                let families: Vec<String> = self
+                    .families
+                    .iter()
+                    .map(|(family, outcome)| format!("{family} {outcome}"))
+                    .collect();
+                write!(f, " ({})", families.join(", "))?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.detail)?;
+        if let Some(fingerprint) = &self.fingerprint {
+            write!(f, ", key {fingerprint}")?;
+        }
+        if let Some(attack) = &self.attack {
+            write!(f, ", broken by {attack}")?;
+        }
+        write!(f, ". {}", self.remediation)
+    }
+}
+
+impl Finding {
+    /// Creates the finding of the given severity, detail and remediation hint.
+    ///
+    #[inline(always)]
+    pub fn new(severity: Severity, detail: &str, remediation: &str) -> Self {
+        Self {
+            severity,
+            detail: detail.to_string(),
+            fingerprint: None,
+            origin: None,
+            attack: None,
+            addrs: Vec::new(),
+            families: BTreeMap::new(),
+            remediation: remediation.to_string(),
+        }
+    }
+
+    /// Creates the critical finding of the RSA key whose private exponent the technique recovered.
+    ///
+    #[inline(always)]
+    pub fn recovered(technique: Technique, pl: &PickLock) -> Self {
+        Self::recovered_by(&technique.to_string(), technique.weakness(), pl)
+    }
+
+    /// Creates the critical finding of the RSA key whose private exponent the attack recovered exploiting the weakness.
+    ///
+    #[inline(always)]
+    pub fn recovered_by(attack: &str, weakness: &str, pl: &PickLock) -> Self {
+        Self::new(
+            Severity::Critical,
+            &format!("private exponent recovered, the key has {weakness}"),
+            RECOVERED_REMEDIATION,
+        )
+        .with_fingerprint(&pl.modulus_fingerprint())
+        .with_attack(attack)
+    }
+
+    /// Returns the rule the finding violates, the attack that succeeded or the detail as kebab case words,
+    /// so the findings of the same weakness share the rule in SARIF reports.
+    ///
+    #[inline(always)]
+    pub fn rule_id(&self) -> String {
+        if let Some(attack) = &self.attack {
+            return attack.clone();
+        }
+        self.detail
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_ascii_lowercase())
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+
+    /// Creates the high finding of the RSA key carrying the ROCA fingerprint, out of reach of the lock picks
+    /// but factored by the Coppersmith attack of the vulnerable generator.
+    ///
+    #[inline(always)]
+    pub fn roca(pl: &PickLock) -> Self {
+        Self::new(
+            Severity::High,
+            "key carries the ROCA fingerprint, CVE-2017-15361, of Infineon generated keys",
+            ROCA_REMEDIATION,
+        )
+        .with_fingerprint(&pl.modulus_fingerprint())
+        .with_attack(ROCA_ATTACK)
+    }
+
+    /// Names the key of the finding by its fingerprint.
+    ///
+    #[inline(always)]
+    pub fn with_fingerprint(mut self, fingerprint: &str) -> Self {
+        self.fingerprint = Some(fingerprint.to_string());
+        self
+    }
+
+    /// Names where the key was found, the file, archive entry or endpoint.
+    ///
+    #[inline(always)]
+    pub fn with_origin(mut self, origin: &str) -> Self {
+        self.origin = Some(origin.to_string());
+        self
+    }
+
+    /// Names the attack that succeeded against the key.
+    ///
+    #[inline(always)]
+    pub fn with_attack(mut self, attack: &str) -> Self {
+        self.attack = Some(attack.to_string());
+        self
+    }
+
+    /// Records the addresses the host name of the origin was resolved to.
+    ///
+    #[inline(always)]
+    pub fn with_addrs(mut self, addrs: &[IpAddr]) -> Self {
+        self.addrs = addrs.to_vec();
+        self
+    }
+
+    /// Records the outcome of connections to the origin over every address family.
+    ///
+    #[inline(always)]
+    pub fn with_dual_stack(mut self, dual_stack: &DualStackReport) -> Self {
+        self.families = dual_stack.outcomes();
+        self
+    }
+}
+
+/// AuditReport collects the findings of the scanners and attacks, listed from the most severe.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AuditReport {
+    pub findings: Vec<Finding>,
+}
+
+impl Display for AuditReport {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if self.findings.is_empty() {
+            return write!(f, "no findings");
+        }
+        let lines: Vec<String> = self.findings.iter().map(|f| f.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+impl AuditReport {
+    /// Creates empty report.
+    ///
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the finding, keeping the findings ordered from the most severe, the ones of equal severity in the order added.
+    ///
+    #[inline(always)]
+    pub fn push(&mut self, finding: Finding) {
+        let at = self
+            .findings
+            .partition_point(|f| f.severity >= finding.severity);
+        self.findings.insert(at, finding);
+    }
+
+    /// Adds all the findings, see push.
+    ///
+    #[inline(always)]
+    pub fn extend(&mut self, findings: impl IntoIterator<Item = Finding>) {
+        findings.into_iter().for_each(|f| self.push(f));
+    }
+
+    /// Returns the severity of the most severe finding, none for the report without findings.
+    ///
+    #[inline(always)]
+    pub fn highest_severity(&self) -> Option<Severity> {
+        self.findings.first().map(|f| f.severity)
+    }
+
+    /// Creates the report of the findings recorded in the audit log, the suppressed ones left out.
+    /// Findings of the scanned endpoints, SSH servers, DKIM records and federation metadata are medium,
+    /// ROCA fingerprints high and the recovered private keys critical. Origin is the source the key was loaded from.
+    ///
+    pub fn from_records(records: &[AuditRecord]) -> Self {
+        let mut sources = BTreeMap::new();
+        let mut report = Self::new();
+        for record in records.iter() {
+            match &record.event {
+                AuditEvent::KeyLoaded { source, key, .. } => {
+                    sources.entry(key.as_str()).or_insert(source.as_str());
+                }
+                AuditEvent::Finding {
+                    attack,
+                    key,
+                    detail,
+                    ..
+                } => {
+                    let (severity, remediation) = match attack.as_str() {
+                        TRANSPORT_ATTACK => (Severity::Medium, TRANSPORT_REMEDIATION),
+                        SSH_ALGORITHMS_ATTACK => (Severity::Medium, SSH_ALGORITHMS_REMEDIATION),
+                        DKIM_KEY_ATTACK => (Severity::Medium, DKIM_KEY_REMEDIATION),
+                        FEDERATION_KEY_ATTACK => (Severity::Medium, FEDERATION_KEY_REMEDIATION),
+                        ROCA_ATTACK => (Severity::High, ROCA_REMEDIATION),
+                        _ => (Severity::Critical, RECOVERED_REMEDIATION),
+                    };
+                    let finding = Finding::new(severity, detail, remediation)
+                        .with_fingerprint(key)
+                        .with_attack(attack);
+                    report.push(match sources.get(key.as_str()) {
+                        Some(source) => finding.with_origin(source),
+                        None => finding,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        report
+    }
+
+    /// Returns the findings of the given severity or more severe.
+    ///
+    #[inline(always)]
+    pub fn at_least(&self, severity: Severity) -> &[Finding] {
+        let len = self.findings.partition_point(|f| f.severity >= severity);
+        &self.findings[..len]
+    }
+
+    /// Serializes the report to pretty printed JSON document of the findings and the schema version of bilbo output.
+    ///
+    #[inline(always)]
+    pub fn to_json(&self) -> Result<String, BilboError> {
+        Ok(serde_json::to_string_pretty(&json!({
+            "schema_version": SCHEMA_VERSION,
+            "findings": self.findings,
+        }))?)
+    }
+
+    /// Serializes the report to SARIF 2.1.0 log of single run, as GitHub code scanning and SAST dashboards ingest.
+    /// Every weakness is a rule of the most severe level of its findings, every finding a result located at its origin
+    /// and tracked across runs by the key fingerprint.
+    ///
+    pub fn to_sarif(&self) -> Result<String, BilboError> {
+        let mut rules: BTreeMap<String, &Finding> = BTreeMap::new();
+        for finding in self.findings.iter() {
+            // Findings are ordered from the most severe, the first one of the rule describes it.
+            rules.entry(finding.rule_id()).or_insert(finding);
+        }
+        let index: BTreeMap<&str, usize> = rules
+            .keys()
+            .enumerate()
+            .map(|(i, id)| (id.as_str(), i))
+            .collect();
+        let rules: Vec<Value> = rules
+            .iter()
+            .map(|(id, finding)| {
+                json!({
+                    "id": id,
+                    "shortDescription": {"text": finding.detail},
+                    "help": {"text": finding.remediation},
+                    "defaultConfiguration": {"level": finding.severity.sarif_level()},
+                    "properties": {
+                        "security-severity": finding.severity.security_severity(),
+                        "tags": ["security", "cryptography"],
+                    },
+                })
+            })
+            .collect();
+        let results: Vec<Value> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                let rule_id = finding.rule_id();
+                let mut result = json!({
+                    "ruleId": rule_id,
+                    "ruleIndex": index[rule_id.as_str()],
+                    "level": finding.severity.sarif_level(),
+                    "message": {"text": finding.to_string()},
+                    "properties": {"severity": finding.severity},
+                });
+                if let Some(origin) = &finding.origin {
+                    result["locations"] = json!([{
+                        "physicalLocation": {"artifactLocation": {"uri": origin.replace('\\', "/")}},
+                    }]);
+                }
+                if !finding.addrs.is_empty() {
+                    result["properties"]["addresses"] = json!(finding.addrs);
+                }
+                if !finding.families.is_empty() {
+                    result["properties"]["families"] = json!(finding.families);
+                }
+                if let Some(fingerprint) = &finding.fingerprint {
+                    result["partialFingerprints"] = json!({SARIF_FINGERPRINT: fingerprint});
+                }
+                result
+            })
+            .collect();
+
+        Ok(serde_json::to_string_pretty(&json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": {"driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }},
+                "results": results,
+            }],
+        }))?)
+    }
+
+    /// Serializes the report to CSV, RFC 4180, of the header and a row per finding, columns severity, rule, fingerprint,
+    /// origin, attack, detail and remediation, in this order, missing values empty. Fields with commas, quotes
+    /// or line breaks are quoted and the fields a spreadsheet would evaluate as formula are prefixed with apostrophe.
+    ///
+    pub fn to_csv(&self) -> String {
+        let mut csv = CSV_COLUMNS.join(",");
+        csv.push_str("\r\n");
+        for finding in self.findings.iter() {
+            let fields = [
+                finding.severity.to_string(),
+                finding.rule_id(),
+                finding.fingerprint.clone().unwrap_or_default(),
+                finding.origin.clone().unwrap_or_default(),
+                finding.attack.clone().unwrap_or_default(),
+                finding.detail.clone(),
+                finding.remediation.clone(),
+            ];
+            let row: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            csv.push_str(&row.join(","));
+            csv.push_str("\r\n");
+        }
+
+        csv
+    }
+
+    /// Serializes the report to JUnit XML of single test suite, every finding a test case named by its rule and origin,
+    /// failed for medium and more severe findings, so CI gates fail the build on them, passed with the detail for the others.
+    ///
+    pub fn to_junit(&self) -> String {
+        let failures = self.at_least(Severity::Medium).len();
+        let tests = self.findings.len();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"{TOOL_NAME}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"0\">\n"
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"{TOOL_NAME}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"0\" skipped=\"0\">\n"
+        ));
+        for finding in self.findings.iter() {
+            let name = finding
+                .origin
+                .as_ref()
+                .or(finding.fingerprint.as_ref())
+                .unwrap_or(&finding.detail);
+            xml.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\">\n",
+                xml_escape(&finding.rule_id()),
+                xml_escape(name)
+            ));
+            let body = xml_escape(&finding.to_string());
+            match finding.severity >= Severity::Medium {
+                true => xml.push_str(&format!(
+                    "      <failure type=\"{}\" message=\"{}\">{body}</failure>\n",
+                    finding.severity,
+                    xml_escape(&finding.detail)
+                )),
+                false => xml.push_str(&format!("      <system-out>{body}</system-out>\n")),
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+
+        xml
+    }
+}
+
+// Quotes the CSV field if it holds separator, quote or line break, doubling its quotes, and defuses formulas.
+#[inline(always)]
+fn csv_field(field: &str) -> String {
+    let field = match field.starts_with(CSV_FORMULA_PREFIXES) {
+        true => format!("'{field}"),
+        false => field.to_string(),
+    };
+    match field.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field,
+    }
+}
+
+// Escapes the XML markup characters and drops the control characters XML 1.0 doesn't allow.
+#[inline(always)]
+fn xml_escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&apos;".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::ConnectAttempt;
+    use num_bigint::BigInt;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn it_should_order_findings_by_severity() -> Result<(), BilboError> {
+        let pl = PickLock::from_exponent_and_modulus(
+            BigInt::from(65_537),
+            BigInt::from(1_099_511_627_791_u64) * BigInt::from(1_099_511_628_211_u64),
+        );
+        let mut report = AuditReport::new();
+        assert_eq!(report.to_string(), "no findings");
+        assert_eq!(report.highest_severity(), None);
+        report.push(
+            Finding::new(Severity::Info, "scan failed", "Check the file.").with_origin("a.pem"),
+        );
+        report.extend([
+            Finding::new(Severity::Medium, "unencrypted private key", "Encrypt it."),
+            Finding::recovered(Technique::Fermat, &pl).with_origin("b.pem"),
+            Finding::new(
+                Severity::Medium,
+                "key shorter than 2048 bits",
+                "Replace it.",
+            ),
+        ]);
+        assert_eq!(report.highest_severity(), Some(Severity::Critical));
+        let details: Vec<&str> = report.findings.iter().map(|f| f.detail.as_str()).collect();
+        assert_eq!(
+            details,
+            vec![
+                "private exponent recovered, the key has primes close to each other",
+                "unencrypted private key",
+                "key shorter than 2048 bits",
+                "scan failed",
+            ]
+        );
+        assert_eq!(report.at_least(Severity::Medium).len(), 3);
+        assert_eq!(
+            report.findings[0].to_string(),
+            format!(
+                "[critical] b.pem: private exponent recovered, the key has primes close to each other, key {}, broken by weak. {RECOVERED_REMEDIATION}",
+                pl.modulus_fingerprint()
+            )
+        );
+
+        let json = serde_json::to_string(&report)?;
+        assert!(json.contains(r#""severity":"critical""#));
+        assert!(!json.contains(r#""fingerprint":null"#));
+        assert!(!json.contains(r#""addrs""#));
+        assert_eq!(serde_json::from_str::<AuditReport>(&json)?, report);
+
+        let live = Finding::roca(&pl)
+            .with_origin("example.com:443")
+            .with_addrs(&["192.0.2.1".parse().unwrap(), "2001:db8::1".parse().unwrap()]);
+        assert!(live
+            .to_string()
+            .starts_with("[high] example.com:443 at 192.0.2.1, 2001:db8::1: key carries"));
+        let json = serde_json::to_string(&live)?;
+        assert!(json.contains(r#""addrs":["192.0.2.1","2001:db8::1"]"#));
+        assert_eq!(serde_json::from_str::<Finding>(&json)?, live);
+
+        let attempt = |addr: &str, outcome| ConnectAttempt {
+            addr: addr.parse().unwrap(),
+            family: AddrFamily::of(&addr.parse::<SocketAddr>().unwrap().ip()),
+            outcome,
+            elapsed_ms: 1,
+            message: None,
+        };
+        let live = live.with_dual_stack(&DualStackReport {
+            attempts: vec![
+                attempt("[2001:db8::1]:443", NetOutcome::Timeout),
+                attempt("192.0.2.1:443", NetOutcome::Success),
+            ],
+        });
+        assert!(live.to_string().starts_with(
+            "[high] example.com:443 at 192.0.2.1, 2001:db8::1 (IPv4 success, IPv6 timeout): key carries"
+        ));
+        let json = serde_json::to_string(&live)?;
+        assert!(json.contains(r#""families":{"ipv4":"success","ipv6":"timeout"}"#));
+        assert_eq!(serde_json::from_str::<Finding>(&json)?, live);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_serialize_report_to_json_and_sarif() -> Result<(), BilboError> {
+        let pl = PickLock::from_exponent_and_modulus(
+            BigInt::from(65_537),
+            BigInt::from(1_099_511_627_791_u64) * BigInt::from(1_099_511_628_211_u64),
+        );
+        let mut report = AuditReport::new();
+        report.extend([
+            Finding::new(
+                Severity::Medium,
+                "key shorter than 2048 bits",
+                "Replace it.",
+            )
+            .with_origin("keys\\old.pem"),
+            Finding::recovered(Technique::Fermat, &pl).with_origin("keys/weak.pem"),
+            Finding::new(Severity::High, "key shorter than 2048 bits", "Replace it.")
+                .with_origin("keys/short.pem"),
+        ]);
+
+        let json: Value = serde_json::from_str(&report.to_json()?)?;
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["findings"][0]["attack"], "weak");
+        assert_eq!(serde_json::from_value::<AuditReport>(json)?, report);
+
+        let sarif: Value = serde_json::from_str(&report.to_sarif()?)?;
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "bilbo");
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0]["id"], "key-shorter-than-2048-bits");
+        assert_eq!(rules[0]["defaultConfiguration"]["level"], "error");
+        assert_eq!(rules[1]["id"], "weak");
+        assert_eq!(rules[1]["properties"]["security-severity"], "9.5");
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["ruleIndex"], 1);
+        assert_eq!(
+            results[0]["partialFingerprints"][SARIF_FINGERPRINT],
+            pl.modulus_fingerprint()
+        );
+        assert_eq!(results[2]["level"], "warning");
+        assert_eq!(
+            results[2]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "keys/old.pem"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_serialize_report_to_csv_and_junit_escaping_fields() {
+        let mut report = AuditReport::new();
+        report.extend([
+            Finding::new(
+                Severity::Info,
+                "scan failed, invalid \"PEM\"",
+                "Check <the> file & retry.",
+            )
+            .with_origin("keys/a,b.pem"),
+            Finding::new(
+                Severity::High,
+                "key shorter than 2048 bits",
+                "Replace it.\nNow.",
+            )
+            .with_origin("=HYPERLINK(\"x\")")
+            .with_attack("short\u{1}key"),
+        ]);
+
+        let csv = report.to_csv();
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(
+            lines[0],
+            "severity,rule,fingerprint,origin,attack,detail,remediation"
+        );
+        assert_eq!(
+            lines[1],
+            "high,short\u{1}key,,\"'=HYPERLINK(\"\"x\"\")\",short\u{1}key,key shorter than 2048 bits,\"Replace it.\nNow.\""
+        );
+        assert_eq!(
+            lines[2],
+            "info,scan-failed-invalid-pem,,\"keys/a,b.pem\",,\"scan failed, invalid \"\"PEM\"\"\",Check <the> file & retry."
+        );
+        assert_eq!(lines[3], "");
+        assert_eq!(AuditReport::new().to_csv().lines().count(), 1);
+
+        let junit = report.to_junit();
+        assert!(junit.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+        assert!(junit
+            .contains(r#"<testsuite name="bilbo" tests="2" failures="1" errors="0" skipped="0">"#));
+        assert!(
+            junit.contains(r#"<testcase classname="shortkey" name="=HYPERLINK(&quot;x&quot;)">"#)
+        );
+        assert!(junit.contains(r#"<failure type="high" message="key shorter than 2048 bits">"#));
+        assert!(junit.contains("Check &lt;the&gt; file &amp; retry.</system-out>"));
+        assert!(!junit.contains('\u{1}'));
+    }
+
+    #[test]
+    fn it_should_report_findings_of_audit_log() {
+        let record = |event| AuditRecord {
+            schema_version: SCHEMA_VERSION,
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            event,
+        };
+        let records = [
+            record(AuditEvent::KeyLoaded {
+                source: "keys/weak.pem".to_string(),
+                key: "aa".to_string(),
+                bits: 1024,
+            }),
+            record(AuditEvent::Finding {
+                attack: TRANSPORT_ATTACK.to_string(),
+                key: "bb".to_string(),
+                detail: "offers TLS 1.0".to_string(),
+                evidence: None,
+            }),
+            record(AuditEvent::FindingSuppressed {
+                attack: "weak".to_string(),
+                key: "cc".to_string(),
+                detail: "private exponent recovered".to_string(),
+                reason: "test key".to_string(),
+            }),
+            record(AuditEvent::Finding {
+                attack: "weak".to_string(),
+                key: "aa".to_string(),
+                detail: "private exponent recovered".to_string(),
+                evidence: None,
+            }),
+        ];
+        let report = AuditReport::from_records(&records);
+        assert_eq!(report.findings.len(), 2);
+        assert_eq!(report.findings[0].severity, Severity::Critical);
+        assert_eq!(report.findings[0].origin.as_deref(), Some("keys/weak.pem"));
+        assert_eq!(report.findings[1].severity, Severity::Medium);
+        assert_eq!(report.findings[1].remediation, TRANSPORT_REMEDIATION);
+        assert_eq!(report.findings[1].origin, None);
+    }
+}
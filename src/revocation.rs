@@ -0,0 +1,272 @@
+use openssl::hash::MessageDigest;
+use openssl::ocsp::{
+    OcspCertId, OcspCertStatus, OcspFlag, OcspRequest, OcspResponse, OcspResponseStatus,
+};
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::verify::X509VerifyFlags;
+use openssl::x509::{CrlStatus, X509Crl, X509Ref, X509};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::cache::{Cache, Material};
+use crate::errors::BilboError;
+use crate::http::{get_cached, request, Url};
+
+// Leeway of the OCSP response validity period for clock skew between bilbo and the responder.
+const OCSP_LEEWAY_SECONDS: u32 = 300;
+
+/// Revocation is the status of the certificate reported by its issuer over OCSP or in the CRL.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Revocation {
+    Revoked { source: String, since: String },
+    Good { source: String },
+    Unknown { reason: String },
+}
+
+impl Display for Revocation {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            Revocation::Revoked { source, since } => {
+                write!(f, "weak but revoked since {since} per {source}")
+            }
+            Revocation::Good { source } => write!(f, "weak and still trusted per {source}"),
+            Revocation::Unknown { reason } => write!(f, "weak, revocation unknown: {reason}"),
+        }
+    }
+}
+
+impl Revocation {
+    /// Returns true if the issuer revoked the certificate.
+    ///
+    #[inline(always)]
+    pub fn is_revoked(&self) -> bool {
+        matches!(self, Revocation::Revoked { .. })
+    }
+}
+
+/// Returns the URLs of the OCSP responders named in the authority information access extension of the certificate.
+///
+#[inline(always)]
+pub fn ocsp_responders(cert: &X509Ref) -> Vec<String> {
+    cert.ocsp_responders()
+        .map(|r| r.iter().map(|u| u.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Returns the URLs of the CRL distribution points of the certificate.
+///
+#[inline(always)]
+pub fn crl_distribution_points(cert: &X509Ref) -> Vec<String> {
+    let Some(points) = cert.crl_distribution_points() else {
+        return Vec::new();
+    };
+
+    points
+        .iter()
+        .filter_map(|p| p.distpoint()?.fullname())
+        .flat_map(|names| names.iter().filter_map(|n| n.uri().map(|u| u.to_string())))
+        .collect()
+}
+
+/// Checks whether the issuer revoked the certificate, asking its OCSP responders first, then its CRL distribution points.
+/// Both answers are trusted only if signed by the issuer, the issuer certificate is required.
+/// CRLs are cached, every failure is reported as unknown status with the reasons.
+///
+pub fn check_revocation(der: &[u8], issuer: Option<&[u8]>, cache: &Cache) -> Revocation {
+    let checked = X509::from_der(der).and_then(|cert| {
+        let issuer = issuer.map(X509::from_der).transpose()?;
+        Ok((cert, issuer))
+    });
+    let (cert, issuer) = match checked {
+        Ok(checked) => checked,
+        Err(e) => {
+            return Revocation::Unknown {
+                reason: e.to_string(),
+            }
+        }
+    };
+    let (responders, points) = (ocsp_responders(&cert), crl_distribution_points(&cert));
+    if responders.is_empty() && points.is_empty() {
+        return Revocation::Unknown {
+            reason: "no OCSP responder nor CRL distribution point".to_string(),
+        };
+    }
+    let Some(issuer) = issuer else {
+        return Revocation::Unknown {
+            reason: "issuer certificate not served".to_string(),
+        };
+    };
+
+    let mut failures = Vec::new();
+    for url in responders.iter() {
+        match ask_ocsp(url, &cert, &issuer) {
+            Ok(Some(revocation)) => return revocation,
+            Ok(None) => failures.push(format!("OCSP {url} doesn't know the certificate")),
+            Err(e) => failures.push(format!("OCSP {url}: {e}")),
+        }
+    }
+    for url in points.iter() {
+        match read_crl(url, &cert, &issuer, cache) {
+            Ok(revocation) => return revocation,
+            Err(e) => failures.push(format!("CRL {url}: {e}")),
+        }
+    }
+
+    Revocation::Unknown {
+        reason: failures.join(", "),
+    }
+}
+
+// Posts the OCSP request for the certificate, None if the responder answers with unknown status.
+#[inline(always)]
+fn ask_ocsp(url: &str, cert: &X509, issuer: &X509) -> Result<Option<Revocation>, BilboError> {
+    let id = || OcspCertId::from_cert(MessageDigest::sha1(), cert, issuer);
+    let mut req = OcspRequest::new()?;
+    req.add_id(id()?)?;
+    let response = request(
+        &Url::parse(url)?,
+        "POST",
+        &[("Content-Type", "application/ocsp-request")],
+        &req.to_der()?,
+    )?;
+    if response.status != 200 {
+        return Err(BilboError::GenericError(format!(
+            "responded with status {}",
+            response.status
+        )));
+    }
+    let response = OcspResponse::from_der(&response.body)?;
+    if response.status() != OcspResponseStatus::SUCCESSFUL {
+        return Err(BilboError::GenericError(format!(
+            "responded with OCSP status {}",
+            response.status().as_raw()
+        )));
+    }
+    let basic = response.basic()?;
+
+    // The issuer signs the response itself or delegates it to a responder certificate it issued.
+    let mut certs = Stack::new()?;
+    certs.push(issuer.clone())?;
+    let mut store = X509StoreBuilder::new()?;
+    store.add_cert(issuer.clone())?;
+    store.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+    basic.verify(&certs, &store.build(), OcspFlag::TRUST_OTHER)?;
+
+    let id = id()?;
+    let Some(status) = basic.find_status(&id) else {
+        return Err(BilboError::GenericError(
+            "responded without the certificate status".to_string(),
+        ));
+    };
+    status.check_validity(OCSP_LEEWAY_SECONDS, None)?;
+    let source = format!("OCSP {url}");
+
+    Ok(match status.status {
+        OcspCertStatus::GOOD => Some(Revocation::Good { source }),
+        OcspCertStatus::REVOKED => Some(Revocation::Revoked {
+            source,
+            since: status
+                .revocation_time
+                .map(|t| t.to_string())
+                .unwrap_or_default(),
+        }),
+        _ => None,
+    })
+}
+
+// Fetches the CRL, DER or PEM encoded, and looks the certificate up once the issuer signature verifies.
+#[inline(always)]
+fn read_crl(
+    url: &str,
+    cert: &X509,
+    issuer: &X509,
+    cache: &Cache,
+) -> Result<Revocation, BilboError> {
+    let body = get_cached(&Url::parse(url)?, &[], Material::Crl, cache)?;
+    let crl = X509Crl::from_der(&body).or_else(|_| X509Crl::from_pem(&body))?;
+    let key = issuer.public_key()?;
+    if !crl.verify(&key)? {
+        return Err(BilboError::GenericError(
+            "isn't signed by the issuer".to_string(),
+        ));
+    }
+    let source = format!("CRL {url}");
+
+    Ok(match crl.get_by_cert(cert) {
+        CrlStatus::Revoked(entry) => Revocation::Revoked {
+            source,
+            since: entry.revocation_date().to_string(),
+        },
+        CrlStatus::NotRevoked | CrlStatus::RemoveFromCrl(_) => Revocation::Good { source },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::nid::Nid;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509Extension, X509NameBuilder};
+
+    #[test]
+    #[allow(deprecated)]
+    fn it_should_find_revocation_endpoints_of_certificate() -> Result<(), BilboError> {
+        let key = PKey::from_rsa(Rsa::generate(1024)?)?;
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", "example.com")?;
+        let name = name.build();
+        let mut builder = X509::builder()?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(365)?);
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+        builder.append_extension(X509Extension::new_nid(
+            None,
+            None,
+            Nid::INFO_ACCESS,
+            "OCSP;URI:http://ocsp.example.com",
+        )?)?;
+        builder.append_extension(X509Extension::new_nid(
+            None,
+            None,
+            Nid::CRL_DISTRIBUTION_POINTS,
+            "URI:http://crl.example.com/ca.crl",
+        )?)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+        let cert = builder.build();
+
+        assert_eq!(ocsp_responders(&cert), vec!["http://ocsp.example.com"]);
+        assert_eq!(
+            crl_distribution_points(&cert),
+            vec!["http://crl.example.com/ca.crl"]
+        );
+
+        let cache = Cache::default();
+        let revocation = check_revocation(&cert.to_der()?, None, &cache);
+        assert_eq!(
+            revocation,
+            Revocation::Unknown {
+                reason: "issuer certificate not served".to_string()
+            }
+        );
+        assert!(!revocation.is_revoked());
+        assert_eq!(
+            Revocation::Revoked {
+                source: "CRL http://crl.example.com/ca.crl".to_string(),
+                since: "Jan  1 00:00:00 2026 GMT".to_string(),
+            }
+            .to_string(),
+            "weak but revoked since Jan  1 00:00:00 2026 GMT per CRL http://crl.example.com/ca.crl"
+        );
+
+        Ok(())
+    }
+}
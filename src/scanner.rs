@@ -0,0 +1,1652 @@
+use num_bigint::{BigInt, Sign};
+use openssl::nid::Nid;
+use openssl::pkey::{Id, PKey, Private, Public};
+use openssl::rsa::Rsa;
+use openssl::x509::{X509Req, X509};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+#[cfg(feature = "async")]
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::archive::{archive_kind, gunzip, untar, unzip, ArchiveKind};
+#[cfg(feature = "async")]
+use crate::blocking::Blocking;
+use crate::errors::BilboError;
+use crate::federation::{Jwk, Jwks};
+use crate::pgp;
+use crate::report::{AuditReport, Finding, Severity};
+use crate::rsa::PickLock;
+use crate::ssh::{
+    is_key_type, openssh_private_public_key, openssh_public_key, ppk_pick_lock, HostKey,
+    BLOB_PREFIX, PPK_HEADER,
+};
+use crate::strategy::Technique;
+
+/// Shortest RSA and DSA key the scanner doesn't flag, as NIST SP 800-131A requires since 2014.
+///
+pub const MIN_KEY_BITS: u64 = 2048;
+
+// Key files are a few kilobytes, certificate bundles a few hundred, larger files are skipped unread.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+// Leading bytes of the file read to detect the key material, the rest is read only for detected files.
+const SNIFF_LEN: u64 = 4096;
+// Jar in war in ear is as deep as the artifacts nest, gzip of tar doesn't count as level.
+const ARCHIVE_DEPTH: usize = 3;
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+// Limit of the bytes unpacked from the archive, the nested ones included, and of the archive file size.
+const MAX_UNPACKED: u64 = 64 * 1024 * 1024;
+// Separates the archive from the path of its entry in the origin of the keys, as Java does in jar URLs.
+const ARCHIVE_ENTRY_SEPARATOR: &str = "!";
+const PEM_BEGIN: &str = "-----BEGIN ";
+const PGP_ARMOR_BEGIN: &str = "-----BEGIN PGP ";
+const PROC_TYPE: &str = "Proc-Type";
+const PPK_ENCRYPTION: &str = "Encryption:";
+const DER_SEQUENCE: u8 = 0x30;
+const GIT_COMMIT: &str = "commit ";
+// Mode of regular files in git trees, 100644 or 100755, symbolic links and submodules have other modes.
+const GIT_REGULAR_FILE: &str = "100";
+// OpenSSL reports these sizes, the curve order bits, for the EdDSA keys.
+const ED25519_BITS: u64 = 253;
+const ED448_BITS: u64 = 456;
+// Keys shorter than this are factored by the academic records, the shorter ones by anyone.
+const BROKEN_KEY_BITS: u64 = 1024;
+const UNENCRYPTED_REMEDIATION: &str =
+    "Encrypt the private key with a passphrase, or move it out of the tree to a secret store.";
+const SHORT_KEY_REMEDIATION: &str =
+    "Replace the key by RSA key of at least 2048 bits or by Ed25519 key.";
+const MALFORMED_KEY_REMEDIATION: &str =
+    "Regenerate the key by a vetted library, with public exponent 65537 and modulus of at least 2048 bits.";
+const SHARED_MODULUS_REMEDIATION: &str =
+    "Give every host, appliance and certificate a key of its own, a single compromise exposes all the copies.";
+const FAILURE_REMEDIATION: &str = "Check the file is readable and its key material well formed.";
+
+/// KeyFormat is the encoding the key material was detected in.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyFormat {
+    Pem,
+    Der,
+    OpenSsh,
+    Putty,
+    Jwk,
+    Pgp,
+}
+
+impl Display for KeyFormat {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                KeyFormat::Pem => "PEM",
+                KeyFormat::Der => "DER",
+                KeyFormat::OpenSsh => "OpenSSH",
+                KeyFormat::Putty => "PuTTY",
+                KeyFormat::Jwk => "JWK",
+                KeyFormat::Pgp => "OpenPGP",
+            }
+        )
+    }
+}
+
+/// KeyAlgorithm is the normalized algorithm of the discovered key, NIST curves named P-256, P-384 and P-521
+/// whether found in X.509, SSH or JWK encoding. Unknown holds the name of the algorithm the scanner doesn't tell apart,
+/// or what hides it, such as encrypted PKCS#8.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    Rsa,
+    Dsa,
+    Ec(String),
+    Ed25519,
+    Ed448,
+    Unknown(String),
+}
+
+impl Display for KeyAlgorithm {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            KeyAlgorithm::Rsa => write!(f, "RSA"),
+            KeyAlgorithm::Dsa => write!(f, "DSA"),
+            KeyAlgorithm::Ec(curve) => write!(f, "EC {curve}"),
+            KeyAlgorithm::Ed25519 => write!(f, "Ed25519"),
+            KeyAlgorithm::Ed448 => write!(f, "Ed448"),
+            KeyAlgorithm::Unknown(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl KeyAlgorithm {
+    // Normalizes the key type name of SSH keys, security key variants included.
+    #[inline(always)]
+    fn from_ssh(key_type: &str) -> Self {
+        match key_type {
+            "ssh-rsa" => KeyAlgorithm::Rsa,
+            "ssh-dss" => KeyAlgorithm::Dsa,
+            "ssh-ed25519" | "sk-ssh-ed25519@openssh.com" => KeyAlgorithm::Ed25519,
+            "ssh-ed448" => KeyAlgorithm::Ed448,
+            t => match t
+                .trim_start_matches("sk-")
+                .strip_prefix("ecdsa-sha2-nistp")
+                .and_then(|bits| bits.split('@').next())
+            {
+                Some(bits) => KeyAlgorithm::Ec(format!("P-{bits}")),
+                None => KeyAlgorithm::Unknown(t.to_string()),
+            },
+        }
+    }
+
+    // Bits of the NIST and EdDSA curves, None for the others.
+    #[inline(always)]
+    fn curve_bits(&self) -> Option<u64> {
+        match &self {
+            KeyAlgorithm::Ec(curve) => curve.strip_prefix("P-")?.parse().ok(),
+            KeyAlgorithm::Ed25519 => Some(ED25519_BITS),
+            KeyAlgorithm::Ed448 => Some(ED448_BITS),
+            _ => None,
+        }
+    }
+}
+
+/// KeyWeakness is what the weakness checks found in the discovered key.
+///
+///  - UnencryptedPrivateKey is the private key stored in the clear, readable by anyone reading the file.
+///  - ShortKey is RSA or DSA key shorter than MIN_KEY_BITS.
+///  - RocaFingerprint is RSA modulus generated by the Infineon library, see PickLock::is_roca_vulnerable.
+///  - SmallPrime is RSA modulus with a prime found by PickLock::quick_factor_check or PickLock::lint.
+///  - ClosePrimes is RSA modulus of primes close to each other, picked by PickLock::try_lock_pick_weak_private.
+///  - EvenModulus, PerfectPower, UnitExponent, EvenExponent, SmallExponent and LargeExponent
+///    are the malformed RSA keys found by PickLock::lint.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyWeakness {
+    UnencryptedPrivateKey,
+    ShortKey,
+    RocaFingerprint,
+    SmallPrime,
+    ClosePrimes,
+    EvenModulus,
+    PerfectPower,
+    UnitExponent,
+    EvenExponent,
+    SmallExponent,
+    LargeExponent,
+}
+
+impl Display for KeyWeakness {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            KeyWeakness::UnencryptedPrivateKey => write!(f, "unencrypted private key"),
+            KeyWeakness::ShortKey => write!(f, "key shorter than {MIN_KEY_BITS} bits"),
+            KeyWeakness::RocaFingerprint => write!(f, "ROCA vulnerable modulus"),
+            KeyWeakness::SmallPrime => write!(f, "modulus with a small prime"),
+            KeyWeakness::ClosePrimes => write!(f, "modulus of close primes"),
+            KeyWeakness::EvenModulus => write!(f, "even modulus"),
+            KeyWeakness::PerfectPower => write!(f, "modulus a perfect power"),
+            KeyWeakness::UnitExponent => write!(f, "public exponent of 1"),
+            KeyWeakness::EvenExponent => write!(f, "even public exponent"),
+            KeyWeakness::SmallExponent => write!(f, "public exponent below 65537"),
+            KeyWeakness::LargeExponent => write!(f, "public exponent longer than 256 bits"),
+        }
+    }
+}
+
+impl KeyWeakness {
+    /// Reports the weakness of the key of the bits as finding, graded like DiscoveredKey::findings,
+    /// the lock of RSA keys names the key and the attack of the weaknesses that recover the private key.
+    ///
+    pub fn finding(&self, pl: Option<&PickLock>, bits: u64) -> Finding {
+        match (self, pl) {
+            (KeyWeakness::SmallPrime, Some(pl)) => Finding::recovered(Technique::QuickFactor, pl),
+            (KeyWeakness::ClosePrimes, Some(pl)) => Finding::recovered(Technique::Fermat, pl),
+            (KeyWeakness::RocaFingerprint, Some(pl)) => Finding::roca(pl),
+            (KeyWeakness::ShortKey, _) if bits < BROKEN_KEY_BITS => {
+                Finding::new(Severity::High, &self.to_string(), SHORT_KEY_REMEDIATION)
+            }
+            (KeyWeakness::ShortKey, _) => {
+                Finding::new(Severity::Medium, &self.to_string(), SHORT_KEY_REMEDIATION)
+            }
+            (
+                KeyWeakness::EvenModulus | KeyWeakness::PerfectPower | KeyWeakness::UnitExponent,
+                _,
+            ) => Finding::new(Severity::High, &self.to_string(), MALFORMED_KEY_REMEDIATION),
+            (KeyWeakness::EvenExponent, _) => Finding::new(
+                Severity::Medium,
+                &self.to_string(),
+                MALFORMED_KEY_REMEDIATION,
+            ),
+            (KeyWeakness::SmallExponent | KeyWeakness::LargeExponent, _) => {
+                Finding::new(Severity::Low, &self.to_string(), MALFORMED_KEY_REMEDIATION)
+            }
+            _ => Finding::new(Severity::Medium, &self.to_string(), UNENCRYPTED_REMEDIATION),
+        }
+    }
+}
+
+/// DiscoveredKey is the key material found by KeyScanner, normalized whatever its format.
+///
+///  - origin is the path of the file holding it, the path within the repository for keys found in git history.
+///  - commit is the id of the oldest commit adding the key to git history, None for keys found elsewhere.
+///  - bits is zero when the encryption of the key hides it.
+///  - private is true for private keys, encrypted is true if they are protected by passphrase.
+///  - pick_lock is the lock of RSA keys with readable public part, None for the others.
+///  - weaknesses are the findings of the weakness checks, empty for sound keys.
+///
+pub struct DiscoveredKey {
+    pub origin: String,
+    pub commit: Option<String>,
+    pub format: KeyFormat,
+    pub algorithm: KeyAlgorithm,
+    pub bits: u64,
+    pub private: bool,
+    pub encrypted: bool,
+    pub pick_lock: Option<PickLock>,
+    pub weaknesses: Vec<KeyWeakness>,
+}
+
+impl Display for DiscoveredKey {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        if let Some(commit) = &self.commit {
+            write!(f, "{commit}:")?;
+        }
+        write!(
+            f,
+            "{} {} {} {} bits {} key",
+            self.origin,
+            self.format,
+            self.algorithm,
+            self.bits,
+            match (self.private, self.encrypted) {
+                (false, _) => "public",
+                (true, false) => "private",
+                (true, true) => "encrypted private",
+            }
+        )?;
+        for weakness in self.weaknesses.iter() {
+            write!(f, ", {weakness}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DiscoveredKey {
+    /// Returns true if any of the weakness checks found the key weak.
+    ///
+    #[inline(always)]
+    pub fn is_weak(&self) -> bool {
+        !self.weaknesses.is_empty()
+    }
+
+    /// Returns true if the lock pick checks recovered the private key, see KeyWeakness.
+    ///
+    #[inline(always)]
+    pub fn is_crackable(&self) -> bool {
+        self.weaknesses
+            .iter()
+            .any(|w| matches!(w, KeyWeakness::SmallPrime | KeyWeakness::ClosePrimes))
+    }
+
+    /// Reports every weakness of the key as finding, the recovered private keys critical,
+    /// ROCA fingerprints, keys shorter than 1024 bits and keys broken by their form high,
+    /// the other short, malformed and unencrypted private keys medium, small and large public exponents low.
+    ///
+    pub fn findings(&self) -> Vec<Finding> {
+        let origin = match &self.commit {
+            Some(commit) => format!("{commit}:{}", self.origin),
+            None => self.origin.clone(),
+        };
+        self.weaknesses
+            .iter()
+            .map(|weakness| {
+                let finding = weakness
+                    .finding(self.pick_lock.as_ref(), self.bits)
+                    .with_origin(&origin);
+                match (&self.pick_lock, &finding.fingerprint) {
+                    (Some(pl), None) => finding.with_fingerprint(&pl.modulus_fingerprint()),
+                    _ => finding,
+                }
+            })
+            .collect()
+    }
+
+    // Runs the weakness checks, the lint and lock pick ones on RSA keys only, the cheap lint first.
+    #[inline(always)]
+    fn check(&mut self) {
+        if self.private && !self.encrypted {
+            self.weaknesses.push(KeyWeakness::UnencryptedPrivateKey);
+        }
+        if matches!(self.algorithm, KeyAlgorithm::Rsa | KeyAlgorithm::Dsa)
+            && self.bits > 0
+            && self.bits < MIN_KEY_BITS
+        {
+            self.weaknesses.push(KeyWeakness::ShortKey);
+        }
+        let Some(pl) = &self.pick_lock else {
+            return;
+        };
+        for weakness in pl.lint() {
+            if !self.weaknesses.contains(&weakness) {
+                self.weaknesses.push(weakness);
+            }
+        }
+        if pl.is_roca_vulnerable() {
+            self.weaknesses.push(KeyWeakness::RocaFingerprint);
+        }
+        if self.weaknesses.contains(&KeyWeakness::SmallPrime) {
+            return;
+        }
+        if pl.quick_factor_check().is_some() {
+            self.weaknesses.push(KeyWeakness::SmallPrime);
+        } else if pl.try_lock_pick_weak_private().is_ok() {
+            self.weaknesses.push(KeyWeakness::ClosePrimes);
+        }
+    }
+}
+
+/// ScanReport is what KeyScanner found in the tree, the keys ordered by their origin,
+/// the number of files scanned and skipped as too large, and the files and directories that failed with their error.
+///
+#[derive(Default)]
+pub struct ScanReport {
+    pub keys: Vec<DiscoveredKey>,
+    pub files: usize,
+    pub skipped: usize,
+    pub failures: Vec<(String, String)>,
+}
+
+impl ScanReport {
+    /// Returns the RSA keys of the same modulus found in more than one place, grouped by the SHA-256 of the modulus:
+    /// the same private key copied to several files, cloned appliances and images, or several certificates
+    /// and public keys of the same key, reissued instead of renewed with a new key.
+    /// Keys are counted by the directory holding them, a private key next to its own public key or certificate
+    /// and the same key in several encodings are no sharing, the versions of a file in git history neither.
+    ///
+    pub fn shared_moduli(&self) -> Vec<(String, Vec<&DiscoveredKey>)> {
+        let mut moduli: BTreeMap<String, Vec<&DiscoveredKey>> = BTreeMap::new();
+        for key in self.keys.iter() {
+            if let Some(pl) = &key.pick_lock {
+                moduli
+                    .entry(pl.modulus_fingerprint())
+                    .or_default()
+                    .push(key);
+            }
+        }
+
+        moduli
+            .into_iter()
+            .filter(|(_, keys)| {
+                let places: HashSet<&str> = keys.iter().map(|k| place_of(&k.origin)).collect();
+                places.len() > 1
+            })
+            .collect()
+    }
+
+    /// Reports the findings of all the keys, every key of a shared modulus as medium finding, see ScanReport::shared_moduli,
+    /// and the failures as info findings, see DiscoveredKey::findings.
+    ///
+    pub fn audit_report(&self) -> AuditReport {
+        let mut report = AuditReport::new();
+        report.extend(self.keys.iter().flat_map(|key| key.findings()));
+        for (fingerprint, keys) in self.shared_moduli() {
+            report.extend(keys.iter().map(|key| {
+                let origin = match &key.commit {
+                    Some(commit) => format!("{commit}:{}", key.origin),
+                    None => key.origin.clone(),
+                };
+                Finding::new(
+                    Severity::Medium,
+                    "modulus shared by distinct keys or certificates",
+                    SHARED_MODULUS_REMEDIATION,
+                )
+                .with_origin(&origin)
+                .with_fingerprint(&fingerprint)
+            }));
+        }
+        report.extend(self.failures.iter().map(|(origin, e)| {
+            Finding::new(
+                Severity::Info,
+                &format!("scan failed, {e}"),
+                FAILURE_REMEDIATION,
+            )
+            .with_origin(origin)
+        }));
+
+        report
+    }
+}
+
+/// KeyScanner walks the directory tree and detects PEM, DER, OpenSSH, PuTTY, JWK and OpenPGP key material
+/// by the magic bytes and headers of the first 4 KiB of every file, reading in full the detected files only.
+/// Symbolic links are not followed, so the walk never leaves the tree nor loops.
+/// The lock pick checks of RSA keys take milliseconds per key, up to a third of a second for 4096 bit moduli.
+///
+/// Include and exclude are glob patterns of `*` matching within the path segment, `**` matching across segments
+/// and `?` matching single character. Patterns with `/` match the path relative to the root, the others the name
+/// of the file or directory. Excluded directories are not descended, files are scanned if included by any pattern,
+/// all of them if there is none.
+///
+/// Zip, jar among them, tar and gzip archives are unpacked in memory and their entries scanned whatever their names,
+/// the keys reported with origin `archive!entry`. Nested archives are descended up to 3 levels, gzip is transparent.
+/// Archives of more than 10 000 entries, or unpacking to more than 64 MiB altogether, fail so the bombs are stopped early.
+///
+#[derive(Debug, Clone)]
+pub struct KeyScanner {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    max_file_size: u64,
+    archive_depth: usize,
+    max_archive_entries: usize,
+    max_unpacked: u64,
+}
+
+impl Default for KeyScanner {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_file_size: MAX_FILE_SIZE,
+            archive_depth: ARCHIVE_DEPTH,
+            max_archive_entries: MAX_ARCHIVE_ENTRIES,
+            max_unpacked: MAX_UNPACKED,
+        }
+    }
+}
+
+impl KeyScanner {
+    /// Creates scanner of all the files up to 1 MiB.
+    ///
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scans only the files matching the pattern or any other included.
+    ///
+    #[inline(always)]
+    pub fn with_include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_string());
+        self
+    }
+
+    /// Skips the files and directories matching the pattern.
+    ///
+    #[inline(always)]
+    pub fn with_exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_string());
+        self
+    }
+
+    /// Skips the files larger than the given size in bytes unread, 1 MiB by default.
+    ///
+    #[inline(always)]
+    pub fn with_max_file_size(mut self, bytes: u64) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Descends the nested archives up to the given depth, 3 by default, zero doesn't unpack archives at all.
+    ///
+    #[inline(always)]
+    pub fn with_archive_depth(mut self, depth: usize) -> Self {
+        self.archive_depth = depth;
+        self
+    }
+
+    /// Fails the archives of more entries or unpacking to more bytes, the nested archives included,
+    /// 10 000 entries and 64 MiB by default. Archive files are read up to the unpacked limit, not the file size.
+    ///
+    #[inline(always)]
+    pub fn with_archive_limits(mut self, entries: usize, unpacked: u64) -> Self {
+        self.max_archive_entries = entries;
+        self.max_unpacked = unpacked;
+        self
+    }
+
+    /// Walks the tree of the root directory, or scans the root file, and runs the weakness checks on every key found.
+    /// Fails only if the root can't be read, the entries that can't be are reported as failures.
+    ///
+    pub fn scan(&self, root: &Path) -> Result<ScanReport, BilboError> {
+        let mut report = ScanReport::default();
+        if !fs::symlink_metadata(root)?.is_dir() {
+            self.scan_file(root, &mut report);
+            return Ok(report);
+        }
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let entries = match fs::read_dir(&dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    report
+                        .failures
+                        .push((dir.display().to_string(), e.to_string()));
+                    continue;
+                }
+            };
+            for entry in entries {
+                let (path, file_type) = match entry.and_then(|e| Ok((e.path(), e.file_type()?))) {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        report
+                            .failures
+                            .push((dir.display().to_string(), e.to_string()));
+                        continue;
+                    }
+                };
+                let relative = path
+                    .strip_prefix(root)
+                    .unwrap_or(&path)
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                if self.excluded(&relative) {
+                    continue;
+                }
+                if file_type.is_dir() {
+                    pending.push(path);
+                } else if file_type.is_file() && self.included(&relative) {
+                    self.scan_file(&path, &mut report);
+                }
+            }
+        }
+        report.keys.sort_by(|a, b| a.origin.cmp(&b.origin));
+
+        Ok(report)
+    }
+
+    /// Scans the whole history of the git repository, the blobs of the commits reachable from any ref,
+    /// so the keys committed and deleted long ago are found too. Every unique blob is scanned once,
+    /// reported with the oldest commit adding it and its path there, matched by the include and exclude patterns.
+    /// Runs the git command, its cat-file streams the blobs one at a time and the blobs too large are skipped unread.
+    ///
+    pub fn scan_git_repo(&self, repo: &Path) -> Result<ScanReport, BilboError> {
+        let log = git(
+            repo,
+            &[
+                "-c",
+                "core.quotePath=false",
+                "log",
+                "--all",
+                "--reverse",
+                "-m",
+                "--no-renames",
+                "--diff-filter=AM",
+                "--raw",
+                "--no-abbrev",
+                "--format=commit %H",
+            ],
+        )?;
+        let log = String::from_utf8_lossy(&log);
+        let mut seen = HashSet::new();
+        let mut blobs = Vec::new();
+        let mut commit = "";
+        for line in log.lines() {
+            if let Some(id) = line.strip_prefix(GIT_COMMIT) {
+                commit = id;
+                continue;
+            }
+            // Raw diff line, colon, old and new mode, old and new blob and status, then tab and path.
+            let Some((meta, path)) = line.strip_prefix(':').and_then(|l| l.split_once('\t')) else {
+                continue;
+            };
+            let fields: Vec<&str> = meta.split_whitespace().collect();
+            let [_, mode, _, blob, ..] = fields[..] else {
+                continue;
+            };
+            if mode.starts_with(GIT_REGULAR_FILE)
+                && !self.excluded(path)
+                && self.included(path)
+                && seen.insert(blob)
+            {
+                blobs.push((commit, path, blob));
+            }
+        }
+
+        let mut report = ScanReport::default();
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(BilboError::GenericError(
+                "git cat-file pipes are not available".to_string(),
+            ));
+        };
+        let ids: String = blobs
+            .iter()
+            .map(|(_, _, blob)| format!("{blob}\n"))
+            .collect();
+        // Blob ids are written aside, the pipes would fill up if all of them were written before reading.
+        let writer = std::thread::spawn(move || stdin.write_all(ids.as_bytes()));
+        let mut stdout = BufReader::new(stdout);
+        for (commit, path, _) in blobs.iter() {
+            // Blob header, id, type and size, followed by its content and line feed.
+            let mut header = String::new();
+            stdout.read_line(&mut header)?;
+            let Some(size) = header
+                .split_whitespace()
+                .nth(2)
+                .and_then(|s| s.parse::<u64>().ok())
+            else {
+                return Err(BilboError::GenericError(format!(
+                    "git cat-file answered {} for {commit}:{path}",
+                    header.trim()
+                )));
+            };
+            if size > self.max_file_size {
+                io::copy(&mut stdout.by_ref().take(size + 1), &mut io::sink())?;
+                report.skipped += 1;
+                continue;
+            }
+            let mut data = vec![0; size as usize + 1];
+            stdout.read_exact(&mut data)?;
+            data.pop();
+            report.files += 1;
+            match self.scan_bytes(path, &data) {
+                Ok(keys) => report.keys.extend(keys.into_iter().map(|mut key| {
+                    key.commit = Some(commit.to_string());
+                    key
+                })),
+                Err(e) => report
+                    .failures
+                    .push((format!("{commit}:{path}"), e.to_string())),
+            }
+        }
+        drop(stdout);
+        writer
+            .join()
+            .map_err(|_| BilboError::GenericError("git cat-file writer panicked".to_string()))??;
+        child.wait()?;
+
+        Ok(report)
+    }
+
+    /// Detects and parses the key material of the data read from the origin, and runs the weakness checks on every key.
+    /// Returns no keys if the data holds no key material the scanner detects.
+    ///
+    pub fn scan_bytes(&self, origin: &str, data: &[u8]) -> Result<Vec<DiscoveredKey>, BilboError> {
+        let Some(format) = detect(data, data.len() as u64) else {
+            return Ok(Vec::new());
+        };
+        let found = match format {
+            KeyFormat::Pem => parse_pem(data)?,
+            KeyFormat::Der => parse_der(data)?.into_iter().collect(),
+            KeyFormat::OpenSsh => parse_openssh(data)?,
+            KeyFormat::Putty => parse_ppk(&String::from_utf8_lossy(data))?,
+            KeyFormat::Jwk => parse_jwk(data)?,
+            KeyFormat::Pgp => pgp::parse_keys(data)?
+                .into_iter()
+                .map(|key| Material::rsa(key.pick_lock).private(key.secret, key.encrypted))
+                .collect(),
+        };
+
+        Ok(found
+            .into_iter()
+            .map(|material| {
+                let mut key = DiscoveredKey {
+                    origin: origin.to_string(),
+                    commit: None,
+                    format,
+                    algorithm: material.algorithm,
+                    bits: material.bits,
+                    private: material.private,
+                    encrypted: material.encrypted,
+                    pick_lock: material.pick_lock,
+                    weaknesses: Vec::new(),
+                };
+                key.check();
+                key
+            })
+            .collect())
+    }
+
+    // Excludes the path if it or any of its directories matches an exclude pattern.
+    #[inline(always)]
+    fn excluded(&self, relative: &str) -> bool {
+        relative
+            .match_indices('/')
+            .map(|(i, _)| &relative[..i])
+            .chain([relative])
+            .any(|path| self.exclude.iter().any(|p| matches_path(p, path)))
+    }
+
+    #[inline(always)]
+    fn included(&self, relative: &str) -> bool {
+        self.include.is_empty() || self.include.iter().any(|p| matches_path(p, relative))
+    }
+
+    // Sniffs the head of the file and reads the rest of it only if key material or archive is detected.
+    #[inline(always)]
+    fn scan_file(&self, path: &Path, report: &mut ScanReport) {
+        let origin = path.display().to_string();
+        let scanned = (|| -> Result<Option<Vec<u8>>, BilboError> {
+            let mut file = File::open(path)?;
+            let size = file.metadata()?.len();
+            let archives = self.archive_depth > 0 && size <= self.max_unpacked;
+            if size > self.max_file_size && !archives {
+                return Ok(None);
+            }
+            let mut data = Vec::new();
+            Read::by_ref(&mut file)
+                .take(SNIFF_LEN)
+                .read_to_end(&mut data)?;
+            let archive = archives && archive_kind(&data).is_some();
+            if size > self.max_file_size && !archive {
+                return Ok(None);
+            }
+            if !archive && detect(&data, size).is_none() {
+                return Ok(Some(Vec::new()));
+            }
+            file.read_to_end(&mut data)?;
+
+            Ok(Some(data))
+        })();
+        match scanned {
+            Ok(Some(data)) if data.is_empty() => report.files += 1,
+            Ok(Some(data)) => {
+                let mut budget = self.max_unpacked;
+                self.scan_entry(&origin, &data, self.archive_depth, &mut budget, report);
+            }
+            Ok(None) => report.skipped += 1,
+            Err(e) => {
+                report.files += 1;
+                report.failures.push((origin, e.to_string()));
+            }
+        }
+    }
+
+    // Scans the file or archive entry, descending into it if it is archive and the depth allows.
+    #[inline(always)]
+    fn scan_entry(
+        &self,
+        origin: &str,
+        data: &[u8],
+        depth: usize,
+        budget: &mut u64,
+        report: &mut ScanReport,
+    ) {
+        let limit = usize::try_from(*budget).unwrap_or(usize::MAX);
+        let scanned = match archive_kind(data) {
+            // Gzip holds single file, scanned at the same level and origin unless it fails to inflate.
+            Some(ArchiveKind::Gzip) if depth > 0 => match gunzip(data, limit) {
+                Ok((_, inflated)) => {
+                    *budget -= inflated.len() as u64;
+                    return self.scan_entry(origin, &inflated, depth, budget, report);
+                }
+                Err(e) => Err(e),
+            },
+            Some(kind) if depth > 0 => self.scan_archive(origin, kind, data, depth, budget, report),
+            _ if data.len() as u64 > self.max_file_size => {
+                report.skipped += 1;
+                return;
+            }
+            _ => self
+                .scan_bytes(origin, data)
+                .map(|keys| report.keys.extend(keys)),
+        };
+        report.files += 1;
+        if let Err(e) = scanned {
+            report.failures.push((origin.to_string(), e.to_string()));
+        }
+    }
+
+    // Unpacks the archive and scans its entries one level deeper.
+    // Every byte inflated is taken from the budget shared by the nested archives.
+    #[inline(always)]
+    fn scan_archive(
+        &self,
+        origin: &str,
+        kind: ArchiveKind,
+        data: &[u8],
+        depth: usize,
+        budget: &mut u64,
+        report: &mut ScanReport,
+    ) -> Result<(), BilboError> {
+        let limit = usize::try_from(*budget).unwrap_or(usize::MAX);
+        match kind {
+            ArchiveKind::Zip => {
+                let entries = unzip(data, self.max_archive_entries, limit)?;
+                *budget -= entries.iter().map(|(_, e)| e.len() as u64).sum::<u64>();
+                for (name, entry) in entries.iter() {
+                    let origin = format!("{origin}{ARCHIVE_ENTRY_SEPARATOR}{name}");
+                    self.scan_entry(&origin, entry, depth - 1, budget, report);
+                }
+            }
+            // Tar, gzip is inflated by scan_entry.
+            _ => {
+                for (name, entry) in untar(data, self.max_archive_entries)? {
+                    let origin = format!("{origin}{ARCHIVE_ENTRY_SEPARATOR}{name}");
+                    self.scan_entry(&origin, entry, depth - 1, budget, report);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "async")]
+impl KeyScanner {
+    /// Async variant of scan, the tree is walked on a blocking thread off the async reactor.
+    ///
+    #[inline(always)]
+    pub fn scan_async(&self, root: PathBuf) -> Blocking<Result<ScanReport, BilboError>> {
+        let scanner = self.clone();
+        Blocking::spawn(move || scanner.scan(&root))
+    }
+
+    /// Async variant of scan_git_repo, the history is scanned on a blocking thread off the async reactor.
+    ///
+    #[inline(always)]
+    pub fn scan_git_repo_async(&self, repo: PathBuf) -> Blocking<Result<ScanReport, BilboError>> {
+        let scanner = self.clone();
+        Blocking::spawn(move || scanner.scan_git_repo(&repo))
+    }
+}
+
+// Runs the git command in the repository and returns its standard output, failing with its standard error.
+#[inline(always)]
+fn git(repo: &Path, args: &[&str]) -> Result<Vec<u8>, BilboError> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()?;
+    if !out.status.success() {
+        return Err(BilboError::GenericError(format!(
+            "git failed: {}",
+            String::from_utf8_lossy(&out.stderr).trim()
+        )));
+    }
+
+    Ok(out.stdout)
+}
+
+/// Detects the format of the key material by the head of the data and its whole size.
+/// Text formats are told by their headers: PEM and ASCII armored OpenPGP by their BEGIN line, PuTTY by its first line,
+/// JWK and JWKS by the JSON object with kty member and OpenSSH public keys by the key type followed by the key blob.
+/// Binary formats are told by their first bytes: DER by the SEQUENCE spanning the whole data,
+/// OpenPGP by the public or secret key packet of a known version.
+///
+pub fn detect(head: &[u8], size: u64) -> Option<KeyFormat> {
+    let text = String::from_utf8_lossy(head);
+    let trimmed = text.trim_start();
+    if trimmed.starts_with(PPK_HEADER) {
+        return Some(KeyFormat::Putty);
+    }
+    if text.contains(PGP_ARMOR_BEGIN) {
+        return Some(KeyFormat::Pgp);
+    }
+    if text.contains(PEM_BEGIN) {
+        return Some(KeyFormat::Pem);
+    }
+    if trimmed.starts_with('{') && text.contains("\"kty\"") {
+        return Some(KeyFormat::Jwk);
+    }
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields
+        .windows(2)
+        .any(|pair| is_key_type(pair[0]) && pair[1].starts_with(BLOB_PREFIX))
+    {
+        return Some(KeyFormat::OpenSsh);
+    }
+    // Definite length of the outer SEQUENCE, short form or long form of one or two octets.
+    let der_size = match head {
+        [DER_SEQUENCE, 0x82, hi, lo, ..] => Some(4 + u16::from_be_bytes([*hi, *lo]) as u64),
+        [DER_SEQUENCE, 0x81, len, ..] => Some(3 + *len as u64),
+        [DER_SEQUENCE, len, ..] if *len < 0x80 => Some(2 + *len as u64),
+        _ => None,
+    };
+    if der_size == Some(size) {
+        return Some(KeyFormat::Der);
+    }
+    if is_pgp_key_packet(head) {
+        return Some(KeyFormat::Pgp);
+    }
+
+    None
+}
+
+// Tells OpenPGP key packet, old or new format, RFC 4880 section 4.2, by its tag and the version of its body.
+#[inline(always)]
+fn is_pgp_key_packet(head: &[u8]) -> bool {
+    let Some(&header) = head.first() else {
+        return false;
+    };
+    if header & 0x80 == 0 {
+        return false;
+    }
+    let (tag, version_at) = if header & 0x40 == 0 {
+        let length_octets = match header & 0x03 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => return false,
+        };
+        ((header >> 2) & 0x0f, 1 + length_octets)
+    } else {
+        let length_octets = match head.get(1) {
+            Some(0..=191) => 1,
+            Some(192..=223) => 2,
+            Some(255) => 5,
+            _ => return false,
+        };
+        (header & 0x3f, 1 + length_octets)
+    };
+
+    matches!(tag, 5 | 6) && matches!(head.get(version_at), Some(3..=6))
+}
+
+/// Matches the path relative to the scanned root against the glob pattern, the name of the file or directory
+/// if the pattern has no `/`, see KeyScanner.
+///
+pub fn matches_path(pattern: &str, relative: &str) -> bool {
+    let relative = relative.trim_start_matches('/');
+    if pattern.contains('/') {
+        return glob(
+            pattern.trim_start_matches('/').as_bytes(),
+            relative.as_bytes(),
+        );
+    }
+    let name = relative.rsplit('/').next().unwrap_or(relative);
+
+    glob(pattern.as_bytes(), name.as_bytes())
+}
+
+// Matches the glob, `**/` matches no segment too.
+#[inline(always)]
+fn glob(pattern: &[u8], path: &[u8]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            rest.strip_prefix(b"/").is_some_and(|rest| glob(rest, path))
+                || (0..=path.len()).any(|i| glob(rest, &path[i..]))
+        }
+        [b'*', rest @ ..] => {
+            for i in 0..=path.len() {
+                if glob(rest, &path[i..]) {
+                    return true;
+                }
+                if path.get(i) == Some(&b'/') {
+                    break;
+                }
+            }
+            false
+        }
+        [b'?', rest @ ..] => path.first().is_some_and(|c| *c != b'/') && glob(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && glob(rest, &path[1..]),
+    }
+}
+
+// Material is the key parsed out of the data, before its origin and format are attached.
+struct Material {
+    algorithm: KeyAlgorithm,
+    bits: u64,
+    private: bool,
+    encrypted: bool,
+    pick_lock: Option<PickLock>,
+}
+
+impl Material {
+    #[inline(always)]
+    fn rsa(pl: PickLock) -> Self {
+        Self {
+            algorithm: KeyAlgorithm::Rsa,
+            bits: pl.bits(),
+            private: false,
+            encrypted: false,
+            pick_lock: Some(pl),
+        }
+    }
+
+    // Key of the algorithm whose public part is not readable, bits unknown.
+    #[inline(always)]
+    fn hidden(algorithm: KeyAlgorithm) -> Self {
+        Self {
+            bits: algorithm.curve_bits().unwrap_or_default(),
+            algorithm,
+            private: false,
+            encrypted: false,
+            pick_lock: None,
+        }
+    }
+
+    #[inline(always)]
+    fn public_key(key: &PKey<Public>) -> Result<Self, BilboError> {
+        let algorithm = match key.id() {
+            Id::RSA => {
+                let rsa = key.rsa()?;
+                return Ok(Self::rsa(PickLock::from_exponent_and_modulus(
+                    BigInt::from_bytes_be(Sign::Plus, &rsa.e().to_vec()),
+                    BigInt::from_bytes_be(Sign::Plus, &rsa.n().to_vec()),
+                )));
+            }
+            Id::DSA => KeyAlgorithm::Dsa,
+            Id::EC => KeyAlgorithm::Ec(
+                match key.ec_key()?.group().curve_name() {
+                    Some(Nid::X9_62_PRIME256V1) => "P-256",
+                    Some(Nid::SECP384R1) => "P-384",
+                    Some(Nid::SECP521R1) => "P-521",
+                    Some(nid) => nid.short_name().unwrap_or("unnamed"),
+                    None => "explicit",
+                }
+                .to_string(),
+            ),
+            Id::ED25519 => KeyAlgorithm::Ed25519,
+            Id::ED448 => KeyAlgorithm::Ed448,
+            id => KeyAlgorithm::Unknown(format!("key type {}", id.as_raw())),
+        };
+
+        Ok(Self {
+            bits: algorithm.curve_bits().unwrap_or(key.bits() as u64),
+            algorithm,
+            private: false,
+            encrypted: false,
+            pick_lock: None,
+        })
+    }
+
+    #[inline(always)]
+    fn private_key(key: PKey<Private>) -> Result<Self, BilboError> {
+        Ok(
+            Self::public_key(&PKey::public_key_from_der(&key.public_key_to_der()?)?)?
+                .private(true, false),
+        )
+    }
+
+    #[inline(always)]
+    fn host_key(key: HostKey) -> Self {
+        match key {
+            HostKey::Rsa(pl) => Self::rsa(pl),
+            HostKey::Curve { key_type, .. } | HostKey::Other(key_type) => {
+                Self::hidden(KeyAlgorithm::from_ssh(&key_type))
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn private(mut self, private: bool, encrypted: bool) -> Self {
+        self.private = private;
+        self.encrypted = encrypted;
+        self
+    }
+}
+
+// Parses the certificates, certificate requests, public and private keys of the PEM blocks, other blocks are skipped.
+#[inline(always)]
+fn parse_pem(data: &[u8]) -> Result<Vec<Material>, BilboError> {
+    let blocks = pem_blocks(&String::from_utf8_lossy(data));
+    let mut found = Vec::new();
+    for block in blocks.iter() {
+        let der = block.contents();
+        // Legacy OpenSSL encryption of PKCS#1 and SEC 1 keys is announced by Proc-Type: 4,ENCRYPTED header.
+        let legacy_encrypted = block
+            .headers()
+            .get(PROC_TYPE)
+            .is_some_and(|t| t.contains("ENCRYPTED"));
+        let material = match block.tag() {
+            "CERTIFICATE" => Material::public_key(&X509::from_der(der)?.public_key()?)?,
+            "CERTIFICATE REQUEST" | "NEW CERTIFICATE REQUEST" => {
+                Material::public_key(&X509Req::from_der(der)?.public_key()?)?
+            }
+            "PUBLIC KEY" => Material::public_key(&PKey::public_key_from_der(der)?)?,
+            "RSA PUBLIC KEY" => {
+                Material::public_key(&PKey::from_rsa(Rsa::public_key_from_der_pkcs1(der)?)?)?
+            }
+            "RSA PRIVATE KEY" if legacy_encrypted => {
+                Material::hidden(KeyAlgorithm::Rsa).private(true, true)
+            }
+            "DSA PRIVATE KEY" if legacy_encrypted => {
+                Material::hidden(KeyAlgorithm::Dsa).private(true, true)
+            }
+            "EC PRIVATE KEY" if legacy_encrypted => {
+                Material::hidden(KeyAlgorithm::Ec("encrypted".to_string())).private(true, true)
+            }
+            // OpenSSL tells PKCS#8 from the traditional PKCS#1, DSA and SEC 1 structures itself.
+            "RSA PRIVATE KEY" | "DSA PRIVATE KEY" | "EC PRIVATE KEY" | "PRIVATE KEY" => {
+                Material::private_key(PKey::private_key_from_der(der)?)?
+            }
+            "ENCRYPTED PRIVATE KEY" => {
+                Material::hidden(KeyAlgorithm::Unknown("encrypted PKCS#8".to_string()))
+                    .private(true, true)
+            }
+            "OPENSSH PRIVATE KEY" => {
+                let (key, encrypted) = openssh_private_public_key(der)?;
+                Material::host_key(key).private(true, encrypted)
+            }
+            _ => continue,
+        };
+        found.push(material);
+    }
+
+    Ok(found)
+}
+
+// Finds the PEM blocks of matching BEGIN and END lines wherever they are, embedded in source code or configuration too.
+// Fragments that are not PEM, such as the BEGIN line alone in string constants, are skipped.
+#[inline(always)]
+fn pem_blocks(text: &str) -> Vec<pem::Pem> {
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        rest = &rest[start..];
+        let label = rest[PEM_BEGIN.len()..]
+            .split_once("-----")
+            .map(|(label, _)| label)
+            .unwrap_or_default();
+        let end_line = format!("-----END {label}-----");
+        match rest.find(&end_line) {
+            Some(end) if !label.contains('\n') => {
+                if let Ok(block) = pem::parse(&rest[..end + end_line.len()]) {
+                    blocks.push(block);
+                }
+                rest = &rest[end + end_line.len()..];
+            }
+            _ => rest = &rest[PEM_BEGIN.len()..],
+        }
+    }
+
+    blocks
+}
+
+// Parses DER certificate, subject public key info, PKCS#1 public key, private key or certificate request,
+// None for other structures.
+#[inline(always)]
+fn parse_der(der: &[u8]) -> Result<Option<Material>, BilboError> {
+    if let Ok(cert) = X509::from_der(der) {
+        return Material::public_key(&cert.public_key()?).map(Some);
+    }
+    if let Ok(key) = PKey::public_key_from_der(der) {
+        return Material::public_key(&key).map(Some);
+    }
+    if let Ok(rsa) = Rsa::public_key_from_der_pkcs1(der) {
+        return Material::public_key(&PKey::from_rsa(rsa)?).map(Some);
+    }
+    if let Ok(key) = PKey::private_key_from_der(der) {
+        return Material::private_key(key).map(Some);
+    }
+    if let Ok(csr) = X509Req::from_der(der) {
+        return Material::public_key(&csr.public_key()?).map(Some);
+    }
+
+    Ok(None)
+}
+
+// Parses every key line of id_*.pub, authorized_keys or known_hosts.
+#[inline(always)]
+fn parse_openssh(data: &[u8]) -> Result<Vec<Material>, BilboError> {
+    let mut found = Vec::new();
+    for (number, line) in String::from_utf8_lossy(data).lines().enumerate() {
+        match openssh_public_key(line) {
+            Ok(Some(key)) => found.push(Material::host_key(key)),
+            Ok(None) => (),
+            Err(e) => {
+                return Err(BilboError::InvalidKeyMaterial {
+                    reason: format!("invalid public key at line {}: {e}", number + 1),
+                })
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+// Parses PuTTY private key file, its key type is on the header line and Encryption header tells if it is protected.
+#[inline(always)]
+fn parse_ppk(ppk: &str) -> Result<Vec<Material>, BilboError> {
+    let key_type = ppk
+        .lines()
+        .next()
+        .and_then(|header| header.split_once(':'))
+        .map(|(_, key_type)| key_type.trim())
+        .unwrap_or_default();
+    let encrypted = ppk
+        .lines()
+        .find_map(|l| l.trim().strip_prefix(PPK_ENCRYPTION))
+        .is_some_and(|cipher| cipher.trim() != "none");
+    let material = match ppk_pick_lock(ppk)? {
+        Some(pl) => Material::rsa(pl),
+        None => Material::hidden(KeyAlgorithm::from_ssh(key_type)),
+    };
+
+    Ok(vec![material.private(true, encrypted)])
+}
+
+// Parses single JWK or JWKS, the keys with d member are private.
+#[inline(always)]
+fn parse_jwk(data: &[u8]) -> Result<Vec<Material>, BilboError> {
+    let jwks = match serde_json::from_slice::<Jwks>(data) {
+        Ok(jwks) => jwks.keys,
+        Err(_) => vec![serde_json::from_slice::<Jwk>(data)?],
+    };
+    let mut found = Vec::new();
+    for jwk in jwks.iter() {
+        let material = match jwk.public_key()? {
+            Some(key) => Material::public_key(&key)?,
+            None => Material::hidden(KeyAlgorithm::Unknown(jwk.kty.clone())),
+        };
+        found.push(material.private(jwk.d.is_some(), false));
+    }
+
+    Ok(found)
+}
+
+// Returns the directory, or the archive directory, holding the key of the origin.
+#[inline(always)]
+fn place_of(origin: &str) -> &str {
+    origin.rsplit_once(['/', '!']).map_or("", |(dir, _)| dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssh::{put_mpint, put_string};
+    use num_bigint::BigUint;
+    use num_prime::nt_funcs::next_prime;
+    use openssl::base64::encode_block;
+    use openssl::bn::BigNum;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::symm::Cipher;
+    use std::env::temp_dir;
+
+    #[test]
+    fn it_should_match_include_and_exclude_globs() {
+        assert!(matches_path("*.pem", "certs/server.pem"));
+        assert!(!matches_path("*.pem", "certs/server.pem.bak"));
+        assert!(matches_path("certs/*.pem", "certs/server.pem"));
+        assert!(!matches_path("certs/*.pem", "certs/old/server.pem"));
+        assert!(matches_path("certs/**/*.pem", "certs/server.pem"));
+        assert!(matches_path("**/old/*.pem", "certs/old/server.pem"));
+        assert!(matches_path("id_?sa", "home/.ssh/id_rsa"));
+        assert!(matches_path("target", "target"));
+    }
+
+    #[test]
+    fn it_should_scan_tree_for_key_material_of_all_formats() -> Result<(), BilboError> {
+        let root = temp_dir().join(format!("bilbo-scanner-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("ssh"))?;
+        fs::create_dir_all(root.join("target"))?;
+
+        let weak = Rsa::from_public_components(
+            BigNum::from_slice(&close_primes_modulus()?.to_bytes_be().1)?,
+            BigNum::from_u32(65_537)?,
+        )?;
+        fs::write(root.join("weak.pem"), weak.public_key_to_pem()?)?;
+        fs::write(root.join("weak.der"), weak.public_key_to_der()?)?;
+        let strong = Rsa::generate(2048)?;
+        fs::write(root.join("server.key"), strong.private_key_to_pem()?)?;
+        let ec = EcKey::generate(&*EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?)?;
+        fs::write(
+            root.join("ec.pem"),
+            PKey::from_ec_key(ec)?
+                .private_key_to_pem_pkcs8_passphrase(Cipher::aes_128_cbc(), b"secret")?,
+        )?;
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-ed25519");
+        put_string(&mut blob, &[1; 32]);
+        fs::write(
+            root.join("ssh/authorized_keys"),
+            format!("# keys\nno-pty ssh-ed25519 {} admin\n", encode_block(&blob)),
+        )?;
+        fs::write(
+            root.join("ssh/jwks.json"),
+            r#"{"keys":[{"kty":"OKP","crv":"Ed25519","x":"AA","d":"AA"}]}"#,
+        )?;
+        fs::write(root.join("target/weak.pem"), weak.public_key_to_pem()?)?;
+        fs::write(root.join("README.md"), "No keys here.\n")?;
+        fs::write(root.join("huge.bin"), vec![0; 2048])?;
+
+        let report = KeyScanner::new()
+            .with_exclude("target")
+            .with_max_file_size(1024 * 1024)
+            .scan(&root)?;
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!(report.files, 8);
+        let found: Vec<String> = report
+            .keys
+            .iter()
+            .map(|k| {
+                k.to_string()
+                    .trim_start_matches(&format!("{}/", root.display()))
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "ec.pem PEM encrypted PKCS#8 0 bits encrypted private key",
+                "server.key PEM RSA 2048 bits private key, unencrypted private key",
+                "ssh/authorized_keys OpenSSH Ed25519 253 bits public key",
+                "ssh/jwks.json JWK OKP 0 bits private key, unencrypted private key",
+                "weak.der DER RSA 1024 bits public key, key shorter than 2048 bits, modulus of close primes",
+                "weak.pem PEM RSA 1024 bits public key, key shorter than 2048 bits, modulus of close primes",
+            ]
+        );
+        assert!(!report.keys[0].is_weak() && report.keys[1].is_weak());
+        assert!(report.keys[4].pick_lock.is_some());
+        let audit = report.audit_report();
+        let severities: Vec<Severity> = audit.findings.iter().map(|f| f.severity).collect();
+        assert_eq!(
+            severities,
+            vec![
+                Severity::Critical,
+                Severity::Critical,
+                Severity::Medium,
+                Severity::Medium,
+                Severity::Medium,
+                Severity::Medium,
+            ]
+        );
+        assert_eq!(audit.findings[0].attack.as_deref(), Some("weak"));
+        assert!(audit.findings[0]
+            .origin
+            .as_deref()
+            .unwrap()
+            .ends_with("weak.der"));
+        assert_eq!(
+            audit.findings[2].fingerprint,
+            Some(
+                report.keys[1]
+                    .pick_lock
+                    .as_ref()
+                    .unwrap()
+                    .modulus_fingerprint()
+            )
+        );
+
+        let report = KeyScanner::new()
+            .with_include("*.pem")
+            .with_include("*.key")
+            .with_exclude("**/target")
+            .with_max_file_size(1024)
+            .scan(&root)?;
+        assert_eq!(report.keys.len(), 2);
+        assert_eq!(report.skipped, 1);
+        fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_scan_keys_bundled_in_nested_archives() -> Result<(), BilboError> {
+        let root = temp_dir().join(format!("bilbo-scanner-archives-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root)?;
+        let weak = Rsa::from_public_components(
+            BigNum::from_slice(&close_primes_modulus()?.to_bytes_be().1)?,
+            BigNum::from_u32(65_537)?,
+        )?;
+        let tar_gz = gzip(&tar(&[
+            ("keys/weak.der", &weak.public_key_to_der()?),
+            ("keys/README", b"No keys here."),
+        ]));
+        let jar = zip(&[
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n"),
+            ("META-INF/keys.tar.gz", &tar_gz),
+            ("weak.pem", &weak.public_key_to_pem()?),
+        ]);
+        fs::write(root.join("app.jar"), &jar)?;
+        fs::write(root.join("app.tar"), tar(&[("lib/app.jar", &jar)]))?;
+
+        let report = KeyScanner::new().scan(&root)?;
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        let found: Vec<String> = report
+            .keys
+            .iter()
+            .map(|k| {
+                k.to_string()
+                    .trim_start_matches(&format!("{}/", root.display()))
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                "app.jar!META-INF/keys.tar.gz!keys/weak.der DER RSA 1024 bits public key, key shorter than 2048 bits, modulus of close primes",
+                "app.jar!weak.pem PEM RSA 1024 bits public key, key shorter than 2048 bits, modulus of close primes",
+                "app.tar!lib/app.jar!META-INF/keys.tar.gz!keys/weak.der DER RSA 1024 bits public key, key shorter than 2048 bits, modulus of close primes",
+                "app.tar!lib/app.jar!weak.pem PEM RSA 1024 bits public key, key shorter than 2048 bits, modulus of close primes",
+            ]
+        );
+
+        // Stored archives show the PEM text of their entries, found as is without unpacking them.
+        let report = KeyScanner::new().with_archive_depth(1).scan(&root)?;
+        assert!(report
+            .keys
+            .iter()
+            .any(|k| k.origin.ends_with("app.jar!weak.pem")));
+        assert!(report.keys.iter().all(|k| k.format == KeyFormat::Pem));
+        let report = KeyScanner::new().with_archive_depth(0).scan(&root)?;
+        assert!(report.keys.iter().all(|k| !k.origin.contains('!')));
+        let report = KeyScanner::new()
+            .with_archive_limits(2, 1024 * 1024)
+            .scan(&root)?;
+        assert_eq!(report.failures.len(), 2);
+        assert!(report.keys.is_empty());
+
+        fs::remove_dir_all(&root)?;
+
+        Ok(())
+    }
+
+    // Builds gzip of single stored deflate block.
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 1];
+        out.extend((data.len() as u16).to_le_bytes());
+        out.extend((!(data.len() as u16)).to_le_bytes());
+        out.extend_from_slice(data);
+        out.extend(crate::archive::crc32(data).to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+        out
+    }
+
+    // Builds ustar archive of regular files.
+    fn tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (name, data) in entries {
+            let mut header = [0_u8; 512];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", data.len()).as_bytes());
+            header[148..156].fill(b' ');
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+            let sum: u32 = header.iter().map(|b| *b as u32).sum();
+            header[148..155].copy_from_slice(format!("{sum:06o}\0").as_bytes());
+            out.extend(header);
+            out.extend_from_slice(data);
+            out.resize(out.len().div_ceil(512) * 512, 0);
+        }
+        out.extend([0; 1024]);
+        out
+    }
+
+    // Builds zip archive of stored files.
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let (mut out, mut directory) = (Vec::new(), Vec::new());
+        for (name, data) in entries {
+            let mut fields = Vec::new();
+            fields.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+            fields.extend(crate::archive::crc32(data).to_le_bytes());
+            fields.extend((data.len() as u32).to_le_bytes());
+            fields.extend((data.len() as u32).to_le_bytes());
+            fields.extend((name.len() as u16).to_le_bytes());
+            fields.extend([0, 0]);
+            directory.extend(0x02014b50_u32.to_le_bytes());
+            directory.extend([20, 0]);
+            directory.extend(&fields);
+            directory.extend([0; 10]);
+            directory.extend((out.len() as u32).to_le_bytes());
+            directory.extend(name.as_bytes());
+            out.extend(0x04034b50_u32.to_le_bytes());
+            out.extend(&fields);
+            out.extend(name.as_bytes());
+            out.extend_from_slice(data);
+        }
+        let offset = out.len() as u32;
+        out.extend(&directory);
+        out.extend(0x06054b50_u32.to_le_bytes());
+        out.extend([0; 4]);
+        out.extend((entries.len() as u16).to_le_bytes());
+        out.extend((entries.len() as u16).to_le_bytes());
+        out.extend((directory.len() as u32).to_le_bytes());
+        out.extend(offset.to_le_bytes());
+        out.extend([0, 0]);
+        out
+    }
+
+    #[test]
+    fn it_should_scan_git_history_for_deleted_keys() -> Result<(), BilboError> {
+        let repo = temp_dir().join(format!("bilbo-scanner-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(repo.join("deploy"))?;
+        let run = |args: &[&str]| -> Result<String, BilboError> {
+            let mut all = vec!["-c", "user.name=bilbo", "-c", "user.email=bilbo@localhost"];
+            all.extend_from_slice(args);
+            Ok(String::from_utf8_lossy(&git(&repo, &all)?)
+                .trim()
+                .to_string())
+        };
+        run(&["init", "-q"])?;
+
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-rsa");
+        put_mpint(&mut blob, &BigInt::from(65_537));
+        put_mpint(&mut blob, &close_primes_modulus()?);
+        let line = format!("ssh-rsa {} deploy\n", encode_block(&blob));
+        fs::write(repo.join("deploy/id_rsa.pub"), &line)?;
+        fs::write(repo.join("notes.txt"), "No keys here.\n")?;
+        run(&["add", "-A"])?;
+        run(&["commit", "-q", "-m", "Add deploy key"])?;
+        let added = run(&["rev-parse", "HEAD"])?;
+        // Key moved and deleted later, found once with the commit adding it.
+        fs::remove_file(repo.join("deploy/id_rsa.pub"))?;
+        fs::write(repo.join("deploy/copy.pub"), &line)?;
+        fs::write(repo.join("vendored.pem"), &line)?;
+        fs::write(repo.join("huge.bin"), vec![0; 2048])?;
+        run(&["add", "-A"])?;
+        run(&["commit", "-q", "-m", "Remove deploy key"])?;
+        fs::remove_file(repo.join("deploy/copy.pub"))?;
+        run(&["add", "-A"])?;
+        run(&["commit", "-q", "-m", "Remove copy"])?;
+
+        let report = KeyScanner::new()
+            .with_exclude("vendored.pem")
+            .with_max_file_size(1024)
+            .scan_git_repo(&repo)?;
+        assert!(report.failures.is_empty(), "{:?}", report.failures);
+        assert_eq!((report.files, report.skipped), (2, 1));
+        assert_eq!(report.keys.len(), 1);
+        let key = &report.keys[0];
+        assert_eq!(key.commit.as_deref(), Some(added.as_str()));
+        assert_eq!(key.origin, "deploy/id_rsa.pub");
+        assert!(key.is_crackable());
+        assert!(key
+            .to_string()
+            .starts_with(&format!("{added}:deploy/id_rsa.pub OpenSSH RSA 1024 bits")));
+        assert!(KeyScanner::new()
+            .scan_git_repo(&repo.join("missing"))
+            .is_err());
+        fs::remove_dir_all(&repo)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_detect_key_material_by_its_head() -> Result<(), BilboError> {
+        assert_eq!(
+            detect(b"PuTTY-User-Key-File-3: ssh-rsa\n", 31),
+            Some(KeyFormat::Putty)
+        );
+        assert_eq!(
+            detect(b"-----BEGIN PGP PUBLIC KEY BLOCK-----", 36),
+            Some(KeyFormat::Pgp)
+        );
+        assert_eq!(detect(&[0x99, 0x01, 0x0d, 4], 272), Some(KeyFormat::Pgp));
+        assert_eq!(detect(&[0xc6, 0x33, 4], 53), Some(KeyFormat::Pgp));
+        assert_eq!(detect(&[0x30, 0x82, 0x01, 0x22], 294), Some(KeyFormat::Der));
+        assert_eq!(detect(&[0x30, 0x82, 0x01, 0x22], 300), None);
+        assert_eq!(detect(b"  {\"kty\":\"RSA\"}", 15), Some(KeyFormat::Jwk));
+        assert_eq!(detect(b"fn main() {}", 12), None);
+        let scanner = KeyScanner::new();
+        assert!(scanner.scan_bytes("empty", b"")?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_report_moduli_shared_by_distinct_keys() -> Result<(), BilboError> {
+        let scanner = KeyScanner::new();
+        let (cloned, own) = (Rsa::generate(2048)?, Rsa::generate(2048)?);
+        let mut report = ScanReport::default();
+        for (origin, pem) in [
+            ("appliance-1/key.pem", cloned.private_key_to_pem()?),
+            ("appliance-1/key.pub", cloned.public_key_to_pem()?),
+            ("appliance-2/key.pem", cloned.private_key_to_pem()?),
+            ("host/key.pem", own.private_key_to_pem()?),
+            ("host/key.pub", own.public_key_to_pem()?),
+        ] {
+            report.keys.extend(scanner.scan_bytes(origin, &pem)?);
+        }
+        // The same file in git history is the same copy.
+        let mut history = scanner.scan_bytes("host/key.pem", &own.private_key_to_pem()?)?;
+        history[0].commit = Some("0a1b2c3".to_string());
+        report.keys.append(&mut history);
+
+        let shared = report.shared_moduli();
+        assert_eq!(shared.len(), 1);
+        let (fingerprint, keys) = &shared[0];
+        assert_eq!(keys.len(), 3);
+        assert!(keys.iter().all(|k| k.origin.starts_with("appliance-")));
+        let audit = report.audit_report();
+        let findings: Vec<&Finding> = audit
+            .findings
+            .iter()
+            .filter(|f| {
+                f.fingerprint.as_ref() == Some(fingerprint)
+                    && f.detail.starts_with("modulus shared")
+            })
+            .collect();
+        assert_eq!(findings.len(), 3);
+        assert_eq!(findings[2].origin.as_deref(), Some("appliance-2/key.pem"));
+
+        // Certificates and public keys of one key reissued elsewhere share it too.
+        let mut report = ScanReport::default();
+        for origin in ["site-a/cert.pub", "site-b/cert.pub"] {
+            report
+                .keys
+                .extend(scanner.scan_bytes(origin, &own.public_key_to_pem()?)?);
+        }
+        assert_eq!(report.shared_moduli().len(), 1);
+        assert_eq!(place_of("site-a/cert.pub"), "site-a");
+        assert_eq!(
+            place_of("site-a/app.jar!META-INF/key.pem"),
+            "site-a/app.jar!META-INF"
+        );
+        assert_eq!(place_of("site-a/app.jar!key.pem"), "site-a/app.jar");
+        assert_eq!(place_of("key.pem"), "");
+
+        Ok(())
+    }
+
+    // Modulus of 1024 bits of primes next to each other, short and picked by Fermat.
+    fn close_primes_modulus() -> Result<BigInt, BilboError> {
+        let mut p = BigNum::new()?;
+        p.generate_prime(512, false, None, None)?;
+        let p = BigUint::from_bytes_be(&p.to_vec());
+        let q = next_prime(&(&p + 1_u32), None).unwrap();
+
+        Ok(BigInt::from(p * q))
+    }
+}
@@ -0,0 +1,97 @@
+use serde_json::{Map, Value};
+
+use crate::errors::BilboError;
+
+/// Version of every JSON document bilbo serializes, stored in the `schema_version` field.
+///
+/// Compatibility guarantees:
+///  - Within the same version fields are only added, never removed, renamed or changed in meaning.
+///  - Bumping the version always comes with a migration in this module,
+///    so documents written by any older bilbo can be read by the newer one.
+///  - Documents of a version newer than this one are rejected rather than misread.
+///
+pub const SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA_VERSION_FIELD: &str = "schema_version";
+
+/// Returns the schema version of the given document.
+/// Documents written before versioning was introduced carry no version field and are version 0.
+///
+#[inline(always)]
+pub fn version_of(doc: &Value) -> Result<u32, BilboError> {
+    match doc.get(SCHEMA_VERSION_FIELD) {
+        None => Ok(0),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| {
+                BilboError::GenericError(format!("invalid {SCHEMA_VERSION_FIELD} value {v}"))
+            }),
+    }
+}
+
+/// Upgrades the given document of any supported older version to the current SCHEMA_VERSION.
+///
+#[inline(always)]
+pub fn upgrade(mut doc: Value) -> Result<Value, BilboError> {
+    let mut version = version_of(&doc)?;
+    if version > SCHEMA_VERSION {
+        return Err(BilboError::GenericError(format!(
+            "schema version {version} is newer than supported version {SCHEMA_VERSION}, please upgrade bilbo"
+        )));
+    }
+    while version < SCHEMA_VERSION {
+        doc = match version {
+            0 => from_v0(doc)?,
+            _ => unreachable!("missing migration from schema version {version}"),
+        };
+        version += 1;
+    }
+
+    Ok(doc)
+}
+
+// Version 0 documents are identical to version 1 apart from the missing version field.
+#[inline(always)]
+fn from_v0(doc: Value) -> Result<Value, BilboError> {
+    let Value::Object(fields) = doc else {
+        return Err(BilboError::GenericError(
+            "expected JSON object document".to_string(),
+        ));
+    };
+    let mut upgraded = Map::with_capacity(fields.len() + 1);
+    upgraded.insert(SCHEMA_VERSION_FIELD.to_string(), Value::from(1));
+    upgraded.extend(fields);
+
+    Ok(Value::Object(upgraded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_should_upgrade_unversioned_documents() -> Result<(), BilboError> {
+        let doc = json!({"event": "attack_started", "attack": "weak", "key": "00"});
+        assert_eq!(version_of(&doc)?, 0);
+
+        let doc = upgrade(doc)?;
+        assert_eq!(version_of(&doc)?, SCHEMA_VERSION);
+        assert_eq!(doc["attack"], "weak");
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_documents_from_the_future() {
+        let given = [
+            json!({"schema_version": SCHEMA_VERSION + 1}),
+            json!({"schema_version": "one"}),
+            json!(["not", "an", "object"]),
+        ];
+        for g in given {
+            assert!(upgrade(g).is_err());
+        }
+    }
+}
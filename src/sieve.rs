@@ -0,0 +1,211 @@
+use std::ops::Deref;
+use std::sync::{Arc, RwLock};
+
+// Odd numbers per segment, 2^18 bits, the segment stays in the 32 KiB L1 cache.
+const SEGMENT_BITS: u64 = 1 << 18;
+// Primes below it cross off whole 64 bit words by precomputed masks, larger ones single bits.
+const LANE_PRIMES_BOUND: u64 = 64;
+// Largest bound kept in the process wide cache, 2^26 as the sieves of the attacks, about 31 MiB of primes.
+const MAX_CACHED_BOUND: u64 = 1 << 26;
+
+// Primes up to the bound sieved so far, shared by all the callers.
+static CACHE: RwLock<(u64, Option<Arc<Vec<u64>>>)> = RwLock::new((0, None));
+
+/// Primes is the ascending list of the primes up to a bound, a view of the process wide cache
+/// shared without copying, it derefs to the slice of the primes.
+///
+#[derive(Debug, Clone)]
+pub struct Primes {
+    all: Arc<Vec<u64>>,
+    len: usize,
+}
+
+impl Deref for Primes {
+    type Target = [u64];
+
+    #[inline(always)]
+    fn deref(&self) -> &[u64] {
+        &self.all[..self.len]
+    }
+}
+
+/// Returns the primes up to given bound from the process wide cache, sieving the missing range once,
+/// so the attacks asking for the primes of the same bounds over and over, every ECM curve and p − 1 run,
+/// sieve them once per process. Bounds above 2^26 are sieved for the call alone.
+///
+pub fn primes(bound: u64) -> Primes {
+    if bound > MAX_CACHED_BOUND {
+        let all = Arc::new(sieve(bound));
+        let len = all.len();
+        return Primes { all, len };
+    }
+    if let Ok(cache) = CACHE.read() {
+        if let (sieved, Some(all)) = &*cache {
+            if *sieved >= bound {
+                let len = all.partition_point(|p| *p <= bound);
+                return Primes {
+                    all: all.clone(),
+                    len,
+                };
+            }
+        }
+    }
+    let Ok(mut cache) = CACHE.write() else {
+        let all = Arc::new(sieve(bound));
+        let len = all.len();
+        return Primes { all, len };
+    };
+    // Doubling the bound on growth keeps the resieving of a slowly growing bound linear.
+    let (sieved, known) = &*cache;
+    let target = bound.max(sieved.saturating_mul(2)).min(MAX_CACHED_BOUND);
+    if *sieved < target {
+        let mut all = known.as_ref().map_or_else(Vec::new, |all| all.to_vec());
+        all.extend(sieve_range(sieved + 1, target));
+        *cache = (target, Some(Arc::new(all)));
+    }
+    let all = cache.1.clone().unwrap_or_default();
+    let len = all.partition_point(|p| *p <= bound);
+
+    Primes { all, len }
+}
+
+/// Returns all the primes up to given bound, segmented sieve of Eratosthenes over the odd numbers
+/// with a bit per number, 64 numbers per word. Primes below 64 cross off a whole word at once
+/// by a precomputed mask of its phase, larger primes hit at most one bit of a word and cross off single bits.
+///
+pub fn sieve(bound: u64) -> Vec<u64> {
+    sieve_range(2, bound)
+}
+
+// Returns the primes from lo to hi inclusive, segment by segment.
+fn sieve_range(lo: u64, hi: u64) -> Vec<u64> {
+    let mut primes = Vec::new();
+    if hi < lo.max(2) {
+        return primes;
+    }
+    if lo <= 2 {
+        primes.push(2);
+    }
+    let base = base_primes(hi.isqrt());
+    let lanes: Vec<(u64, Vec<u64>)> = base
+        .iter()
+        .copied()
+        .filter(|p| *p < LANE_PRIMES_BOUND)
+        .map(|p| (p, lane_masks(p)))
+        .collect();
+    // Bit i of the segment starting at odd `start` is the number start + 2i.
+    let mut start = lo.max(3) | 1;
+    let mut words = vec![0_u64; (SEGMENT_BITS / 64) as usize];
+    while start <= hi {
+        let bits = SEGMENT_BITS.min((hi - start) / 2 + 1);
+        let used = bits.div_ceil(64) as usize;
+        words[..used].fill(u64::MAX);
+        if !bits.is_multiple_of(64) {
+            words[used - 1] = (1_u64 << (bits % 64)) - 1;
+        }
+        for (p, masks) in lanes.iter() {
+            // Bit j is a multiple of p when start + 2j ≡ 0 mod p, that is r + j ≡ 0 mod p of phase r = start · 2⁻¹ mod p,
+            // the next word's phase is 64 bits later.
+            let mut phase = start % p * p.div_ceil(2) % p;
+            for word in words[..used].iter_mut() {
+                *word &= !masks[phase as usize];
+                phase = (phase + 64) % p;
+            }
+        }
+        for p in base.iter().copied().filter(|p| *p >= LANE_PRIMES_BOUND) {
+            // First odd multiple of p in the segment, not below p².
+            let mut m = (p * p).max(start.div_ceil(p) * p);
+            if m % 2 == 0 {
+                m += p;
+            }
+            let mut bit = (m - start) / 2;
+            while bit < bits {
+                words[(bit / 64) as usize] &= !(1_u64 << (bit % 64));
+                bit += p;
+            }
+        }
+        for (i, word) in words[..used].iter().enumerate() {
+            let mut word = *word;
+            while word != 0 {
+                let bit = i as u64 * 64 + word.trailing_zeros() as u64;
+                primes.push(start + 2 * bit);
+                word &= word - 1;
+            }
+        }
+        start += 2 * SEGMENT_BITS;
+    }
+    // The lane masks cross off the small primes themselves, they are put back here.
+    let small = base
+        .iter()
+        .copied()
+        .filter(|p| *p < LANE_PRIMES_BOUND && *p >= lo.max(3) && *p <= hi);
+    let mut all: Vec<u64> = small.collect();
+    if all.is_empty() {
+        return primes;
+    }
+    all.append(&mut primes);
+    all.sort_unstable();
+
+    all
+}
+
+// Returns the odd primes up to bound by the plain sieve, the sieving primes of the segments.
+fn base_primes(bound: u64) -> Vec<u64> {
+    let mut composite = vec![false; bound as usize + 1];
+    let mut primes = Vec::new();
+    for i in (3..=bound as usize).step_by(2) {
+        if composite[i] {
+            continue;
+        }
+        primes.push(i as u64);
+        for j in (i * i..=bound as usize).step_by(2 * i) {
+            composite[j] = true;
+        }
+    }
+
+    primes
+}
+
+// Returns the mask of every phase r of the word, bit j set when the odd number of bit j is a multiple of p,
+// given the number of bit 0 has residue class index r, (r + j) ≡ 0 mod p.
+fn lane_masks(p: u64) -> Vec<u64> {
+    (0..p)
+        .map(|r| {
+            (0..64)
+                .filter(|j| (r + j) % p == 0)
+                .fold(0, |mask, j| mask | 1_u64 << j)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_should_sieve_primes_across_segments() {
+        let naive = |bound: u64| -> Vec<u64> {
+            (2..=bound)
+                .filter(|n| (2..).take_while(|d| d * d <= *n).all(|d| n % d != 0))
+                .collect()
+        };
+        for bound in [0, 1, 2, 3, 20, 64, 127, 1_000] {
+            assert_eq!(sieve(bound), naive(bound), "bound {bound}");
+        }
+        let bound = 2 * 2 * SEGMENT_BITS + 1_001;
+        let expected = naive(bound);
+        assert_eq!(sieve(bound), expected);
+        assert_eq!(
+            sieve_range(1_000_000, bound),
+            expected
+                .iter()
+                .copied()
+                .filter(|p| *p >= 1_000_000)
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(&primes(bound)[..], &expected[..]);
+        assert_eq!(&primes(100)[..], &expected[..25]);
+        assert_eq!(primes(1).len(), 0);
+    }
+}
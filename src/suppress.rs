@@ -0,0 +1,181 @@
+use std::io::BufRead;
+
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::errors::BilboError;
+
+// Keys are identified by the hex encoded SHA-256 of the modulus.
+const FINGERPRINT_LEN: usize = 64;
+const ANY_ATTACK: &str = "*";
+
+/// Suppression is an accepted risk, findings of the key by the attack are not reported.
+/// Attack None suppresses findings of the key by every attack.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suppression {
+    pub key: String,
+    pub attack: Option<String>,
+    pub reason: String,
+}
+
+impl Suppression {
+    /// Returns true if the suppression covers the finding of the key by the attack.
+    ///
+    #[inline(always)]
+    pub fn covers(&self, key: &str, attack: &str) -> bool {
+        self.key.eq_ignore_ascii_case(key) && self.attack.as_deref().is_none_or(|a| a == attack)
+    }
+}
+
+/// Allowlist of accepted risks and known test keys, read from the text file, one suppression per line:
+///
+/// ```text
+/// # fingerprint                                                    attack  reason
+/// aed8f64f94b262892e6f08ad971b55ae7984877035f2ffaf8a2ef1a659bd717e         # test key of the fixtures
+/// 5f1c0e2d6b8a4c3f9e7d1b0a2c4e6f8091a3b5c7d9e1f3a5b7c9d1e3f5a7b9c1 weak    # accepted until rotation
+/// ```
+///
+/// The key is the SHA-256 of the modulus as written to the audit log, attack `*` or no attack matches every attack,
+/// text after `#` is the reason reported when the finding is suppressed.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Allowlist {
+    suppressions: Vec<Suppression>,
+}
+
+impl Allowlist {
+    /// Reads the allowlist from the text format.
+    ///
+    pub fn read(reader: impl BufRead) -> Result<Self, BilboError> {
+        let mut suppressions = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            let (rule, reason) = line.split_once('#').unwrap_or((&line, ""));
+            let words: Vec<&str> = rule.split_whitespace().collect();
+            let (key, attack) = match words.as_slice() {
+                [] => continue,
+                [key] => (*key, None),
+                [key, ANY_ATTACK] => (*key, None),
+                [key, attack] => (*key, Some(attack.to_string())),
+                _ => {
+                    return Err(BilboError::GenericError(format!(
+                        "line {}: expected '<fingerprint> [attack] [# reason]', found {line:?}",
+                        number + 1
+                    )))
+                }
+            };
+            if key.len() != FINGERPRINT_LEN || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(BilboError::GenericError(format!(
+                    "line {}: {key} is not SHA-256 fingerprint of the modulus",
+                    number + 1
+                )));
+            }
+            suppressions.push(Suppression {
+                key: key.to_ascii_lowercase(),
+                attack,
+                reason: reason.trim().to_string(),
+            });
+        }
+
+        Ok(Self { suppressions })
+    }
+
+    /// Returns the suppression covering the finding of the key by the attack, if any.
+    ///
+    #[inline(always)]
+    pub fn suppression(&self, key: &str, attack: &str) -> Option<&Suppression> {
+        self.suppressions.iter().find(|s| s.covers(key, attack))
+    }
+
+    /// Returns all suppressions of the allowlist.
+    ///
+    #[inline(always)]
+    pub fn suppressions(&self) -> &[Suppression] {
+        &self.suppressions
+    }
+
+    /// Drops finding records covered by the allowlist, other records are kept in order.
+    ///
+    #[inline(always)]
+    pub fn filter(&self, records: Vec<AuditRecord>) -> Vec<AuditRecord> {
+        records
+            .into_iter()
+            .filter(|r| match &r.event {
+                AuditEvent::Finding { attack, key, .. } => self.suppression(key, attack).is_none(),
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::found_by;
+
+    #[test]
+    fn it_should_suppress_every_attack_of_allowlisted_key() -> Result<(), BilboError> {
+        let test_key = "a".repeat(FINGERPRINT_LEN);
+        let given = format!("# allowlist\n\n{test_key}  # test key\n");
+
+        let allowlist = Allowlist::read(given.as_bytes())?;
+        assert_eq!(allowlist.suppressions().len(), 1);
+        for attack in ["weak", "strong"] {
+            assert_eq!(
+                allowlist
+                    .suppression(&test_key, attack)
+                    .map(|s| s.reason.as_str()),
+                Some("test key")
+            );
+        }
+        assert!(allowlist
+            .suppression(&"b".repeat(FINGERPRINT_LEN), "weak")
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_suppress_only_the_given_attack() -> Result<(), BilboError> {
+        let accepted = "B".repeat(FINGERPRINT_LEN);
+        let given = format!("{accepted} weak # accepted risk\n");
+
+        let allowlist = Allowlist::read(given.as_bytes())?;
+        assert!(allowlist
+            .suppression(&"b".repeat(FINGERPRINT_LEN), "weak")
+            .is_some());
+        assert!(allowlist.suppression(&accepted, "strong").is_none());
+        let any = Allowlist::read(format!("{accepted} *").as_bytes())?;
+        assert!(any.suppression(&accepted, "strong").is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_filter_suppressed_findings_of_the_audit_log() -> Result<(), BilboError> {
+        let (test_key, other) = ("a".repeat(FINGERPRINT_LEN), "c".repeat(FINGERPRINT_LEN));
+        let allowlist = Allowlist::read(test_key.as_bytes())?;
+
+        let kept = allowlist.filter(vec![
+            found_by(&test_key, "strong"),
+            found_by(&other, "strong"),
+        ]);
+        assert_eq!(kept, vec![found_by(&other, "strong")]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_should_reject_malformed_lines() {
+        let key = "a".repeat(FINGERPRINT_LEN);
+        for given in [
+            "abc".to_string(),
+            "g".repeat(FINGERPRINT_LEN),
+            format!("{key} weak strong"),
+        ] {
+            assert!(
+                Allowlist::read(given.as_bytes()).is_err(),
+                "{given:?} shall be rejected"
+            );
+        }
+    }
+}
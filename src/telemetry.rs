@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::read_to_string;
+use std::time::Instant;
+
+const BYTES_IN_KIB: u64 = 1024;
+
+/// Telemetry is the effort spent by a single attack run, returned alongside its success or failure,
+/// so benchmarks and reports read it without scraping stdout.
+///
+///  - iterations, steps of the attack main loop, Fermat steps, rho steps, gcds or elliptic curves.
+///  - primes_tested, candidate primes checked for dividing the modulus.
+///  - candidates_sieved, integers sieved to produce the candidate primes.
+///  - wall_time_ms, wall clock time of the run.
+///  - peak_memory_bytes, peak resident memory of the whole process when the run finished, 0 where unavailable.
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Telemetry {
+    pub iterations: u64,
+    pub primes_tested: u64,
+    pub candidates_sieved: u64,
+    pub wall_time_ms: u64,
+    pub peak_memory_bytes: u64,
+}
+
+impl Display for Telemetry {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} iterations, {} primes tested, {} candidates sieved in {} ms, peak memory {} KiB",
+            self.iterations,
+            self.primes_tested,
+            self.candidates_sieved,
+            self.wall_time_ms,
+            self.peak_memory_bytes / BYTES_IN_KIB
+        )
+    }
+}
+
+impl Telemetry {
+    /// Adds the counters of other telemetry, used to join the work of parallel workers.
+    ///
+    #[inline(always)]
+    pub fn merge(&mut self, other: &Telemetry) {
+        self.iterations += other.iterations;
+        self.primes_tested += other.primes_tested;
+        self.candidates_sieved += other.candidates_sieved;
+    }
+
+    /// Stamps the wall time elapsed since given start and the peak memory of the process.
+    ///
+    #[inline(always)]
+    pub fn finish(&mut self, start: Instant) {
+        self.wall_time_ms = start.elapsed().as_millis() as u64;
+        self.peak_memory_bytes = peak_memory_bytes();
+    }
+}
+
+/// Returns the peak resident memory of the process in bytes, read from VmHWM of /proc/self/status.
+/// Returns 0 on platforms without procfs.
+///
+#[inline(always)]
+pub fn peak_memory_bytes() -> u64 {
+    status_bytes("VmHWM:")
+}
+
+/// Returns the current resident memory of the process in bytes, read from VmRSS of /proc/self/status.
+/// Returns 0 on platforms without procfs.
+///
+#[inline(always)]
+pub fn resident_memory_bytes() -> u64 {
+    status_bytes("VmRSS:")
+}
+
+#[inline(always)]
+fn status_bytes(field: &str) -> u64 {
+    let Ok(status) = read_to_string("/proc/self/status") else {
+        return 0;
+    };
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field))
+        .and_then(|kib| kib.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kib| kib * BYTES_IN_KIB)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::BilboError;
+    use crate::rsa::PickLock;
+    use num_bigint::BigInt;
+    use std::time::Duration;
+
+    #[test]
+    fn it_should_accumulate_counters_of_merged_telemetry() {
+        let worker = Telemetry {
+            iterations: 10,
+            primes_tested: 3,
+            candidates_sieved: 100,
+            wall_time_ms: 7,
+            peak_memory_bytes: 4 * BYTES_IN_KIB,
+        };
+        let mut joined = Telemetry::default();
+        joined.merge(&worker);
+        joined.merge(&worker);
+
+        assert_eq!(joined.iterations, 20);
+        assert_eq!(joined.primes_tested, 6);
+        assert_eq!(joined.candidates_sieved, 200);
+        assert_eq!(
+            (joined.wall_time_ms, joined.peak_memory_bytes),
+            (0, 0),
+            "time and memory are stamped by finish only"
+        );
+        assert_eq!(
+            worker.to_string(),
+            "10 iterations, 3 primes tested, 100 candidates sieved in 7 ms, peak memory 4 KiB"
+        );
+    }
+
+    #[test]
+    fn it_should_stamp_wall_time_and_memory_on_finish() {
+        let start = Instant::now() - Duration::from_millis(50);
+        let mut telemetry = Telemetry {
+            iterations: 5,
+            ..Telemetry::default()
+        };
+        telemetry.finish(start);
+
+        assert!(telemetry.wall_time_ms >= 50);
+        assert_eq!(telemetry.iterations, 5);
+        if cfg!(target_os = "linux") {
+            assert!(telemetry.peak_memory_bytes >= resident_memory_bytes() / 2);
+            assert!(telemetry.peak_memory_bytes > 0);
+        }
+    }
+
+    #[test]
+    fn it_should_reset_the_snapshot_of_every_attack_run() -> Result<(), BilboError> {
+        let pl = PickLock::from_exponent_and_modulus(
+            BigInt::from(65_537),
+            BigInt::from(1_000_003_u64 * 999_983),
+        );
+        assert_eq!(pl.last_telemetry(), Telemetry::default());
+
+        pl.try_lock_pick_weak_private()?;
+        let first = pl.last_telemetry();
+        assert!(first.iterations > 0);
+        // A failing run replaces the snapshot instead of adding to it.
+        let _ = pl.try_lock_pick_auxiliary_gcd(&[BigInt::from(7), BigInt::from(11)]);
+        let second = pl.last_telemetry();
+        assert_eq!(second.iterations, 2);
+        let (_, spent) = pl.with_telemetry(|pl| pl.try_lock_pick_auxiliary_gcd(&[BigInt::from(7)]));
+        assert_eq!(spent.iterations, 1);
+        assert_eq!(spent, pl.last_telemetry());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use openssl::asn1::Asn1Time;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::{X509NameBuilder, X509};
+
+use crate::audit::{AuditEvent, AuditRecord};
+use crate::errors::BilboError;
+use crate::schema::SCHEMA_VERSION;
+
+/// Creates the audit log record of the private exponent of the key recovered by the attack.
+///
+pub fn found_by(key: &str, attack: &str) -> AuditRecord {
+    AuditRecord {
+        schema_version: SCHEMA_VERSION,
+        timestamp: "2024-01-01T00:00:00.000Z".to_string(),
+        event: AuditEvent::Finding {
+            attack: attack.to_string(),
+            key: key.to_string(),
+            detail: "private exponent recovered".to_string(),
+            evidence: None,
+        },
+    }
+}
+
+/// Creates the self signed certificate of the common name and key, valid from now for the days,
+/// expired a day after the epoch if valid for no days.
+///
+pub fn self_signed(cn: &str, key: &PKey<Private>, days: u32) -> Result<X509, BilboError> {
+    let mut name = X509NameBuilder::new()?;
+    name.append_entry_by_text("CN", cn)?;
+    let name = name.build();
+    let mut builder = X509::builder()?;
+    builder.set_subject_name(&name)?;
+    builder.set_issuer_name(&name)?;
+    builder.set_pubkey(key)?;
+    let (not_before, not_after) = match days {
+        0 => (Asn1Time::from_unix(0)?, Asn1Time::from_unix(86_400)?),
+        days => (Asn1Time::days_from_now(0)?, Asn1Time::days_from_now(days)?),
+    };
+    builder.set_not_before(&not_before)?;
+    builder.set_not_after(&not_after)?;
+    builder.sign(key, MessageDigest::sha256())?;
+
+    Ok(builder.build())
+}
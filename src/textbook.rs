@@ -0,0 +1,229 @@
+use num_bigint::{BigInt, Sign};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::rsa::small_e_root_attack;
+
+// Padded ciphertexts are uniform in [0, n), one this many bits shorter than n appears with probability 2^-64.
+const SMALL_CIPHERTEXT_MARGIN: u64 = 64;
+// Largest public exponent the polynomial based message recovery attacks are practical for.
+const SMALL_EXPONENT: u32 = 17;
+
+/// TextbookSign is an observation suggesting the ciphertexts were produced by unpadded RSA.
+///
+///  - Repeated, the same ciphertext occurs more than once, encryption is deterministic.
+///  - SmallCiphertext, the ciphertext is far shorter than the modulus, padded ciphertexts never are.
+///  - PerfectPower, the ciphertext is an exact e-th power, m^e did not wrap the modulus and m is recovered.
+///  - FixedPoint, the ciphertext is 0, 1 or n − 1, those messages encrypt to themselves.
+///  - OutOfRange, the ciphertext is not below the modulus, it was not produced by this key.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextbookSign {
+    Repeated { indices: Vec<usize> },
+    SmallCiphertext { index: usize, bits: u64 },
+    PerfectPower { index: usize, message: BigInt },
+    FixedPoint { index: usize },
+    OutOfRange { index: usize },
+}
+
+impl Display for TextbookSign {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self {
+            TextbookSign::Repeated { indices } => write!(
+                f,
+                "ciphertexts {} are equal, encryption is deterministic",
+                indices
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            TextbookSign::SmallCiphertext { index, bits } => {
+                write!(f, "ciphertext {index} has only {bits} bits")
+            }
+            TextbookSign::PerfectPower { index, message } => {
+                write!(
+                    f,
+                    "ciphertext {index} is exact power of message {message:#x}"
+                )
+            }
+            TextbookSign::FixedPoint { index } => {
+                write!(f, "ciphertext {index} is a fixed point of RSA")
+            }
+            TextbookSign::OutOfRange { index } => {
+                write!(f, "ciphertext {index} is not below the modulus")
+            }
+        }
+    }
+}
+
+/// MessageRecoveryAttack applicable once unpadded RSA is detected.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MessageRecoveryAttack {
+    /// Integer e-th root of the ciphertext, works when m^e < n.
+    EthRoot,
+    /// Encrypt candidate messages and compare, works for deterministic encryption of low entropy messages.
+    Dictionary,
+    /// Coppersmith stereotyped message, works with small e when most of the message is known.
+    StereotypedMessage,
+    /// Franklin-Reiter related message, works with small e for messages with known affine relation.
+    RelatedMessage,
+    /// Chosen ciphertext by blinding c·r^e, works when a decryption oracle is reachable.
+    ChosenCiphertext,
+}
+
+impl Display for MessageRecoveryAttack {
+    #[inline(always)]
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match &self {
+                MessageRecoveryAttack::EthRoot => "e-th root",
+                MessageRecoveryAttack::Dictionary => "dictionary",
+                MessageRecoveryAttack::StereotypedMessage => "stereotyped message",
+                MessageRecoveryAttack::RelatedMessage => "related message",
+                MessageRecoveryAttack::ChosenCiphertext => "chosen ciphertext",
+            }
+        )
+    }
+}
+
+/// TextbookReport lists the signs of unpadded RSA found in the corpus and the attacks they enable.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextbookReport {
+    pub signs: Vec<TextbookSign>,
+    pub attacks: Vec<MessageRecoveryAttack>,
+}
+
+impl TextbookReport {
+    /// Returns true if any sign of unpadded RSA was found.
+    ///
+    #[inline(always)]
+    pub fn is_textbook(&self) -> bool {
+        self.signs
+            .iter()
+            .any(|s| !matches!(s, TextbookSign::OutOfRange { .. }))
+    }
+}
+
+/// Analyzes ciphertexts encrypted with the public key (e, n) for signs of textbook RSA,
+/// encryption without padding, and recommends applicable message recovery attacks.
+///
+pub fn analyze_ciphertexts(e: &BigInt, n: &BigInt, ciphertexts: &[BigInt]) -> TextbookReport {
+    let exponent = u32::try_from(e).ok();
+    let one = BigInt::from(1);
+    let small_bits = n.bits().saturating_sub(SMALL_CIPHERTEXT_MARGIN);
+    let mut report = TextbookReport::default();
+    let mut seen: BTreeMap<&BigInt, Vec<usize>> = BTreeMap::new();
+
+    for (index, c) in ciphertexts.iter().enumerate() {
+        if c.sign() == Sign::Minus || c >= n {
+            report.signs.push(TextbookSign::OutOfRange { index });
+            continue;
+        }
+        seen.entry(c).or_default().push(index);
+        if c <= &one || c == &(n - &one) {
+            report.signs.push(TextbookSign::FixedPoint { index });
+            continue;
+        }
+        if let Some(message) = exponent.and_then(|_| small_e_root_attack(c, e, n).ok()) {
+            report
+                .signs
+                .push(TextbookSign::PerfectPower { index, message });
+            continue;
+        }
+        if c.bits() < small_bits {
+            report.signs.push(TextbookSign::SmallCiphertext {
+                index,
+                bits: c.bits(),
+            });
+        }
+    }
+    report.signs.extend(
+        seen.into_values()
+            .filter(|indices| indices.len() > 1)
+            .map(|indices| TextbookSign::Repeated { indices }),
+    );
+
+    if !report.is_textbook() {
+        return report;
+    }
+    for sign in report.signs.iter() {
+        match sign {
+            TextbookSign::PerfectPower { .. } => {
+                report.attacks.push(MessageRecoveryAttack::EthRoot)
+            }
+            TextbookSign::Repeated { .. } => report.attacks.push(MessageRecoveryAttack::Dictionary),
+            _ => {}
+        }
+    }
+    if exponent.is_some_and(|e| e <= SMALL_EXPONENT) {
+        report
+            .attacks
+            .push(MessageRecoveryAttack::StereotypedMessage);
+        report.attacks.push(MessageRecoveryAttack::RelatedMessage);
+    }
+    report.attacks.push(MessageRecoveryAttack::ChosenCiphertext);
+    report.attacks.sort();
+    report.attacks.dedup();
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn it_should_detect_unpadded_rsa() {
+        let e = BigInt::from(3);
+        let n = BigInt::parse_bytes(b"c3a2f6a7d3e1b5c9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f1a0b9c8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f3", 16).unwrap();
+        let padded = rand::thread_rng().gen_bigint_range(&(&n >> 1), &n);
+        let message = BigInt::from(0x0062_696c_626f_u64);
+        let given = vec![
+            padded.clone(),
+            message.pow(3),
+            padded,
+            BigInt::from(1),
+            n.clone(),
+        ];
+
+        let report = analyze_ciphertexts(&e, &n, &given);
+        assert!(report.is_textbook());
+        assert_eq!(
+            report.signs,
+            vec![
+                TextbookSign::PerfectPower { index: 1, message },
+                TextbookSign::FixedPoint { index: 3 },
+                TextbookSign::OutOfRange { index: 4 },
+                TextbookSign::Repeated {
+                    indices: vec![0, 2]
+                },
+            ]
+        );
+        assert_eq!(
+            report.attacks,
+            vec![
+                MessageRecoveryAttack::EthRoot,
+                MessageRecoveryAttack::Dictionary,
+                MessageRecoveryAttack::StereotypedMessage,
+                MessageRecoveryAttack::RelatedMessage,
+                MessageRecoveryAttack::ChosenCiphertext,
+            ]
+        );
+
+        let clean = [
+            rand::thread_rng().gen_bigint_range(&(&n >> 1), &n),
+            rand::thread_rng().gen_bigint_range(&(&n >> 1), &n),
+        ];
+        assert_eq!(
+            analyze_ciphertexts(&e, &n, &clean),
+            TextbookReport::default()
+        );
+    }
+}